@@ -0,0 +1,143 @@
+use std::fmt;
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::errors::*;
+use super::header::*;
+
+#[cfg(test)]
+mod application_defined_test;
+
+// Length of the name of an ApplicationDefined packet
+pub const APP_NAME_LENGTH: usize = 4;
+
+// The ApplicationDefined packet carries application specific data, it is
+// intended for experimental use as new applications and features are developed.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ApplicationDefined {
+    // Subtype allowing a set of APP packets to be defined under one name
+    pub sub_type: u8,
+    // SSRC/CSRC of the originator of this packet
+    pub sender_ssrc: u32,
+    // Four ASCII characters naming the application
+    pub name: String,
+    // Application dependent data, its length must be a multiple of 32 bits
+    pub data: Vec<u8>,
+}
+
+impl fmt::Display for ApplicationDefined {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ApplicationDefined from {:x}\n\tSubtype: {}\n\tName: {:?}\n\tData: {:?}\n",
+            self.sender_ssrc, self.sub_type, self.name, self.data
+        )
+    }
+}
+
+impl ApplicationDefined {
+    fn size(&self) -> usize {
+        HEADER_LENGTH + SSRC_LENGTH + APP_NAME_LENGTH + self.data.len()
+    }
+
+    // Unmarshal decodes the packet from binary.
+    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |V=2|P| subtype |   PT=APP=204  |             length            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                           SSRC/CSRC                           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                          name (ASCII)                         |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                   application-dependent data                ...
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        let header = Header::unmarshal(reader)?;
+
+        if header.packet_type != PacketType::ApplicationDefined {
+            return Err(ERR_WRONG_TYPE.clone());
+        }
+
+        let body_length = header.length as usize * 4;
+        if body_length < SSRC_LENGTH + APP_NAME_LENGTH {
+            return Err(ERR_PACKET_TOO_SHORT.clone());
+        }
+
+        let sender_ssrc = reader.read_u32::<BigEndian>()?;
+
+        let mut name = [0u8; APP_NAME_LENGTH];
+        reader.read_exact(&mut name)?;
+        if !name.is_ascii() {
+            return Err(ERR_INVALID_APP_NAME.clone());
+        }
+        let name = String::from_utf8(name.to_vec()).map_err(|_| ERR_INVALID_APP_NAME.clone())?;
+
+        let mut data = vec![0u8; body_length - SSRC_LENGTH - APP_NAME_LENGTH];
+        reader.read_exact(&mut data)?;
+
+        if header.padding {
+            // The last octet of the padding holds its length
+            let padding_len = *data.last().ok_or_else(|| ERR_WRONG_PADDING.clone())? as usize;
+            if padding_len == 0 || padding_len > data.len() {
+                return Err(ERR_WRONG_PADDING.clone());
+            }
+            data.truncate(data.len() - padding_len);
+        }
+
+        Ok(ApplicationDefined {
+            sub_type: header.count,
+            sender_ssrc,
+            name,
+            data,
+        })
+    }
+
+    // Header returns the Header associated with this packet.
+    pub fn header(&self) -> Header {
+        Header {
+            padding: false,
+            count: self.sub_type,
+            packet_type: PacketType::ApplicationDefined,
+            length: ((self.size() / 4) - 1) as u16,
+        }
+    }
+
+    // destination_ssrc returns an array of SSRC values that this packet refers to.
+    pub fn destination_ssrc(&self) -> Vec<u32> {
+        vec![self.sender_ssrc]
+    }
+
+    // Marshal encodes the packet in binary.
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.sub_type as usize > COUNT_MAX {
+            return Err(ERR_INVALID_APP_SUB_TYPE.clone());
+        }
+        if self.name.len() != APP_NAME_LENGTH || !self.name.is_ascii() {
+            return Err(ERR_INVALID_APP_NAME.clone());
+        }
+        // The data must end on a 32 bit boundary, the padding bit is reserved
+        // to the last packet of a compound packet
+        if !self.data.len().is_multiple_of(4) {
+            return Err(ERR_INVALID_APP_DATA.clone());
+        }
+        if self.size() > (u16::MAX as usize + 1) * 4 {
+            return Err(ERR_PACKET_TOO_LONG.clone());
+        }
+
+        let header = self.header();
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.sender_ssrc)?;
+        writer.write_all(self.name.as_bytes())?;
+        writer.write_all(&self.data)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use super::*;
+
+use std::io::{BufReader, BufWriter};
+
+use util::Error;
+
+#[test]
+fn test_application_defined_unmarshal() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "valid",
+            vec![
+                // v=2, p=0, subtype=5, APP, len=3
+                0x85, 0xcc, 0x00, 0x03, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45, // data
+                0x01, 0x02, 0x03, 0x04,
+            ],
+            ApplicationDefined {
+                sub_type: 5,
+                sender_ssrc: 0x902f9e2e,
+                name: "TELE".to_string(),
+                data: vec![0x01, 0x02, 0x03, 0x04],
+            },
+            None,
+        ),
+        (
+            "padded data",
+            vec![
+                // v=2, p=1, subtype=0, APP, len=3
+                0xa0, 0xcc, 0x00, 0x03, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45, // data + padding
+                0x01, 0x02, 0x00, 0x02,
+            ],
+            ApplicationDefined {
+                sub_type: 0,
+                sender_ssrc: 0x902f9e2e,
+                name: "TELE".to_string(),
+                data: vec![0x01, 0x02],
+            },
+            None,
+        ),
+        (
+            "no data",
+            vec![
+                // v=2, p=0, subtype=1, APP, len=2
+                0x81, 0xcc, 0x00, 0x02, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45,
+            ],
+            ApplicationDefined {
+                sub_type: 1,
+                sender_ssrc: 0x902f9e2e,
+                name: "TELE".to_string(),
+                data: vec![],
+            },
+            None,
+        ),
+        (
+            "bad padding",
+            vec![
+                // v=2, p=1, subtype=0, APP, len=3
+                0xa0, 0xcc, 0x00, 0x03, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45, // padding longer than data
+                0x01, 0x02, 0x00, 0x05,
+            ],
+            ApplicationDefined::default(),
+            Some(ERR_WRONG_PADDING.clone()),
+        ),
+        (
+            "missing name",
+            vec![
+                // v=2, p=0, subtype=0, APP, len=1
+                0x80, 0xcc, 0x00, 0x01, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e,
+            ],
+            ApplicationDefined::default(),
+            Some(ERR_PACKET_TOO_SHORT.clone()),
+        ),
+        (
+            "truncated data",
+            vec![
+                // v=2, p=0, subtype=0, APP, len=3
+                0x80, 0xcc, 0x00, 0x03, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45,
+            ],
+            ApplicationDefined::default(),
+            Some(ERR_FAILED_TO_FILL_WHOLE_BUFFER.clone()),
+        ),
+        (
+            "non ASCII name",
+            vec![
+                // v=2, p=0, subtype=0, APP, len=2
+                0x80, 0xcc, 0x00, 0x02, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TÉL
+                0x54, 0xc3, 0x89, 0x4c,
+            ],
+            ApplicationDefined::default(),
+            Some(ERR_INVALID_APP_NAME.clone()),
+        ),
+        (
+            "wrong type",
+            vec![
+                // v=2, p=0, count=0, BYE, len=2
+                0x80, 0xcb, 0x00, 0x02, // ssrc=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+                0x54, 0x45, 0x4c, 0x45,
+            ],
+            ApplicationDefined::default(),
+            Some(ERR_WRONG_TYPE.clone()),
+        ),
+    ];
+
+    for (name, data, want, want_error) in tests {
+        let mut reader = BufReader::new(data.as_slice());
+        let result = ApplicationDefined::unmarshal(&mut reader);
+        if let Some(err) = want_error {
+            if let Err(got) = result {
+                assert_eq!(
+                    got, err,
+                    "Unmarshal {} header: err = {}, want {}",
+                    name, got, err
+                );
+            } else {
+                panic!("want error in test {}", name);
+            }
+        } else {
+            if let Ok(got) = result {
+                assert_eq!(
+                    got, want,
+                    "Unmarshal {} header: got {:?}, want {:?}",
+                    name, got, want,
+                )
+            } else {
+                panic!("must no error in test {}", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_application_defined_roundtrip() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "valid",
+            ApplicationDefined {
+                sub_type: 31,
+                sender_ssrc: 1,
+                name: "TELE".to_string(),
+                data: vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+            },
+            None,
+        ),
+        (
+            "unaligned data",
+            ApplicationDefined {
+                sub_type: 2,
+                sender_ssrc: 5000,
+                name: "TELE".to_string(),
+                data: vec![0x01, 0x02, 0x03, 0x04, 0x05],
+            },
+            Some(ERR_INVALID_APP_DATA.clone()),
+        ),
+        (
+            "subtype too large",
+            ApplicationDefined {
+                sub_type: 32,
+                sender_ssrc: 1,
+                name: "TELE".to_string(),
+                data: vec![],
+            },
+            Some(ERR_INVALID_APP_SUB_TYPE.clone()),
+        ),
+        (
+            "short name",
+            ApplicationDefined {
+                sub_type: 0,
+                sender_ssrc: 1,
+                name: "TEL".to_string(),
+                data: vec![],
+            },
+            Some(ERR_INVALID_APP_NAME.clone()),
+        ),
+        (
+            "non ASCII name",
+            ApplicationDefined {
+                sub_type: 0,
+                sender_ssrc: 1,
+                name: "TÉL".to_string(),
+                data: vec![],
+            },
+            Some(ERR_INVALID_APP_NAME.clone()),
+        ),
+    ];
+
+    for (name, report, marshal_error) in tests {
+        let mut data: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(data.as_mut());
+            let result = report.marshal(&mut writer);
+            if let Some(err) = marshal_error {
+                if let Err(got) = result {
+                    assert_eq!(
+                        got, err,
+                        "marshal {} header: err = {}, want {}",
+                        name, got, err
+                    );
+                } else {
+                    panic!("want error in test {}", name);
+                }
+                continue;
+            } else {
+                assert!(result.is_ok(), "must no error in test {}", name);
+            }
+        }
+
+        assert_eq!(data.len() % 4, 0, "{} is not 32 bit aligned", name);
+
+        let mut reader = BufReader::new(data.as_slice());
+        let decoded = ApplicationDefined::unmarshal(&mut reader)?;
+        assert_eq!(
+            decoded, report,
+            "{} header round trip: got {:?}, want {:?}",
+            name, decoded, report
+        )
+    }
+
+    Ok(())
+}
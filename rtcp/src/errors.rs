@@ -28,5 +28,23 @@ lazy_static! {
     pub static ref ERR_BAD_UNIQUE_IDENTIFIER: Error =
         Error::new("rtcp: invalid unique identifier".to_owned());
     pub static ref ERR_BAD_MEDIA_SSRC: Error = Error::new("rtcp: invalid media SSRC".to_owned());
+    pub static ref ERR_PACKET_TOO_LONG: Error = Error::new("rtcp: packet too long".to_owned());
+    pub static ref ERR_WRONG_PADDING: Error = Error::new("rtcp: invalid padding".to_owned());
+    pub static ref ERR_INVALID_APP_NAME: Error =
+        Error::new("rtcp: app name must be 4 ASCII characters".to_owned());
+    pub static ref ERR_INVALID_APP_SUB_TYPE: Error =
+        Error::new("rtcp: app subtype must be < 32".to_owned());
+    pub static ref ERR_INVALID_APP_DATA: Error =
+        Error::new("rtcp: app data must be a multiple of 32 bits".to_owned());
+    pub static ref ERR_INVALID_BLOCK_SIZE: Error =
+        Error::new("rtcp: invalid report block size".to_owned());
+    pub static ref ERR_WRONG_BLOCK_TYPE: Error =
+        Error::new("rtcp: wrong report block type".to_owned());
+    pub static ref ERR_INVALID_THINNING: Error =
+        Error::new("rtcp: thinning must be < 16".to_owned());
+    pub static ref ERR_INVALID_TTL_OR_HOP_LIMIT_TYPE: Error =
+        Error::new("rtcp: invalid TTL or hop limit type".to_owned());
+    pub static ref ERR_INVALID_OVERHEAD: Error =
+        Error::new("rtcp: overhead must be < 512".to_owned());
     pub static ref ERR_DELTA_EXCEED_LIMIT: Error = Error::new("delta exceed limit".to_owned());
 }
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::io::{BufReader, Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::errors::*;
+use super::header::*;
+
+mod dlrr;
+mod prt;
+mod rle;
+mod rrt;
+mod ssr;
+mod unknown;
+mod vm;
+
+pub use dlrr::*;
+pub use prt::*;
+pub use rle::*;
+pub use rrt::*;
+pub use ssr::*;
+pub use unknown::*;
+pub use vm::*;
+
+#[cfg(test)]
+mod extended_report_test;
+
+// Report block types registered with IANA. See: https://www.iana.org/assignments/rtcp-xr-block-types/rtcp-xr-block-types.xhtml
+pub const BLOCK_TYPE_LOSS_RLE: u8 = 1; // RFC 3611, 4.1
+pub const BLOCK_TYPE_DUPLICATE_RLE: u8 = 2; // RFC 3611, 4.2
+pub const BLOCK_TYPE_PACKET_RECEIPT_TIMES: u8 = 3; // RFC 3611, 4.3
+pub const BLOCK_TYPE_RECEIVER_REFERENCE_TIME: u8 = 4; // RFC 3611, 4.4
+pub const BLOCK_TYPE_DLRR: u8 = 5; // RFC 3611, 4.5
+pub const BLOCK_TYPE_STATISTICS_SUMMARY: u8 = 6; // RFC 3611, 4.6
+pub const BLOCK_TYPE_VOIP_METRICS: u8 = 7; // RFC 3611, 4.7
+
+pub const XR_HEADER_LENGTH: usize = 4;
+
+// XRHeader is the common header of all report blocks of an ExtendedReport
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct XRHeader {
+    pub block_type: u8,
+    pub type_specific: u8,
+    // The length of this report block in 32-bit words minus one,
+    // including the header.
+    pub block_length: u16,
+}
+
+impl XRHeader {
+    // new returns the header of a report block of size octets, header included
+    pub(crate) fn new(block_type: u8, type_specific: u8, size: usize) -> Result<Self, Error> {
+        if !size.is_multiple_of(4) || size / 4 - 1 > u16::MAX as usize {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+        Ok(XRHeader {
+            block_type,
+            type_specific,
+            block_length: (size / 4 - 1) as u16,
+        })
+    }
+
+    // Marshal encodes the XRHeader in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |      BT       | type-specific |         block length          |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+        writer.write_u8(self.block_type)?;
+        writer.write_u8(self.type_specific)?;
+        writer.write_u16::<BigEndian>(self.block_length)?;
+        Ok(())
+    }
+
+    // Unmarshal decodes the XRHeader from binary
+    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let block_type = reader.read_u8()?;
+        let type_specific = reader.read_u8()?;
+        let block_length = reader.read_u16::<BigEndian>()?;
+        Ok(XRHeader {
+            block_type,
+            type_specific,
+            block_length,
+        })
+    }
+}
+
+// ReportBlock is a single report block of an ExtendedReport
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReportBlock {
+    LossRLE(RLEReportBlock),
+    DuplicateRLE(RLEReportBlock),
+    PacketReceiptTimes(PacketReceiptTimesReportBlock),
+    ReceiverReferenceTime(ReceiverReferenceTimeReportBlock),
+    DLRR(DLRRReportBlock),
+    StatisticsSummary(StatisticsSummaryReportBlock),
+    VoIPMetrics(VoIPMetricsReportBlock),
+    Unknown(UnknownReportBlock),
+}
+
+impl ReportBlock {
+    pub fn size(&self) -> usize {
+        match self {
+            ReportBlock::LossRLE(b) => b.size(),
+            ReportBlock::DuplicateRLE(b) => b.size(),
+            ReportBlock::PacketReceiptTimes(b) => b.size(),
+            ReportBlock::ReceiverReferenceTime(b) => b.size(),
+            ReportBlock::DLRR(b) => b.size(),
+            ReportBlock::StatisticsSummary(b) => b.size(),
+            ReportBlock::VoIPMetrics(b) => b.size(),
+            ReportBlock::Unknown(b) => b.size(),
+        }
+    }
+
+    // destination_ssrc returns an array of SSRC values that this block refers to.
+    pub fn destination_ssrc(&self) -> Vec<u32> {
+        match self {
+            ReportBlock::LossRLE(b) => vec![b.ssrc],
+            ReportBlock::DuplicateRLE(b) => vec![b.ssrc],
+            ReportBlock::PacketReceiptTimes(b) => vec![b.ssrc],
+            ReportBlock::ReceiverReferenceTime(_) => vec![],
+            ReportBlock::DLRR(b) => b.reports.iter().map(|r| r.ssrc).collect(),
+            ReportBlock::StatisticsSummary(b) => vec![b.ssrc],
+            ReportBlock::VoIPMetrics(b) => vec![b.ssrc],
+            ReportBlock::Unknown(_) => vec![],
+        }
+    }
+
+    // Marshal encodes the report block in binary, header included
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        match self {
+            ReportBlock::LossRLE(b) => b.marshal(BLOCK_TYPE_LOSS_RLE, writer),
+            ReportBlock::DuplicateRLE(b) => b.marshal(BLOCK_TYPE_DUPLICATE_RLE, writer),
+            ReportBlock::PacketReceiptTimes(b) => b.marshal(writer),
+            ReportBlock::ReceiverReferenceTime(b) => b.marshal(writer),
+            ReportBlock::DLRR(b) => b.marshal(writer),
+            ReportBlock::StatisticsSummary(b) => b.marshal(writer),
+            ReportBlock::VoIPMetrics(b) => b.marshal(writer),
+            ReportBlock::Unknown(b) => b.marshal(writer),
+        }
+    }
+
+    // Unmarshal decodes a single report block, header included, from binary
+    pub fn unmarshal(raw_data: &[u8]) -> Result<Self, Error> {
+        if raw_data.len() < XR_HEADER_LENGTH {
+            return Err(ERR_PACKET_TOO_SHORT.clone());
+        }
+        let mut reader = BufReader::new(raw_data);
+        let header = XRHeader::unmarshal(&mut reader)?;
+
+        match header.block_type {
+            BLOCK_TYPE_LOSS_RLE => Ok(ReportBlock::LossRLE(RLEReportBlock::unmarshal(
+                &header,
+                &mut reader,
+            )?)),
+            BLOCK_TYPE_DUPLICATE_RLE => Ok(ReportBlock::DuplicateRLE(RLEReportBlock::unmarshal(
+                &header,
+                &mut reader,
+            )?)),
+            BLOCK_TYPE_PACKET_RECEIPT_TIMES => Ok(ReportBlock::PacketReceiptTimes(
+                PacketReceiptTimesReportBlock::unmarshal(&header, &mut reader)?,
+            )),
+            BLOCK_TYPE_RECEIVER_REFERENCE_TIME => Ok(ReportBlock::ReceiverReferenceTime(
+                ReceiverReferenceTimeReportBlock::unmarshal(&header, &mut reader)?,
+            )),
+            BLOCK_TYPE_DLRR => Ok(ReportBlock::DLRR(DLRRReportBlock::unmarshal(
+                &header,
+                &mut reader,
+            )?)),
+            BLOCK_TYPE_STATISTICS_SUMMARY => Ok(ReportBlock::StatisticsSummary(
+                StatisticsSummaryReportBlock::unmarshal(&header, &mut reader)?,
+            )),
+            BLOCK_TYPE_VOIP_METRICS => Ok(ReportBlock::VoIPMetrics(
+                VoIPMetricsReportBlock::unmarshal(&header, &mut reader)?,
+            )),
+            _ => Ok(ReportBlock::Unknown(UnknownReportBlock::unmarshal(
+                &header,
+                &mut reader,
+            )?)),
+        }
+    }
+}
+
+// The ExtendedReport (XR) packet carries report blocks conveying information
+// beyond the reception statistics of SR and RR packets, see RFC 3611.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ExtendedReport {
+    // The synchronization source identifier for the originator of this XR packet.
+    pub sender_ssrc: u32,
+    pub reports: Vec<ReportBlock>,
+}
+
+impl fmt::Display for ExtendedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = format!("ExtendedReport from {:x}\n", self.sender_ssrc);
+        for report in &self.reports {
+            out += format!("\t{:?}\n", report).as_str();
+        }
+
+        write!(f, "{}", out)
+    }
+}
+
+impl ExtendedReport {
+    fn size(&self) -> usize {
+        let mut reports_length = 0;
+        for report in &self.reports {
+            reports_length += report.size();
+        }
+        HEADER_LENGTH + SSRC_LENGTH + reports_length
+    }
+
+    // Unmarshal decodes the ExtendedReport from binary
+    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |V=2|P|reserved |   PT=XR=207   |             length            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                              SSRC                             |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * :                         report blocks                         :
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        let header = Header::unmarshal(reader)?;
+
+        if header.packet_type != PacketType::ExtendedReport {
+            return Err(ERR_WRONG_TYPE.clone());
+        }
+
+        let body_length = header.length as usize * 4;
+        if body_length < SSRC_LENGTH {
+            return Err(ERR_PACKET_TOO_SHORT.clone());
+        }
+
+        let sender_ssrc = reader.read_u32::<BigEndian>()?;
+
+        let mut body = vec![0u8; body_length - SSRC_LENGTH];
+        reader.read_exact(&mut body)?;
+
+        if header.padding {
+            // The last octet of the padding holds its length
+            let padding_len = *body.last().ok_or_else(|| ERR_WRONG_PADDING.clone())? as usize;
+            if padding_len == 0 || padding_len > body.len() {
+                return Err(ERR_WRONG_PADDING.clone());
+            }
+            body.truncate(body.len() - padding_len);
+        }
+
+        let mut reports = vec![];
+        let mut raw_data = body.as_slice();
+        while !raw_data.is_empty() {
+            if raw_data.len() < XR_HEADER_LENGTH {
+                return Err(ERR_PACKET_TOO_SHORT.clone());
+            }
+            let block_length = ((raw_data[2] as usize) << 8 | raw_data[3] as usize) + 1;
+            let bytes_processed = block_length * 4;
+            if bytes_processed > raw_data.len() {
+                return Err(ERR_PACKET_TOO_SHORT.clone());
+            }
+            reports.push(ReportBlock::unmarshal(&raw_data[..bytes_processed])?);
+            raw_data = &raw_data[bytes_processed..];
+        }
+
+        Ok(ExtendedReport {
+            sender_ssrc,
+            reports,
+        })
+    }
+
+    // Header returns the Header associated with this packet.
+    pub fn header(&self) -> Header {
+        Header {
+            padding: false,
+            count: 0,
+            packet_type: PacketType::ExtendedReport,
+            length: ((self.size() / 4) - 1) as u16,
+        }
+    }
+
+    // destination_ssrc returns an array of SSRC values that this packet refers to.
+    pub fn destination_ssrc(&self) -> Vec<u32> {
+        let mut ssrcs = vec![];
+        for report in &self.reports {
+            ssrcs.extend(report.destination_ssrc());
+        }
+        ssrcs
+    }
+
+    // Marshal encodes the ExtendedReport in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.size() > (u16::MAX as usize + 1) * 4 {
+            return Err(ERR_PACKET_TOO_LONG.clone());
+        }
+
+        let header = self.header();
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.sender_ssrc)?;
+
+        for report in &self.reports {
+            report.marshal(writer)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const DLRR_REPORT_LENGTH: usize = 12;
+
+// DLRRReport is a sub-block of a DLRRReportBlock, answering the last
+// ReceiverReferenceTimeReportBlock received from a receiver
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct DLRRReport {
+    // SSRC of the receiver
+    pub ssrc: u32,
+    // Middle 32 bits of the NTP timestamp of the last receiver reference
+    // time report received from ssrc
+    pub last_rr: u32,
+    // Delay since that report was received, in units of 1/65536 seconds
+    pub dlrr: u32,
+}
+
+impl DLRRReport {
+    // round_trip_time returns the round trip time to the sender of this
+    // report, now is the middle 32 bits of the NTP time this report was
+    // received at. None is returned without a reference time report.
+    pub fn round_trip_time(&self, now: u32) -> Option<Duration> {
+        if self.last_rr == 0 {
+            return None;
+        }
+        let rtt = now.wrapping_sub(self.last_rr).wrapping_sub(self.dlrr);
+        // Clocks out of sync give a negative round trip time
+        if rtt >= 0x8000_0000 {
+            return None;
+        }
+        Some(Duration::from_micros((rtt as u64 * 1_000_000) >> 16))
+    }
+}
+
+// DLRRReportBlock carries the delay since the last receiver reference time
+// report of receivers, RFC 3611 4.5
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct DLRRReportBlock {
+    pub reports: Vec<DLRRReport>,
+}
+
+impl DLRRReportBlock {
+    pub fn size(&self) -> usize {
+        XR_HEADER_LENGTH + self.reports.len() * DLRR_REPORT_LENGTH
+    }
+
+    // Marshal encodes the DLRRReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=5      |   reserved    |         block length          |
+         * +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+         * |                 SSRC_1 (SSRC of first receiver)               | sub-
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ block
+         * |                         last RR (LRR)                         |   1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                   delay since last RR (DLRR)                  |
+         * +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+         * :                               ...                             :
+         * +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+         */
+
+        let header = XRHeader::new(BLOCK_TYPE_DLRR, 0, self.size())?;
+        header.marshal(writer)?;
+
+        for report in &self.reports {
+            writer.write_u32::<BigEndian>(report.ssrc)?;
+            writer.write_u32::<BigEndian>(report.last_rr)?;
+            writer.write_u32::<BigEndian>(report.dlrr)?;
+        }
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the DLRRReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_DLRR {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+
+        let reports_length = header.block_length as usize * 4;
+        if !reports_length.is_multiple_of(DLRR_REPORT_LENGTH) {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let mut reports = vec![];
+        for _ in 0..reports_length / DLRR_REPORT_LENGTH {
+            let ssrc = reader.read_u32::<BigEndian>()?;
+            let last_rr = reader.read_u32::<BigEndian>()?;
+            let dlrr = reader.read_u32::<BigEndian>()?;
+            reports.push(DLRRReport {
+                ssrc,
+                last_rr,
+                dlrr,
+            });
+        }
+
+        Ok(DLRRReportBlock { reports })
+    }
+}
@@ -0,0 +1,419 @@
+use super::*;
+
+use std::io::{BufReader, BufWriter};
+use std::time::Duration;
+
+use util::Error;
+
+fn test_packet() -> ExtendedReport {
+    ExtendedReport {
+        sender_ssrc: 0x01020304,
+        reports: vec![
+            ReportBlock::LossRLE(RLEReportBlock {
+                t: 12,
+                ssrc: 0x12345689,
+                begin_seq: 5,
+                end_seq: 12,
+                chunks: vec![Chunk(0x4006), Chunk(0x0006), Chunk(0x8765)],
+            }),
+            ReportBlock::DuplicateRLE(RLEReportBlock {
+                t: 6,
+                ssrc: 0x12345689,
+                begin_seq: 5,
+                end_seq: 12,
+                chunks: vec![Chunk(0x4123), Chunk(0x3fff)],
+            }),
+            ReportBlock::PacketReceiptTimes(PacketReceiptTimesReportBlock {
+                t: 3,
+                ssrc: 0x98765432,
+                begin_seq: 15432,
+                end_seq: 15577,
+                receipt_times: vec![0x11111111, 0x22222222],
+            }),
+            ReportBlock::ReceiverReferenceTime(ReceiverReferenceTimeReportBlock {
+                ntp_timestamp: 0x0102030405060708,
+            }),
+            ReportBlock::DLRR(DLRRReportBlock {
+                reports: vec![
+                    DLRRReport {
+                        ssrc: 0x88888888,
+                        last_rr: 0x12345678,
+                        dlrr: 0x99999999,
+                    },
+                    DLRRReport {
+                        ssrc: 0x09090909,
+                        last_rr: 0x12345678,
+                        dlrr: 0x99999999,
+                    },
+                ],
+            }),
+            ReportBlock::StatisticsSummary(StatisticsSummaryReportBlock {
+                loss_reports: true,
+                duplicate_reports: true,
+                jitter_reports: true,
+                ttl_or_hop_limit: TTLorHopLimitType::IPv4,
+                ssrc: 0xFEDCBA98,
+                begin_seq: 0x1234,
+                end_seq: 0x5678,
+                lost_packets: 0x11111111,
+                dup_packets: 0x22222222,
+                min_jitter: 0x33333333,
+                max_jitter: 0x44444444,
+                mean_jitter: 0x55555555,
+                dev_jitter: 0x66666666,
+                min_ttl_or_hl: 0x01,
+                max_ttl_or_hl: 0x02,
+                mean_ttl_or_hl: 0x03,
+                dev_ttl_or_hl: 0x04,
+            }),
+            ReportBlock::VoIPMetrics(VoIPMetricsReportBlock {
+                ssrc: 0x89ABCDEF,
+                loss_rate: 0x05,
+                discard_rate: 0x06,
+                burst_density: 0x07,
+                gap_density: 0x08,
+                burst_duration: 0x1111,
+                gap_duration: 0x2222,
+                round_trip_delay: 0x3333,
+                end_system_delay: 0x4444,
+                signal_level: -17,
+                noise_level: -60,
+                rerl: 0x33,
+                gmin: 0x44,
+                r_factor: 0x55,
+                ext_r_factor: 0x66,
+                mos_lq: 0x77,
+                mos_cq: 0x88,
+                rx_config: 0x99,
+                jb_nominal: 0x1122,
+                jb_maximum: 0x3344,
+                jb_abs_max: 0x5566,
+            }),
+            ReportBlock::Unknown(UnknownReportBlock {
+                block_type: 42,
+                type_specific: 0x7F,
+                contents: vec![0xde, 0xad, 0xbe, 0xef],
+            }),
+        ],
+    }
+}
+
+fn test_packet_bytes() -> Vec<u8> {
+    vec![
+        // v=2, p=0, XR, len=46
+        0x80, 0xcf, 0x00, 0x2e, // ssrc=0x01020304
+        0x01, 0x02, 0x03, 0x04, // Loss RLE, T=12, len=4
+        0x01, 0x0c, 0x00, 0x04, // ssrc=0x12345689
+        0x12, 0x34, 0x56, 0x89, // begin_seq=5, end_seq=12
+        0x00, 0x05, 0x00, 0x0c, // chunks
+        0x40, 0x06, 0x00, 0x06, //
+        0x87, 0x65, 0x00, 0x00, // Duplicate RLE, T=6, len=3
+        0x02, 0x06, 0x00, 0x03, // ssrc=0x12345689
+        0x12, 0x34, 0x56, 0x89, // begin_seq=5, end_seq=12
+        0x00, 0x05, 0x00, 0x0c, // chunks
+        0x41, 0x23, 0x3f, 0xff, // Packet Receipt Times, T=3, len=4
+        0x03, 0x03, 0x00, 0x04, // ssrc=0x98765432
+        0x98, 0x76, 0x54, 0x32, // begin_seq=15432, end_seq=15577
+        0x3c, 0x48, 0x3c, 0xd9, // receipt times
+        0x11, 0x11, 0x11, 0x11, //
+        0x22, 0x22, 0x22, 0x22, // Receiver Reference Time, len=2
+        0x04, 0x00, 0x00, 0x02, // ntp timestamp
+        0x01, 0x02, 0x03, 0x04, //
+        0x05, 0x06, 0x07, 0x08, // DLRR, len=6
+        0x05, 0x00, 0x00, 0x06, // ssrc=0x88888888
+        0x88, 0x88, 0x88, 0x88, // last_rr
+        0x12, 0x34, 0x56, 0x78, // dlrr
+        0x99, 0x99, 0x99, 0x99, // ssrc=0x09090909
+        0x09, 0x09, 0x09, 0x09, // last_rr
+        0x12, 0x34, 0x56, 0x78, // dlrr
+        0x99, 0x99, 0x99, 0x99, // Statistics Summary, L=1, D=1, J=1, ToH=1, len=9
+        0x06, 0xe8, 0x00, 0x09, // ssrc=0xFEDCBA98
+        0xfe, 0xdc, 0xba, 0x98, // begin_seq, end_seq
+        0x12, 0x34, 0x56, 0x78, // lost_packets
+        0x11, 0x11, 0x11, 0x11, // dup_packets
+        0x22, 0x22, 0x22, 0x22, // min_jitter
+        0x33, 0x33, 0x33, 0x33, // max_jitter
+        0x44, 0x44, 0x44, 0x44, // mean_jitter
+        0x55, 0x55, 0x55, 0x55, // dev_jitter
+        0x66, 0x66, 0x66, 0x66, // ttl or hop limit
+        0x01, 0x02, 0x03, 0x04, // VoIP Metrics, len=8
+        0x07, 0x00, 0x00, 0x08, // ssrc=0x89ABCDEF
+        0x89, 0xab, 0xcd, 0xef, // rates and densities
+        0x05, 0x06, 0x07, 0x08, // burst and gap durations
+        0x11, 0x11, 0x22, 0x22, // delays
+        0x33, 0x33, 0x44, 0x44, // levels, rerl, gmin
+        0xef, 0xc4, 0x33, 0x44, // r factors, mos
+        0x55, 0x66, 0x77, 0x88, // rx config, reserved, jb nominal
+        0x99, 0x00, 0x11, 0x22, // jb maximum, jb abs max
+        0x33, 0x44, 0x55, 0x66, // Unknown block type 42, len=1
+        0x2a, 0x7f, 0x00, 0x01, // contents
+        0xde, 0xad, 0xbe, 0xef,
+    ]
+}
+
+#[test]
+fn test_extended_report_unmarshal() -> Result<(), Error> {
+    let data = test_packet_bytes();
+    let mut reader = BufReader::new(data.as_slice());
+    let got = ExtendedReport::unmarshal(&mut reader)?;
+    let want = test_packet();
+    assert_eq!(got, want, "Unmarshal got {:?}, want {:?}", got, want);
+
+    Ok(())
+}
+
+#[test]
+fn test_extended_report_marshal() -> Result<(), Error> {
+    let mut data: Vec<u8> = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(data.as_mut());
+        test_packet().marshal(&mut writer)?;
+    }
+    assert_eq!(data, test_packet_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn test_extended_report_unmarshal_errors() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "wrong type",
+            vec![
+                // v=2, p=0, RR, len=1
+                0x80, 0xc9, 0x00, 0x01, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04,
+            ],
+            ERR_WRONG_TYPE.clone(),
+        ),
+        (
+            "missing ssrc",
+            vec![
+                // v=2, p=0, XR, len=0
+                0x80, 0xcf, 0x00, 0x00,
+            ],
+            ERR_PACKET_TOO_SHORT.clone(),
+        ),
+        (
+            "truncated packet",
+            vec![
+                // v=2, p=0, XR, len=2
+                0x80, 0xcf, 0x00, 0x02, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04,
+            ],
+            ERR_FAILED_TO_FILL_WHOLE_BUFFER.clone(),
+        ),
+        (
+            "block exceeds packet",
+            vec![
+                // v=2, p=0, XR, len=3
+                0x80, 0xcf, 0x00, 0x03, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04, // Receiver Reference Time, len=2
+                0x04, 0x00, 0x00, 0x02, // ntp timestamp
+                0x01, 0x02, 0x03, 0x04,
+            ],
+            ERR_PACKET_TOO_SHORT.clone(),
+        ),
+        (
+            "bad fixed block length",
+            vec![
+                // v=2, p=0, XR, len=3
+                0x80, 0xcf, 0x00, 0x03, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04, // Receiver Reference Time, len=1
+                0x04, 0x00, 0x00, 0x01, // ntp timestamp
+                0x01, 0x02, 0x03, 0x04,
+            ],
+            ERR_INVALID_BLOCK_SIZE.clone(),
+        ),
+        (
+            "bad dlrr block length",
+            vec![
+                // v=2, p=0, XR, len=3
+                0x80, 0xcf, 0x00, 0x03, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04, // DLRR, len=1
+                0x05, 0x00, 0x00, 0x01, // ssrc=0x88888888
+                0x88, 0x88, 0x88, 0x88,
+            ],
+            ERR_INVALID_BLOCK_SIZE.clone(),
+        ),
+        (
+            "reserved ttl or hop limit type",
+            vec![
+                // v=2, p=0, XR, len=11
+                0x80, 0xcf, 0x00, 0x0b, // ssrc=0x01020304
+                0x01, 0x02, 0x03, 0x04, // Statistics Summary, ToH=3, len=9
+                0x06, 0x18, 0x00, 0x09, // body
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            ERR_INVALID_TTL_OR_HOP_LIMIT_TYPE.clone(),
+        ),
+    ];
+
+    for (name, data, want) in tests {
+        let mut reader = BufReader::new(data.as_slice());
+        let result = ExtendedReport::unmarshal(&mut reader);
+        if let Err(got) = result {
+            assert_eq!(
+                got, want,
+                "Unmarshal {} header: err = {}, want {}",
+                name, got, want
+            );
+        } else {
+            panic!("want error in test {}", name);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_extended_report_roundtrip() -> Result<(), Error> {
+    let tests = vec![
+        ("all blocks", test_packet(), None),
+        (
+            "no blocks",
+            ExtendedReport {
+                sender_ssrc: 1,
+                reports: vec![],
+            },
+            None,
+        ),
+        (
+            "even chunks",
+            ExtendedReport {
+                sender_ssrc: 1,
+                reports: vec![ReportBlock::LossRLE(RLEReportBlock {
+                    t: 0,
+                    ssrc: 2,
+                    begin_seq: 100,
+                    end_seq: 130,
+                    chunks: vec![Chunk::new_run_length(1, 15), Chunk::new_bit_vector(0x7FF0)],
+                })],
+            },
+            None,
+        ),
+        (
+            "invalid thinning",
+            ExtendedReport {
+                sender_ssrc: 1,
+                reports: vec![ReportBlock::DuplicateRLE(RLEReportBlock {
+                    t: 16,
+                    ..Default::default()
+                })],
+            },
+            Some(ERR_INVALID_THINNING.clone()),
+        ),
+        (
+            "unaligned unknown block",
+            ExtendedReport {
+                sender_ssrc: 1,
+                reports: vec![ReportBlock::Unknown(UnknownReportBlock {
+                    block_type: 200,
+                    type_specific: 0,
+                    contents: vec![1, 2, 3],
+                })],
+            },
+            Some(ERR_INVALID_BLOCK_SIZE.clone()),
+        ),
+    ];
+
+    for (name, report, marshal_error) in tests {
+        let mut data: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(data.as_mut());
+            let result = report.marshal(&mut writer);
+            if let Some(err) = marshal_error {
+                if let Err(got) = result {
+                    assert_eq!(
+                        got, err,
+                        "marshal {} header: err = {}, want {}",
+                        name, got, err
+                    );
+                } else {
+                    panic!("want error in test {}", name);
+                }
+                continue;
+            } else {
+                assert!(result.is_ok(), "must no error in test {}", name);
+            }
+        }
+
+        let mut reader = BufReader::new(data.as_slice());
+        let decoded = ExtendedReport::unmarshal(&mut reader)?;
+        assert_eq!(
+            decoded, report,
+            "{} header round trip: got {:?}, want {:?}",
+            name, decoded, report
+        )
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_extended_report_destination_ssrc() {
+    assert_eq!(
+        test_packet().destination_ssrc(),
+        vec![0x12345689, 0x12345689, 0x98765432, 0x88888888, 0x09090909, 0xFEDCBA98, 0x89ABCDEF]
+    );
+}
+
+#[test]
+fn test_chunk() {
+    let tests = vec![
+        (Chunk(0x4006), ChunkType::RunLength, Some(1), 6),
+        (Chunk(0x0006), ChunkType::RunLength, Some(0), 6),
+        (Chunk(0x8765), ChunkType::BitVector, None, 0x0765),
+        (Chunk(0x0000), ChunkType::TerminatingNull, None, 0),
+        (
+            Chunk::new_run_length(1, 0xFFFF),
+            ChunkType::RunLength,
+            Some(1),
+            0x3FFF,
+        ),
+        (
+            Chunk::new_bit_vector(0xFFFF),
+            ChunkType::BitVector,
+            None,
+            0x7FFF,
+        ),
+    ];
+
+    for (chunk, chunk_type, run_type, value) in tests {
+        assert_eq!(chunk.chunk_type(), chunk_type, "{:?}", chunk);
+        assert_eq!(chunk.run_type(), run_type, "{:?}", chunk);
+        assert_eq!(chunk.value(), value, "{:?}", chunk);
+    }
+}
+
+#[test]
+fn test_dlrr_round_trip_time() {
+    let rrt = ReceiverReferenceTimeReportBlock {
+        ntp_timestamp: 0x0000_1234_8000_0000,
+    };
+    assert_eq!(rrt.last_rr(), 0x1234_8000);
+
+    // Held 1s by the sender, answered 1.5s after sending the reference time
+    let report = DLRRReport {
+        ssrc: 1,
+        last_rr: rrt.last_rr(),
+        dlrr: 0x0001_0000,
+    };
+    assert_eq!(
+        report.round_trip_time(0x1236_0000),
+        Some(Duration::from_millis(500))
+    );
+
+    // Reception earlier than possible
+    assert_eq!(report.round_trip_time(0x1234_8000), None);
+
+    // No reference time report received
+    let report = DLRRReport {
+        ssrc: 1,
+        last_rr: 0,
+        dlrr: 0,
+    };
+    assert_eq!(report.round_trip_time(0x1236_0000), None);
+}
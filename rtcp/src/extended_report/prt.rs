@@ -0,0 +1,96 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const PRT_FIXED_LENGTH: usize = XR_HEADER_LENGTH + SSRC_LENGTH + 4;
+
+// PacketReceiptTimesReportBlock reports the arrival time of each packet
+// received, RFC 3611 4.3
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct PacketReceiptTimesReportBlock {
+    // Only packets whose sequence number is a multiple of 2^t are reported
+    pub t: u8,
+    // SSRC of the RTP source being reported upon
+    pub ssrc: u32,
+    // First sequence number reported
+    pub begin_seq: u16,
+    // Last sequence number reported plus one
+    pub end_seq: u16,
+    // Arrival times in the RTP timestamp clock of the source, one per
+    // sequence number reported
+    pub receipt_times: Vec<u32>,
+}
+
+impl PacketReceiptTimesReportBlock {
+    pub fn size(&self) -> usize {
+        PRT_FIXED_LENGTH + self.receipt_times.len() * 4
+    }
+
+    // Marshal encodes the PacketReceiptTimesReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=3      | rsvd. |   T   |         block length          |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        SSRC of source                         |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |          begin_seq            |             end_seq           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |       Receipt time of packet begin_seq                        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * :                              ...                              :
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        if self.t > 0x0F {
+            return Err(ERR_INVALID_THINNING.clone());
+        }
+
+        let header = XRHeader::new(BLOCK_TYPE_PACKET_RECEIPT_TIMES, self.t, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.ssrc)?;
+        writer.write_u16::<BigEndian>(self.begin_seq)?;
+        writer.write_u16::<BigEndian>(self.end_seq)?;
+        for receipt_time in &self.receipt_times {
+            writer.write_u32::<BigEndian>(*receipt_time)?;
+        }
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the PacketReceiptTimesReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_PACKET_RECEIPT_TIMES {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+
+        let block_size = (header.block_length as usize + 1) * 4;
+        if block_size < PRT_FIXED_LENGTH {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let ssrc = reader.read_u32::<BigEndian>()?;
+        let begin_seq = reader.read_u16::<BigEndian>()?;
+        let end_seq = reader.read_u16::<BigEndian>()?;
+
+        let mut receipt_times = vec![];
+        for _ in 0..(block_size - PRT_FIXED_LENGTH) / 4 {
+            receipt_times.push(reader.read_u32::<BigEndian>()?);
+        }
+
+        Ok(PacketReceiptTimesReportBlock {
+            t: header.type_specific & 0x0F,
+            ssrc,
+            begin_seq,
+            end_seq,
+            receipt_times,
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const RLE_FIXED_LENGTH: usize = XR_HEADER_LENGTH + SSRC_LENGTH + 4;
+const CHUNK_LENGTH: usize = 2;
+
+// ChunkType is the type of a Chunk, given by its first bits
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChunkType {
+    RunLength,
+    BitVector,
+    TerminatingNull,
+}
+
+// Chunk is a 16 bit run length or bit vector chunk of a RLE report block
+//
+// A run length chunk reports a run of run_length packets which were all
+// received (run type 1) or all lost (run type 0). A bit vector chunk reports
+// the next 15 packets, from the most significant bit, 1 if received.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Chunk(pub u16);
+
+impl Chunk {
+    // new_run_length returns a run length chunk, run_length is truncated to 14 bits
+    pub fn new_run_length(run_type: u8, run_length: u16) -> Self {
+        Chunk(((run_type as u16 & 0x01) << 14) | (run_length & 0x3FFF))
+    }
+
+    // new_bit_vector returns a bit vector chunk, bits is truncated to 15 bits
+    pub fn new_bit_vector(bits: u16) -> Self {
+        Chunk(0x8000 | (bits & 0x7FFF))
+    }
+
+    pub fn chunk_type(&self) -> ChunkType {
+        if self.0 == 0 {
+            ChunkType::TerminatingNull
+        } else if self.0 & 0x8000 == 0 {
+            ChunkType::RunLength
+        } else {
+            ChunkType::BitVector
+        }
+    }
+
+    // run_type returns the run type of a run length chunk
+    pub fn run_type(&self) -> Option<u8> {
+        match self.chunk_type() {
+            ChunkType::RunLength => Some(((self.0 >> 14) & 0x01) as u8),
+            _ => None,
+        }
+    }
+
+    // value returns the run length of a run length chunk, or the bits of a
+    // bit vector chunk
+    pub fn value(&self) -> u16 {
+        match self.chunk_type() {
+            ChunkType::RunLength => self.0 & 0x3FFF,
+            ChunkType::BitVector => self.0 & 0x7FFF,
+            ChunkType::TerminatingNull => 0,
+        }
+    }
+}
+
+// RLEReportBlock is the layout shared by the Loss RLE and Duplicate RLE report
+// blocks, RFC 3611 4.1 and 4.2
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct RLEReportBlock {
+    // Only packets whose sequence number is a multiple of 2^t are reported
+    pub t: u8,
+    // SSRC of the RTP source being reported upon
+    pub ssrc: u32,
+    // First sequence number reported
+    pub begin_seq: u16,
+    // Last sequence number reported plus one
+    pub end_seq: u16,
+    pub chunks: Vec<Chunk>,
+}
+
+impl RLEReportBlock {
+    pub fn size(&self) -> usize {
+        let chunks_length = self.chunks.len() * CHUNK_LENGTH;
+        RLE_FIXED_LENGTH + chunks_length + chunks_length % 4
+    }
+
+    // Marshal encodes the RLEReportBlock in binary as a block of block_type,
+    // a terminating null chunk is appended to fill the last 32 bits
+    pub fn marshal<W: Write>(&self, block_type: u8, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=1|2    | rsvd. |   T   |         block length          |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        SSRC of source                         |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |          begin_seq            |             end_seq           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |          chunk 1              |             chunk 2           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * :                              ...                              :
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        if block_type != BLOCK_TYPE_LOSS_RLE && block_type != BLOCK_TYPE_DUPLICATE_RLE {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+        if self.t > 0x0F {
+            return Err(ERR_INVALID_THINNING.clone());
+        }
+
+        let header = XRHeader::new(block_type, self.t, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.ssrc)?;
+        writer.write_u16::<BigEndian>(self.begin_seq)?;
+        writer.write_u16::<BigEndian>(self.end_seq)?;
+        for chunk in &self.chunks {
+            writer.write_u16::<BigEndian>(chunk.0)?;
+        }
+        if !self.chunks.len().is_multiple_of(2) {
+            writer.write_u16::<BigEndian>(0)?;
+        }
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the RLEReportBlock following header from binary,
+    // a terminating null chunk filling the last 32 bits is dropped
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_LOSS_RLE && header.block_type != BLOCK_TYPE_DUPLICATE_RLE
+        {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+
+        let block_size = (header.block_length as usize + 1) * 4;
+        if block_size < RLE_FIXED_LENGTH {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let ssrc = reader.read_u32::<BigEndian>()?;
+        let begin_seq = reader.read_u16::<BigEndian>()?;
+        let end_seq = reader.read_u16::<BigEndian>()?;
+
+        let mut chunks = vec![];
+        for _ in 0..(block_size - RLE_FIXED_LENGTH) / CHUNK_LENGTH {
+            chunks.push(Chunk(reader.read_u16::<BigEndian>()?));
+        }
+        if chunks.len() % 2 == 0 && chunks.last() == Some(&Chunk(0)) {
+            chunks.pop();
+        }
+
+        Ok(RLEReportBlock {
+            t: header.type_specific & 0x0F,
+            ssrc,
+            begin_seq,
+            end_seq,
+            chunks,
+        })
+    }
+}
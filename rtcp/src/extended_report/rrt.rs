@@ -0,0 +1,66 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const RRT_LENGTH: usize = XR_HEADER_LENGTH + 8;
+
+// ReceiverReferenceTimeReportBlock carries the wallclock time of a receiver,
+// letting receive-only endpoints take part in round trip time measurements,
+// RFC 3611 4.4
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ReceiverReferenceTimeReportBlock {
+    // 64 bit NTP timestamp of the time this report was sent
+    pub ntp_timestamp: u64,
+}
+
+impl ReceiverReferenceTimeReportBlock {
+    pub fn size(&self) -> usize {
+        RRT_LENGTH
+    }
+
+    // last_rr returns the middle 32 bits of the NTP timestamp, to be echoed
+    // by DLRR sub-blocks answering this report
+    pub fn last_rr(&self) -> u32 {
+        (self.ntp_timestamp >> 16) as u32
+    }
+
+    // Marshal encodes the ReceiverReferenceTimeReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=4      |   reserved    |       block length = 2        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |              NTP timestamp, most significant word             |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |             NTP timestamp, least significant word             |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        let header = XRHeader::new(BLOCK_TYPE_RECEIVER_REFERENCE_TIME, 0, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_u64::<BigEndian>(self.ntp_timestamp)?;
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the ReceiverReferenceTimeReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_RECEIVER_REFERENCE_TIME {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+        if (header.block_length as usize + 1) * 4 != RRT_LENGTH {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let ntp_timestamp = reader.read_u64::<BigEndian>()?;
+
+        Ok(ReceiverReferenceTimeReportBlock { ntp_timestamp })
+    }
+}
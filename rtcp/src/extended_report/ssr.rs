@@ -0,0 +1,157 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const SSR_LENGTH: usize = XR_HEADER_LENGTH + 36;
+
+const LOSS_REPORT_FLAG: u8 = 0x80;
+const DUPLICATE_REPORT_FLAG: u8 = 0x40;
+const JITTER_REPORT_FLAG: u8 = 0x20;
+const TTL_OR_HOP_LIMIT_SHIFT: u8 = 3;
+const TTL_OR_HOP_LIMIT_MASK: u8 = 0x03;
+
+// TTLorHopLimitType tells if the TTL or hop limit fields of a
+// StatisticsSummaryReportBlock are set, and for which IP version
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum TTLorHopLimitType {
+    #[default]
+    Missing = 0,
+    IPv4 = 1,
+    IPv6 = 2,
+}
+
+// StatisticsSummaryReportBlock summarizes the reception of a source over a
+// sequence number interval, RFC 3611 4.6
+//
+// Fields are only meaningful when the matching flag is set.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct StatisticsSummaryReportBlock {
+    pub loss_reports: bool,
+    pub duplicate_reports: bool,
+    pub jitter_reports: bool,
+    pub ttl_or_hop_limit: TTLorHopLimitType,
+    // SSRC of the RTP source being reported upon
+    pub ssrc: u32,
+    // First sequence number reported
+    pub begin_seq: u16,
+    // Last sequence number reported plus one
+    pub end_seq: u16,
+    pub lost_packets: u32,
+    pub dup_packets: u32,
+    // Jitter in RTP timestamp units
+    pub min_jitter: u32,
+    pub max_jitter: u32,
+    pub mean_jitter: u32,
+    pub dev_jitter: u32,
+    pub min_ttl_or_hl: u8,
+    pub max_ttl_or_hl: u8,
+    pub mean_ttl_or_hl: u8,
+    pub dev_ttl_or_hl: u8,
+}
+
+impl StatisticsSummaryReportBlock {
+    pub fn size(&self) -> usize {
+        SSR_LENGTH
+    }
+
+    // Marshal encodes the StatisticsSummaryReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=6      |L|D|J|ToH|rsvd.|       block length = 9        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        SSRC of source                         |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |          begin_seq            |             end_seq           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        lost_packets                           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        dup_packets                            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                         min_jitter                            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                         max_jitter                            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                         mean_jitter                           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                         dev_jitter                            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * | min_ttl_or_hl | max_ttl_or_hl |mean_ttl_or_hl | dev_ttl_or_hl |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        let mut type_specific = (self.ttl_or_hop_limit as u8) << TTL_OR_HOP_LIMIT_SHIFT;
+        if self.loss_reports {
+            type_specific |= LOSS_REPORT_FLAG;
+        }
+        if self.duplicate_reports {
+            type_specific |= DUPLICATE_REPORT_FLAG;
+        }
+        if self.jitter_reports {
+            type_specific |= JITTER_REPORT_FLAG;
+        }
+
+        let header = XRHeader::new(BLOCK_TYPE_STATISTICS_SUMMARY, type_specific, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.ssrc)?;
+        writer.write_u16::<BigEndian>(self.begin_seq)?;
+        writer.write_u16::<BigEndian>(self.end_seq)?;
+        writer.write_u32::<BigEndian>(self.lost_packets)?;
+        writer.write_u32::<BigEndian>(self.dup_packets)?;
+        writer.write_u32::<BigEndian>(self.min_jitter)?;
+        writer.write_u32::<BigEndian>(self.max_jitter)?;
+        writer.write_u32::<BigEndian>(self.mean_jitter)?;
+        writer.write_u32::<BigEndian>(self.dev_jitter)?;
+        writer.write_u8(self.min_ttl_or_hl)?;
+        writer.write_u8(self.max_ttl_or_hl)?;
+        writer.write_u8(self.mean_ttl_or_hl)?;
+        writer.write_u8(self.dev_ttl_or_hl)?;
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the StatisticsSummaryReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_STATISTICS_SUMMARY {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+        if (header.block_length as usize + 1) * 4 != SSR_LENGTH {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let ttl_or_hop_limit =
+            match (header.type_specific >> TTL_OR_HOP_LIMIT_SHIFT) & TTL_OR_HOP_LIMIT_MASK {
+                0 => TTLorHopLimitType::Missing,
+                1 => TTLorHopLimitType::IPv4,
+                2 => TTLorHopLimitType::IPv6,
+                _ => return Err(ERR_INVALID_TTL_OR_HOP_LIMIT_TYPE.clone()),
+            };
+
+        Ok(StatisticsSummaryReportBlock {
+            loss_reports: header.type_specific & LOSS_REPORT_FLAG != 0,
+            duplicate_reports: header.type_specific & DUPLICATE_REPORT_FLAG != 0,
+            jitter_reports: header.type_specific & JITTER_REPORT_FLAG != 0,
+            ttl_or_hop_limit,
+            ssrc: reader.read_u32::<BigEndian>()?,
+            begin_seq: reader.read_u16::<BigEndian>()?,
+            end_seq: reader.read_u16::<BigEndian>()?,
+            lost_packets: reader.read_u32::<BigEndian>()?,
+            dup_packets: reader.read_u32::<BigEndian>()?,
+            min_jitter: reader.read_u32::<BigEndian>()?,
+            max_jitter: reader.read_u32::<BigEndian>()?,
+            mean_jitter: reader.read_u32::<BigEndian>()?,
+            dev_jitter: reader.read_u32::<BigEndian>()?,
+            min_ttl_or_hl: reader.read_u8()?,
+            max_ttl_or_hl: reader.read_u8()?,
+            mean_ttl_or_hl: reader.read_u8()?,
+            dev_ttl_or_hl: reader.read_u8()?,
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use super::*;
+
+// UnknownReportBlock is a report block of a type not implemented, it is kept
+// as is so it can be passed through
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct UnknownReportBlock {
+    pub block_type: u8,
+    pub type_specific: u8,
+    // Contents of the block following its header, a multiple of 32 bits
+    pub contents: Vec<u8>,
+}
+
+impl UnknownReportBlock {
+    pub fn size(&self) -> usize {
+        XR_HEADER_LENGTH + self.contents.len()
+    }
+
+    // Marshal encodes the UnknownReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let header = XRHeader::new(self.block_type, self.type_specific, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_all(&self.contents)?;
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the UnknownReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        let mut contents = vec![0u8; header.block_length as usize * 4];
+        reader.read_exact(&mut contents)?;
+
+        Ok(UnknownReportBlock {
+            block_type: header.block_type,
+            type_specific: header.type_specific,
+            contents,
+        })
+    }
+}
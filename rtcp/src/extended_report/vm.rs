@@ -0,0 +1,165 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+const VM_LENGTH: usize = XR_HEADER_LENGTH + 32;
+
+// VoIPMetricsReportBlock reports metrics for monitoring voice over IP calls,
+// RFC 3611 4.7
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct VoIPMetricsReportBlock {
+    // SSRC of the RTP source being reported upon
+    pub ssrc: u32,
+    // Packet loss and discard rates, as fixed point numbers with the binary
+    // point at the left edge of the field
+    pub loss_rate: u8,
+    pub discard_rate: u8,
+    pub burst_density: u8,
+    pub gap_density: u8,
+    // Mean burst and gap durations in milliseconds
+    pub burst_duration: u16,
+    pub gap_duration: u16,
+    // Round trip and end system delays in milliseconds
+    pub round_trip_delay: u16,
+    pub end_system_delay: u16,
+    // Signal and noise levels in dBm, as signed integers
+    pub signal_level: i8,
+    pub noise_level: i8,
+    // Residual echo return loss in dB
+    pub rerl: u8,
+    // Gap threshold
+    pub gmin: u8,
+    pub r_factor: u8,
+    pub ext_r_factor: u8,
+    // MOS scores multiplied by 10
+    pub mos_lq: u8,
+    pub mos_cq: u8,
+    // Packet loss concealment and jitter buffer configuration
+    pub rx_config: u8,
+    // Jitter buffer delays in milliseconds
+    pub jb_nominal: u16,
+    pub jb_maximum: u16,
+    pub jb_abs_max: u16,
+}
+
+impl VoIPMetricsReportBlock {
+    pub fn size(&self) -> usize {
+        VM_LENGTH
+    }
+
+    // Marshal encodes the VoIPMetricsReportBlock in binary
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     BT=7      |   reserved    |       block length = 8        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                        SSRC of source                         |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |   loss rate   | discard rate  | burst density |  gap density  |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |       burst duration          |         gap duration          |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |     round trip delay          |       end system delay        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * | signal level  |  noise level  |     RERL      |     Gmin      |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |   R factor    | ext. R factor |    MOS-LQ     |    MOS-CQ     |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |   RX config   |   reserved    |          JB nominal           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |          JB maximum           |          JB abs max           |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+
+        let header = XRHeader::new(BLOCK_TYPE_VOIP_METRICS, 0, self.size())?;
+        header.marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.ssrc)?;
+        writer.write_u8(self.loss_rate)?;
+        writer.write_u8(self.discard_rate)?;
+        writer.write_u8(self.burst_density)?;
+        writer.write_u8(self.gap_density)?;
+        writer.write_u16::<BigEndian>(self.burst_duration)?;
+        writer.write_u16::<BigEndian>(self.gap_duration)?;
+        writer.write_u16::<BigEndian>(self.round_trip_delay)?;
+        writer.write_u16::<BigEndian>(self.end_system_delay)?;
+        writer.write_i8(self.signal_level)?;
+        writer.write_i8(self.noise_level)?;
+        writer.write_u8(self.rerl)?;
+        writer.write_u8(self.gmin)?;
+        writer.write_u8(self.r_factor)?;
+        writer.write_u8(self.ext_r_factor)?;
+        writer.write_u8(self.mos_lq)?;
+        writer.write_u8(self.mos_cq)?;
+        writer.write_u8(self.rx_config)?;
+        writer.write_u8(0)?;
+        writer.write_u16::<BigEndian>(self.jb_nominal)?;
+        writer.write_u16::<BigEndian>(self.jb_maximum)?;
+        writer.write_u16::<BigEndian>(self.jb_abs_max)?;
+
+        Ok(())
+    }
+
+    // Unmarshal decodes the VoIPMetricsReportBlock following header from binary
+    pub fn unmarshal<R: Read>(header: &XRHeader, reader: &mut R) -> Result<Self, Error> {
+        if header.block_type != BLOCK_TYPE_VOIP_METRICS {
+            return Err(ERR_WRONG_BLOCK_TYPE.clone());
+        }
+        if (header.block_length as usize + 1) * 4 != VM_LENGTH {
+            return Err(ERR_INVALID_BLOCK_SIZE.clone());
+        }
+
+        let ssrc = reader.read_u32::<BigEndian>()?;
+        let loss_rate = reader.read_u8()?;
+        let discard_rate = reader.read_u8()?;
+        let burst_density = reader.read_u8()?;
+        let gap_density = reader.read_u8()?;
+        let burst_duration = reader.read_u16::<BigEndian>()?;
+        let gap_duration = reader.read_u16::<BigEndian>()?;
+        let round_trip_delay = reader.read_u16::<BigEndian>()?;
+        let end_system_delay = reader.read_u16::<BigEndian>()?;
+        let signal_level = reader.read_i8()?;
+        let noise_level = reader.read_i8()?;
+        let rerl = reader.read_u8()?;
+        let gmin = reader.read_u8()?;
+        let r_factor = reader.read_u8()?;
+        let ext_r_factor = reader.read_u8()?;
+        let mos_lq = reader.read_u8()?;
+        let mos_cq = reader.read_u8()?;
+        let rx_config = reader.read_u8()?;
+        let _reserved = reader.read_u8()?;
+        let jb_nominal = reader.read_u16::<BigEndian>()?;
+        let jb_maximum = reader.read_u16::<BigEndian>()?;
+        let jb_abs_max = reader.read_u16::<BigEndian>()?;
+
+        Ok(VoIPMetricsReportBlock {
+            ssrc,
+            loss_rate,
+            discard_rate,
+            burst_density,
+            gap_density,
+            burst_duration,
+            gap_duration,
+            round_trip_delay,
+            end_system_delay,
+            signal_level,
+            noise_level,
+            rerl,
+            gmin,
+            r_factor,
+            ext_r_factor,
+            mos_lq,
+            mos_cq,
+            rx_config,
+            jb_nominal,
+            jb_maximum,
+            jb_abs_max,
+        })
+    }
+}
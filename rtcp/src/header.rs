@@ -20,9 +20,10 @@ pub enum PacketType {
     ReceiverReport = 201,            // RFC 3550, 6.4.2
     SourceDescription = 202,         // RFC 3550, 6.5
     Goodbye = 203,                   // RFC 3550, 6.6
-    ApplicationDefined = 204,        // RFC 3550, 6.7 (unimplemented)
+    ApplicationDefined = 204,        // RFC 3550, 6.7
     TransportSpecificFeedback = 205, // RFC 4585, 6051
     PayloadSpecificFeedback = 206,   // RFC 4585, 6.3
+    ExtendedReport = 207,            // RFC 3611
 }
 
 impl Default for PacketType {
@@ -37,6 +38,8 @@ pub const FORMAT_PLI: u8 = 1;
 pub const FORMAT_FIR: u8 = 4;
 pub const FORMAT_TLN: u8 = 1;
 pub const FORMAT_RRR: u8 = 5;
+pub const FORMAT_TMMBR: u8 = 3;
+pub const FORMAT_TMMBN: u8 = 4;
 pub const FORMAT_REMB: u8 = 15;
 //https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#page-5
 pub const FORMAT_TCC: u8 = 15;
@@ -52,6 +55,7 @@ impl fmt::Display for PacketType {
             PacketType::ApplicationDefined => "APP",
             PacketType::TransportSpecificFeedback => "TSFB",
             PacketType::PayloadSpecificFeedback => "PSFB",
+            PacketType::ExtendedReport => "XR",
         };
         write!(f, "{}", s)
     }
@@ -64,9 +68,10 @@ impl From<u8> for PacketType {
             201 => PacketType::ReceiverReport,            // RFC 3550, 6.4.2
             202 => PacketType::SourceDescription,         // RFC 3550, 6.5
             203 => PacketType::Goodbye,                   // RFC 3550, 6.6
-            204 => PacketType::ApplicationDefined,        // RFC 3550, 6.7 (unimplemented)
+            204 => PacketType::ApplicationDefined,        // RFC 3550, 6.7
             205 => PacketType::TransportSpecificFeedback, // RFC 4585, 6051
             206 => PacketType::PayloadSpecificFeedback,   // RFC 4585, 6.3
+            207 => PacketType::ExtendedReport,            // RFC 3611
             _ => PacketType::Unsupported,
         }
     }
@@ -4,8 +4,10 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod application_defined;
 pub mod compound_packet;
 pub mod errors;
+pub mod extended_report;
 pub mod full_intra_request;
 pub mod goodbye;
 pub mod header;
@@ -19,6 +21,7 @@ pub mod reception_report;
 pub mod sender_report;
 pub mod slice_loss_indication;
 pub mod source_description;
+pub mod temporary_maximum_media_stream_bitrate;
 pub mod transport_layer_cc;
 pub mod transport_layer_nack;
 mod util;
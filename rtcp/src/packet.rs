@@ -2,8 +2,10 @@ use std::io::{BufReader, Read, Write};
 
 use util::Error;
 
+use super::application_defined::*;
 use super::compound_packet::*;
 use super::errors::*;
+use super::extended_report::*;
 use super::full_intra_request::*;
 use super::goodbye::*;
 use super::header::*;
@@ -15,6 +17,7 @@ use super::receiver_report::*;
 use super::sender_report::*;
 use super::slice_loss_indication::*;
 use super::source_description::*;
+use super::temporary_maximum_media_stream_bitrate::*;
 use super::transport_layer_cc::*;
 use super::transport_layer_nack::*;
 
@@ -28,11 +31,15 @@ pub enum Packet {
     ReceiverReport(ReceiverReport),
     SourceDescription(SourceDescription),
     Goodbye(Goodbye),
+    ApplicationDefined(ApplicationDefined),
+    ExtendedReport(ExtendedReport),
     RawPacket(RawPacket),
 
     TransportLayerNack(TransportLayerNack),
     RapidResynchronizationRequest(RapidResynchronizationRequest),
     TransportLayerCC(TransportLayerCC),
+    TemporaryMaximumMediaStreamBitrateRequest(TemporaryMaximumMediaStreamBitrateRequest),
+    TemporaryMaximumMediaStreamBitrateNotification(TemporaryMaximumMediaStreamBitrateNotification),
 
     PictureLossIndication(PictureLossIndication),
     SliceLossIndication(SliceLossIndication),
@@ -49,11 +56,15 @@ impl Packet {
             Packet::ReceiverReport(p) => p.marshal(writer)?,
             Packet::SourceDescription(p) => p.marshal(writer)?,
             Packet::Goodbye(p) => p.marshal(writer)?,
+            Packet::ApplicationDefined(p) => p.marshal(writer)?,
+            Packet::ExtendedReport(p) => p.marshal(writer)?,
             Packet::RawPacket(p) => p.marshal(writer)?,
 
             Packet::TransportLayerNack(p) => p.marshal(writer)?,
             Packet::RapidResynchronizationRequest(p) => p.marshal(writer)?,
             Packet::TransportLayerCC(p) => p.marshal(writer)?,
+            Packet::TemporaryMaximumMediaStreamBitrateRequest(p) => p.marshal(writer)?,
+            Packet::TemporaryMaximumMediaStreamBitrateNotification(p) => p.marshal(writer)?,
 
             Packet::PictureLossIndication(p) => p.marshal(writer)?,
             Packet::SliceLossIndication(p) => p.marshal(writer)?,
@@ -71,11 +82,15 @@ impl Packet {
             Packet::ReceiverReport(p) => p.destination_ssrc(),
             Packet::SourceDescription(p) => p.destination_ssrc(),
             Packet::Goodbye(p) => p.destination_ssrc(),
+            Packet::ApplicationDefined(p) => p.destination_ssrc(),
+            Packet::ExtendedReport(p) => p.destination_ssrc(),
             Packet::RawPacket(p) => p.destination_ssrc(),
 
             Packet::TransportLayerNack(p) => p.destination_ssrc(),
             Packet::RapidResynchronizationRequest(p) => p.destination_ssrc(),
             Packet::TransportLayerCC(p) => p.destination_ssrc(),
+            Packet::TemporaryMaximumMediaStreamBitrateRequest(p) => p.destination_ssrc(),
+            Packet::TemporaryMaximumMediaStreamBitrateNotification(p) => p.destination_ssrc(),
 
             Packet::PictureLossIndication(p) => p.destination_ssrc(),
             Packet::SliceLossIndication(p) => p.destination_ssrc(),
@@ -141,6 +156,12 @@ fn unmarshaler<R: Read>(reader: &mut R, header: &Header) -> Result<Packet, Error
             SourceDescription::unmarshal(reader)?,
         )),
         PacketType::Goodbye => Ok(Packet::Goodbye(Goodbye::unmarshal(reader)?)),
+        PacketType::ApplicationDefined => Ok(Packet::ApplicationDefined(
+            ApplicationDefined::unmarshal(reader)?,
+        )),
+        PacketType::ExtendedReport => {
+            Ok(Packet::ExtendedReport(ExtendedReport::unmarshal(reader)?))
+        }
         PacketType::TransportSpecificFeedback => match header.count {
             FORMAT_TLN => Ok(Packet::TransportLayerNack(TransportLayerNack::unmarshal(
                 reader,
@@ -151,6 +172,12 @@ fn unmarshaler<R: Read>(reader: &mut R, header: &Header) -> Result<Packet, Error
             FORMAT_TCC => Ok(Packet::TransportLayerCC(TransportLayerCC::unmarshal(
                 reader,
             )?)),
+            FORMAT_TMMBR => Ok(Packet::TemporaryMaximumMediaStreamBitrateRequest(
+                TemporaryMaximumMediaStreamBitrateRequest::unmarshal(reader)?,
+            )),
+            FORMAT_TMMBN => Ok(Packet::TemporaryMaximumMediaStreamBitrateNotification(
+                TemporaryMaximumMediaStreamBitrateNotification::unmarshal(reader)?,
+            )),
             _ => Ok(Packet::RawPacket(RawPacket::unmarshal(reader)?)),
         },
         PacketType::PayloadSpecificFeedback => match header.count {
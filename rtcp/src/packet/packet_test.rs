@@ -131,3 +131,137 @@ fn test_packet_invalid_header_length() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_packet_unmarshal_application_defined() -> Result<(), Error> {
+    let data = vec![
+        // v=2, p=0, subtype=3, APP, len=3
+        0x83, 0xcc, 0x0, 0x3, // ssrc=0x902f9e2e
+        0x90, 0x2f, 0x9e, 0x2e, // name=TELE
+        0x54, 0x45, 0x4c, 0x45, // data
+        0x1, 0x2, 0x3, 0x4,
+    ];
+
+    let packet = unmarshal(data.as_slice())?;
+    if let Packet::ApplicationDefined(app) = &packet {
+        let want = ApplicationDefined {
+            sub_type: 3,
+            sender_ssrc: 0x902f9e2e,
+            name: "TELE".to_string(),
+            data: vec![0x1, 0x2, 0x3, 0x4],
+        };
+        assert_eq!(*app, want, "Unmarshal APP got {:?}, want {:?}", app, want);
+    } else {
+        panic!("want ApplicationDefined, got {:?}", packet);
+    }
+    assert_eq!(packet.destination_ssrc(), vec![0x902f9e2e]);
+
+    let mut actual: Vec<u8> = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(actual.as_mut());
+        packet.marshal(&mut writer)?;
+    }
+    assert_eq!(actual, data);
+
+    Ok(())
+}
+
+#[test]
+fn test_packet_unmarshal_extended_report() -> Result<(), Error> {
+    let data = vec![
+        // v=2, p=0, XR, len=8
+        0x80, 0xcf, 0x0, 0x8, // ssrc=0x902f9e2e
+        0x90, 0x2f, 0x9e, 0x2e, // Receiver Reference Time, len=2
+        0x4, 0x0, 0x0, 0x2, // ntp timestamp
+        0x1, 0x2, 0x3, 0x4, //
+        0x5, 0x6, 0x7, 0x8, // DLRR, len=3
+        0x5, 0x0, 0x0, 0x3, // ssrc=0xbc5e9a40
+        0xbc, 0x5e, 0x9a, 0x40, // last_rr
+        0x3, 0x4, 0x5, 0x6, // dlrr
+        0x0, 0x1, 0x0, 0x0,
+    ];
+
+    let packet = unmarshal(data.as_slice())?;
+    if let Packet::ExtendedReport(xr) = &packet {
+        let want = ExtendedReport {
+            sender_ssrc: 0x902f9e2e,
+            reports: vec![
+                ReportBlock::ReceiverReferenceTime(ReceiverReferenceTimeReportBlock {
+                    ntp_timestamp: 0x0102030405060708,
+                }),
+                ReportBlock::DLRR(DLRRReportBlock {
+                    reports: vec![DLRRReport {
+                        ssrc: 0xbc5e9a40,
+                        last_rr: 0x03040506,
+                        dlrr: 0x00010000,
+                    }],
+                }),
+            ],
+        };
+        assert_eq!(*xr, want, "Unmarshal XR got {:?}, want {:?}", xr, want);
+    } else {
+        panic!("want ExtendedReport, got {:?}", packet);
+    }
+    assert_eq!(packet.destination_ssrc(), vec![0xbc5e9a40]);
+
+    let mut actual: Vec<u8> = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(actual.as_mut());
+        packet.marshal(&mut writer)?;
+    }
+    assert_eq!(actual, data);
+
+    Ok(())
+}
+
+#[test]
+fn test_packet_unmarshal_temporary_maximum_media_stream_bitrate() -> Result<(), Error> {
+    let data = vec![
+        // v=2, p=0, FMT=3, TSFB, len=4
+        0x83, 0xcd, 0x0, 0x4, // sender=0x902f9e2e
+        0x90, 0x2f, 0x9e, 0x2e, // media=0
+        0x0, 0x0, 0x0, 0x0, // ssrc=0xbc5e9a40
+        0xbc, 0x5e, 0x9a, 0x40, // exp=3, mantissa=125000, overhead=40
+        0x0f, 0xd0, 0x90, 0x28, // v=2, p=0, FMT=4, TSFB, len=4
+        0x84, 0xcd, 0x0, 0x4, // sender=0xbc5e9a40
+        0xbc, 0x5e, 0x9a, 0x40, // media=0
+        0x0, 0x0, 0x0, 0x0, // ssrc=0x902f9e2e
+        0x90, 0x2f, 0x9e, 0x2e, // exp=3, mantissa=125000, overhead=40
+        0x0f, 0xd0, 0x90, 0x28,
+    ];
+
+    let packet = unmarshal(data.as_slice())?;
+    if let Packet::CompoundPacket(compound) = &packet {
+        assert_eq!(compound.0.len(), 2);
+        if let Packet::TemporaryMaximumMediaStreamBitrateRequest(tmmbr) = &compound.0[0] {
+            assert_eq!(tmmbr.sender_ssrc, 0x902f9e2e);
+            assert_eq!(
+                tmmbr.entries,
+                vec![TMMBEntry {
+                    ssrc: 0xbc5e9a40,
+                    bitrate: 1_000_000,
+                    overhead: 40,
+                }]
+            );
+        } else {
+            panic!("want TMMBR, got {:?}", compound.0[0]);
+        }
+        if let Packet::TemporaryMaximumMediaStreamBitrateNotification(tmmbn) = &compound.0[1] {
+            assert_eq!(tmmbn.sender_ssrc, 0xbc5e9a40);
+            assert_eq!(compound.0[1].destination_ssrc(), vec![0x902f9e2e]);
+        } else {
+            panic!("want TMMBN, got {:?}", compound.0[1]);
+        }
+
+        let mut actual: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(actual.as_mut());
+            marshal(&compound.0, &mut writer)?;
+        }
+        assert_eq!(actual, data);
+    } else {
+        panic!("want CompoundPacket, got {:?}", packet);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,380 @@
+use std::fmt;
+use std::io::{Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use util::Error;
+
+use super::errors::*;
+use super::header::*;
+
+#[cfg(test)]
+mod temporary_maximum_media_stream_bitrate_test;
+
+const TMMB_OFFSET: usize = 8;
+const TMMB_ENTRY_LENGTH: usize = 8;
+
+const MANTISSA_BITS: u32 = 17;
+const MANTISSA_MASK: u32 = (1 << MANTISSA_BITS) - 1;
+const OVERHEAD_BITS: u32 = 9;
+pub const MAX_OVERHEAD: u16 = (1 << OVERHEAD_BITS) - 1;
+
+// A TMMBEntry is a (ssrc, bitrate, overhead) tuple, as carried by
+// TemporaryMaximumMediaStreamBitrateRequest and
+// TemporaryMaximumMediaStreamBitrateNotification.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct TMMBEntry {
+    // SSRC of the media sender in a request, of the owner of the tuple in a
+    // notification
+    pub ssrc: u32,
+    // Maximum total media bitrate in bits per second
+    pub bitrate: u64,
+    // Per packet overhead in bytes, beyond the RTP payload
+    pub overhead: u16,
+}
+
+impl TMMBEntry {
+    // max_net_bitrate returns the maximum bitrate left to RTP payloads when
+    // sending packet_rate packets per second
+    pub fn max_net_bitrate(&self, packet_rate: u64) -> u64 {
+        self.bitrate
+            .saturating_sub(packet_rate.saturating_mul(8 * self.overhead as u64))
+    }
+
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.overhead > MAX_OVERHEAD {
+            return Err(ERR_INVALID_OVERHEAD.clone());
+        }
+
+        // The mantissa only has 17 bits, the bitrate is truncated so it is
+        // never raised
+        let shift = 64 - self.bitrate.leading_zeros();
+        let (mantissa, exp) = if shift <= MANTISSA_BITS {
+            (self.bitrate as u32, 0)
+        } else {
+            (
+                (self.bitrate >> (shift - MANTISSA_BITS)) as u32,
+                shift - MANTISSA_BITS,
+            )
+        };
+
+        writer.write_u32::<BigEndian>(self.ssrc)?;
+        writer.write_u32::<BigEndian>(
+            exp << (MANTISSA_BITS + OVERHEAD_BITS)
+                | mantissa << OVERHEAD_BITS
+                | self.overhead as u32,
+        )?;
+
+        Ok(())
+    }
+
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let ssrc = reader.read_u32::<BigEndian>()?;
+        let b = reader.read_u32::<BigEndian>()?;
+
+        let exp = b >> (MANTISSA_BITS + OVERHEAD_BITS);
+        let mantissa = ((b >> OVERHEAD_BITS) & MANTISSA_MASK) as u64;
+        let bitrate = if mantissa == 0 {
+            0
+        } else if exp > 64 - MANTISSA_BITS {
+            // Truncated so it fits in a u64
+            u64::MAX
+        } else {
+            mantissa << exp
+        };
+
+        Ok(TMMBEntry {
+            ssrc,
+            bitrate,
+            overhead: (b & MAX_OVERHEAD as u32) as u16,
+        })
+    }
+}
+
+// bounding_set returns the entries of the bounding set of entries, see RFC 5104
+// Section 3.5.4.2. An entry limits the net bitrate of a sender for some packet
+// rate when it is in the bounding set, other entries can be ignored.
+//
+// Entries sharing the bitrate and overhead of a bounding tuple are all returned,
+// each owner has to be listed in the TemporaryMaximumMediaStreamBitrateNotification.
+pub fn bounding_set(entries: &[TMMBEntry]) -> Vec<TMMBEntry> {
+    // The lowest bitrate bounds packet rates near zero, the highest overhead
+    // of those bounds the most beyond
+    let mut current = match entries.iter().min_by(|a, b| {
+        a.bitrate
+            .cmp(&b.bitrate)
+            .then_with(|| b.overhead.cmp(&a.overhead))
+    }) {
+        Some(e) => e,
+        None => return vec![],
+    };
+
+    let mut bounding = vec![current];
+    loop {
+        // The next bounding tuple is the first one to cross the net bitrate
+        // of the current one as the packet rate increases
+        let mut next: Option<&TMMBEntry> = None;
+        for e in entries.iter().filter(|e| e.overhead > current.overhead) {
+            let closer = match next {
+                None => true,
+                Some(n) => {
+                    let e_crossing = e.bitrate.saturating_sub(current.bitrate) as u128
+                        * (n.overhead - current.overhead) as u128;
+                    let n_crossing = n.bitrate.saturating_sub(current.bitrate) as u128
+                        * (e.overhead - current.overhead) as u128;
+                    e_crossing < n_crossing || (e_crossing == n_crossing && e.overhead > n.overhead)
+                }
+            };
+            if closer {
+                next = Some(e);
+            }
+        }
+
+        match next {
+            Some(n) => {
+                bounding.push(n);
+                current = n;
+            }
+            None => break,
+        }
+    }
+
+    entries
+        .iter()
+        .filter(|e| {
+            bounding
+                .iter()
+                .any(|b| b.bitrate == e.bitrate && b.overhead == e.overhead)
+        })
+        .cloned()
+        .collect()
+}
+
+fn unmarshal_entries<R: Read>(header: &Header, reader: &mut R) -> Result<Vec<TMMBEntry>, Error> {
+    let fci_length = (header.length as usize * 4)
+        .checked_sub(TMMB_OFFSET)
+        .ok_or_else(|| ERR_PACKET_TOO_SHORT.clone())?;
+    if !fci_length.is_multiple_of(TMMB_ENTRY_LENGTH) {
+        return Err(ERR_PACKET_TOO_SHORT.clone());
+    }
+
+    let mut entries = vec![];
+    for _ in 0..fci_length / TMMB_ENTRY_LENGTH {
+        entries.push(TMMBEntry::unmarshal(reader)?);
+    }
+    Ok(entries)
+}
+
+fn fmt_entries(entries: &[TMMBEntry]) -> String {
+    let mut out = String::new();
+    for e in entries {
+        out += format!(" ({:x} {} {})", e.ssrc, e.bitrate, e.overhead).as_str();
+    }
+    out
+}
+
+// The TemporaryMaximumMediaStreamBitrateRequest (TMMBR) packet asks media
+// senders to cap their bitrate. See RFC 5104 Section 4.2.1.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct TemporaryMaximumMediaStreamBitrateRequest {
+    // SSRC of sender
+    pub sender_ssrc: u32,
+
+    // SSRC of media: always 0
+    // pub media_ssrc: u32,
+    pub entries: Vec<TMMBEntry>,
+}
+
+impl fmt::Display for TemporaryMaximumMediaStreamBitrateRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "TemporaryMaximumMediaStreamBitrateRequest {:x}{}",
+            self.sender_ssrc,
+            fmt_entries(&self.entries)
+        )
+    }
+}
+
+impl TemporaryMaximumMediaStreamBitrateRequest {
+    fn size(&self) -> usize {
+        HEADER_LENGTH + TMMB_OFFSET + self.entries.len() * TMMB_ENTRY_LENGTH
+    }
+
+    // Unmarshal decodes the TemporaryMaximumMediaStreamBitrateRequest from binary
+    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |V=2|P|  FMT=3  |   PT=205      |             length            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                  SSRC of packet sender                        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                  SSRC of media source = 0                     |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                              SSRC                             |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * | MxTBR Exp |  MxTBR Mantissa                 |Measured Overhead|
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * :                              ...                              :
+         */
+        let header = Header::unmarshal(reader)?;
+
+        if header.packet_type != PacketType::TransportSpecificFeedback
+            || header.count != FORMAT_TMMBR
+        {
+            return Err(ERR_WRONG_TYPE.clone());
+        }
+
+        let sender_ssrc = reader.read_u32::<BigEndian>()?;
+        let media_ssrc = reader.read_u32::<BigEndian>()?;
+        if media_ssrc != 0 {
+            return Err(ERR_BAD_MEDIA_SSRC.clone());
+        }
+
+        let entries = unmarshal_entries(&header, reader)?;
+
+        Ok(TemporaryMaximumMediaStreamBitrateRequest {
+            sender_ssrc,
+            entries,
+        })
+    }
+
+    // Header returns the Header associated with this packet.
+    pub fn header(&self) -> Header {
+        Header {
+            padding: false,
+            count: FORMAT_TMMBR,
+            packet_type: PacketType::TransportSpecificFeedback,
+            length: ((self.size() / 4) - 1) as u16,
+        }
+    }
+
+    // destination_ssrc returns an array of SSRC values that this packet refers to.
+    pub fn destination_ssrc(&self) -> Vec<u32> {
+        self.entries.iter().map(|e| e.ssrc).collect()
+    }
+
+    // Marshal encodes the packet in binary.
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.size() > (u16::MAX as usize + 1) * 4 {
+            return Err(ERR_PACKET_TOO_LONG.clone());
+        }
+
+        self.header().marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.sender_ssrc)?;
+        writer.write_u32::<BigEndian>(0)?; // media_ssrc always zero
+
+        for e in &self.entries {
+            e.marshal(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The TemporaryMaximumMediaStreamBitrateNotification (TMMBN) packet answers
+// TMMBR packets with the bounding set the media sender complies with.
+// See RFC 5104 Section 4.2.2.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct TemporaryMaximumMediaStreamBitrateNotification {
+    // SSRC of sender
+    pub sender_ssrc: u32,
+
+    // SSRC of media: always 0
+    // pub media_ssrc: u32,
+    pub entries: Vec<TMMBEntry>,
+}
+
+impl fmt::Display for TemporaryMaximumMediaStreamBitrateNotification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "TemporaryMaximumMediaStreamBitrateNotification {:x}{}",
+            self.sender_ssrc,
+            fmt_entries(&self.entries)
+        )
+    }
+}
+
+impl TemporaryMaximumMediaStreamBitrateNotification {
+    fn size(&self) -> usize {
+        HEADER_LENGTH + TMMB_OFFSET + self.entries.len() * TMMB_ENTRY_LENGTH
+    }
+
+    // Unmarshal decodes the TemporaryMaximumMediaStreamBitrateNotification from binary
+    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |V=2|P|  FMT=4  |   PT=205      |             length            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                  SSRC of packet sender                        |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                  SSRC of media source = 0                     |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |                              SSRC                             |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * | MxTBR Exp |  MxTBR Mantissa                 |Measured Overhead|
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * :                              ...                              :
+         */
+        let header = Header::unmarshal(reader)?;
+
+        if header.packet_type != PacketType::TransportSpecificFeedback
+            || header.count != FORMAT_TMMBN
+        {
+            return Err(ERR_WRONG_TYPE.clone());
+        }
+
+        let sender_ssrc = reader.read_u32::<BigEndian>()?;
+        let media_ssrc = reader.read_u32::<BigEndian>()?;
+        if media_ssrc != 0 {
+            return Err(ERR_BAD_MEDIA_SSRC.clone());
+        }
+
+        // An empty bounding set is notified without entries
+        let entries = unmarshal_entries(&header, reader)?;
+
+        Ok(TemporaryMaximumMediaStreamBitrateNotification {
+            sender_ssrc,
+            entries,
+        })
+    }
+
+    // Header returns the Header associated with this packet.
+    pub fn header(&self) -> Header {
+        Header {
+            padding: false,
+            count: FORMAT_TMMBN,
+            packet_type: PacketType::TransportSpecificFeedback,
+            length: ((self.size() / 4) - 1) as u16,
+        }
+    }
+
+    // destination_ssrc returns an array of SSRC values that this packet refers to.
+    pub fn destination_ssrc(&self) -> Vec<u32> {
+        self.entries.iter().map(|e| e.ssrc).collect()
+    }
+
+    // Marshal encodes the packet in binary.
+    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.size() > (u16::MAX as usize + 1) * 4 {
+            return Err(ERR_PACKET_TOO_LONG.clone());
+        }
+
+        self.header().marshal(writer)?;
+
+        writer.write_u32::<BigEndian>(self.sender_ssrc)?;
+        writer.write_u32::<BigEndian>(0)?; // media_ssrc always zero
+
+        for e in &self.entries {
+            e.marshal(writer)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use super::*;
+
+use std::io::{BufReader, BufWriter};
+
+use util::Error;
+
+#[test]
+fn test_temporary_maximum_media_stream_bitrate_request_unmarshal() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "valid",
+            vec![
+                // v=2, p=0, FMT=3, TSFB, len=4
+                0x83, 0xcd, 0x00, 0x04, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0
+                0x00, 0x00, 0x00, 0x00, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78, // exp=3, mantissa=125000, overhead=40
+                0x0f, 0xd0, 0x90, 0x28,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 0x902f9e2e,
+                entries: vec![TMMBEntry {
+                    ssrc: 0x12345678,
+                    bitrate: 1_000_000,
+                    overhead: 40,
+                }],
+            },
+            None,
+        ),
+        (
+            "saturated bitrate",
+            vec![
+                // v=2, p=0, FMT=3, TSFB, len=4
+                0x83, 0xcd, 0x00, 0x04, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0
+                0x00, 0x00, 0x00, 0x00, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78, // exp=63, mantissa=1, overhead=511
+                0xfc, 0x00, 0x03, 0xff,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 0x902f9e2e,
+                entries: vec![TMMBEntry {
+                    ssrc: 0x12345678,
+                    bitrate: u64::MAX,
+                    overhead: 511,
+                }],
+            },
+            None,
+        ),
+        (
+            "zero bitrate",
+            vec![
+                // v=2, p=0, FMT=3, TSFB, len=4
+                0x83, 0xcd, 0x00, 0x04, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0
+                0x00, 0x00, 0x00, 0x00, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78, // exp=63, mantissa=0, overhead=0
+                0xfc, 0x00, 0x00, 0x00,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 0x902f9e2e,
+                entries: vec![TMMBEntry {
+                    ssrc: 0x12345678,
+                    bitrate: 0,
+                    overhead: 0,
+                }],
+            },
+            None,
+        ),
+        (
+            "partial entry",
+            vec![
+                // v=2, p=0, FMT=3, TSFB, len=3
+                0x83, 0xcd, 0x00, 0x03, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0
+                0x00, 0x00, 0x00, 0x00, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest::default(),
+            Some(ERR_PACKET_TOO_SHORT.clone()),
+        ),
+        (
+            "non zero media ssrc",
+            vec![
+                // v=2, p=0, FMT=3, TSFB, len=4
+                0x83, 0xcd, 0x00, 0x04, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78, // exp=3, mantissa=125000, overhead=40
+                0x0f, 0xd0, 0x90, 0x28,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest::default(),
+            Some(ERR_BAD_MEDIA_SSRC.clone()),
+        ),
+        (
+            "wrong format",
+            vec![
+                // v=2, p=0, FMT=4, TSFB, len=4
+                0x84, 0xcd, 0x00, 0x04, // sender=0x902f9e2e
+                0x90, 0x2f, 0x9e, 0x2e, // media=0
+                0x00, 0x00, 0x00, 0x00, // ssrc=0x12345678
+                0x12, 0x34, 0x56, 0x78, // exp=3, mantissa=125000, overhead=40
+                0x0f, 0xd0, 0x90, 0x28,
+            ],
+            TemporaryMaximumMediaStreamBitrateRequest::default(),
+            Some(ERR_WRONG_TYPE.clone()),
+        ),
+    ];
+
+    for (name, data, want, want_error) in tests {
+        let mut reader = BufReader::new(data.as_slice());
+        let result = TemporaryMaximumMediaStreamBitrateRequest::unmarshal(&mut reader);
+        if let Some(err) = want_error {
+            if let Err(got) = result {
+                assert_eq!(
+                    got, err,
+                    "Unmarshal {} header: err = {}, want {}",
+                    name, got, err
+                );
+            } else {
+                panic!("want error in test {}", name);
+            }
+        } else {
+            if let Ok(got) = result {
+                assert_eq!(
+                    got, want,
+                    "Unmarshal {} header: got {:?}, want {:?}",
+                    name, got, want,
+                )
+            } else {
+                panic!("must no error in test {}", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_temporary_maximum_media_stream_bitrate_request_roundtrip() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "valid",
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 1,
+                entries: vec![
+                    TMMBEntry {
+                        ssrc: 2,
+                        bitrate: 100_000,
+                        overhead: 0,
+                    },
+                    TMMBEntry {
+                        ssrc: 3,
+                        bitrate: 3 << 40,
+                        overhead: MAX_OVERHEAD,
+                    },
+                ],
+            },
+            None,
+        ),
+        (
+            "overhead too large",
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 1,
+                entries: vec![TMMBEntry {
+                    ssrc: 2,
+                    bitrate: 100_000,
+                    overhead: 512,
+                }],
+            },
+            Some(ERR_INVALID_OVERHEAD.clone()),
+        ),
+        (
+            "too many entries",
+            TemporaryMaximumMediaStreamBitrateRequest {
+                sender_ssrc: 1,
+                entries: vec![TMMBEntry::default(); 32767],
+            },
+            Some(ERR_PACKET_TOO_LONG.clone()),
+        ),
+    ];
+
+    for (name, report, marshal_error) in tests {
+        let mut data: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(data.as_mut());
+            let result = report.marshal(&mut writer);
+            if let Some(err) = marshal_error {
+                if let Err(got) = result {
+                    assert_eq!(
+                        got, err,
+                        "marshal {} header: err = {}, want {}",
+                        name, got, err
+                    );
+                } else {
+                    panic!("want error in test {}", name);
+                }
+                continue;
+            } else {
+                assert!(result.is_ok(), "must no error in test {}", name);
+            }
+        }
+
+        let mut reader = BufReader::new(data.as_slice());
+        let decoded = TemporaryMaximumMediaStreamBitrateRequest::unmarshal(&mut reader)?;
+        assert_eq!(
+            decoded, report,
+            "{} header round trip: got {:?}, want {:?}",
+            name, decoded, report
+        )
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_temporary_maximum_media_stream_bitrate_notification_roundtrip() -> Result<(), Error> {
+    let tests = vec![
+        (
+            "valid",
+            TemporaryMaximumMediaStreamBitrateNotification {
+                sender_ssrc: 1,
+                entries: vec![TMMBEntry {
+                    ssrc: 2,
+                    bitrate: 1_000_000,
+                    overhead: 40,
+                }],
+            },
+        ),
+        (
+            "empty bounding set",
+            TemporaryMaximumMediaStreamBitrateNotification {
+                sender_ssrc: 1,
+                entries: vec![],
+            },
+        ),
+    ];
+
+    for (name, report) in tests {
+        let mut data: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(data.as_mut());
+            report.marshal(&mut writer)?;
+        }
+
+        let mut reader = BufReader::new(data.as_slice());
+        let decoded = TemporaryMaximumMediaStreamBitrateNotification::unmarshal(&mut reader)?;
+        assert_eq!(
+            decoded, report,
+            "{} header round trip: got {:?}, want {:?}",
+            name, decoded, report
+        )
+    }
+
+    let report = TemporaryMaximumMediaStreamBitrateNotification {
+        sender_ssrc: 1,
+        entries: vec![TMMBEntry::default(); 32767],
+    };
+    let mut data: Vec<u8> = vec![];
+    assert_eq!(report.marshal(&mut data), Err(ERR_PACKET_TOO_LONG.clone()));
+
+    Ok(())
+}
+
+#[test]
+fn test_tmmb_entry_bitrate_truncation() -> Result<(), Error> {
+    let tests = vec![
+        // Fits in the mantissa
+        (131_071, 131_071),
+        (131_072, 131_072),
+        // Low bits are dropped, never rounded up
+        (1_000_001, 1_000_000),
+        (0xFFFF_FFFF_FFFF_FFFF, 0xFFFF_8000_0000_0000),
+    ];
+
+    for (bitrate, want) in tests {
+        let entry = TMMBEntry {
+            ssrc: 1,
+            bitrate,
+            overhead: 0,
+        };
+        let mut data: Vec<u8> = vec![];
+        entry.marshal(&mut data)?;
+        let decoded = TMMBEntry::unmarshal(&mut data.as_slice())?;
+        assert_eq!(decoded.bitrate, want, "bitrate {}", bitrate);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bounding_set() {
+    let entry = |ssrc: u32, bitrate: u64, overhead: u16| TMMBEntry {
+        ssrc,
+        bitrate,
+        overhead,
+    };
+
+    let tests = vec![
+        ("empty", vec![], vec![]),
+        (
+            "single",
+            vec![entry(1, 1_000_000, 40)],
+            vec![entry(1, 1_000_000, 40)],
+        ),
+        (
+            "higher overhead crosses first",
+            vec![
+                entry(1, 1_000_000, 40),
+                entry(2, 1_200_000, 100),
+                entry(3, 2_000_000, 60),
+                entry(4, 1_000_000, 40),
+                entry(5, 3_000_000, 10),
+            ],
+            vec![
+                entry(1, 1_000_000, 40),
+                entry(2, 1_200_000, 100),
+                entry(4, 1_000_000, 40),
+            ],
+        ),
+        (
+            "three tuples bounding",
+            vec![
+                entry(1, 1_000_000, 40),
+                entry(2, 1_200_000, 100),
+                entry(6, 900_000, 0),
+            ],
+            vec![
+                entry(1, 1_000_000, 40),
+                entry(2, 1_200_000, 100),
+                entry(6, 900_000, 0),
+            ],
+        ),
+        (
+            "same bitrate keeps the highest overhead",
+            vec![entry(1, 1_000_000, 40), entry(2, 1_000_000, 20)],
+            vec![entry(1, 1_000_000, 40)],
+        ),
+    ];
+
+    for (name, entries, want) in tests {
+        let got = bounding_set(&entries);
+        assert_eq!(got, want, "{}: got {:?}, want {:?}", name, got, want);
+
+        // Every entry allows at least the net bitrate of the bounding set
+        for packet_rate in &[0, 100, 400, 1_000, 10_000] {
+            let bound = got.iter().map(|e| e.max_net_bitrate(*packet_rate)).min();
+            let min = entries
+                .iter()
+                .map(|e| e.max_net_bitrate(*packet_rate))
+                .min();
+            assert_eq!(bound, min, "{} at {} packets/s", name, packet_rate);
+        }
+    }
+}
+
+#[test]
+fn test_tmmb_entry_max_net_bitrate() {
+    let entry = TMMBEntry {
+        ssrc: 1,
+        bitrate: 1_000_000,
+        overhead: 40,
+    };
+    assert_eq!(entry.max_net_bitrate(0), 1_000_000);
+    assert_eq!(entry.max_net_bitrate(100), 968_000);
+    assert_eq!(entry.max_net_bitrate(10_000), 0);
+}
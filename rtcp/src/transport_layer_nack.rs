@@ -37,7 +37,7 @@ impl NackPair {
         while b != 0 {
             if (b & (1 << i)) != 0 {
                 b &= !(1 << i);
-                out.push(self.packet_id + i + 1);
+                out.push(self.packet_id.wrapping_add(i + 1));
             }
             i += 1;
         }
@@ -142,3 +142,19 @@ fn test_transport_layer_nack_roundtrip() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_nack_pair_packet_list() {
+    let pair = NackPair {
+        packet_id: 42,
+        lost_packets: 0b101,
+    };
+    assert_eq!(pair.packet_list(), vec![42, 43, 45]);
+
+    // Sequence numbers wrap around
+    let pair = NackPair {
+        packet_id: 65534,
+        lost_packets: 0b11,
+    };
+    assert_eq!(pair.packet_list(), vec![65534, 65535, 0]);
+}
@@ -0,0 +1,94 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use rtp::header::Header;
+use rtp::packet::Packet;
+use rtp::packet_ref::{PacketMut, PacketRef};
+
+fn raw_packet() -> Vec<u8> {
+    let mut packet = Packet::new(
+        Header {
+            version: 2,
+            marker: true,
+            payload_type: 96,
+            sequence_number: 27023,
+            timestamp: 3653407706,
+            ssrc: 476325762,
+            csrc: vec![0x11223344],
+            ..Default::default()
+        },
+        vec![0x98; 1200],
+    );
+    packet.header.set_extension(1, &[0xAA, 0xBB, 0xCC]).unwrap();
+    packet.header.set_extension(3, &[0x01, 0x02]).unwrap();
+
+    let mut raw = vec![];
+    packet.marshal(&mut raw).unwrap();
+    raw
+}
+
+fn benchmark_unmarshal(c: &mut Criterion) {
+    let raw = raw_packet();
+
+    c.bench_function("Packet::unmarshal", |b| {
+        b.iter(|| {
+            let mut reader = black_box(raw.as_slice());
+            Packet::unmarshal(&mut reader).unwrap()
+        })
+    });
+    c.bench_function("PacketRef::new", |b| {
+        b.iter(|| PacketRef::new(black_box(&raw)).unwrap())
+    });
+}
+
+fn benchmark_marshal(c: &mut Criterion) {
+    let raw = raw_packet();
+    let mut reader = raw.as_slice();
+    let packet = Packet::unmarshal(&mut reader).unwrap();
+
+    c.bench_function("Packet::marshal", |b| {
+        let mut out = Vec::with_capacity(raw.len());
+        b.iter(|| {
+            out.clear();
+            black_box(&packet).marshal(&mut out).unwrap();
+        })
+    });
+}
+
+// Rewriting the header of a received packet, as a forwarder does
+fn benchmark_rewrite(c: &mut Criterion) {
+    let raw = raw_packet();
+
+    c.bench_function("Packet rewrite", |b| {
+        let mut out = Vec::with_capacity(raw.len());
+        b.iter(|| {
+            let mut reader = black_box(raw.as_slice());
+            let mut packet = Packet::unmarshal(&mut reader).unwrap();
+            packet.header.ssrc = 0xDEADBEEF;
+            packet.header.sequence_number = packet.header.sequence_number.wrapping_add(1);
+            packet.header.timestamp = packet.header.timestamp.wrapping_add(3000);
+            packet.header.set_extension(3, &[0x03, 0x04]).unwrap();
+            out.clear();
+            packet.marshal(&mut out).unwrap();
+        })
+    });
+    c.bench_function("PacketMut rewrite", |b| {
+        let mut buf = raw.clone();
+        b.iter(|| {
+            let mut packet = PacketMut::new(black_box(&mut buf)).unwrap();
+            packet.set_ssrc(0xDEADBEEF);
+            let sequence_number = packet.as_packet_ref().sequence_number();
+            packet.set_sequence_number(sequence_number.wrapping_add(1));
+            let timestamp = packet.as_packet_ref().timestamp();
+            packet.set_timestamp(timestamp.wrapping_add(3000));
+            packet.set_extension(3, &[0x03, 0x04]).unwrap();
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    benchmark_unmarshal,
+    benchmark_marshal,
+    benchmark_rewrite
+);
+criterion_main!(benches);
@@ -0,0 +1,373 @@
+use crate::packetizer::{Depacketizer, Payloader};
+
+use std::io::Read;
+
+use util::Error;
+
+#[cfg(test)]
+mod av1_test;
+
+// OBU types, https://aomediacodec.github.io/av1-spec/#obu-header-semantics
+pub const OBU_TYPE_SEQUENCE_HEADER: u8 = 1;
+pub const OBU_TYPE_TEMPORAL_DELIMITER: u8 = 2;
+pub const OBU_TYPE_FRAME_HEADER: u8 = 3;
+pub const OBU_TYPE_TILE_GROUP: u8 = 4;
+pub const OBU_TYPE_METADATA: u8 = 5;
+pub const OBU_TYPE_FRAME: u8 = 6;
+pub const OBU_TYPE_REDUNDANT_FRAME_HEADER: u8 = 7;
+pub const OBU_TYPE_TILE_LIST: u8 = 8;
+pub const OBU_TYPE_PADDING: u8 = 15;
+
+const OBU_TYPE_SHIFT: u8 = 3;
+const OBU_TYPE_BITMASK: u8 = 0x0F;
+const OBU_EXTENSION_FLAG_BITMASK: u8 = 0x04;
+const OBU_HAS_SIZE_FIELD_BITMASK: u8 = 0x02;
+
+const AV1_AGGREGATION_HEADER_SIZE: usize = 1;
+const Z_BITMASK: u8 = 0x80;
+const Y_BITMASK: u8 = 0x40;
+const W_BITMASK: u8 = 0x30;
+const W_SHIFT: u8 = 4;
+const N_BITMASK: u8 = 0x08;
+// W can signal up to 3 OBU elements, the last one without length field
+const MAX_W_ELEMENTS: usize = 3;
+
+fn obu_type(header: u8) -> u8 {
+    (header >> OBU_TYPE_SHIFT) & OBU_TYPE_BITMASK
+}
+
+fn obu_header_size(header: u8) -> usize {
+    if header & OBU_EXTENSION_FLAG_BITMASK != 0 {
+        2
+    } else {
+        1
+    }
+}
+
+// leb128_size returns the number of bytes needed to encode value as LEB128
+fn leb128_size(mut value: usize) -> usize {
+    let mut size = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        size += 1;
+    }
+    size
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// read_leb128 returns the decoded value and the number of bytes consumed
+fn read_leb128(data: &[u8]) -> Result<(usize, usize), Error> {
+    let mut value = 0usize;
+    // At most 8 bytes are allowed by the AV1 specification
+    for (i, &b) in data.iter().enumerate().take(8) {
+        value |= ((b & 0x7F) as usize) << (i * 7);
+        if b & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(Error::new("AV1: invalid LEB128 value".to_string()))
+}
+
+// split_obus splits a temporal unit in low overhead bitstream format into
+// OBUs, returned without their obu_size field
+fn split_obus(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut obus = vec![];
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = data[offset];
+        let header_size = obu_header_size(header);
+        if offset + header_size > data.len() {
+            return Err(Error::new("AV1: OBU header is truncated".to_string()));
+        }
+
+        let (payload_offset, payload_size) = if header & OBU_HAS_SIZE_FIELD_BITMASK != 0 {
+            let (size, n) = read_leb128(&data[offset + header_size..])?;
+            (offset + header_size + n, size)
+        } else {
+            // Without a size field the OBU extends to the end of the temporal unit
+            (offset + header_size, data.len() - (offset + header_size))
+        };
+        if payload_offset + payload_size > data.len() {
+            return Err(Error::new(format!(
+                "AV1: OBU size {} exceeds temporal unit",
+                payload_size
+            )));
+        }
+
+        let mut obu = Vec::with_capacity(header_size + payload_size);
+        obu.push(header & !OBU_HAS_SIZE_FIELD_BITMASK);
+        obu.extend_from_slice(&data[offset + 1..offset + header_size]);
+        obu.extend_from_slice(&data[payload_offset..payload_offset + payload_size]);
+        obus.push(obu);
+
+        offset = payload_offset + payload_size;
+    }
+
+    Ok(obus)
+}
+
+// AV1PacketBuilder collects the OBU elements of a single RTP payload
+#[derive(Default)]
+struct AV1PacketBuilder {
+    elements: Vec<Vec<u8>>,
+    size: usize,
+    z: bool,
+}
+
+impl AV1PacketBuilder {
+    fn build(&mut self, y: bool, n: bool) -> Vec<u8> {
+        let elements = std::mem::take(&mut self.elements);
+        let w = if elements.len() <= MAX_W_ELEMENTS {
+            elements.len()
+        } else {
+            0
+        };
+
+        let mut header = (w as u8) << W_SHIFT;
+        if self.z {
+            header |= Z_BITMASK;
+        }
+        if y {
+            header |= Y_BITMASK;
+        }
+        if n {
+            header |= N_BITMASK;
+        }
+
+        let mut out = Vec::with_capacity(AV1_AGGREGATION_HEADER_SIZE + self.size);
+        out.push(header);
+        let count = elements.len();
+        for (i, element) in elements.into_iter().enumerate() {
+            // With W set the last element has no length field
+            if w == 0 || i + 1 != count {
+                write_leb128(&mut out, element.len());
+            }
+            out.extend_from_slice(&element);
+        }
+
+        self.size = 0;
+        self.z = y;
+        out
+    }
+}
+
+// AV1Payloader payloads AV1 temporal units
+//
+// Temporal delimiters and tile lists are dropped, the remaining OBUs are
+// aggregated and fragmented following the AV1 RTP payload format.
+pub struct AV1Payloader;
+
+impl Payloader for AV1Payloader {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+        let mut payload_data = vec![];
+        reader.read_to_end(&mut payload_data)?;
+
+        let mut payloads = vec![];
+        // An OBU element needs at least a length byte and a data byte
+        if payload_data.is_empty() || mtu <= (AV1_AGGREGATION_HEADER_SIZE + 2) as isize {
+            return Ok(payloads);
+        }
+        let max_size = mtu as usize - AV1_AGGREGATION_HEADER_SIZE;
+
+        let obus: Vec<Vec<u8>> = split_obus(&payload_data)?
+            .into_iter()
+            .filter(|obu| {
+                let t = obu_type(obu[0]);
+                t != OBU_TYPE_TEMPORAL_DELIMITER && t != OBU_TYPE_TILE_LIST
+            })
+            .collect();
+        // A sequence header starts a new coded video sequence
+        let new_sequence = obus
+            .iter()
+            .any(|obu| obu_type(obu[0]) == OBU_TYPE_SEQUENCE_HEADER);
+
+        let mut builder = AV1PacketBuilder::default();
+        for obu in &obus {
+            let mut offset = 0;
+            while offset < obu.len() {
+                let free = max_size - builder.size;
+                let mut n = std::cmp::min(obu.len() - offset, free.saturating_sub(1));
+                while n > 0 && leb128_size(n) + n > free {
+                    n -= 1;
+                }
+
+                if n == 0 {
+                    // The packet is full, continue the OBU in the next one
+                    let y = offset != 0;
+                    payloads.push(builder.build(y, new_sequence && payloads.is_empty()));
+                    continue;
+                }
+
+                builder.elements.push(obu[offset..offset + n].to_vec());
+                builder.size += leb128_size(n) + n;
+                offset += n;
+            }
+        }
+
+        if !builder.elements.is_empty() {
+            payloads.push(builder.build(false, new_sequence && payloads.is_empty()));
+        }
+
+        Ok(payloads)
+    }
+}
+
+// AV1Packet represents the AV1 aggregation header and the OBUs of a RTP packet
+//
+// OBU fragments are buffered across calls to depacketize, so a single
+// AV1Packet should be used for all packets of a stream.
+#[derive(Debug, Default, Clone)]
+pub struct AV1Packet {
+    // Aggregation header
+    pub z: bool, /* first OBU element continues an OBU of the previous packet */
+    pub y: bool, /* last OBU element continues in the next packet */
+    pub w: u8,   /* number of OBU elements, 0 if every element has a length field */
+    pub n: bool, /* first packet of a coded video sequence */
+
+    // Complete OBUs of this packet in low overhead bitstream format,
+    // every OBU carries its size as LEB128
+    pub payload: Vec<u8>,
+
+    // Set when a sequence header was completed by this packet
+    pub keyframe: bool,
+
+    fragment: Vec<u8>,
+}
+
+impl Depacketizer for AV1Packet {
+    fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let mut packet = vec![];
+        reader.read_to_end(&mut packet)?;
+        if packet.len() <= AV1_AGGREGATION_HEADER_SIZE {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        let header = packet[0];
+        self.z = header & Z_BITMASK != 0;
+        self.y = header & Y_BITMASK != 0;
+        self.w = (header & W_BITMASK) >> W_SHIFT;
+        self.n = header & N_BITMASK != 0;
+        self.payload.clear();
+        self.keyframe = false;
+
+        let elements = self.parse_elements(&packet[AV1_AGGREGATION_HEADER_SIZE..])?;
+        let count = elements.len();
+
+        if !self.z {
+            // The end of the buffered OBU was lost
+            self.fragment.clear();
+        }
+
+        for (i, element) in elements.into_iter().enumerate() {
+            let first = i == 0;
+            let last = i + 1 == count;
+
+            let obu = if first && self.z {
+                if self.fragment.is_empty() {
+                    // The start of this OBU was lost
+                    continue;
+                }
+                let mut obu = std::mem::take(&mut self.fragment);
+                obu.extend_from_slice(element);
+                obu
+            } else {
+                element.to_vec()
+            };
+
+            if last && self.y {
+                self.fragment = obu;
+            } else {
+                self.push_obu(&obu)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // A frame does not start with the continuation of an OBU
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.is_empty() {
+            false
+        } else {
+            payload[0] & Z_BITMASK == 0
+        }
+    }
+}
+
+impl AV1Packet {
+    fn parse_elements<'a>(&self, data: &'a [u8]) -> Result<Vec<&'a [u8]>, Error> {
+        let mut elements = vec![];
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let size = if self.w != 0 && elements.len() + 1 == self.w as usize {
+                // The last element of a W counted packet has no length field
+                data.len() - offset
+            } else {
+                let (size, n) = read_leb128(&data[offset..])?;
+                offset += n;
+                size
+            };
+
+            if size == 0 || offset + size > data.len() {
+                return Err(Error::new(format!(
+                    "AV1: invalid OBU element size {}",
+                    size
+                )));
+            }
+            elements.push(&data[offset..offset + size]);
+            offset += size;
+        }
+
+        if self.w != 0 && elements.len() != self.w as usize {
+            return Err(Error::new(format!(
+                "AV1: expected {} OBU elements, found {}",
+                self.w,
+                elements.len()
+            )));
+        }
+
+        Ok(elements)
+    }
+
+    // push_obu appends an OBU with its obu_size field to the payload
+    fn push_obu(&mut self, obu: &[u8]) -> Result<(), Error> {
+        let header = obu[0];
+        let header_size = obu_header_size(header);
+        if obu.len() < header_size {
+            return Err(Error::new("AV1: OBU header is truncated".to_string()));
+        }
+
+        let t = obu_type(header);
+        if t == OBU_TYPE_TEMPORAL_DELIMITER || t == OBU_TYPE_TILE_LIST {
+            return Ok(());
+        }
+        if t == OBU_TYPE_SEQUENCE_HEADER {
+            self.keyframe = true;
+        }
+
+        if header & OBU_HAS_SIZE_FIELD_BITMASK != 0 {
+            self.payload.extend_from_slice(obu);
+            return Ok(());
+        }
+
+        self.payload.push(header | OBU_HAS_SIZE_FIELD_BITMASK);
+        self.payload.extend_from_slice(&obu[1..header_size]);
+        write_leb128(&mut self.payload, obu.len() - header_size);
+        self.payload.extend_from_slice(&obu[header_size..]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+fn payload(mtu: isize, temporal_unit: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut pck = AV1Payloader;
+    let mut reader = BufReader::new(temporal_unit);
+    pck.payload(mtu, &mut reader)
+}
+
+fn depacketize(pck: &mut AV1Packet, raw: &[u8]) -> Result<(), Error> {
+    let mut reader = BufReader::new(raw);
+    pck.depacketize(&mut reader)
+}
+
+#[test]
+fn test_leb128() -> Result<(), Error> {
+    let tests: Vec<(usize, Vec<u8>)> = vec![
+        (0, vec![0x00]),
+        (5, vec![0x05]),
+        (127, vec![0x7F]),
+        (128, vec![0x80, 0x01]),
+        (300, vec![0xAC, 0x02]),
+        (16384, vec![0x80, 0x80, 0x01]),
+    ];
+
+    for (value, encoded) in tests {
+        let mut out = vec![];
+        write_leb128(&mut out, value);
+        assert_eq!(out, encoded, "write_leb128({})", value);
+        assert_eq!(leb128_size(value), encoded.len(), "leb128_size({})", value);
+        assert_eq!(read_leb128(&encoded)?, (value, encoded.len()));
+    }
+
+    assert!(read_leb128(&[0x80, 0x80]).is_err(), "Truncated LEB128");
+    assert!(read_leb128(&[0xFF; 9]).is_err(), "Too long LEB128");
+
+    Ok(())
+}
+
+#[test]
+fn test_av1_payload() -> Result<(), Error> {
+    // Empty payload
+    assert!(payload(100, &[])?.is_empty());
+
+    // MTU too small
+    assert!(payload(3, &[0x0A, 0x01, 0xAA])?.is_empty());
+
+    // Temporal delimiter is dropped, sizes are stripped and W counts elements
+    let temporal_unit = [
+        0x12, 0x00, // temporal delimiter
+        0x0A, 0x02, 0xAA, 0xBB, // sequence header
+        0x32, 0x01, 0xCC, // frame
+    ];
+    let payloads = payload(100, &temporal_unit)?;
+    assert_eq!(
+        payloads,
+        vec![vec![0x28, 0x03, 0x08, 0xAA, 0xBB, 0x30, 0xCC]]
+    );
+
+    // Inter frame without sequence header, last OBU without size field
+    let payloads = payload(100, &[0x30, 0xCC, 0xDD])?;
+    assert_eq!(payloads, vec![vec![0x10, 0x30, 0xCC, 0xDD]]);
+
+    // More than 3 elements use W=0 and length fields everywhere
+    let temporal_unit = [0x2A, 0x00, 0x2A, 0x00, 0x2A, 0x00, 0x2A, 0x00];
+    let payloads = payload(100, &temporal_unit)?;
+    assert_eq!(
+        payloads,
+        vec![vec![0x00, 0x01, 0x28, 0x01, 0x28, 0x01, 0x28, 0x01, 0x28]]
+    );
+
+    // Fragmentation across packets
+    let temporal_unit = [0x32, 0x06, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+    let payloads = payload(4, &temporal_unit)?;
+    assert_eq!(
+        payloads,
+        vec![
+            vec![0x50, 0x30, 0x01],
+            vec![0xD0, 0x02, 0x03],
+            vec![0xD0, 0x04, 0x05],
+            vec![0x90, 0x06],
+        ]
+    );
+
+    // Malformed OBU size
+    assert!(payload(100, &[0x0A, 0x05, 0xAA]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_av1_unmarshal() -> Result<(), Error> {
+    let mut pck = AV1Packet::default();
+
+    // Empty packet
+    assert!(depacketize(&mut pck, &[]).is_err());
+    // Aggregation header only
+    assert!(depacketize(&mut pck, &[0x00]).is_err());
+    // Element length exceeds packet
+    assert!(depacketize(&mut pck, &[0x00, 0x05, 0x30]).is_err());
+    // Zero length element
+    assert!(depacketize(&mut pck, &[0x00, 0x00]).is_err());
+    // W does not match the number of elements
+    assert!(depacketize(&mut pck, &[0x30, 0x01, 0x30, 0x01, 0x30]).is_err());
+
+    // Sequence header and frame, W=2
+    depacketize(&mut pck, &[0x28, 0x03, 0x08, 0xAA, 0xBB, 0x30, 0xCC])?;
+    assert!(!pck.z);
+    assert!(!pck.y);
+    assert_eq!(pck.w, 2);
+    assert!(pck.n);
+    assert!(pck.keyframe);
+    assert_eq!(pck.payload, vec![0x0A, 0x02, 0xAA, 0xBB, 0x32, 0x01, 0xCC]);
+
+    // W=0 with length fields everywhere
+    depacketize(&mut pck, &[0x00, 0x02, 0x30, 0xCC, 0x02, 0x30, 0xDD])?;
+    assert_eq!(pck.w, 0);
+    assert!(!pck.n);
+    assert!(!pck.keyframe);
+    assert_eq!(pck.payload, vec![0x32, 0x01, 0xCC, 0x32, 0x01, 0xDD]);
+
+    // OBU with extension header
+    depacketize(&mut pck, &[0x10, 0x34, 0x28, 0xEE])?;
+    assert_eq!(pck.payload, vec![0x36, 0x28, 0x01, 0xEE]);
+
+    Ok(())
+}
+
+#[test]
+fn test_av1_unmarshal_fragments() -> Result<(), Error> {
+    let mut pck = AV1Packet::default();
+
+    depacketize(&mut pck, &[0x50, 0x30, 0x01])?;
+    assert!(pck.y);
+    assert!(pck.payload.is_empty());
+
+    depacketize(&mut pck, &[0xD0, 0x02, 0x03])?;
+    assert!(pck.z && pck.y);
+    assert!(pck.payload.is_empty());
+
+    depacketize(&mut pck, &[0x90, 0x04])?;
+    assert_eq!(pck.payload, vec![0x32, 0x04, 0x01, 0x02, 0x03, 0x04]);
+
+    // Continuation without a start is dropped
+    let mut pck = AV1Packet::default();
+    depacketize(&mut pck, &[0xA0, 0x02, 0x03, 0x01, 0x30, 0xCC])?;
+    assert_eq!(pck.payload, vec![0x32, 0x01, 0xCC]);
+
+    // A buffered fragment is dropped when its end was lost
+    let mut pck = AV1Packet::default();
+    depacketize(&mut pck, &[0x50, 0x30, 0x01])?;
+    depacketize(&mut pck, &[0x10, 0x30, 0xCC])?;
+    assert_eq!(pck.payload, vec![0x32, 0x01, 0xCC]);
+
+    Ok(())
+}
+
+#[test]
+fn test_av1_round_trip() -> Result<(), Error> {
+    let mut frame = vec![0x32, 0x80, 0x02];
+    frame.extend((0..256).map(|i| i as u8));
+    let mut temporal_unit = vec![0x12, 0x00, 0x0A, 0x03, 0x00, 0x00, 0x00];
+    temporal_unit.extend_from_slice(&frame);
+
+    for mtu in &[5, 20, 100, 1200] {
+        let payloads = payload(*mtu, &temporal_unit)?;
+        assert!(payloads.iter().all(|p| p.len() <= *mtu as usize));
+        assert!(payloads[0][0] & N_BITMASK != 0, "mtu {}", mtu);
+
+        let mut pck = AV1Packet::default();
+        let mut out = vec![];
+        let mut keyframe = false;
+        for p in &payloads {
+            depacketize(&mut pck, p)?;
+            out.extend_from_slice(&pck.payload);
+            keyframe |= pck.keyframe;
+        }
+        assert!(keyframe);
+        assert_eq!(out, temporal_unit[2..].to_vec(), "mtu {}", mtu);
+    }
+
+    Ok(())
+}
@@ -10,7 +10,7 @@ mod g711_test;
 pub struct G711Payloader;
 
 impl Payloader for G711Payloader {
-    fn payload<R: Read>(&self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
         let mut payloads = vec![];
         if mtu <= 0 {
             return Ok(payloads);
@@ -6,7 +6,7 @@ use util::Error;
 
 #[test]
 fn test_g711_payload() -> Result<(), Error> {
-    let pck = G711Payloader;
+    let mut pck = G711Payloader;
 
     const TEST_LEN: usize = 10000;
     const TEST_MTU: isize = 1500;
@@ -6,7 +6,7 @@ use util::Error;
 
 #[test]
 fn test_g722_payload() -> Result<(), Error> {
-    let pck = G722Payloader;
+    let mut pck = G722Payloader;
 
     const TEST_LEN: usize = 10000;
     const TEST_MTU: isize = 1500;
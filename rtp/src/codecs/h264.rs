@@ -2,28 +2,62 @@ use crate::packetizer::{Depacketizer, Payloader};
 
 use std::io::Read;
 
-use byteorder::ReadBytesExt;
 use util::Error;
 
 #[cfg(test)]
 mod h264_test;
 
-pub struct H264Payloader;
+// H264Payloader payloads H264 packets
+//
+// SPS and PPS are held back until the next NAL unit, across calls to payload,
+// and aggregated with it into a STAP-A when it is an IDR that fits the MTU.
+#[derive(Debug, Default)]
+pub struct H264Payloader {
+    // Caches the latest SPS and PPS and prepends them to IDR NAL units
+    // sent without them
+    cache_parameter_sets: bool,
+    // Packetization mode 0, every NAL unit is sent in its own packet
+    single_nal_mode: bool,
+
+    // Latest parameter sets, when cached
+    sps_nalu: Option<Vec<u8>>,
+    pps_nalu: Option<Vec<u8>>,
+
+    // Parameter sets waiting for the next NAL unit
+    pending_sps: Option<Vec<u8>>,
+    pending_pps: Option<Vec<u8>>,
+}
 
 const STAPA_NALU_TYPE: u8 = 24;
+const STAPB_NALU_TYPE: u8 = 25;
+const MTAP16_NALU_TYPE: u8 = 26;
+const MTAP24_NALU_TYPE: u8 = 27;
 const FUA_NALU_TYPE: u8 = 28;
+const FUB_NALU_TYPE: u8 = 29;
 
 const FUA_HEADER_SIZE: isize = 2;
 const STAPA_HEADER_SIZE: usize = 1;
 const STAPA_NALU_LENGTH_SIZE: usize = 2;
+const MTAP_HEADER_SIZE: usize = 1;
+const MTAP_NALU_LENGTH_SIZE: usize = 2;
+const MTAP_DOND_SIZE: usize = 1;
+const DON_SIZE: usize = 2;
+
+const IDR_NALU_TYPE: u8 = 5;
+const SPS_NALU_TYPE: u8 = 7;
+const PPS_NALU_TYPE: u8 = 8;
+const AUD_NALU_TYPE: u8 = 9;
+const FILLER_NALU_TYPE: u8 = 12;
 
 const NALU_TYPE_BITMASK: u8 = 0x1F;
+const NALU_FORBIDDEN_BITMASK: u8 = 0x80;
 const NALU_REF_IDC_BITMASK: u8 = 0x60;
 const FUA_START_BITMASK: u8 = 0x80;
+const FUA_END_BITMASK: u8 = 0x40;
 
 static ANNEXB_NALUSTART_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
 
-fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
+pub(crate) fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
     let mut zero_count = 0;
 
     for (i, &b) in nalu[start..].iter().enumerate() {
@@ -42,7 +76,7 @@ fn emit(nalu: &[u8], mtu: isize, payloads: &mut Vec<Vec<u8>>) {
     let nalu_type = nalu[0] & NALU_TYPE_BITMASK;
     let nalu_ref_idc = nalu[0] & NALU_REF_IDC_BITMASK;
 
-    if nalu_type == 9 || nalu_type == 12 {
+    if nalu_type == AUD_NALU_TYPE || nalu_type == FILLER_NALU_TYPE {
         return;
     }
 
@@ -119,9 +153,139 @@ fn emit(nalu: &[u8], mtu: isize, payloads: &mut Vec<Vec<u8>>) {
     }
 }
 
+impl H264Payloader {
+    pub fn new() -> Self {
+        H264Payloader::default()
+    }
+
+    // with_cache_parameter_sets caches the latest SPS and PPS and prepends
+    // them to IDR NAL units sent without them
+    pub fn with_cache_parameter_sets(mut self, cache_parameter_sets: bool) -> Self {
+        self.cache_parameter_sets = cache_parameter_sets;
+        self
+    }
+
+    // with_single_nal_mode sends every NAL unit in its own packet, as
+    // packetization mode 0 requires. NAL units larger than the MTU cannot be
+    // sent in this mode and fail the payload call.
+    pub fn with_single_nal_mode(mut self, single_nal_mode: bool) -> Self {
+        self.single_nal_mode = single_nal_mode;
+        self
+    }
+
+    fn process(
+        &mut self,
+        nalu: &[u8],
+        mtu: isize,
+        payloads: &mut Vec<Vec<u8>>,
+    ) -> Result<(), Error> {
+        if nalu.is_empty() {
+            return Ok(());
+        }
+
+        let nalu_type = nalu[0] & NALU_TYPE_BITMASK;
+        match nalu_type {
+            AUD_NALU_TYPE | FILLER_NALU_TYPE => return Ok(()),
+            SPS_NALU_TYPE => {
+                if self.cache_parameter_sets {
+                    self.sps_nalu = Some(nalu.to_vec());
+                }
+                self.pending_sps = Some(nalu.to_vec());
+                return Ok(());
+            }
+            PPS_NALU_TYPE => {
+                if self.cache_parameter_sets {
+                    self.pps_nalu = Some(nalu.to_vec());
+                }
+                self.pending_pps = Some(nalu.to_vec());
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let mut parameter_sets = vec![];
+        let (sps, pps) = (self.pending_sps.take(), self.pending_pps.take());
+        if nalu_type == IDR_NALU_TYPE {
+            // Parameter sets missing from the IDR picture are taken from the cache
+            parameter_sets.extend(sps.or_else(|| self.sps_nalu.clone()));
+            parameter_sets.extend(pps.or_else(|| self.pps_nalu.clone()));
+
+            if !self.single_nal_mode && !parameter_sets.is_empty() {
+                parameter_sets.push(nalu.to_vec());
+                if stapa_size(&parameter_sets) as isize <= mtu {
+                    emit_stapa(&parameter_sets, payloads);
+                    return Ok(());
+                }
+                parameter_sets.pop();
+            }
+        } else {
+            parameter_sets.extend(sps);
+            parameter_sets.extend(pps);
+        }
+
+        if !self.single_nal_mode
+            && parameter_sets.len() > 1
+            && stapa_size(&parameter_sets) as isize <= mtu
+        {
+            emit_stapa(&parameter_sets, payloads);
+        } else {
+            for parameter_set in &parameter_sets {
+                self.emit(parameter_set, mtu, payloads)?;
+            }
+        }
+
+        self.emit(nalu, mtu, payloads)
+    }
+
+    fn emit(&self, nalu: &[u8], mtu: isize, payloads: &mut Vec<Vec<u8>>) -> Result<(), Error> {
+        if self.single_nal_mode && nalu.len() as isize > mtu {
+            return Err(Error::new(format!(
+                "h264 NAL unit of {} bytes exceeds the MTU of {} bytes in single NAL unit mode",
+                nalu.len(),
+                mtu
+            )));
+        }
+        emit(nalu, mtu, payloads);
+        Ok(())
+    }
+}
+
+fn stapa_size(nalus: &[Vec<u8>]) -> usize {
+    STAPA_HEADER_SIZE
+        + nalus
+            .iter()
+            .map(|nalu| STAPA_NALU_LENGTH_SIZE + nalu.len())
+            .sum::<usize>()
+}
+
+fn emit_stapa(nalus: &[Vec<u8>], payloads: &mut Vec<Vec<u8>>) {
+    // +---------------+
+    // |0|1|2|3|4|5|6|7|
+    // +-+-+-+-+-+-+-+-+
+    // |F|NRI|  Type   |
+    // +---------------+
+    // F is set if any aggregated NAL unit has it set, NRI is the highest
+    // of the aggregated NAL units
+    let f = nalus
+        .iter()
+        .fold(0, |f, nalu| f | (nalu[0] & NALU_FORBIDDEN_BITMASK));
+    let nri = nalus
+        .iter()
+        .map(|nalu| nalu[0] & NALU_REF_IDC_BITMASK)
+        .max()
+        .unwrap_or(0);
+
+    let mut out = vec![f | nri | STAPA_NALU_TYPE];
+    for nalu in nalus {
+        out.extend_from_slice(&(nalu.len() as u16).to_be_bytes());
+        out.extend_from_slice(nalu);
+    }
+    payloads.push(out);
+}
+
 // Payload fragments a H264 packet across one or more byte arrays
 impl Payloader for H264Payloader {
-    fn payload<R: Read>(&self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
         let mut payloads = vec![];
 
         let mut nals = vec![];
@@ -132,7 +296,7 @@ impl Payloader for H264Payloader {
 
         let (mut next_ind_start, mut next_ind_len) = next_ind(&nals, 0);
         if next_ind_start == -1 {
-            emit(&nals, mtu, &mut payloads);
+            self.process(&nals, mtu, &mut payloads)?;
         } else {
             while next_ind_start != -1 {
                 let prev_start = (next_ind_start + next_ind_len) as usize;
@@ -140,84 +304,227 @@ impl Payloader for H264Payloader {
                 next_ind_start = next_ind_start2;
                 next_ind_len = next_ind_len2;
                 if next_ind_start != -1 {
-                    emit(
+                    self.process(
                         &nals[prev_start..next_ind_start as usize],
                         mtu,
                         &mut payloads,
-                    );
+                    )?;
                 } else {
                     // Emit until end of stream, no end indicator found
-                    emit(&nals[prev_start..], mtu, &mut payloads);
+                    self.process(&nals[prev_start..], mtu, &mut payloads)?;
                 }
             }
         }
-
         Ok(payloads)
     }
 }
 
+// H264Packet depacketizes H264 RTP payloads
+//
+// NAL units are emitted with Annex B start codes, or with their length as
+// 4 byte prefix in AVC mode. Fragmentation units are emitted as they arrive
+// in Annex B mode and once complete in AVC mode.
 #[derive(Debug, Default)]
 pub struct H264Packet {
-    payload: Vec<u8>,
+    // Emits NAL units in AVC format instead of Annex B
+    pub is_avc: bool,
+
+    // Decoding order number of the first NAL unit of an interleaved
+    // mode packet (STAP-B, MTAP16, MTAP24 and FU-B)
+    pub don: Option<u16>,
+    pub payload: Vec<u8>,
+
+    fua_buffer: Option<Vec<u8>>,
 }
 
 impl Depacketizer for H264Packet {
     fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
         self.payload.clear();
+        self.don = None;
+
+        let mut packet = vec![];
+        reader.read_to_end(&mut packet)?;
+        if packet.is_empty() {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
 
         // NALU Types
         // https://tools.ietf.org/html/rfc6184#section-5.4
-        let b0 = reader.read_u8()?;
+        let b0 = packet[0];
         let nalu_type = b0 & NALU_TYPE_BITMASK;
-        if nalu_type > 0 && nalu_type < 24 {
-            self.payload.append(&mut ANNEXB_NALUSTART_CODE.to_vec());
-            self.payload.push(b0);
-            reader.read_to_end(&mut self.payload)?;
-            Ok(())
-        } else if nalu_type == STAPA_NALU_TYPE {
-            let mut curr_offset = 0;
-            let mut payload = vec![];
-            reader.read_to_end(&mut payload)?;
-
-            while curr_offset + 1 < payload.len() {
-                let nalu_size =
-                    ((payload[curr_offset] as usize) << 8) | payload[curr_offset + 1] as usize;
-                curr_offset += STAPA_NALU_LENGTH_SIZE;
-
-                if curr_offset + nalu_size > payload.len() {
-                    return Err(Error::new(format!(
-                        "STAP-A declared size({}) is larger than buffer({})",
-                        nalu_size,
-                        payload.len() - curr_offset
-                    )));
-                }
-                self.payload.append(&mut ANNEXB_NALUSTART_CODE.to_vec());
-                self.payload
-                    .append(&mut payload[curr_offset..curr_offset + nalu_size].to_vec());
-                curr_offset += nalu_size;
+        match nalu_type {
+            1..=23 => {
+                self.push_nalu(&packet);
+                Ok(())
+            }
+            STAPA_NALU_TYPE => self.parse_stap(&packet[STAPA_HEADER_SIZE..]),
+            STAPB_NALU_TYPE => {
+                self.don = Some(read_don(&packet, STAPA_HEADER_SIZE)?);
+                self.parse_stap(&packet[STAPA_HEADER_SIZE + DON_SIZE..])
+            }
+            MTAP16_NALU_TYPE | MTAP24_NALU_TYPE => {
+                let ts_offset_size = if nalu_type == MTAP16_NALU_TYPE { 2 } else { 3 };
+                // DONB, the decoding order number base
+                let donb = read_don(&packet, MTAP_HEADER_SIZE)?;
+                self.don = Some(donb);
+                self.parse_mtap(&packet[MTAP_HEADER_SIZE + DON_SIZE..], ts_offset_size)
             }
+            FUA_NALU_TYPE => self.parse_fu(&packet, false),
+            FUB_NALU_TYPE => self.parse_fu(&packet, true),
+            _ => Err(Error::new(format!(
+                "nalu type {} is currently not handled",
+                nalu_type
+            ))),
+        }
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // Fragmented NAL units start with the fragment carrying the start bit
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.len() < 2 {
+            return false;
+        }
+
+        let nalu_type = payload[0] & NALU_TYPE_BITMASK;
+        if nalu_type == FUA_NALU_TYPE || nalu_type == FUB_NALU_TYPE {
+            payload[1] & FUA_START_BITMASK != 0
+        } else {
+            true
+        }
+    }
+}
+
+impl H264Packet {
+    fn push_nalu(&mut self, nalu: &[u8]) {
+        if self.is_avc {
+            self.payload
+                .extend_from_slice(&(nalu.len() as u32).to_be_bytes());
+        } else {
+            self.payload.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+        }
+        self.payload.extend_from_slice(nalu);
+    }
+
+    // STAP-A and STAP-B aggregation units, after the DON of STAP-B
+    fn parse_stap(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let mut curr_offset = 0;
+        while curr_offset + 1 < payload.len() {
+            let nalu_size =
+                ((payload[curr_offset] as usize) << 8) | payload[curr_offset + 1] as usize;
+            curr_offset += STAPA_NALU_LENGTH_SIZE;
+
+            if curr_offset + nalu_size > payload.len() {
+                return Err(Error::new(format!(
+                    "STAP-A declared size({}) is larger than buffer({})",
+                    nalu_size,
+                    payload.len() - curr_offset
+                )));
+            }
+            self.push_nalu(&payload[curr_offset..curr_offset + nalu_size]);
+            curr_offset += nalu_size;
+        }
+
+        Ok(())
+    }
+
+    // MTAP16 and MTAP24 aggregation units, after the DONB:
+    //
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    // |          NALU Size            |     DOND      |  TS offset    |
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    // | TS offset (MTAP24)|  NAL unit ...                             |
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    fn parse_mtap(&mut self, payload: &[u8], ts_offset_size: usize) -> Result<(), Error> {
+        let unit_header_size = MTAP_NALU_LENGTH_SIZE + MTAP_DOND_SIZE + ts_offset_size;
+
+        let mut curr_offset = 0;
+        while curr_offset < payload.len() {
+            if curr_offset + unit_header_size > payload.len() {
+                return Err(Error::new(format!(
+                    "MTAP unit header({}) is larger than buffer({})",
+                    unit_header_size,
+                    payload.len() - curr_offset
+                )));
+            }
+            let nalu_size =
+                ((payload[curr_offset] as usize) << 8) | payload[curr_offset + 1] as usize;
+            curr_offset += unit_header_size;
+
+            if curr_offset + nalu_size > payload.len() {
+                return Err(Error::new(format!(
+                    "MTAP declared size({}) is larger than buffer({})",
+                    nalu_size,
+                    payload.len() - curr_offset
+                )));
+            }
+            self.push_nalu(&payload[curr_offset..curr_offset + nalu_size]);
+            curr_offset += nalu_size;
+        }
+
+        Ok(())
+    }
+
+    // FU-A and FU-B fragmentation units, FU-B is only used for the first
+    // fragment and carries the DON of the fragmented NAL unit
+    fn parse_fu(&mut self, packet: &[u8], is_fub: bool) -> Result<(), Error> {
+        if packet.len() < FUA_HEADER_SIZE as usize {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        let b0 = packet[0];
+        let b1 = packet[1];
+        let start = b1 & FUA_START_BITMASK != 0;
+        let end = b1 & FUA_END_BITMASK != 0;
+
+        let mut offset = FUA_HEADER_SIZE as usize;
+        if is_fub {
+            if !start {
+                return Err(Error::new(
+                    "FU-B must only be used for the first fragment".to_string(),
+                ));
+            }
+            self.don = Some(read_don(packet, offset)?);
+            offset += DON_SIZE;
+        }
+        let data = &packet[offset..];
 
-            Ok(())
-        } else if nalu_type == FUA_NALU_TYPE {
-            let b1 = reader.read_u8()?;
-            if b1 & FUA_START_BITMASK != 0 {
+        if !self.is_avc {
+            if start {
                 let nalu_ref_idc = b0 & NALU_REF_IDC_BITMASK;
                 let fragmented_nalu_type = b1 & NALU_TYPE_BITMASK;
 
-                self.payload.append(&mut ANNEXB_NALUSTART_CODE.to_vec());
+                self.payload.extend_from_slice(&ANNEXB_NALUSTART_CODE);
                 self.payload.push(nalu_ref_idc | fragmented_nalu_type);
-                reader.read_to_end(&mut self.payload)?;
+            }
+            self.payload.extend_from_slice(data);
+            return Ok(());
+        }
 
-                Ok(())
-            } else {
-                reader.read_to_end(&mut self.payload)?;
-                Ok(())
+        // The NAL unit length is only known once all fragments arrived
+        if start {
+            let nalu_ref_idc = b0 & NALU_REF_IDC_BITMASK;
+            let fragmented_nalu_type = b1 & NALU_TYPE_BITMASK;
+            self.fua_buffer = Some(vec![nalu_ref_idc | fragmented_nalu_type]);
+        }
+        if let Some(fua_buffer) = &mut self.fua_buffer {
+            fua_buffer.extend_from_slice(data);
+        }
+        if end {
+            if let Some(nalu) = self.fua_buffer.take() {
+                self.push_nalu(&nalu);
             }
-        } else {
-            Err(Error::new(format!(
-                "nalu type {} is currently not handled",
-                nalu_type
-            )))
         }
+
+        Ok(())
+    }
+}
+
+fn read_don(packet: &[u8], offset: usize) -> Result<u16, Error> {
+    if offset + DON_SIZE > packet.len() {
+        return Err(Error::new("Payload is not large enough".to_string()));
     }
+    Ok(((packet[offset] as u16) << 8) | packet[offset + 1] as u16)
 }
@@ -21,7 +21,7 @@ fn test_h264_payload() -> Result<(), Error> {
         vec![0x1c, 0x40, 0x13, 0x14, 0x15],
     ];
 
-    let pck = H264Payloader;
+    let mut pck = H264Payloader::default();
 
     // Positive MTU, empty payload
     let mut reader = BufReader::new(empty.as_slice());
@@ -171,3 +171,257 @@ fn test_h264packet_unmarshal() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_h264packet_unmarshal_interleaved() -> Result<(), Error> {
+    let mut pkt = H264Packet::default();
+
+    // STAP-B
+    let stapb = vec![0x79, 0x01, 0x02, 0x00, 0x02, 0x67, 0x42, 0x00, 0x01, 0x68];
+    let mut reader = BufReader::new(stapb.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.don, Some(0x0102));
+    assert_eq!(
+        pkt.payload,
+        vec![0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x00, 0x00, 0x01, 0x68]
+    );
+
+    // STAP-B without DON
+    let data = vec![0x79, 0x01];
+    let mut reader = BufReader::new(data.as_slice());
+    assert!(pkt.depacketize(&mut reader).is_err());
+
+    // MTAP16
+    let mtap16 = vec![
+        0x7a, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x65, 0xaa, 0x00, 0x01, 0x01, 0x00, 0x10,
+        0x41,
+    ];
+    let mut reader = BufReader::new(mtap16.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.don, Some(5));
+    assert_eq!(
+        pkt.payload,
+        vec![0x00, 0x00, 0x00, 0x01, 0x65, 0xaa, 0x00, 0x00, 0x00, 0x01, 0x41]
+    );
+
+    // MTAP24
+    let mtap24 = vec![
+        0x7b, 0x00, 0x06, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x65, 0xaa,
+    ];
+    let mut reader = BufReader::new(mtap24.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.don, Some(6));
+    assert_eq!(pkt.payload, vec![0x00, 0x00, 0x00, 0x01, 0x65, 0xaa]);
+
+    // MTAP16 with truncated unit header
+    let data = vec![0x7a, 0x00, 0x05, 0x00, 0x02, 0x00];
+    let mut reader = BufReader::new(data.as_slice());
+    assert!(pkt.depacketize(&mut reader).is_err());
+
+    // MTAP16 with declared size larger than buffer
+    let data = vec![0x7a, 0x00, 0x05, 0x00, 0x03, 0x00, 0x00, 0x00, 0x65, 0xaa];
+    let mut reader = BufReader::new(data.as_slice());
+    assert!(pkt.depacketize(&mut reader).is_err());
+
+    // FU-B followed by FU-A
+    let fub = vec![0x7d, 0x85, 0x00, 0x07, 0x01, 0x02];
+    let mut reader = BufReader::new(fub.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.don, Some(7));
+    assert_eq!(pkt.payload, vec![0x00, 0x00, 0x00, 0x01, 0x65, 0x01, 0x02]);
+
+    let fua = vec![0x7c, 0x45, 0x03];
+    let mut reader = BufReader::new(fua.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.don, None);
+    assert_eq!(pkt.payload, vec![0x03]);
+
+    // FU-B must be the first fragment
+    let data = vec![0x7d, 0x05, 0x00, 0x07, 0x01];
+    let mut reader = BufReader::new(data.as_slice());
+    assert!(pkt.depacketize(&mut reader).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_h264packet_unmarshal_avc() -> Result<(), Error> {
+    let mut pkt = H264Packet {
+        is_avc: true,
+        ..Default::default()
+    };
+
+    // Single NALU
+    let data = vec![0x65, 0x90, 0x90];
+    let mut reader = BufReader::new(data.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(pkt.payload, vec![0x00, 0x00, 0x00, 0x03, 0x65, 0x90, 0x90]);
+
+    // STAP-A
+    let data = vec![0x78, 0x00, 0x02, 0x67, 0x42, 0x00, 0x01, 0x68];
+    let mut reader = BufReader::new(data.as_slice());
+    pkt.depacketize(&mut reader)?;
+    assert_eq!(
+        pkt.payload,
+        vec![0x00, 0x00, 0x00, 0x02, 0x67, 0x42, 0x00, 0x00, 0x00, 0x01, 0x68]
+    );
+
+    // FU-A is emitted once complete
+    let fragments = vec![
+        vec![0x7c, 0x85, 0x01, 0x02],
+        vec![0x7c, 0x05, 0x03],
+        vec![0x7c, 0x45, 0x04],
+    ];
+    let mut results = vec![];
+    for f in &fragments {
+        let mut reader = BufReader::new(f.as_slice());
+        pkt.depacketize(&mut reader)?;
+        results.push(pkt.payload.clone());
+    }
+    assert_eq!(
+        results,
+        vec![
+            vec![],
+            vec![],
+            vec![0x00, 0x00, 0x00, 0x05, 0x65, 0x01, 0x02, 0x03, 0x04],
+        ]
+    );
+
+    // Fragments without a start are dropped
+    for f in &fragments[1..] {
+        let mut reader = BufReader::new(f.as_slice());
+        pkt.depacketize(&mut reader)?;
+        assert!(pkt.payload.is_empty());
+    }
+
+    Ok(())
+}
+
+fn annexb(nalus: &[&Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![];
+    for nalu in nalus {
+        out.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+        out.extend_from_slice(nalu);
+    }
+    out
+}
+
+fn stapa(nalus: &[&Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![0x78];
+    for nalu in nalus {
+        out.extend_from_slice(&(nalu.len() as u16).to_be_bytes());
+        out.extend_from_slice(nalu);
+    }
+    out
+}
+
+#[test]
+fn test_h264_payload_parameter_sets() -> Result<(), Error> {
+    let sps = vec![0x67, 0x42, 0xc0, 0x1f];
+    let pps = vec![0x68, 0xce, 0x3c, 0x80];
+    let sei = vec![0x06, 0x05, 0x01];
+    let idr = vec![0x65, 0x88, 0x84, 0x00];
+    let non_idr = vec![0x41, 0x9a, 0x02];
+
+    let mut pck = H264Payloader::new();
+
+    // SPS and PPS are aggregated with the IDR
+    let data = annexb(&[&sps, &pps, &idr]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps, &idr])]);
+
+    // SPS and PPS are held back until the IDR when the encoder sends one
+    // NAL unit per call
+    for nalu in &[&sps, &pps] {
+        let result = pck.payload(1200, &mut nalu.as_slice())?;
+        assert!(result.is_empty(), "parameter set was not held back");
+    }
+    let result = pck.payload(1200, &mut idr.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps, &idr])]);
+
+    // IDR too large to be aggregated
+    let data = annexb(&[&sps, &pps, &idr]);
+    let result = pck.payload(16, &mut data.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps]), idr.clone()]);
+
+    // Parameter sets are aggregated on their own before another NAL unit
+    let data = annexb(&[&sps, &pps, &sei, &idr]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps]), sei.clone(), idr.clone()]);
+
+    let data = annexb(&[&sps, &pps, &non_idr]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps]), non_idr.clone()]);
+
+    // IDR is not modified without caching
+    let result = pck.payload(1200, &mut idr.as_slice())?;
+    assert_eq!(result, vec![idr.clone()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_h264_payload_cache_parameter_sets() -> Result<(), Error> {
+    let sps = vec![0x67, 0x42, 0xc0, 0x1f];
+    let pps = vec![0x68, 0xce, 0x3c, 0x80];
+    let pps2 = vec![0x68, 0xce, 0x3c, 0x81];
+    let idr = vec![0x65, 0x88, 0x84, 0x21];
+    let idr2 = vec![0x65, 0x99];
+    let non_idr = vec![0x41, 0x9a, 0x02];
+
+    let mut pck = H264Payloader::new().with_cache_parameter_sets(true);
+
+    // IDR without parameter sets before any were seen
+    let result = pck.payload(1200, &mut idr.as_slice())?;
+    assert_eq!(result, vec![idr.clone()]);
+
+    // Parameter sets sent one per call go out once, with the IDR
+    for nalu in &[&sps, &pps] {
+        let result = pck.payload(1200, &mut nalu.as_slice())?;
+        assert!(result.is_empty(), "parameter set was not held back");
+    }
+    let result = pck.payload(1200, &mut idr.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps, &idr])]);
+
+    // Non IDR NAL units are not modified
+    let result = pck.payload(1200, &mut non_idr.as_slice())?;
+    assert_eq!(result, vec![non_idr.clone()]);
+
+    // Cached parameter sets are prepended to every IDR sent without them
+    let data = annexb(&[&idr, &idr2]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(
+        result,
+        vec![stapa(&[&sps, &pps, &idr]), stapa(&[&sps, &pps, &idr2])]
+    );
+
+    // A PPS sent without SPS is completed with the cached SPS
+    let data = annexb(&[&pps2, &idr]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(result, vec![stapa(&[&sps, &pps2, &idr])]);
+
+    Ok(())
+}
+
+#[test]
+fn test_h264_payload_single_nal_mode() -> Result<(), Error> {
+    let sps = vec![0x67, 0x42, 0xc0, 0x1f];
+    let pps = vec![0x68, 0xce, 0x3c, 0x80];
+    let idr = vec![0x65, 0x88, 0x84, 0x00, 0x01, 0x02, 0x03];
+
+    let mut pck = H264Payloader::new().with_single_nal_mode(true);
+
+    // Every NAL unit is sent alone
+    let data = annexb(&[&sps, &pps, &idr]);
+    let result = pck.payload(1200, &mut data.as_slice())?;
+    assert_eq!(result, vec![sps.clone(), pps.clone(), idr.clone()]);
+
+    // NAL units larger than the MTU cannot be fragmented
+    let data = annexb(&[&sps, &pps, &idr]);
+    assert!(
+        pck.payload(5, &mut data.as_slice()).is_err(),
+        "NAL unit larger than the MTU was not rejected"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,535 @@
+use super::h264::next_ind;
+use crate::packetizer::{Depacketizer, Payloader};
+
+use std::io::Read;
+
+use util::Error;
+
+#[cfg(test)]
+mod h265_test;
+
+// NAL unit types of RFC 7798
+const AGGREGATION_PACKET_TYPE: u8 = 48;
+const FRAGMENTATION_UNIT_TYPE: u8 = 49;
+const PACI_TYPE: u8 = 50;
+
+// NAL unit types of ITU-T H.265 that are not sent
+const AUD_NALU_TYPE: u8 = 35;
+const FD_NALU_TYPE: u8 = 38;
+
+const NALU_HEADER_SIZE: usize = 2;
+const FU_HEADER_SIZE: usize = 1;
+const PACI_HEADER_SIZE: usize = 2;
+const AP_NALU_LENGTH_SIZE: usize = 2;
+const DONL_SIZE: usize = 2;
+const DOND_SIZE: usize = 1;
+
+const FU_START_BITMASK: u8 = 0x80;
+const FU_END_BITMASK: u8 = 0x40;
+const FU_TYPE_BITMASK: u8 = 0x3F;
+
+static ANNEXB_NALUSTART_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+// H265NALUHeader is the two byte NAL unit header of H.265
+//
+// +---------------+---------------+
+// |0|1|2|3|4|5|6|7|0|1|2|3|4|5|6|7|
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |F|   Type    |  LayerId  | TID |
+// +-------------+-----------------+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct H265NALUHeader(pub u16);
+
+impl H265NALUHeader {
+    pub fn new(b0: u8, b1: u8) -> Self {
+        H265NALUHeader(((b0 as u16) << 8) | b1 as u16)
+    }
+
+    fn from_parts(f: bool, nalu_type: u8, layer_id: u8, tid: u8) -> Self {
+        let mut v = ((nalu_type as u16 & 0x3F) << 9)
+            | ((layer_id as u16 & 0x3F) << 3)
+            | (tid as u16 & 0x07);
+        if f {
+            v |= 0x8000;
+        }
+        H265NALUHeader(v)
+    }
+
+    // Forbidden zero bit
+    pub fn f(&self) -> bool {
+        self.0 & 0x8000 != 0
+    }
+
+    pub fn nalu_type(&self) -> u8 {
+        ((self.0 >> 9) & 0x3F) as u8
+    }
+
+    pub fn layer_id(&self) -> u8 {
+        ((self.0 >> 3) & 0x3F) as u8
+    }
+
+    // Temporal ID plus 1
+    pub fn tid(&self) -> u8 {
+        (self.0 & 0x07) as u8
+    }
+
+    fn to_bytes(self) -> [u8; 2] {
+        self.0.to_be_bytes()
+    }
+}
+
+// H265Payloader payloads H265 packets following RFC 7798
+//
+// NAL units fitting the MTU are aggregated into Aggregation Packets,
+// larger ones are split into Fragmentation Units.
+#[derive(Debug, Default)]
+pub struct H265Payloader {
+    // Adds DONL and DOND fields, required when sprop-max-don-diff is
+    // greater than 0
+    pub add_donl: bool,
+
+    don: u16,
+}
+
+impl H265Payloader {
+    fn donl_size(&self) -> usize {
+        if self.add_donl {
+            DONL_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn dond_size(&self) -> usize {
+        if self.add_donl {
+            DOND_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn push_donl(&mut self, out: &mut Vec<u8>) {
+        if self.add_donl {
+            out.extend_from_slice(&self.don.to_be_bytes());
+        }
+        self.don = self.don.wrapping_add(1);
+    }
+
+    fn emit_single(&mut self, nalu: &[u8], payloads: &mut Vec<Vec<u8>>) {
+        let mut out = Vec::with_capacity(nalu.len() + self.donl_size());
+        out.extend_from_slice(&nalu[..NALU_HEADER_SIZE]);
+        self.push_donl(&mut out);
+        out.extend_from_slice(&nalu[NALU_HEADER_SIZE..]);
+        payloads.push(out);
+    }
+
+    fn emit_aggregation(&mut self, nalus: &[&[u8]], payloads: &mut Vec<Vec<u8>>) {
+        if nalus.len() == 1 {
+            self.emit_single(nalus[0], payloads);
+            return;
+        }
+
+        // The F bit is set if any aggregated NAL unit has it set, LayerId
+        // and TID are the lowest of the aggregated NAL units
+        let headers: Vec<H265NALUHeader> = nalus
+            .iter()
+            .map(|nalu| H265NALUHeader::new(nalu[0], nalu[1]))
+            .collect();
+        let header = H265NALUHeader::from_parts(
+            headers.iter().any(|h| h.f()),
+            AGGREGATION_PACKET_TYPE,
+            headers.iter().map(|h| h.layer_id()).min().unwrap_or(0),
+            headers.iter().map(|h| h.tid()).min().unwrap_or(0),
+        );
+
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |    PayloadHdr (Type=48)       |  (DONL)  NALU 1 Size ...     |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |  NALU 1 HDR   |                                               |
+         * +-+-+-+-+-+-+-+-+         NALU 1 Data                           |
+         * |                   . . .                                       |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |  (DOND)       |  NALU 2 Size                  |  NALU 2 HDR   |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+        let mut out = header.to_bytes().to_vec();
+        for (i, nalu) in nalus.iter().enumerate() {
+            if i == 0 {
+                self.push_donl(&mut out);
+            } else {
+                if self.add_donl {
+                    // NAL units are sent in decoding order
+                    out.push(0);
+                }
+                self.don = self.don.wrapping_add(1);
+            }
+            out.extend_from_slice(&(nalu.len() as u16).to_be_bytes());
+            out.extend_from_slice(nalu);
+        }
+        payloads.push(out);
+    }
+
+    fn emit_fragmented(&mut self, nalu: &[u8], mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        let overhead = NALU_HEADER_SIZE + FU_HEADER_SIZE + self.donl_size();
+        if mtu <= overhead {
+            return;
+        }
+
+        /*
+         *  0                   1                   2                   3
+         *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         * |    PayloadHdr (Type=49)       |   FU header   | DONL (cond)   |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-|
+         * | DONL (cond)   |                                               |
+         * |-+-+-+-+-+-+-+-+                                               |
+         * |                         FU payload                            |
+         * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+         */
+        let header = H265NALUHeader::new(nalu[0], nalu[1]);
+        let payload_header = H265NALUHeader::from_parts(
+            header.f(),
+            FRAGMENTATION_UNIT_TYPE,
+            header.layer_id(),
+            header.tid(),
+        );
+
+        // The NAL unit header is conveyed in the PayloadHdr and FU header
+        let data = &nalu[NALU_HEADER_SIZE..];
+        let mut offset = 0;
+        while offset < data.len() {
+            let first = offset == 0;
+            let max_fragment_size = if first {
+                mtu - overhead
+            } else {
+                mtu - NALU_HEADER_SIZE - FU_HEADER_SIZE
+            };
+            let fragment_size = std::cmp::min(max_fragment_size, data.len() - offset);
+            let last = offset + fragment_size == data.len();
+
+            let mut fu_header = header.nalu_type();
+            if first {
+                fu_header |= FU_START_BITMASK;
+            }
+            if last {
+                fu_header |= FU_END_BITMASK;
+            }
+
+            let mut out = payload_header.to_bytes().to_vec();
+            out.push(fu_header);
+            if first {
+                self.push_donl(&mut out);
+            }
+            out.extend_from_slice(&data[offset..offset + fragment_size]);
+            payloads.push(out);
+
+            offset += fragment_size;
+        }
+    }
+}
+
+impl Payloader for H265Payloader {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+        let mut payloads = vec![];
+
+        let mut nals = vec![];
+        reader.read_to_end(&mut nals)?;
+        if nals.is_empty() || mtu <= 0 {
+            return Ok(payloads);
+        }
+        let mtu = mtu as usize;
+
+        let mut nalus = vec![];
+        let (mut next_ind_start, mut next_ind_len) = next_ind(&nals, 0);
+        if next_ind_start == -1 {
+            nalus.push(&nals[..]);
+        } else {
+            while next_ind_start != -1 {
+                let prev_start = (next_ind_start + next_ind_len) as usize;
+                let (next_ind_start2, next_ind_len2) = next_ind(&nals, prev_start);
+                next_ind_start = next_ind_start2;
+                next_ind_len = next_ind_len2;
+                if next_ind_start != -1 {
+                    nalus.push(&nals[prev_start..next_ind_start as usize]);
+                } else {
+                    // Emit until end of stream, no end indicator found
+                    nalus.push(&nals[prev_start..]);
+                }
+            }
+        }
+
+        let ap_header_size = NALU_HEADER_SIZE + self.donl_size();
+        let mut bucket: Vec<&[u8]> = vec![];
+        let mut bucket_size = 0;
+        for nalu in nalus {
+            if nalu.len() <= NALU_HEADER_SIZE {
+                continue;
+            }
+            let nalu_type = H265NALUHeader::new(nalu[0], nalu[1]).nalu_type();
+            if nalu_type == AUD_NALU_TYPE || nalu_type == FD_NALU_TYPE {
+                continue;
+            }
+
+            if nalu.len() + self.donl_size() > mtu {
+                if !bucket.is_empty() {
+                    self.emit_aggregation(&bucket, &mut payloads);
+                    bucket.clear();
+                }
+                self.emit_fragmented(nalu, mtu, &mut payloads);
+                continue;
+            }
+
+            let aggregated_size = AP_NALU_LENGTH_SIZE + nalu.len();
+            if !bucket.is_empty() && bucket_size + self.dond_size() + aggregated_size > mtu {
+                self.emit_aggregation(&bucket, &mut payloads);
+                bucket.clear();
+            }
+
+            if bucket.is_empty() {
+                bucket_size = ap_header_size + aggregated_size;
+            } else {
+                bucket_size += self.dond_size() + aggregated_size;
+            }
+            bucket.push(nalu);
+        }
+
+        if !bucket.is_empty() {
+            self.emit_aggregation(&bucket, &mut payloads);
+        }
+
+        Ok(payloads)
+    }
+}
+
+// H265PACI is the header of a PACI packet, RFC 7798 section 4.4.4
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct H265PACI {
+    pub a: bool,       /* F bit of the payload header */
+    pub ctype: u8,     /* Type of the payload header */
+    pub phs_size: u8,  /* length of the header extension */
+    pub f0: bool,      /* TSCI present */
+    pub f1: bool,      /* reserved */
+    pub f2: bool,      /* reserved */
+    pub y: bool,       /* PHES extension */
+    pub phes: Vec<u8>, /* header extension */
+}
+
+// H265Packet depacketizes H265 RTP payloads into Annex B NAL units
+//
+// Fragmentation Units are not buffered, the payloads of consecutive
+// packets concatenated form the NAL unit stream.
+#[derive(Debug, Default, Clone)]
+pub struct H265Packet {
+    // Set when sprop-max-don-diff is greater than 0, packets then carry
+    // DONL and DOND fields
+    pub with_donl: bool,
+
+    // Decoding order number of the first NAL unit of the packet
+    pub donl: Option<u16>,
+    pub paci: Option<H265PACI>,
+    pub payload: Vec<u8>,
+}
+
+impl Depacketizer for H265Packet {
+    fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let mut packet = vec![];
+        reader.read_to_end(&mut packet)?;
+
+        self.payload.clear();
+        self.donl = None;
+        self.paci = None;
+
+        self.parse(&packet, true)
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // Fragmented NAL units start with the fragment carrying the start bit
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.len() <= NALU_HEADER_SIZE {
+            return false;
+        }
+
+        let header = H265NALUHeader::new(payload[0], payload[1]);
+        if header.nalu_type() == FRAGMENTATION_UNIT_TYPE {
+            payload[NALU_HEADER_SIZE] & FU_START_BITMASK != 0
+        } else {
+            true
+        }
+    }
+}
+
+impl H265Packet {
+    fn parse(&mut self, packet: &[u8], allow_paci: bool) -> Result<(), Error> {
+        if packet.len() <= NALU_HEADER_SIZE {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        let header = H265NALUHeader::new(packet[0], packet[1]);
+        if header.f() {
+            return Err(Error::new("H265: forbidden zero bit is set".to_string()));
+        }
+
+        match header.nalu_type() {
+            AGGREGATION_PACKET_TYPE => self.parse_aggregation(packet),
+            FRAGMENTATION_UNIT_TYPE => self.parse_fragmentation(packet, header),
+            PACI_TYPE if allow_paci => self.parse_paci(packet, header),
+            PACI_TYPE => Err(Error::new("H265: nested PACI packet".to_string())),
+            nalu_type if nalu_type > PACI_TYPE => Err(Error::new(format!(
+                "nalu type {} is currently not handled",
+                nalu_type
+            ))),
+            _ => {
+                let mut offset = NALU_HEADER_SIZE;
+                if self.with_donl {
+                    self.donl = Some(read_u16(packet, offset)?);
+                    offset += DONL_SIZE;
+                }
+                self.payload.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+                self.payload.extend_from_slice(&packet[..NALU_HEADER_SIZE]);
+                self.payload.extend_from_slice(&packet[offset..]);
+                Ok(())
+            }
+        }
+    }
+
+    fn parse_aggregation(&mut self, packet: &[u8]) -> Result<(), Error> {
+        let mut offset = NALU_HEADER_SIZE;
+        let mut count = 0;
+        while offset < packet.len() {
+            if self.with_donl {
+                if count == 0 {
+                    self.donl = Some(read_u16(packet, offset)?);
+                    offset += DONL_SIZE;
+                } else {
+                    offset += DOND_SIZE;
+                }
+            }
+
+            let nalu_size = read_u16(packet, offset)? as usize;
+            offset += AP_NALU_LENGTH_SIZE;
+            if offset + nalu_size > packet.len() {
+                return Err(Error::new(format!(
+                    "AP declared size({}) is larger than buffer({})",
+                    nalu_size,
+                    packet.len().saturating_sub(offset)
+                )));
+            }
+
+            self.payload.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+            self.payload
+                .extend_from_slice(&packet[offset..offset + nalu_size]);
+            offset += nalu_size;
+            count += 1;
+        }
+
+        if count < 2 {
+            return Err(Error::new(
+                "H265: AP must contain at least two NAL units".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn parse_fragmentation(&mut self, packet: &[u8], header: H265NALUHeader) -> Result<(), Error> {
+        let fu_header = *packet
+            .get(NALU_HEADER_SIZE)
+            .ok_or_else(|| Error::new("Payload is not large enough".to_string()))?;
+        let start = fu_header & FU_START_BITMASK != 0;
+        let end = fu_header & FU_END_BITMASK != 0;
+        if start && end {
+            return Err(Error::new(
+                "H265: FU start and end bits are both set".to_string(),
+            ));
+        }
+
+        let mut offset = NALU_HEADER_SIZE + FU_HEADER_SIZE;
+        if start {
+            if self.with_donl {
+                self.donl = Some(read_u16(packet, offset)?);
+                offset += DONL_SIZE;
+            }
+
+            let nalu_header = H265NALUHeader::from_parts(
+                header.f(),
+                fu_header & FU_TYPE_BITMASK,
+                header.layer_id(),
+                header.tid(),
+            );
+            self.payload.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+            self.payload.extend_from_slice(&nalu_header.to_bytes());
+        }
+
+        if offset >= packet.len() {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+        self.payload.extend_from_slice(&packet[offset..]);
+
+        Ok(())
+    }
+
+    /*
+     *  0                   1                   2                   3
+     *  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+     * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+     * |    PayloadHdr (Type=50)       |A|   cType   | PHSsize |F0..2|Y|
+     * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+     * |        Payload Header Extension Structure (PHES)              |
+     * |=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=|
+     * |                  PACI payload: NAL unit                       |
+     * |                   . . .                                       |
+     * +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+     */
+    fn parse_paci(&mut self, packet: &[u8], header: H265NALUHeader) -> Result<(), Error> {
+        if packet.len() < NALU_HEADER_SIZE + PACI_HEADER_SIZE {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        let b2 = packet[NALU_HEADER_SIZE];
+        let b3 = packet[NALU_HEADER_SIZE + 1];
+        let mut paci = H265PACI {
+            a: b2 & 0x80 != 0,
+            ctype: (b2 >> 1) & 0x3F,
+            phs_size: ((b2 & 0x01) << 4) | (b3 >> 4),
+            f0: b3 & 0x08 != 0,
+            f1: b3 & 0x04 != 0,
+            f2: b3 & 0x02 != 0,
+            y: b3 & 0x01 != 0,
+            phes: vec![],
+        };
+
+        let offset = NALU_HEADER_SIZE + PACI_HEADER_SIZE;
+        let payload_offset = offset + paci.phs_size as usize;
+        if payload_offset > packet.len() {
+            return Err(Error::new(format!(
+                "PACI declared PHES size({}) is larger than buffer({})",
+                paci.phs_size,
+                packet.len() - offset
+            )));
+        }
+        paci.phes = packet[offset..payload_offset].to_vec();
+
+        // The PACI payload header is the payload header with F and Type
+        // taken from A and cType
+        let payload_header =
+            H265NALUHeader::from_parts(paci.a, paci.ctype, header.layer_id(), header.tid());
+        let mut inner = payload_header.to_bytes().to_vec();
+        inner.extend_from_slice(&packet[payload_offset..]);
+
+        self.paci = Some(paci);
+        self.parse(&inner, false)
+    }
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> Result<u16, Error> {
+    if offset + 2 > packet.len() {
+        return Err(Error::new("Payload is not large enough".to_string()));
+    }
+    Ok(((packet[offset] as u16) << 8) | packet[offset + 1] as u16)
+}
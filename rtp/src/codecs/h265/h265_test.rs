@@ -0,0 +1,299 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+const VPS: [u8; 3] = [0x40, 0x01, 0xA1];
+const SPS: [u8; 3] = [0x42, 0x01, 0xA2];
+const PPS: [u8; 3] = [0x44, 0x01, 0xA3];
+const AUD: [u8; 3] = [0x46, 0x01, 0x50];
+
+fn annexb(nalus: &[&[u8]]) -> Vec<u8> {
+    let mut out = vec![];
+    for nalu in nalus {
+        out.extend_from_slice(&ANNEXB_NALUSTART_CODE);
+        out.extend_from_slice(nalu);
+    }
+    out
+}
+
+fn payload(pck: &mut H265Payloader, mtu: isize, data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut reader = BufReader::new(data);
+    pck.payload(mtu, &mut reader)
+}
+
+fn depacketize(pck: &mut H265Packet, raw: &[u8]) -> Result<(), Error> {
+    let mut reader = BufReader::new(raw);
+    pck.depacketize(&mut reader)
+}
+
+#[test]
+fn test_h265_nalu_header() {
+    let header = H265NALUHeader::new(0x26, 0x01);
+    assert!(!header.f());
+    assert_eq!(header.nalu_type(), 19);
+    assert_eq!(header.layer_id(), 0);
+    assert_eq!(header.tid(), 1);
+
+    let header = H265NALUHeader::new(0xE1, 0x0B);
+    assert!(header.f());
+    assert_eq!(header.nalu_type(), 48);
+    assert_eq!(header.layer_id(), 33);
+    assert_eq!(header.tid(), 3);
+
+    assert_eq!(H265NALUHeader::from_parts(true, 48, 33, 3), header);
+}
+
+#[test]
+fn test_h265_payload() -> Result<(), Error> {
+    let mut pck = H265Payloader::default();
+
+    // Empty payload
+    assert!(payload(&mut pck, 100, &[])?.is_empty());
+
+    // Negative MTU
+    assert!(payload(&mut pck, -1, &VPS)?.is_empty());
+
+    // NAL unit without start code
+    let payloads = payload(&mut pck, 100, &[0x02, 0x01, 0xAA])?;
+    assert_eq!(payloads, vec![vec![0x02, 0x01, 0xAA]]);
+
+    // Aggregation Packet, AUD is dropped
+    let data = annexb(&[&AUD, &VPS, &SPS, &PPS]);
+    let payloads = payload(&mut pck, 100, &data)?;
+    assert_eq!(
+        payloads,
+        vec![vec![
+            0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x03, 0x42, 0x01, 0xA2, 0x00, 0x03,
+            0x44, 0x01, 0xA3,
+        ]]
+    );
+
+    // Aggregation limited by MTU, the remaining NAL unit is sent alone
+    let payloads = payload(&mut pck, 12, &data)?;
+    assert_eq!(
+        payloads,
+        vec![
+            vec![0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x03, 0x42, 0x01, 0xA2],
+            PPS.to_vec(),
+        ]
+    );
+
+    // Fragmentation Units
+    let idr = [0x26, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let payloads = payload(&mut pck, 6, &annexb(&[&VPS, &idr]))?;
+    assert_eq!(
+        payloads,
+        vec![
+            VPS.to_vec(),
+            vec![0x62, 0x01, 0x93, 1, 2, 3],
+            vec![0x62, 0x01, 0x13, 4, 5, 6],
+            vec![0x62, 0x01, 0x13, 7, 8, 9],
+            vec![0x62, 0x01, 0x53, 10],
+        ]
+    );
+
+    // MTU too small for a Fragmentation Unit
+    assert!(payload(&mut pck, 3, &idr)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_h265_payload_donl() -> Result<(), Error> {
+    let mut pck = H265Payloader {
+        add_donl: true,
+        ..Default::default()
+    };
+
+    // An Aggregation Packet would exceed the MTU, every NAL unit is sent
+    // alone with its own DONL
+    let idr = [0x26, 0x01, 1, 2, 3, 4, 5, 6];
+    let payloads = payload(&mut pck, 8, &annexb(&[&VPS, &SPS, &PPS, &idr]))?;
+    assert_eq!(
+        payloads,
+        vec![
+            vec![0x40, 0x01, 0x00, 0x00, 0xA1],
+            vec![0x42, 0x01, 0x00, 0x01, 0xA2],
+            vec![0x44, 0x01, 0x00, 0x02, 0xA3],
+            vec![0x62, 0x01, 0x93, 0x00, 0x03, 1, 2, 3],
+            vec![0x62, 0x01, 0x53, 4, 5, 6],
+        ]
+    );
+
+    // DOND follows the first NAL unit of an Aggregation Packet
+    let payloads = payload(&mut pck, 100, &annexb(&[&VPS, &SPS]))?;
+    assert_eq!(
+        payloads,
+        vec![vec![
+            0x60, 0x01, 0x00, 0x04, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x00, 0x03, 0x42, 0x01,
+            0xA2,
+        ]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_h265_unmarshal() -> Result<(), Error> {
+    let mut pck = H265Packet::default();
+
+    // Empty packet
+    assert!(depacketize(&mut pck, &[]).is_err());
+    // NAL unit header only
+    assert!(depacketize(&mut pck, &[0x02, 0x01]).is_err());
+    // Forbidden zero bit
+    assert!(depacketize(&mut pck, &[0x82, 0x01, 0xAA]).is_err());
+    // Unhandled type
+    assert!(depacketize(&mut pck, &[0x66, 0x01, 0xAA]).is_err());
+
+    // Single NAL unit
+    depacketize(&mut pck, &[0x02, 0x01, 0xAA])?;
+    assert_eq!(pck.payload, annexb(&[&[0x02, 0x01, 0xAA]]));
+    assert_eq!(pck.donl, None);
+
+    // Aggregation Packet
+    depacketize(
+        &mut pck,
+        &[
+            0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x03, 0x42, 0x01, 0xA2,
+        ],
+    )?;
+    assert_eq!(pck.payload, annexb(&[&VPS, &SPS]));
+
+    // Aggregation Packet with a single NAL unit
+    assert!(depacketize(&mut pck, &[0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0xA1]).is_err());
+    // Aggregation Packet with declared size larger than buffer
+    assert!(depacketize(
+        &mut pck,
+        &[0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x04, 0x42]
+    )
+    .is_err());
+
+    // Fragmentation Unit with start and end bits
+    assert!(depacketize(&mut pck, &[0x62, 0x01, 0xD3, 0xAA]).is_err());
+    // Fragmentation Unit without payload
+    assert!(depacketize(&mut pck, &[0x62, 0x01, 0x13]).is_err());
+
+    // Fragmentation Units
+    depacketize(&mut pck, &[0x62, 0x01, 0x93, 1, 2, 3])?;
+    assert_eq!(
+        pck.payload,
+        vec![0x00, 0x00, 0x00, 0x01, 0x26, 0x01, 1, 2, 3]
+    );
+    depacketize(&mut pck, &[0x62, 0x01, 0x13, 4, 5, 6])?;
+    assert_eq!(pck.payload, vec![4, 5, 6]);
+    depacketize(&mut pck, &[0x62, 0x01, 0x53, 7])?;
+    assert_eq!(pck.payload, vec![7]);
+
+    Ok(())
+}
+
+#[test]
+fn test_h265_unmarshal_donl() -> Result<(), Error> {
+    let mut pck = H265Packet {
+        with_donl: true,
+        ..Default::default()
+    };
+
+    // Single NAL unit without room for DONL
+    assert!(depacketize(&mut pck, &[0x02, 0x01, 0x00]).is_err());
+
+    depacketize(&mut pck, &[0x02, 0x01, 0x00, 0x05, 0xAA])?;
+    assert_eq!(pck.donl, Some(5));
+    assert_eq!(pck.payload, annexb(&[&[0x02, 0x01, 0xAA]]));
+
+    depacketize(
+        &mut pck,
+        &[
+            0x60, 0x01, 0x01, 0x00, 0x00, 0x03, 0x40, 0x01, 0xA1, 0x00, 0x00, 0x03, 0x42, 0x01,
+            0xA2,
+        ],
+    )?;
+    assert_eq!(pck.donl, Some(256));
+    assert_eq!(pck.payload, annexb(&[&VPS, &SPS]));
+
+    depacketize(&mut pck, &[0x62, 0x01, 0x93, 0x00, 0x07, 1, 2])?;
+    assert_eq!(pck.donl, Some(7));
+    assert_eq!(pck.payload, vec![0x00, 0x00, 0x00, 0x01, 0x26, 0x01, 1, 2]);
+
+    // Only the first fragment carries DONL
+    depacketize(&mut pck, &[0x62, 0x01, 0x53, 3])?;
+    assert_eq!(pck.donl, None);
+    assert_eq!(pck.payload, vec![3]);
+
+    Ok(())
+}
+
+#[test]
+fn test_h265_unmarshal_paci() -> Result<(), Error> {
+    let mut pck = H265Packet::default();
+
+    // PACI carrying a single NAL unit with a two byte PHES
+    depacketize(&mut pck, &[0x64, 0x01, 0x02, 0x28, 0xDE, 0xAD, 0xAA])?;
+    assert_eq!(
+        pck.paci,
+        Some(H265PACI {
+            a: false,
+            ctype: 1,
+            phs_size: 2,
+            f0: true,
+            f1: false,
+            f2: false,
+            y: false,
+            phes: vec![0xDE, 0xAD],
+        })
+    );
+    assert_eq!(pck.payload, annexb(&[&[0x02, 0x01, 0xAA]]));
+
+    // PACI carrying a Fragmentation Unit
+    depacketize(&mut pck, &[0x64, 0x01, 0x62, 0x00, 0x93, 1])?;
+    assert_eq!(pck.paci.as_ref().map(|p| p.ctype), Some(49));
+    assert_eq!(pck.payload, vec![0x00, 0x00, 0x00, 0x01, 0x26, 0x01, 1]);
+
+    // Nested PACI
+    assert!(depacketize(&mut pck, &[0x64, 0x01, 0x64, 0x00, 0xAA]).is_err());
+    // PHES larger than buffer
+    assert!(depacketize(&mut pck, &[0x64, 0x01, 0x02, 0x40, 0xAA]).is_err());
+    // Truncated PACI header
+    assert!(depacketize(&mut pck, &[0x64, 0x01, 0x02]).is_err());
+
+    // Single NAL unit clears PACI
+    depacketize(&mut pck, &[0x02, 0x01, 0xAA])?;
+    assert_eq!(pck.paci, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_h265_round_trip() -> Result<(), Error> {
+    let mut idr = vec![0x26, 0x01];
+    idr.extend((0..200).map(|i| i as u8));
+    let trail = [0x02, 0x01, 0xBB, 0xCC];
+    let data = annexb(&[&VPS, &SPS, &PPS, &idr, &trail]);
+
+    for &add_donl in &[false, true] {
+        for &mtu in &[10, 50, 1200] {
+            let mut payloader = H265Payloader {
+                add_donl,
+                ..Default::default()
+            };
+            let payloads = payload(&mut payloader, mtu, &data)?;
+            assert!(payloads.iter().all(|p| p.len() <= mtu as usize));
+
+            let mut pck = H265Packet {
+                with_donl: add_donl,
+                ..Default::default()
+            };
+            let mut out = vec![];
+            for p in &payloads {
+                depacketize(&mut pck, p)?;
+                out.extend_from_slice(&pck.payload);
+            }
+            assert_eq!(out, data, "mtu {} donl {}", mtu, add_donl);
+        }
+    }
+
+    Ok(())
+}
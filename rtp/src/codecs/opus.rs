@@ -1,39 +1,316 @@
 use crate::packetizer::{Depacketizer, Payloader};
 
 use std::io::Read;
+use std::time::Duration;
 
 use util::Error;
 
 #[cfg(test)]
 mod opus_test;
 
+// Opus RTP clock rate, RFC 7587 4.1
+pub const OPUS_CLOCK_RATE: u32 = 48000;
+
+// Largest frame of an Opus packet, RFC 6716 3.2.1
+const MAX_FRAME_LENGTH: usize = 1275;
+// Longest duration of an Opus packet, RFC 6716 3.2.5
+const MAX_PACKET_SAMPLES: u32 = 5760;
+
+const TOC_STEREO_MASK: u8 = 0x04;
+const TOC_CODE_MASK: u8 = 0x03;
+const FRAME_COUNT_VBR_MASK: u8 = 0x80;
+const FRAME_COUNT_PADDING_MASK: u8 = 0x40;
+const FRAME_COUNT_MASK: u8 = 0x3F;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OpusMode {
+    Silk,
+    Hybrid,
+    Celt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OpusBandwidth {
+    Narrowband,
+    Mediumband,
+    Wideband,
+    SuperWideband,
+    Fullband,
+}
+
+// OpusToc is the table-of-contents byte starting every Opus packet, RFC 6716
+// 3.1
+//
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// | config  |s| c |
+// +-+-+-+-+-+-+-+-+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OpusToc {
+    pub config: u8,
+    pub stereo: bool,
+    // Frame count code, 0 to 3
+    pub code: u8,
+}
+
+impl OpusToc {
+    pub fn new(toc: u8) -> Self {
+        OpusToc {
+            config: toc >> 3,
+            stereo: toc & TOC_STEREO_MASK != 0,
+            code: toc & TOC_CODE_MASK,
+        }
+    }
+
+    pub fn mode(&self) -> OpusMode {
+        match self.config {
+            0..=11 => OpusMode::Silk,
+            12..=15 => OpusMode::Hybrid,
+            _ => OpusMode::Celt,
+        }
+    }
+
+    pub fn bandwidth(&self) -> OpusBandwidth {
+        match self.config {
+            0..=3 | 16..=19 => OpusBandwidth::Narrowband,
+            4..=7 => OpusBandwidth::Mediumband,
+            8..=11 | 20..=23 => OpusBandwidth::Wideband,
+            12..=13 | 24..=27 => OpusBandwidth::SuperWideband,
+            _ => OpusBandwidth::Fullband,
+        }
+    }
+
+    // frame_samples returns the number of samples of each frame at 48 kHz
+    pub fn frame_samples(&self) -> u32 {
+        match self.mode() {
+            // 10, 20, 40 or 60 ms
+            OpusMode::Silk => [480, 960, 1920, 2880][(self.config % 4) as usize],
+            // 10 or 20 ms
+            OpusMode::Hybrid => [480, 960][(self.config % 2) as usize],
+            // 2.5, 5, 10 or 20 ms
+            OpusMode::Celt => [120, 240, 480, 960][(self.config % 4) as usize],
+        }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        samples_to_duration(self.frame_samples())
+    }
+}
+
+// OpusPacketInfo describes the frames of an Opus packet, RFC 6716 3.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpusPacketInfo {
+    pub toc: OpusToc,
+    pub frame_lengths: Vec<usize>,
+    // Bytes of padding of code 3 packets, excluding the padding length
+    pub padding: usize,
+}
+
+impl OpusPacketInfo {
+    // parse validates an Opus packet against the requirements of RFC 6716
+    // 3.4 and returns its layout
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.is_empty() {
+            return Err(Error::new("opus packet is empty".to_owned()));
+        }
+        let toc = OpusToc::new(data[0]);
+        let mut rest = &data[1..];
+        let mut padding = 0;
+
+        let frame_lengths = match toc.code {
+            0 => vec![rest.len()],
+            1 => {
+                if !rest.len().is_multiple_of(2) {
+                    return Err(Error::new(format!(
+                        "opus code 1 packet has an odd length of {} bytes",
+                        rest.len()
+                    )));
+                }
+                vec![rest.len() / 2; 2]
+            }
+            2 => {
+                let (first, size) = parse_frame_length(rest)?;
+                rest = &rest[size..];
+                if first > rest.len() {
+                    return Err(Error::new(format!(
+                        "opus frame of {} bytes exceeds the packet",
+                        first
+                    )));
+                }
+                vec![first, rest.len() - first]
+            }
+            _ => {
+                if rest.is_empty() {
+                    return Err(Error::new(
+                        "opus code 3 packet is missing the frame count".to_owned(),
+                    ));
+                }
+                let frame_count_byte = rest[0];
+                rest = &rest[1..];
+                let count = (frame_count_byte & FRAME_COUNT_MASK) as usize;
+                if count == 0 {
+                    return Err(Error::new("opus code 3 packet has no frames".to_owned()));
+                }
+                if count as u32 * toc.frame_samples() > MAX_PACKET_SAMPLES {
+                    return Err(Error::new(format!(
+                        "opus packet of {} frames exceeds 120 ms",
+                        count
+                    )));
+                }
+
+                if frame_count_byte & FRAME_COUNT_PADDING_MASK != 0 {
+                    // A padding length byte of 255 adds 254 bytes and is
+                    // followed by another one
+                    loop {
+                        let (&byte, tail) = rest.split_first().ok_or_else(|| {
+                            Error::new("opus padding length is truncated".to_owned())
+                        })?;
+                        rest = tail;
+                        if byte == 255 {
+                            padding += 254;
+                        } else {
+                            padding += byte as usize;
+                            break;
+                        }
+                    }
+                    if padding > rest.len() {
+                        return Err(Error::new(format!(
+                            "opus padding of {} bytes exceeds the packet",
+                            padding
+                        )));
+                    }
+                    rest = &rest[..rest.len() - padding];
+                }
+
+                if frame_count_byte & FRAME_COUNT_VBR_MASK != 0 {
+                    let mut frame_lengths = Vec::with_capacity(count);
+                    let mut total = 0;
+                    for _ in 0..count - 1 {
+                        let (length, size) = parse_frame_length(rest)?;
+                        rest = &rest[size..];
+                        frame_lengths.push(length);
+                        total += length;
+                    }
+                    if total > rest.len() {
+                        return Err(Error::new(format!(
+                            "opus frames of {} bytes exceed the packet",
+                            total
+                        )));
+                    }
+                    frame_lengths.push(rest.len() - total);
+                    frame_lengths
+                } else {
+                    if !rest.len().is_multiple_of(count) {
+                        return Err(Error::new(format!(
+                            "opus {} bytes cannot be split into {} frames",
+                            rest.len(),
+                            count
+                        )));
+                    }
+                    vec![rest.len() / count; count]
+                }
+            }
+        };
+
+        if let Some(length) = frame_lengths.iter().find(|l| **l > MAX_FRAME_LENGTH) {
+            return Err(Error::new(format!(
+                "opus frame of {} bytes exceeds {} bytes",
+                length, MAX_FRAME_LENGTH
+            )));
+        }
+
+        Ok(OpusPacketInfo {
+            toc,
+            frame_lengths,
+            padding,
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_lengths.len()
+    }
+
+    // samples returns the number of samples of the packet at the RTP clock
+    // rate, which the timestamp of the next packet advances by
+    pub fn samples(&self) -> u32 {
+        self.toc.frame_samples() * self.frame_count() as u32
+    }
+
+    pub fn duration(&self) -> Duration {
+        samples_to_duration(self.samples())
+    }
+}
+
+// parse_frame_length returns a frame length coded in one or two bytes and
+// the number of bytes used, RFC 6716 3.2.1
+fn parse_frame_length(data: &[u8]) -> Result<(usize, usize), Error> {
+    match data {
+        [] => Err(Error::new("opus frame length is truncated".to_owned())),
+        [first, ..] if *first < 252 => Ok((*first as usize, 1)),
+        [first, second, ..] => Ok((*second as usize * 4 + *first as usize, 2)),
+        _ => Err(Error::new("opus frame length is truncated".to_owned())),
+    }
+}
+
+fn samples_to_duration(samples: u32) -> Duration {
+    Duration::from_micros(samples as u64 * 1_000_000 / OPUS_CLOCK_RATE as u64)
+}
+
+// OpusPayloader payloads an Opus packet
+//
+// An Opus packet is carried whole in a single RTP packet, RFC 7587 4.2, so
+// packets larger than the MTU are rejected rather than fragmented. The
+// encoder must produce packets of fewer frames or a lower bitrate instead.
 pub struct OpusPayloader;
 
 impl Payloader for OpusPayloader {
-    fn payload<R: Read>(&self, _mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
         let mut payload = vec![];
         reader.read_to_end(&mut payload)?;
         if payload.is_empty() {
-            Ok(vec![])
-        } else {
-            Ok(vec![payload])
+            return Ok(vec![]);
+        }
+
+        OpusPacketInfo::parse(&payload)?;
+        if payload.len() as isize > mtu {
+            return Err(Error::new(format!(
+                "opus packet of {} bytes exceeds the mtu of {} bytes",
+                payload.len(),
+                mtu
+            )));
         }
+
+        Ok(vec![payload])
     }
 }
 
 #[derive(Debug, Default)]
 pub struct OpusPacket {
     payload: Vec<u8>,
+    info: Option<OpusPacketInfo>,
 }
 
 impl Depacketizer for OpusPacket {
     fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
         self.payload.clear();
+        self.info = None;
         reader.read_to_end(&mut self.payload)?;
         if self.payload.is_empty() {
-            Err(Error::new("Payload is not large enough".to_string()))
-        } else {
-            Ok(())
+            return Err(Error::new("Payload is not large enough".to_string()));
         }
+
+        self.info = Some(OpusPacketInfo::parse(&self.payload)?);
+        Ok(())
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+impl OpusPacket {
+    // info returns the layout of the last depacketized Opus packet
+    pub fn info(&self) -> Option<&OpusPacketInfo> {
+        self.info.as_ref()
     }
 }
@@ -1,6 +1,7 @@
 use super::*;
 
 use std::io::BufReader;
+use std::time::Duration;
 
 use util::Error;
 
@@ -25,7 +26,7 @@ fn test_opus_unmarshal() -> Result<(), Error> {
 
 #[test]
 fn test_opus_payload() -> Result<(), Error> {
-    let pck = OpusPayloader;
+    let mut pck = OpusPayloader;
     let empty = vec![];
     let payload = vec![0x90, 0x90, 0x90];
 
@@ -34,20 +35,209 @@ fn test_opus_payload() -> Result<(), Error> {
     let result = pck.payload(1, &mut reader)?;
     assert!(result.is_empty(), "Generated payload should be empty");
 
-    // Positive MTU, small payload
+    // Payload fitting the MTU
     let mut reader = BufReader::new(payload.as_slice());
-    let result = pck.payload(1, &mut reader)?;
-    assert_eq!(result.len(), 1, "Generated payload should be the 1");
+    let result = pck.payload(3, &mut reader)?;
+    assert_eq!(result, vec![payload.clone()], "Payload must be same");
 
-    // Negative MTU, small payload
-    let mut reader = BufReader::new(payload.as_slice());
-    let result = pck.payload(-1, &mut reader)?;
-    assert_eq!(result.len(), 1, "Generated payload should be the 1");
+    // Opus packets are not fragmented
+    for mtu in &[2, 1, -1] {
+        let mut reader = BufReader::new(payload.as_slice());
+        let result = pck.payload(*mtu, &mut reader);
+        assert!(result.is_err(), "Payload larger than MTU {} must fail", mtu);
+    }
 
-    // Positive MTU, small payload
-    let mut reader = BufReader::new(payload.as_slice());
-    let result = pck.payload(2, &mut reader)?;
-    assert_eq!(result.len(), 1, "Generated payload should be the 1");
+    // Malformed packet, a code 1 packet of an odd length
+    let malformed = vec![0x91, 0x90, 0x90, 0x90];
+    let mut reader = BufReader::new(malformed.as_slice());
+    assert!(pck.payload(100, &mut reader).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_opus_toc() {
+    struct TestCase {
+        toc: u8,
+        mode: OpusMode,
+        bandwidth: OpusBandwidth,
+        frame_samples: u32,
+        stereo: bool,
+    }
+
+    let test_cases = vec![
+        TestCase {
+            toc: 0x00,
+            mode: OpusMode::Silk,
+            bandwidth: OpusBandwidth::Narrowband,
+            frame_samples: 480,
+            stereo: false,
+        },
+        TestCase {
+            toc: 11 << 3 | 0x04,
+            mode: OpusMode::Silk,
+            bandwidth: OpusBandwidth::Wideband,
+            frame_samples: 2880,
+            stereo: true,
+        },
+        TestCase {
+            toc: 13 << 3,
+            mode: OpusMode::Hybrid,
+            bandwidth: OpusBandwidth::SuperWideband,
+            frame_samples: 960,
+            stereo: false,
+        },
+        TestCase {
+            toc: 14 << 3,
+            mode: OpusMode::Hybrid,
+            bandwidth: OpusBandwidth::Fullband,
+            frame_samples: 480,
+            stereo: false,
+        },
+        TestCase {
+            toc: 16 << 3,
+            mode: OpusMode::Celt,
+            bandwidth: OpusBandwidth::Narrowband,
+            frame_samples: 120,
+            stereo: false,
+        },
+        TestCase {
+            toc: 31 << 3 | 0x07,
+            mode: OpusMode::Celt,
+            bandwidth: OpusBandwidth::Fullband,
+            frame_samples: 960,
+            stereo: true,
+        },
+    ];
+
+    for t in test_cases {
+        let toc = OpusToc::new(t.toc);
+        assert_eq!(toc.mode(), t.mode, "toc {:#x}", t.toc);
+        assert_eq!(toc.bandwidth(), t.bandwidth, "toc {:#x}", t.toc);
+        assert_eq!(toc.frame_samples(), t.frame_samples, "toc {:#x}", t.toc);
+        assert_eq!(toc.stereo, t.stereo, "toc {:#x}", t.toc);
+        assert_eq!(toc.code, t.toc & 0x03, "toc {:#x}", t.toc);
+    }
+
+    // 2.5 ms CELT frames
+    assert_eq!(
+        OpusToc::new(16 << 3).frame_duration(),
+        Duration::from_micros(2500)
+    );
+}
+
+#[test]
+fn test_opus_packet_info() -> Result<(), Error> {
+    // 20 ms fullband CELT frames
+    let toc = 31 << 3;
+
+    // (packet, frame lengths, padding)
+    let test_cases: Vec<(Vec<u8>, Vec<usize>, usize)> = vec![
+        // Code 0, a single frame, possibly empty for DTX
+        (vec![toc], vec![0], 0),
+        (vec![toc, 1, 2, 3], vec![3], 0),
+        // Code 1, two frames of equal length
+        (vec![toc | 1, 1, 2, 3, 4], vec![2, 2], 0),
+        // Code 2, the length of the first frame is coded
+        (vec![toc | 2, 1, 0xAA, 0xBB, 0xCC], vec![1, 2], 0),
+        (
+            [&[toc | 2, 252, 1][..], &[0; 300][..]].concat(),
+            vec![256, 44],
+            0,
+        ),
+        // Code 3 CBR with padding
+        (
+            [&[toc | 3, 0x43, 2][..], &[0; 8][..]].concat(),
+            vec![2, 2, 2],
+            2,
+        ),
+        // Code 3 VBR
+        (vec![toc | 3, 0x82, 1, 0xAA, 0xBB, 0xCC], vec![1, 2], 0),
+        // Code 3 with a padding length over several bytes
+        (
+            [&[toc | 3, 0x41, 255, 1][..], &[0; 256][..]].concat(),
+            vec![1],
+            255,
+        ),
+    ];
+
+    for (packet, frame_lengths, padding) in test_cases {
+        let info = OpusPacketInfo::parse(&packet)?;
+        assert_eq!(
+            info.frame_lengths,
+            frame_lengths,
+            "packet {:?}",
+            &packet[..3.min(packet.len())]
+        );
+        assert_eq!(info.padding, padding);
+        assert_eq!(info.samples(), 960 * frame_lengths.len() as u32);
+        assert_eq!(
+            info.duration(),
+            Duration::from_millis(20 * frame_lengths.len() as u64)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_opus_packet_info_malformed() {
+    let toc = 31 << 3;
+    let test_cases: Vec<(&str, Vec<u8>)> = vec![
+        ("empty packet", vec![]),
+        (
+            "code 0 frame too long",
+            [&[toc][..], &[0; 1276][..]].concat(),
+        ),
+        ("code 1 odd length", vec![toc | 1, 1, 2, 3]),
+        ("code 2 missing length", vec![toc | 2]),
+        ("code 2 truncated two byte length", vec![toc | 2, 252]),
+        ("code 2 first frame too long", vec![toc | 2, 5, 1, 2]),
+        ("code 3 missing frame count", vec![toc | 3]),
+        ("code 3 no frames", vec![toc | 3, 0x00]),
+        // 7 frames of 20 ms exceed 120 ms
+        ("code 3 too long", vec![toc | 3, 0x07, 0, 0, 0, 0, 0, 0, 0]),
+        ("code 3 CBR uneven", vec![toc | 3, 0x02, 1, 2, 3]),
+        ("code 3 padding truncated", vec![toc | 3, 0x41, 255]),
+        ("code 3 padding too long", vec![toc | 3, 0x41, 4, 0, 0]),
+        ("code 3 VBR frames too long", vec![toc | 3, 0x82, 4, 1, 2]),
+        ("code 3 VBR missing length", vec![toc | 3, 0x83, 1]),
+    ];
+
+    for (name, packet) in test_cases {
+        assert!(OpusPacketInfo::parse(&packet).is_err(), "{}", name);
+    }
+
+    // 2.5 ms frames allow up to 48 frames
+    let mut packet = vec![16 << 3 | 3, 48];
+    assert_eq!(
+        OpusPacketInfo::parse(&packet)
+            .map(|info| info.samples())
+            .ok(),
+        Some(5760)
+    );
+    packet[1] = 49;
+    assert!(OpusPacketInfo::parse(&packet).is_err());
+}
+
+#[test]
+fn test_opus_depacketize_info() -> Result<(), Error> {
+    let mut pck = OpusPacket::default();
+    let raw_bytes = vec![1 << 3 | 0x01, 0xAA, 0xBB];
+    let mut reader = BufReader::new(raw_bytes.as_slice());
+    pck.depacketize(&mut reader)?;
+
+    let info = pck.info().expect("info must be set");
+    assert_eq!(info.toc.mode(), OpusMode::Silk);
+    assert_eq!(info.frame_count(), 2);
+    // Two 20 ms frames
+    assert_eq!(info.samples(), 1920);
+
+    // A malformed packet clears the info
+    let raw_bytes = vec![0x01, 0xAA];
+    let mut reader = BufReader::new(raw_bytes.as_slice());
+    assert!(pck.depacketize(&mut reader).is_err());
+    assert!(pck.info().is_none());
 
     Ok(())
 }
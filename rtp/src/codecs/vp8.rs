@@ -13,7 +13,7 @@ const VP8HEADER_SIZE: isize = 1;
 pub struct VP8Payloader;
 
 impl Payloader for VP8Payloader {
-    fn payload<R: Read>(&self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
         /*
          * https://tools.ietf.org/html/rfc7741#section-4.2
          *
@@ -70,28 +70,32 @@ impl Payloader for VP8Payloader {
     }
 }
 
-#[derive(Debug, Default)]
-struct VP8Packet {
+// VP8Packet represents the VP8 payload descriptor and payload of a RTP packet
+#[derive(Debug, Default, Clone)]
+pub struct VP8Packet {
     // Required Header
-    x: u8,   /* extended controlbits present */
-    n: u8,   /* (non-reference frame)  when set to 1 this frame can be discarded */
-    s: u8,   /* start of VP8 partition */
-    pid: u8, /* partition index */
+    pub x: u8,   /* extended controlbits present */
+    pub n: u8,   /* (non-reference frame)  when set to 1 this frame can be discarded */
+    pub s: u8,   /* start of VP8 partition */
+    pub pid: u8, /* partition index */
 
     // Optional Header
-    i: u8, /* 1 if PictureID is present */
-    l: u8, /* 1 if TL0PICIDX is present */
-    t: u8, /* 1 if TID is present */
-    k: u8, /* 1 if KEYIDX is present */
+    pub i: u8, /* 1 if PictureID is present */
+    pub l: u8, /* 1 if TL0PICIDX is present */
+    pub t: u8, /* 1 if TID is present */
+    pub k: u8, /* 1 if KEYIDX is present */
 
-    picture_id: u16, /* 8 or 16 bits, picture ID */
-    tl0_pic_idx: u8, /* 8 bits temporal level zero index */
+    pub picture_id: u16, /* 8 or 16 bits, picture ID */
+    pub tl0_pic_idx: u8, /* 8 bits temporal level zero index */
 
-    tid: u8,
-    y: u8,
-    key_idx: u8,
+    pub tid: u8,
+    pub y: u8,
+    pub key_idx: u8,
 
-    payload: Vec<u8>,
+    pub payload: Vec<u8>,
+
+    // Set when the payload starts a key frame
+    pub keyframe: bool,
 }
 
 impl Depacketizer for VP8Packet {
@@ -111,7 +115,7 @@ impl Depacketizer for VP8Packet {
         //T/K:|TID|Y| KEYIDX  | (OPTIONAL)
         //    +-+-+-+-+-+-+-+-+
 
-        self.payload.clear();
+        *self = VP8Packet::default();
         let mut num_bytes = 0;
 
         let mut b = reader.read_u8()?;
@@ -129,11 +133,6 @@ impl Depacketizer for VP8Packet {
             self.l = (b & 0x40) >> 6;
             self.t = (b & 0x20) >> 5;
             self.k = (b & 0x10) >> 4;
-        } else {
-            self.i = 0;
-            self.l = 0;
-            self.t = 0;
-            self.k = 0;
         }
 
         if self.i == 1 {
@@ -162,17 +161,177 @@ impl Depacketizer for VP8Packet {
             self.key_idx = b & 0b00011111;
         }
 
+        // The first byte skipped after a short descriptor is the VP8
+        // payload header
+        let mut payload_header = None;
         while num_bytes < 3 {
-            reader.read_u8()?;
+            let skipped = reader.read_u8()?;
+            payload_header.get_or_insert(skipped);
             num_bytes += 1;
         }
 
         reader.read_to_end(&mut self.payload)?;
 
         if self.payload.is_empty() {
-            Err(Error::new("Payload is not large enough".to_string()))
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        // The payload header of a frame starts partition 0, its P bit is 0
+        // for key frames, RFC 7741 4.3
+        let payload_header = payload_header.unwrap_or(self.payload[0]);
+        self.keyframe = self.s == 1 && self.pid == 0 && payload_header & 0x01 == 0;
+
+        Ok(())
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // The S bit marks the start of a VP8 partition, a frame starts with
+    // partition 0
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.is_empty() {
+            false
         } else {
-            Ok(())
+            payload[0] & 0x10 != 0
+        }
+    }
+}
+
+// Offsets of the PictureID, with its M bit, and TL0PICIDX fields of a VP8
+// payload descriptor
+struct DescriptorOffsets {
+    picture_id: Option<(usize, bool)>,
+    tl0_pic_idx: Option<usize>,
+}
+
+fn descriptor_offsets(payload: &[u8]) -> Result<DescriptorOffsets, Error> {
+    let too_short = || Error::new("VP8 payload descriptor is truncated".to_string());
+
+    let b = *payload.first().ok_or_else(too_short)?;
+    if b & 0x80 == 0 {
+        return Ok(DescriptorOffsets {
+            picture_id: None,
+            tl0_pic_idx: None,
+        });
+    }
+    let x = *payload.get(1).ok_or_else(too_short)?;
+
+    let mut pos = 2;
+    let mut picture_id = None;
+    if x & 0x80 != 0 {
+        let m = *payload.get(pos).ok_or_else(too_short)? & 0x80 != 0;
+        picture_id = Some((pos, m));
+        pos += if m { 2 } else { 1 };
+    }
+
+    let mut tl0_pic_idx = None;
+    if x & 0x40 != 0 {
+        tl0_pic_idx = Some(pos);
+        pos += 1;
+    }
+
+    if pos > payload.len() {
+        return Err(too_short());
+    }
+    Ok(DescriptorOffsets {
+        picture_id,
+        tl0_pic_idx,
+    })
+}
+
+// VP8DescriptorRewriter renumbers the PictureID and TL0PICIDX of VP8
+// payload descriptors so a forwarded stream stays continuous when an SFU
+// switches between simulcast layers, each numbering its pictures on its
+// own.
+//
+// The fields are rewritten in place, a 7 bit PictureID stays 7 bits.
+#[derive(Debug, Default, Clone)]
+pub struct VP8DescriptorRewriter {
+    // Added to the fields of the current source, modulo their range
+    picture_id_offset: u16,
+    tl0_pic_idx_offset: u8,
+    // Newest fields forwarded, the PictureID with the mask of its 7 or 15
+    // bit field
+    last_picture_id: Option<(u16, u16)>,
+    last_tl0_pic_idx: Option<u8>,
+    // Offsets are computed again from the next packet
+    switching: bool,
+}
+
+impl VP8DescriptorRewriter {
+    pub fn new() -> Self {
+        VP8DescriptorRewriter::default()
+    }
+
+    // switch_source makes the next packet rewritten continue the numbering
+    // of the packets forwarded so far, it should start a key frame of the
+    // new source
+    pub fn switch_source(&mut self) {
+        self.switching = true;
+    }
+
+    // rewrite renumbers the payload descriptor of a VP8 payload
+    pub fn rewrite(&mut self, payload: &mut [u8]) -> Result<(), Error> {
+        let DescriptorOffsets {
+            picture_id,
+            tl0_pic_idx,
+        } = descriptor_offsets(payload)?;
+
+        if let Some((pos, m)) = picture_id {
+            let (value, mask) = if m {
+                (
+                    (((payload[pos] & 0x7F) as u16) << 8) | payload[pos + 1] as u16,
+                    0x7FFF,
+                )
+            } else {
+                ((payload[pos] & 0x7F) as u16, 0x7F)
+            };
+            if self.switching {
+                self.picture_id_offset = match self.last_picture_id {
+                    Some((last, _)) => last.wrapping_add(1).wrapping_sub(value) & mask,
+                    None => 0,
+                };
+            }
+
+            let rewritten = value.wrapping_add(self.picture_id_offset) & mask;
+            if m {
+                payload[pos] = 0x80 | (rewritten >> 8) as u8;
+                payload[pos + 1] = rewritten as u8;
+            } else {
+                payload[pos] = rewritten as u8;
+            }
+
+            match self.last_picture_id {
+                // Older in the space of the field, unless the field width changed
+                Some((last, last_mask))
+                    if last_mask == mask && rewritten.wrapping_sub(last) & mask > mask / 2 => {}
+                _ => self.last_picture_id = Some((rewritten, mask)),
+            }
+        }
+
+        if let Some(pos) = tl0_pic_idx {
+            let value = payload[pos];
+            if self.switching {
+                self.tl0_pic_idx_offset = match self.last_tl0_pic_idx {
+                    Some(last) => last.wrapping_add(1).wrapping_sub(value),
+                    None => 0,
+                };
+            }
+
+            let rewritten = value.wrapping_add(self.tl0_pic_idx_offset);
+            payload[pos] = rewritten;
+
+            match self.last_tl0_pic_idx {
+                Some(last) if rewritten.wrapping_sub(last) >= 0x80 => {}
+                _ => self.last_tl0_pic_idx = Some(rewritten),
+            }
+        }
+
+        if picture_id.is_some() || tl0_pic_idx.is_some() {
+            self.switching = false;
         }
+        Ok(())
     }
 }
@@ -114,9 +114,184 @@ fn test_vp8_unmarshal() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_vp8_descriptor_fields() -> Result<(), Error> {
+    let mut pck = VP8Packet::default();
+
+    // All flags, 16 bit PictureID 0x1234, TL0PICIDX 7, TID 2, Y, KEYIDX 5
+    let raw_bytes = vec![0xB0, 0xF0, 0x92, 0x34, 0x07, 0xB5, 0x00, 0x9D];
+    let mut reader = BufReader::new(raw_bytes.as_slice());
+    pck.depacketize(&mut reader)?;
+    assert_eq!(pck.n, 1, "N must be 1");
+    assert_eq!(pck.s, 1, "S must be 1");
+    assert_eq!(pck.picture_id, 0x1234, "PictureID must be 0x1234");
+    assert_eq!(pck.tl0_pic_idx, 7, "TL0PICIDX must be 7");
+    assert_eq!(pck.tid, 2, "TID must be 2");
+    assert_eq!(pck.y, 1, "Y must be 1");
+    assert_eq!(pck.key_idx, 21, "KEYIDX must be 21");
+    assert_eq!(pck.payload, vec![0x00, 0x9D]);
+
+    // Fields absent from the next packet are cleared
+    let raw_bytes = vec![0x10, 0x01, 0x02, 0x03];
+    let mut reader = BufReader::new(raw_bytes.as_slice());
+    pck.depacketize(&mut reader)?;
+    assert_eq!(pck.picture_id, 0, "PictureID must be cleared");
+    assert_eq!(pck.tl0_pic_idx, 0, "TL0PICIDX must be cleared");
+
+    Ok(())
+}
+
+#[test]
+fn test_vp8_keyframe() -> Result<(), Error> {
+    // (payload, keyframe)
+    let test_cases = vec![
+        // Start of partition 0, P bit 0
+        (vec![0x10, 0x00, 0x9D, 0x01], true),
+        // Extended descriptor with a 7 bit PictureID
+        (vec![0x90, 0x80, 0x05, 0x50, 0x9D], true),
+        // Interframe, P bit 1
+        (vec![0x10, 0x01, 0x9D, 0x01], false),
+        // Continuation of a partition
+        (vec![0x00, 0x00, 0x9D, 0x01], false),
+        // Start of partition 1
+        (vec![0x11, 0x00, 0x9D, 0x01], false),
+    ];
+
+    let mut pck = VP8Packet::default();
+    for (raw_bytes, keyframe) in test_cases {
+        let mut reader = BufReader::new(raw_bytes.as_slice());
+        pck.depacketize(&mut reader)?;
+        assert_eq!(pck.keyframe, keyframe, "payload {:?}", raw_bytes);
+    }
+
+    // The payloader marks the start of partition 0
+    let frame = vec![0x50, 0x9D, 0x01, 0x2A, 0x00, 0x00, 0x00, 0x00];
+    let mut reader = BufReader::new(frame.as_slice());
+    let payloads = VP8Payloader.payload(5, &mut reader)?;
+    let mut reader = BufReader::new(payloads[0].as_slice());
+    pck.depacketize(&mut reader)?;
+    assert!(pck.keyframe, "First payload must start a key frame");
+    let mut reader = BufReader::new(payloads[1].as_slice());
+    pck.depacketize(&mut reader)?;
+    assert!(!pck.keyframe, "Second payload must not start a key frame");
+
+    Ok(())
+}
+
+// descriptor returns a payload with a 15 bit PictureID and a TL0PICIDX
+fn descriptor(picture_id: u16, tl0_pic_idx: u8) -> Vec<u8> {
+    vec![
+        0x90,
+        0xC0,
+        0x80 | (picture_id >> 8) as u8,
+        picture_id as u8,
+        tl0_pic_idx,
+        0x00,
+    ]
+}
+
+fn rewritten_fields(payload: &[u8]) -> Result<(u16, u8), Error> {
+    let mut pck = VP8Packet::default();
+    let mut reader = BufReader::new(payload);
+    pck.depacketize(&mut reader)?;
+    Ok((pck.picture_id, pck.tl0_pic_idx))
+}
+
+#[test]
+fn test_vp8_descriptor_rewriter() -> Result<(), Error> {
+    let mut rewriter = VP8DescriptorRewriter::new();
+
+    // The first source is forwarded as is, two packets per picture
+    let mut output = vec![];
+    for (picture_id, tl0_pic_idx) in &[(100, 10), (100, 10), (101, 11), (101, 11)] {
+        let mut payload = descriptor(*picture_id, *tl0_pic_idx);
+        rewriter.rewrite(&mut payload)?;
+        output.push(rewritten_fields(&payload)?);
+    }
+
+    // The second source continues the numbering, across the 15 bit wrap
+    rewriter.switch_source();
+    for (picture_id, tl0_pic_idx) in &[(0x7FFF, 255), (0x7FFF, 255), (0, 0), (1, 1)] {
+        let mut payload = descriptor(*picture_id, *tl0_pic_idx);
+        rewriter.rewrite(&mut payload)?;
+        output.push(rewritten_fields(&payload)?);
+    }
+
+    // A reordered packet of an older picture does not move the numbering
+    let mut payload = descriptor(0x7FFF, 255);
+    rewriter.rewrite(&mut payload)?;
+    output.push(rewritten_fields(&payload)?);
+
+    // Back to the first source
+    rewriter.switch_source();
+    let mut payload = descriptor(105, 15);
+    rewriter.rewrite(&mut payload)?;
+    output.push(rewritten_fields(&payload)?);
+
+    assert_eq!(
+        output,
+        vec![
+            (100, 10),
+            (100, 10),
+            (101, 11),
+            (101, 11),
+            (102, 12),
+            (102, 12),
+            (103, 13),
+            (104, 14),
+            (102, 12),
+            (105, 15),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_vp8_descriptor_rewriter_short_picture_id() -> Result<(), Error> {
+    let mut rewriter = VP8DescriptorRewriter::new();
+
+    let mut payload = descriptor(1000, 0);
+    rewriter.rewrite(&mut payload)?;
+
+    // A 7 bit PictureID stays 7 bits and wraps at 128
+    rewriter.switch_source();
+    let mut payload = vec![0x90, 0x80, 0x05, 0x00];
+    rewriter.rewrite(&mut payload)?;
+    assert_eq!(payload, vec![0x90, 0x80, (1001 & 0x7F) as u8, 0x00]);
+
+    // A 7 bit source wraps at 128, a switch continues from the wrapped
+    // PictureID
+    let mut rewriter = VP8DescriptorRewriter::new();
+    let mut output = vec![];
+    for picture_id in &[0x7E, 0x7F, 0x00] {
+        let mut payload = vec![0x90, 0x80, *picture_id, 0x00];
+        rewriter.rewrite(&mut payload)?;
+        output.push(payload[2]);
+    }
+    rewriter.switch_source();
+    let mut payload = vec![0x90, 0x80, 0x40, 0x00];
+    rewriter.rewrite(&mut payload)?;
+    output.push(payload[2]);
+    assert_eq!(output, vec![0x7E, 0x7F, 0x00, 0x01]);
+
+    // Descriptors without the fields are left untouched
+    let mut payload = vec![0x10, 0x00];
+    rewriter.rewrite(&mut payload)?;
+    assert_eq!(payload, vec![0x10, 0x00]);
+
+    // Truncated descriptors are rejected
+    for truncated in &[vec![], vec![0x80], vec![0x80, 0x80], vec![0x80, 0x80, 0x80]] {
+        let mut payload = truncated.clone();
+        assert!(rewriter.rewrite(&mut payload).is_err());
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_vp8_payload() -> Result<(), Error> {
-    let pck = VP8Payloader;
+    let mut pck = VP8Payloader;
     let empty = vec![];
     let payload = vec![0x90, 0x90, 0x90];
 
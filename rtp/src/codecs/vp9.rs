@@ -0,0 +1,524 @@
+use crate::packetizer::{Depacketizer, Payloader};
+
+use std::io::Read;
+
+use util::Error;
+
+#[cfg(test)]
+mod vp9_test;
+
+const VP9HEADER_SIZE: usize = 1;
+const MAX_SPATIAL_LAYERS: u8 = 5;
+const MAX_VP9REF_PICS: usize = 3;
+
+// VP9 bitstream constants, https://storage.googleapis.com/downloads.webmproject.org/docs/vp9/vp9-bitstream-specification-v0.6-20160331.pdf
+const VP9_FRAME_MARKER: u8 = 2;
+const VP9_FRAME_SYNC_CODE: u32 = 0x49_83_42;
+const VP9_CS_RGB: u8 = 7;
+
+// BitReader reads a byte slice MSB first
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, pos: 0 }
+    }
+
+    fn read_bits(&mut self, n: usize) -> Option<u32> {
+        let mut v = 0u32;
+        for _ in 0..n {
+            let byte = *self.data.get(self.pos / 8)?;
+            let bit = (byte >> (7 - (self.pos % 8))) & 0x01;
+            v = (v << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Some(v)
+    }
+}
+
+// vp9_keyframe_size returns the frame size of a VP9 key frame, or None if
+// the frame is not a key frame.
+fn vp9_keyframe_size(frame: &[u8]) -> Option<(u16, u16)> {
+    let mut r = BitReader::new(frame);
+
+    if r.read_bits(2)? as u8 != VP9_FRAME_MARKER {
+        return None;
+    }
+    let profile_low_bit = r.read_bits(1)?;
+    let profile_high_bit = r.read_bits(1)?;
+    let profile = (profile_high_bit << 1) | profile_low_bit;
+    if profile == 3 {
+        // reserved_zero
+        r.read_bits(1)?;
+    }
+
+    // show_existing_frame
+    if r.read_bits(1)? == 1 {
+        return None;
+    }
+    // frame_type, 0 is KEY_FRAME
+    if r.read_bits(1)? != 0 {
+        return None;
+    }
+    // show_frame, error_resilient_mode
+    r.read_bits(2)?;
+
+    if r.read_bits(24)? != VP9_FRAME_SYNC_CODE {
+        return None;
+    }
+
+    // color_config
+    if profile >= 2 {
+        // ten_or_twelve_bit
+        r.read_bits(1)?;
+    }
+    let color_space = r.read_bits(3)? as u8;
+    if color_space != VP9_CS_RGB {
+        // color_range
+        r.read_bits(1)?;
+        if profile == 1 || profile == 3 {
+            // subsampling_x, subsampling_y, reserved_zero
+            r.read_bits(3)?;
+        }
+    } else if profile == 1 || profile == 3 {
+        // reserved_zero
+        r.read_bits(1)?;
+    }
+
+    // frame_size
+    let width = r.read_bits(16)? + 1;
+    let height = r.read_bits(16)? + 1;
+
+    Some((width as u16, height as u16))
+}
+
+// VP9Payloader payloads VP9 frames, one frame per call.
+//
+// Temporal layers are supported, spatial layers are not: every frame is sent
+// as spatial layer 0. Key frames carry a scalability structure describing that
+// single spatial layer with the resolution found in the frame header.
+#[derive(Debug, Default)]
+pub struct VP9Payloader {
+    // Flexible mode signals references with P_DIFF, non-flexible mode
+    // with TL0PICIDX and the picture group of the scalability structure
+    pub flexible_mode: bool,
+
+    // Temporal layer of the next frame
+    pub tid: u8,
+    // Switching up point
+    pub u: bool,
+
+    picture_id: u16,
+    tl0_pic_idx: u8,
+    started: bool,
+}
+
+impl VP9Payloader {
+    // Creates a payloader starting from a random picture ID
+    pub fn new(flexible_mode: bool) -> Self {
+        VP9Payloader {
+            flexible_mode,
+            picture_id: rand::random::<u16>() & 0x7FFF,
+            ..Default::default()
+        }
+    }
+
+    // Advances picture ID and TL0PICIDX when a new picture starts
+    fn next_picture(&mut self) {
+        if self.started {
+            self.picture_id = (self.picture_id + 1) & 0x7FFF;
+            if self.tid == 0 {
+                self.tl0_pic_idx = self.tl0_pic_idx.wrapping_add(1);
+            }
+        }
+        self.started = true;
+    }
+
+    fn descriptor(&self, keyframe: Option<(u16, u16)>, first: bool, last: bool) -> Vec<u8> {
+        /*
+         * https://tools.ietf.org/html/draft-ietf-payload-vp9-16#section-4.2
+         *
+         *       0 1 2 3 4 5 6 7
+         *      +-+-+-+-+-+-+-+-+
+         *      |I|P|L|F|B|E|V|Z| (REQUIRED)
+         *      +-+-+-+-+-+-+-+-+
+         * I:   |M| PICTURE ID  | (REQUIRED)
+         *      +-+-+-+-+-+-+-+-+
+         * M:   | EXTENDED PID  | (RECOMMENDED)
+         *      +-+-+-+-+-+-+-+-+
+         * L:   | TID |U| SID |D| (CONDITIONALLY RECOMMENDED)
+         *      +-+-+-+-+-+-+-+-+                             -\
+         *      |   TL0PICIDX   | (CONDITIONALLY REQUIRED)      - non-flexible mode only
+         *      +-+-+-+-+-+-+-+-+                             -/
+         * P,F: | P_DIFF      |N| (CONDITIONALLY REQUIRED)    - flexible mode only
+         *      +-+-+-+-+-+-+-+-+
+         * V:   | SS            |
+         *      | ..            |
+         *      +-+-+-+-+-+-+-+-+
+         */
+        let inter_picture = keyframe.is_none();
+        let ss = first && !inter_picture;
+
+        let mut b0 = 0xA0; // I and L
+        if inter_picture {
+            b0 |= 0x40;
+        }
+        if self.flexible_mode {
+            b0 |= 0x10;
+        }
+        if first {
+            b0 |= 0x08;
+        }
+        if last {
+            b0 |= 0x04;
+        }
+        if ss {
+            b0 |= 0x02;
+        }
+
+        let mut out = vec![
+            b0,
+            0x80 | (self.picture_id >> 8) as u8,
+            self.picture_id as u8,
+            (self.tid & 0x07) << 5 | (self.u as u8) << 4,
+        ];
+
+        if !self.flexible_mode {
+            out.push(self.tl0_pic_idx);
+        } else if inter_picture {
+            // Reference the previous picture
+            out.push(1 << 1);
+        }
+
+        if let Some((width, height)) = keyframe {
+            if ss {
+                /*
+                 *      +-+-+-+-+-+-+-+-+
+                 * V:   | N_S |Y|G|-|-|-|
+                 *      +-+-+-+-+-+-+-+-+
+                 * Y:   |     WIDTH     | (16 bits)
+                 *      |     HEIGHT    | (16 bits)
+                 *      +-+-+-+-+-+-+-+-+
+                 * G:   |      N_G      |
+                 *      +-+-+-+-+-+-+-+-+
+                 * N_G: |  T  |U| R |-|-|
+                 *      +-+-+-+-+-+-+-+-+
+                 *      |    P_DIFF     | R times
+                 *      +-+-+-+-+-+-+-+-+
+                 */
+                let g = !self.flexible_mode;
+                out.push(0x10 | if g { 0x08 } else { 0x00 });
+                out.extend_from_slice(&width.to_be_bytes());
+                out.extend_from_slice(&height.to_be_bytes());
+                if g {
+                    // A single picture referencing the previous one
+                    out.push(1);
+                    out.push(1 << 2);
+                    out.push(1);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+impl Payloader for VP9Payloader {
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+        let mut payload_data = vec![];
+        reader.read_to_end(&mut payload_data)?;
+
+        let mut payloads = vec![];
+        if payload_data.is_empty() || mtu <= 0 {
+            return Ok(payloads);
+        }
+
+        self.next_picture();
+        let keyframe = vp9_keyframe_size(&payload_data);
+
+        let mut payload_data_index = 0;
+        while payload_data_index < payload_data.len() {
+            let first = payload_data_index == 0;
+            let header_size = self.descriptor(keyframe, first, false).len();
+            let max_fragment_size = mtu as usize;
+            if max_fragment_size <= header_size {
+                return Ok(vec![]);
+            }
+
+            let current_fragment_size = std::cmp::min(
+                max_fragment_size - header_size,
+                payload_data.len() - payload_data_index,
+            );
+            let last = payload_data_index + current_fragment_size == payload_data.len();
+
+            let mut out = self.descriptor(keyframe, first, last);
+            out.extend_from_slice(
+                &payload_data[payload_data_index..payload_data_index + current_fragment_size],
+            );
+            payloads.push(out);
+
+            payload_data_index += current_fragment_size;
+        }
+
+        Ok(payloads)
+    }
+}
+
+// VP9Packet represents the VP9 payload descriptor and payload of a RTP packet
+#[derive(Debug, Default, Clone)]
+pub struct VP9Packet {
+    // Required header
+    pub i: bool, /* picture ID is present */
+    pub p: bool, /* inter-picture predicted frame */
+    pub l: bool, /* layer indices are present */
+    pub f: bool, /* flexible mode */
+    pub b: bool, /* start of a frame */
+    pub e: bool, /* end of a frame */
+    pub v: bool, /* scalability structure (SS) data present */
+    pub z: bool, /* not a reference frame for upper spatial layers */
+
+    // Recommended headers
+    pub picture_id: u16, /* 7 or 16 bits, picture ID */
+
+    // Conditionally recommended headers
+    pub tid: u8, /* temporal layer ID */
+    pub u: bool, /* switching up point */
+    pub sid: u8, /* spatial layer ID */
+    pub d: bool, /* inter-layer dependency used */
+
+    // Conditionally required headers
+    pub p_diff: Vec<u8>, /* reference index */
+    pub tl0_pic_idx: u8, /* temporal layer zero index */
+
+    // Scalability structure headers
+    pub ns: u8, /* N_S + 1 indicates the number of spatial layers present in the VP9 stream */
+    pub y: bool, /* each spatial layer's frame resolution present */
+    pub g: bool, /* PG description present flag */
+    pub ng: u8, /* N_G indicates the number of pictures in a Picture Group (PG) */
+    pub width: Vec<u16>,
+    pub height: Vec<u16>,
+    pub pgtid: Vec<u8>, /* temporal layer ID of pictures in a Picture Group */
+    pub pgu: Vec<bool>, /* switching up point of pictures in a Picture Group */
+    pub pgp_diff: Vec<Vec<u8>>, /* reference indecies of pictures in a Picture Group */
+
+    pub payload: Vec<u8>,
+}
+
+impl Depacketizer for VP9Packet {
+    fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let mut packet = vec![];
+        reader.read_to_end(&mut packet)?;
+        if packet.is_empty() {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+
+        *self = VP9Packet::default();
+
+        let b = packet[0];
+        self.i = b & 0x80 != 0;
+        self.p = b & 0x40 != 0;
+        self.l = b & 0x20 != 0;
+        self.f = b & 0x10 != 0;
+        self.b = b & 0x08 != 0;
+        self.e = b & 0x04 != 0;
+        self.v = b & 0x02 != 0;
+        self.z = b & 0x01 != 0;
+
+        let mut pos = VP9HEADER_SIZE;
+
+        if self.i {
+            pos = self.parse_picture_id(&packet, pos)?;
+        }
+
+        if self.l {
+            pos = self.parse_layer_info(&packet, pos)?;
+        }
+
+        if self.f && self.p {
+            pos = self.parse_ref_indices(&packet, pos)?;
+        }
+
+        if self.v {
+            pos = self.parse_ssdata(&packet, pos)?;
+        }
+
+        if pos >= packet.len() {
+            return Err(Error::new("Payload is not large enough".to_string()));
+        }
+        self.payload = packet[pos..].to_vec();
+
+        Ok(())
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // The B bit marks the start of a frame
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if payload.is_empty() {
+            false
+        } else {
+            payload[0] & 0x08 != 0
+        }
+    }
+}
+
+impl VP9Packet {
+    // Picture ID:
+    //
+    //      +-+-+-+-+-+-+-+-+
+    // I:   |M| PICTURE ID  |   M:0 => picture id is 7 bits.
+    //      +-+-+-+-+-+-+-+-+   M:1 => picture id is 15 bits.
+    // M:   | EXTENDED PID  |
+    //      +-+-+-+-+-+-+-+-+
+    //
+    fn parse_picture_id(&mut self, packet: &[u8], mut pos: usize) -> Result<usize, Error> {
+        let b = *packet.get(pos).ok_or_else(short_packet)?;
+        self.picture_id = (b & 0x7F) as u16;
+        if b & 0x80 != 0 {
+            pos += 1;
+            let ext = *packet.get(pos).ok_or_else(short_packet)?;
+            // picture id is 15 bits
+            self.picture_id = (self.picture_id << 8) | ext as u16;
+        }
+
+        Ok(pos + 1)
+    }
+
+    // Layer indices (flexible mode):
+    //
+    //      +-+-+-+-+-+-+-+-+
+    // L:   |  T  |U|  S  |D|
+    //      +-+-+-+-+-+-+-+-+
+    //
+    // Layer indices (non-flexible mode):
+    //
+    //      +-+-+-+-+-+-+-+-+
+    // L:   |  T  |U|  S  |D|
+    //      +-+-+-+-+-+-+-+-+
+    //      |   TL0PICIDX   |
+    //      +-+-+-+-+-+-+-+-+
+    //
+    fn parse_layer_info(&mut self, packet: &[u8], mut pos: usize) -> Result<usize, Error> {
+        let b = *packet.get(pos).ok_or_else(short_packet)?;
+        self.tid = b >> 5;
+        self.u = b & 0x10 != 0;
+        self.sid = (b >> 1) & 0x07;
+        self.d = b & 0x01 != 0;
+        pos += 1;
+
+        if self.sid >= MAX_SPATIAL_LAYERS {
+            return Err(Error::new(format!(
+                "VP9: too many spatial layers, SID {}",
+                self.sid
+            )));
+        }
+
+        if !self.f {
+            self.tl0_pic_idx = *packet.get(pos).ok_or_else(short_packet)?;
+            pos += 1;
+        }
+
+        Ok(pos)
+    }
+
+    // Reference indices:
+    //
+    //      +-+-+-+-+-+-+-+-+                P=1,F=1: At least one reference index
+    // P,F: | P_DIFF      |N|  up to 3 times          has to be specified.
+    //      +-+-+-+-+-+-+-+-+                    N=1: An additional P_DIFF follows
+    //                                                current P_DIFF.
+    //
+    fn parse_ref_indices(&mut self, packet: &[u8], mut pos: usize) -> Result<usize, Error> {
+        loop {
+            let b = *packet.get(pos).ok_or_else(short_packet)?;
+            self.p_diff.push(b >> 1);
+            pos += 1;
+
+            if b & 0x01 == 0 {
+                break;
+            }
+            if self.p_diff.len() >= MAX_VP9REF_PICS {
+                return Err(Error::new(format!(
+                    "VP9: too many reference pictures, more than {}",
+                    MAX_VP9REF_PICS
+                )));
+            }
+        }
+
+        Ok(pos)
+    }
+
+    // Scalability structure (SS):
+    //
+    //      +-+-+-+-+-+-+-+-+
+    // V:   | N_S |Y|G|-|-|-|
+    //      +-+-+-+-+-+-+-+-+              -|
+    // Y:   |     WIDTH     | (OPTIONAL)    .
+    //      +               +               .
+    //      |               | (OPTIONAL)    .
+    //      +-+-+-+-+-+-+-+-+               . N_S + 1 times
+    //      |     HEIGHT    | (OPTIONAL)    .
+    //      +               +               .
+    //      |               | (OPTIONAL)    .
+    //      +-+-+-+-+-+-+-+-+              -|
+    // G:   |      N_G      | (OPTIONAL)
+    //      +-+-+-+-+-+-+-+-+                           -|
+    // N_G: |  T  |U| R |-|-| (OPTIONAL)                 .
+    //      +-+-+-+-+-+-+-+-+              -|            . N_G times
+    //      |    P_DIFF     | (OPTIONAL)    . R times    .
+    //      +-+-+-+-+-+-+-+-+              -|           -|
+    //
+    fn parse_ssdata(&mut self, packet: &[u8], mut pos: usize) -> Result<usize, Error> {
+        let b = *packet.get(pos).ok_or_else(short_packet)?;
+        self.ns = b >> 5;
+        self.y = b & 0x10 != 0;
+        self.g = b & 0x08 != 0;
+        pos += 1;
+
+        let ns = self.ns as usize + 1;
+        self.ng = 0;
+
+        if self.y {
+            if packet.len() < pos + 4 * ns {
+                return Err(short_packet());
+            }
+            for _ in 0..ns {
+                self.width
+                    .push(((packet[pos] as u16) << 8) | packet[pos + 1] as u16);
+                self.height
+                    .push(((packet[pos + 2] as u16) << 8) | packet[pos + 3] as u16);
+                pos += 4;
+            }
+        }
+
+        if self.g {
+            self.ng = *packet.get(pos).ok_or_else(short_packet)?;
+            pos += 1;
+        }
+
+        for _ in 0..self.ng {
+            let b = *packet.get(pos).ok_or_else(short_packet)?;
+            self.pgtid.push(b >> 5);
+            self.pgu.push(b & 0x10 != 0);
+            let r = ((b >> 2) & 0x03) as usize;
+            pos += 1;
+
+            if packet.len() < pos + r {
+                return Err(short_packet());
+            }
+            self.pgp_diff.push(packet[pos..pos + r].to_vec());
+            pos += r;
+        }
+
+        Ok(pos)
+    }
+}
+
+fn short_packet() -> Error {
+    Error::new("Payload is not large enough".to_string())
+}
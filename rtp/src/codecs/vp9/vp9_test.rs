@@ -0,0 +1,290 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+// Profile 0 key frame header of a 640x480 frame
+const KEYFRAME: [u8; 10] = [0x82, 0x49, 0x83, 0x42, 0x00, 0x27, 0xF0, 0x1D, 0xF0, 0xAA];
+// Profile 0 inter frame header
+const INTERFRAME: [u8; 4] = [0x86, 0x00, 0x40, 0x92];
+
+fn depacketize(raw: &[u8]) -> Result<VP9Packet, Error> {
+    let mut pck = VP9Packet::default();
+    let mut reader = BufReader::new(raw);
+    pck.depacketize(&mut reader)?;
+    Ok(pck)
+}
+
+#[test]
+fn test_vp9_unmarshal() -> Result<(), Error> {
+    // Empty
+    assert!(depacketize(&[]).is_err(), "Empty packet must be err");
+
+    // NonFlexible
+    let pck = depacketize(&[0x00, 0xAA])?;
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // NonFlexiblePictureID
+    let pck = depacketize(&[0x80, 0x02, 0xAA])?;
+    assert!(pck.i);
+    assert_eq!(pck.picture_id, 0x02);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // NonFlexiblePictureIDExt
+    let pck = depacketize(&[0x80, 0x81, 0xFF, 0xAA])?;
+    assert!(pck.i);
+    assert_eq!(pck.picture_id, 0x01FF);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // NonFlexiblePictureIDExt_ShortPacket0
+    assert!(depacketize(&[0x80, 0x81]).is_err());
+
+    // NonFlexiblePictureIDExt_ShortPacket1
+    assert!(depacketize(&[0x80]).is_err());
+
+    // NonFlexibleLayerIndicePictureID
+    let pck = depacketize(&[0xA0, 0x02, 0x23, 0x01, 0xAA])?;
+    assert!(pck.i);
+    assert!(pck.l);
+    assert_eq!(pck.picture_id, 0x02);
+    assert_eq!(pck.tid, 0x01);
+    assert_eq!(pck.sid, 0x01);
+    assert!(pck.d);
+    assert_eq!(pck.tl0_pic_idx, 0x01);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // FlexibleLayerIndicePictureID
+    let pck = depacketize(&[0xB0, 0x02, 0x23, 0x01, 0xAA])?;
+    assert!(pck.f);
+    assert_eq!(pck.picture_id, 0x02);
+    assert_eq!(pck.tid, 0x01);
+    assert_eq!(pck.sid, 0x01);
+    assert!(pck.d);
+    assert_eq!(pck.tl0_pic_idx, 0x00);
+    assert_eq!(pck.payload, vec![0x01, 0xAA]);
+
+    // NonFlexibleLayerIndicePictureID_ShortPacket0
+    assert!(depacketize(&[0xA0, 0x02, 0x23]).is_err());
+
+    // NonFlexibleLayerIndicePictureID_ShortPacket1
+    assert!(depacketize(&[0xA0, 0x02]).is_err());
+
+    // TooManySpatialLayers
+    assert!(depacketize(&[0xA0, 0x02, 0x0A, 0x01, 0xAA]).is_err());
+
+    // FlexiblePictureIDRefIndex
+    let pck = depacketize(&[0xD0, 0x02, 0x03, 0x04, 0xAA])?;
+    assert!(pck.i);
+    assert!(pck.p);
+    assert!(pck.f);
+    assert_eq!(pck.picture_id, 0x02);
+    assert_eq!(pck.p_diff, vec![0x01, 0x02]);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // FlexiblePictureIDRefIndex_TooManyPDiff
+    assert!(depacketize(&[0xD0, 0x02, 0x03, 0x05, 0x07, 0x09, 0x10, 0xAA]).is_err());
+
+    // FlexiblePictureIDRefIndexNoPayload
+    assert!(depacketize(&[0xD0, 0x02, 0x03, 0x04]).is_err());
+
+    // FlexiblePictureIDRefIndex_ShortPacket0
+    assert!(depacketize(&[0xD0, 0x02, 0x03]).is_err());
+
+    // ScalabilityStructureResolutionsNoPayload
+    let raw = [
+        0x0A,
+        (1 << 5) | (1 << 4), // NS:1 Y:1 G:0
+        (640 >> 8) as u8,
+        (640 & 0xff) as u8,
+        (360 >> 8) as u8,
+        (360 & 0xff) as u8,
+        (1280 >> 8) as u8,
+        (1280 & 0xff) as u8,
+        (720 >> 8) as u8,
+        (720 & 0xff) as u8,
+    ];
+    assert!(depacketize(&raw).is_err());
+
+    // ScalabilityStructureResolutions
+    let mut raw = raw.to_vec();
+    raw.push(0xAA);
+    let pck = depacketize(&raw)?;
+    assert!(pck.b);
+    assert!(pck.v);
+    assert_eq!(pck.ns, 1);
+    assert!(pck.y);
+    assert!(!pck.g);
+    assert_eq!(pck.ng, 0);
+    assert_eq!(pck.width, vec![640, 1280]);
+    assert_eq!(pck.height, vec![360, 720]);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    // ScalabilityStructureNoPayload
+    let raw = [
+        0x0A,
+        (1 << 3), // NS:0 Y:0 G:1
+        2,
+        (1 << 4),            // T:0 U:1 R:0 -
+        (2 << 5) | (1 << 2), // T:2 U:0 R:1 -
+        33,
+    ];
+    assert!(depacketize(&raw).is_err());
+
+    // ScalabilityStructure
+    let mut raw = raw.to_vec();
+    raw.push(0xAA);
+    let pck = depacketize(&raw)?;
+    assert!(pck.v);
+    assert_eq!(pck.ns, 0);
+    assert!(!pck.y);
+    assert!(pck.g);
+    assert_eq!(pck.ng, 2);
+    assert_eq!(pck.pgtid, vec![0, 2]);
+    assert_eq!(pck.pgu, vec![true, false]);
+    assert_eq!(pck.pgp_diff, vec![vec![], vec![33]]);
+    assert_eq!(pck.payload, vec![0xAA]);
+
+    Ok(())
+}
+
+#[test]
+fn test_vp9_payload() -> Result<(), Error> {
+    let mut pck = VP9Payloader::default();
+
+    // Empty payload
+    let empty: &[u8] = &[];
+    let mut reader = BufReader::new(empty);
+    let payloads = pck.payload(10, &mut reader)?;
+    assert!(payloads.is_empty(), "Generated payload should be empty");
+
+    // Negative MTU
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(-1, &mut reader)?;
+    assert!(payloads.is_empty(), "Generated payload should be empty");
+
+    // MTU smaller than the descriptor
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(5, &mut reader)?;
+    assert!(payloads.is_empty(), "Generated payload should be empty");
+
+    // Inter frame split in two packets
+    let mut pck = VP9Payloader::default();
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(7, &mut reader)?;
+    assert_eq!(
+        payloads,
+        vec![
+            vec![0xE8, 0x80, 0x00, 0x00, 0x00, 0x86, 0x00],
+            vec![0xE4, 0x80, 0x00, 0x00, 0x00, 0x40, 0x92],
+        ]
+    );
+
+    // Next frame advances the picture ID and TL0PICIDX
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    assert_eq!(
+        payloads,
+        vec![vec![0xEC, 0x80, 0x01, 0x00, 0x01, 0x86, 0x00, 0x40, 0x92]]
+    );
+
+    // Upper temporal layer keeps TL0PICIDX
+    pck.tid = 1;
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    assert_eq!(
+        payloads,
+        vec![vec![0xEC, 0x80, 0x02, 0x20, 0x01, 0x86, 0x00, 0x40, 0x92]]
+    );
+
+    // Base temporal layer advances TL0PICIDX again, spatial layer is always 0
+    pck.tid = 0;
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    assert_eq!(
+        payloads,
+        vec![vec![0xEC, 0x80, 0x03, 0x00, 0x02, 0x86, 0x00, 0x40, 0x92]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_vp9_payload_picture_id_wrap() -> Result<(), Error> {
+    let mut pck = VP9Payloader::new(false);
+    pck.picture_id = 0x7FFF;
+    pck.started = true;
+
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    let p = depacketize(&payloads[0])?;
+    assert_eq!(p.picture_id, 0, "Picture ID must wrap to 0");
+
+    Ok(())
+}
+
+#[test]
+fn test_vp9_payload_keyframe() -> Result<(), Error> {
+    // Non-flexible mode carries the resolution and a picture group
+    let mut pck = VP9Payloader::default();
+    let mut reader = BufReader::new(&KEYFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    assert_eq!(payloads.len(), 1);
+
+    let p = depacketize(&payloads[0])?;
+    assert!(!p.p, "Key frame must not be inter-picture predicted");
+    assert!(p.b && p.e && p.v);
+    assert_eq!(p.ns, 0);
+    assert!(p.y && p.g);
+    assert_eq!(p.width, vec![640]);
+    assert_eq!(p.height, vec![480]);
+    assert_eq!(p.ng, 1);
+    assert_eq!(p.pgtid, vec![0]);
+    assert_eq!(p.pgu, vec![false]);
+    assert_eq!(p.pgp_diff, vec![vec![1]]);
+    assert_eq!(p.payload, KEYFRAME.to_vec());
+
+    // SS is only sent with the first packet of the frame
+    let mut reader = BufReader::new(&KEYFRAME[..]);
+    let payloads = pck.payload(20, &mut reader)?;
+    assert_eq!(payloads.len(), 2);
+    let first = depacketize(&payloads[0])?;
+    let second = depacketize(&payloads[1])?;
+    assert!(first.v && first.b && !first.e);
+    assert!(!second.v && !second.b && second.e);
+    let mut frame = first.payload;
+    frame.extend_from_slice(&second.payload);
+    assert_eq!(frame, KEYFRAME.to_vec());
+
+    // Flexible mode carries the resolution only
+    let mut pck = VP9Payloader::new(true);
+    let mut reader = BufReader::new(&KEYFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    let p = depacketize(&payloads[0])?;
+    assert!(p.f && p.v && p.y && !p.g);
+    assert!(p.p_diff.is_empty());
+    assert_eq!(p.width, vec![640]);
+    assert_eq!(p.height, vec![480]);
+    assert_eq!(p.payload, KEYFRAME.to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn test_vp9_payload_flexible() -> Result<(), Error> {
+    let mut pck = VP9Payloader::new(true);
+    pck.tid = 2;
+    pck.u = true;
+
+    let mut reader = BufReader::new(&INTERFRAME[..]);
+    let payloads = pck.payload(100, &mut reader)?;
+    let p = depacketize(&payloads[0])?;
+    assert!(p.i && p.p && p.l && p.f && p.b && p.e);
+    assert!(!p.v);
+    assert_eq!(p.tid, 2);
+    assert!(p.u);
+    assert_eq!(p.p_diff, vec![1]);
+    assert_eq!(p.payload, INTERFRAME.to_vec());
+
+    Ok(())
+}
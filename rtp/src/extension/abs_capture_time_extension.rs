@@ -0,0 +1,93 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use util::Error;
+
+use super::abs_send_time_extension::{ntp2unix, unix2ntp};
+use super::HeaderExtension;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+#[cfg(test)]
+mod abs_capture_time_extension_test;
+
+const ESTIMATED_CAPTURE_CLOCK_OFFSET_SIZE: usize = 8;
+
+// AbsCaptureTimeExtension is a extension payload format in
+// http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time
+//
+// Data layout of the shortened version, without the clock offset:
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=7 |     absolute capture timestamp (bit 0-23)     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |             absolute capture timestamp (bit 24-55)            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ... (56-63)  |
+// +-+-+-+-+-+-+-+-+
+//
+// The full version appends the estimated capture clock offset on 64 bits.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct AbsCaptureTimeExtension {
+    // NTP timestamp in UQ32.32 format of the capture of the first frame
+    // sample on the original capture system
+    pub timestamp: u64,
+    // Offset in Q32.32 format between the capture system clock and the
+    // sender clock, if known
+    pub estimated_capture_clock_offset: Option<i64>,
+}
+
+impl HeaderExtension for AbsCaptureTimeExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::ABS_CAPTURE_TIME_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u64::<BigEndian>(self.timestamp)?;
+        if let Some(offset) = self.estimated_capture_clock_offset {
+            writer.write_i64::<BigEndian>(offset)?;
+        }
+
+        Ok(())
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let timestamp = reader.read_u64::<BigEndian>()?;
+
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest)?;
+        let estimated_capture_clock_offset = if rest.is_empty() {
+            None
+        } else if rest.len() >= ESTIMATED_CAPTURE_CLOCK_OFFSET_SIZE {
+            Some((&rest[..]).read_i64::<BigEndian>()?)
+        } else {
+            return Err(Error::new(
+                "abs capture time extension has a truncated clock offset".to_owned(),
+            ));
+        };
+
+        Ok(AbsCaptureTimeExtension {
+            timestamp,
+            estimated_capture_clock_offset,
+        })
+    }
+}
+
+impl AbsCaptureTimeExtension {
+    // new makes a AbsCaptureTimeExtension from a capture time since the unix
+    // epoch
+    pub fn new(capture_time: Duration) -> Self {
+        AbsCaptureTimeExtension {
+            timestamp: unix2ntp(capture_time),
+            estimated_capture_clock_offset: None,
+        }
+    }
+
+    // capture_time returns the capture time since the unix epoch
+    pub fn capture_time(&self) -> Duration {
+        ntp2unix(self.timestamp)
+    }
+}
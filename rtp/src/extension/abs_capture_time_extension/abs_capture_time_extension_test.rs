@@ -0,0 +1,68 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+#[test]
+fn test_abs_capture_time_extension_too_small() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![0x00; 7];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(AbsCaptureTimeExtension::unmarshal(&mut reader).is_err());
+
+    // Truncated clock offset
+    let raw: Vec<u8> = vec![0x00; 12];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(AbsCaptureTimeExtension::unmarshal(&mut reader).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_abs_capture_time_extension() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+    let mut reader = BufReader::new(raw.as_slice());
+    let a1 = AbsCaptureTimeExtension::unmarshal(&mut reader)?;
+    let a2 = AbsCaptureTimeExtension {
+        timestamp: 0x0102030405060708,
+        estimated_capture_clock_offset: None,
+    };
+    assert_eq!(a1, a2);
+
+    let mut dst: Vec<u8> = vec![];
+    a2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    Ok(())
+}
+
+#[test]
+fn test_abs_capture_time_extension_clock_offset() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0x80, 0x00, 0x00,
+        0x00,
+    ];
+    let mut reader = BufReader::new(raw.as_slice());
+    let a1 = AbsCaptureTimeExtension::unmarshal(&mut reader)?;
+    let a2 = AbsCaptureTimeExtension {
+        timestamp: 0x0102030405060708,
+        // -0.5 seconds
+        estimated_capture_clock_offset: Some(-(1 << 31)),
+    };
+    assert_eq!(a1, a2);
+
+    let mut dst: Vec<u8> = vec![];
+    a2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    Ok(())
+}
+
+#[test]
+fn test_abs_capture_time_extension_capture_time() {
+    let capture_time = Duration::from_millis(1_600_000_000_250);
+    let a = AbsCaptureTimeExtension::new(capture_time);
+    assert_eq!(a.timestamp >> 32, 1_600_000_000 + 0x83AA7E80);
+    assert_eq!(a.timestamp & 0xFFFFFFFF, 1 << 30);
+    assert_eq!(a.capture_time(), capture_time);
+}
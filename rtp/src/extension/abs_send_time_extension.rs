@@ -2,6 +2,8 @@ use std::io::{Read, Write};
 use std::time::Duration;
 use util::Error;
 
+use super::HeaderExtension;
+
 use byteorder::{ReadBytesExt, WriteBytesExt};
 
 #[cfg(test)]
@@ -12,12 +14,16 @@ const ABS_SEND_TIME_EXTENSION_SIZE: usize = 3;
 // AbsSendTimeExtension is a extension payload format in
 // http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
 pub struct AbsSendTimeExtension {
-    timestamp: u64,
+    pub timestamp: u64,
 }
 
-impl AbsSendTimeExtension {
+impl HeaderExtension for AbsSendTimeExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::ABS_SEND_TIME_URI
+    }
+
     // Marshal serializes the members to buffer.
-    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         writer.write_u8(((self.timestamp & 0xFF0000) >> 16) as u8)?;
         writer.write_u8(((self.timestamp & 0xFF00) >> 8) as u8)?;
         writer.write_u8((self.timestamp & 0xFF) as u8)?;
@@ -26,7 +32,7 @@ impl AbsSendTimeExtension {
     }
 
     // Unmarshal parses the passed byte slice and stores the result in the members.
-    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let b0 = reader.read_u8()?;
         let b1 = reader.read_u8()?;
         let b2 = reader.read_u8()?;
@@ -34,7 +40,9 @@ impl AbsSendTimeExtension {
 
         Ok(AbsSendTimeExtension { timestamp })
     }
+}
 
+impl AbsSendTimeExtension {
     // Estimate absolute send time according to the receive time.
     // Note that if the transmission delay is larger than 64 seconds, estimated time will be wrong.
     pub fn estimate(&self, receive: Duration) -> Duration {
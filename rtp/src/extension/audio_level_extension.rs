@@ -2,6 +2,8 @@ use std::io::{Read, Write};
 
 use util::Error;
 
+use super::HeaderExtension;
+
 use byteorder::{ReadBytesExt, WriteBytesExt};
 
 #[cfg(test)]
@@ -28,13 +30,17 @@ mod audio_level_extension_test;
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(PartialEq, Debug)]
 pub struct AudioLevelExtension {
-    level: u8,
-    voice: bool,
+    pub level: u8,
+    pub voice: bool,
 }
 
-impl AudioLevelExtension {
+impl HeaderExtension for AudioLevelExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::AUDIO_LEVEL_URI
+    }
+
     // Marshal serializes the members to buffer
-    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         if self.level > 127 {
             return Err(Error::new("audio level overflow".to_owned()));
         }
@@ -46,7 +52,7 @@ impl AudioLevelExtension {
     }
 
     // Unmarshal parses the passed byte slice and stores the result in the members
-    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let b = reader.read_u8()?;
 
         Ok(AudioLevelExtension {
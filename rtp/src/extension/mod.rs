@@ -1,3 +1,26 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+pub mod abs_capture_time_extension;
 pub mod abs_send_time_extension;
 pub mod audio_level_extension;
+pub mod playout_delay_extension;
+pub mod registry;
+pub mod sdes_extension;
 pub mod transport_cc_extension;
+pub mod video_content_type_extension;
+pub mod video_orientation_extension;
+
+// HeaderExtension is a typed RTP header extension identified by the URI it
+// is negotiated with
+pub trait HeaderExtension: Sized {
+    // uri returns the URI of the extension, as found in a=extmap
+    fn uri() -> &'static str;
+
+    // marshal serializes the extension payload
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error>;
+
+    // unmarshal parses an extension payload
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error>;
+}
@@ -0,0 +1,92 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use util::Error;
+
+use super::HeaderExtension;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+#[cfg(test)]
+mod playout_delay_extension_test;
+
+// Delays are expressed in 10ms units on 12 bits
+pub const PLAYOUT_DELAY_GRANULARITY: Duration = Duration::from_millis(10);
+pub const PLAYOUT_DELAY_MAX_VALUE: u16 = 0xFFF;
+
+// PlayoutDelayExtension is a extension payload format in
+// http://www.webrtc.org/experiments/rtp-hdrext/playout-delay
+//
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=2 |       MIN delay       |       MAX delay       |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct PlayoutDelayExtension {
+    // Delays in units of PLAYOUT_DELAY_GRANULARITY
+    pub min_delay: u16,
+    pub max_delay: u16,
+}
+
+impl HeaderExtension for PlayoutDelayExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::PLAYOUT_DELAY_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.min_delay > PLAYOUT_DELAY_MAX_VALUE || self.max_delay > PLAYOUT_DELAY_MAX_VALUE {
+            return Err(Error::new("playout delay overflow".to_owned()));
+        }
+
+        writer.write_u8((self.min_delay >> 4) as u8)?;
+        writer.write_u8(((self.min_delay << 4) as u8) | (self.max_delay >> 8) as u8)?;
+        writer.write_u8(self.max_delay as u8)?;
+
+        Ok(())
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let b0 = reader.read_u8()? as u16;
+        let b1 = reader.read_u8()? as u16;
+        let b2 = reader.read_u8()? as u16;
+
+        Ok(PlayoutDelayExtension {
+            min_delay: b0 << 4 | b1 >> 4,
+            max_delay: (b1 & 0x0F) << 8 | b2,
+        })
+    }
+}
+
+impl PlayoutDelayExtension {
+    // new makes a PlayoutDelayExtension from durations, rounded down to
+    // PLAYOUT_DELAY_GRANULARITY
+    pub fn new(min_delay: Duration, max_delay: Duration) -> Result<Self, Error> {
+        let granularity = PLAYOUT_DELAY_GRANULARITY.as_millis();
+        let min = min_delay.as_millis() / granularity;
+        let max = max_delay.as_millis() / granularity;
+        if min > max {
+            return Err(Error::new(
+                "playout min delay must not be larger than max delay".to_owned(),
+            ));
+        }
+        if max > PLAYOUT_DELAY_MAX_VALUE as u128 {
+            return Err(Error::new("playout delay overflow".to_owned()));
+        }
+
+        Ok(PlayoutDelayExtension {
+            min_delay: min as u16,
+            max_delay: max as u16,
+        })
+    }
+
+    pub fn min(&self) -> Duration {
+        PLAYOUT_DELAY_GRANULARITY * self.min_delay as u32
+    }
+
+    pub fn max(&self) -> Duration {
+        PLAYOUT_DELAY_GRANULARITY * self.max_delay as u32
+    }
+}
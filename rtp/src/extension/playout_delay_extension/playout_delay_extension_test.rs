@@ -0,0 +1,64 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+#[test]
+fn test_playout_delay_extension_too_small() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![0x00, 0x00];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(PlayoutDelayExtension::unmarshal(&mut reader).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_playout_delay_extension() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![0x01, 0x01, 0x00];
+    let mut reader = BufReader::new(raw.as_slice());
+    let p1 = PlayoutDelayExtension::unmarshal(&mut reader)?;
+    let p2 = PlayoutDelayExtension {
+        min_delay: 1 << 4,
+        max_delay: 1 << 8,
+    };
+    assert_eq!(p1, p2);
+
+    let mut dst: Vec<u8> = vec![];
+    p2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    let raw: Vec<u8> = vec![0xFF, 0xFF, 0xFF];
+    let mut reader = BufReader::new(raw.as_slice());
+    let p = PlayoutDelayExtension::unmarshal(&mut reader)?;
+    assert_eq!(p.min_delay, PLAYOUT_DELAY_MAX_VALUE);
+    assert_eq!(p.max_delay, PLAYOUT_DELAY_MAX_VALUE);
+
+    Ok(())
+}
+
+#[test]
+fn test_playout_delay_extension_overflow() -> Result<(), Error> {
+    let p = PlayoutDelayExtension {
+        min_delay: 0x1000,
+        max_delay: 0,
+    };
+    let mut dst: Vec<u8> = vec![];
+    assert!(p.marshal(&mut dst).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_playout_delay_extension_duration() -> Result<(), Error> {
+    let p = PlayoutDelayExtension::new(Duration::from_millis(105), Duration::from_secs(1))?;
+    assert_eq!(p.min_delay, 10);
+    assert_eq!(p.max_delay, 100);
+    assert_eq!(p.min(), Duration::from_millis(100));
+    assert_eq!(p.max(), Duration::from_secs(1));
+
+    assert!(PlayoutDelayExtension::new(Duration::from_secs(2), Duration::from_secs(1)).is_err());
+    assert!(PlayoutDelayExtension::new(Duration::from_secs(0), Duration::from_secs(41)).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use sdp::extmap::ExtMap;
+use util::Error;
+
+#[cfg(test)]
+mod registry_test;
+
+// HeaderExtensionRegistry maps the header extension ids negotiated for a
+// session to the URIs of the extensions
+#[derive(Debug, Default, Clone)]
+pub struct HeaderExtensionRegistry {
+    uris: HashMap<u8, String>,
+    ids: HashMap<String, u8>,
+}
+
+impl HeaderExtensionRegistry {
+    pub fn new() -> Self {
+        HeaderExtensionRegistry::default()
+    }
+
+    // from_ext_maps builds a registry from the a=extmap attributes of a
+    // media description
+    pub fn from_ext_maps(ext_maps: &[ExtMap]) -> Result<Self, Error> {
+        let mut registry = HeaderExtensionRegistry::new();
+        for ext_map in ext_maps {
+            let uri = match ext_map.uri() {
+                Some(uri) => uri,
+                None => return Err(Error::new(format!("extmap {} has no uri", ext_map.value()))),
+            };
+            if ext_map.value() < 1 || ext_map.value() > 255 {
+                return Err(Error::new(format!(
+                    "extmap {} is not a valid header extension id",
+                    ext_map.value()
+                )));
+            }
+            registry.register(ext_map.value() as u8, uri.as_str())?;
+        }
+        Ok(registry)
+    }
+
+    // register maps id to uri
+    pub fn register(&mut self, id: u8, uri: &str) -> Result<(), Error> {
+        if id == 0 {
+            return Err(Error::new(
+                "header extension id must be between 1 and 255".to_owned(),
+            ));
+        }
+        if let Some(registered) = self.uris.get(&id) {
+            if registered != uri {
+                return Err(Error::new(format!(
+                    "header extension id {} is already registered for {}",
+                    id, registered
+                )));
+            }
+        }
+        if let Some(registered) = self.ids.get(uri) {
+            if *registered != id {
+                return Err(Error::new(format!(
+                    "header extension {} is already registered with id {}",
+                    uri, registered
+                )));
+            }
+        }
+
+        self.uris.insert(id, uri.to_owned());
+        self.ids.insert(uri.to_owned(), id);
+        Ok(())
+    }
+
+    // id returns the id negotiated for uri
+    pub fn id(&self, uri: &str) -> Option<u8> {
+        self.ids.get(uri).copied()
+    }
+
+    // uri returns the uri negotiated with id
+    pub fn uri(&self, id: u8) -> Option<&str> {
+        self.uris.get(&id).map(|uri| uri.as_str())
+    }
+}
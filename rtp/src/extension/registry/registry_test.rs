@@ -0,0 +1,50 @@
+use super::*;
+
+use sdp::extmap::*;
+
+use std::io::BufReader;
+
+fn ext_map(line: &str) -> Result<ExtMap, Error> {
+    let mut reader = BufReader::new(line.as_bytes());
+    ExtMap::unmarshal(&mut reader)
+}
+
+#[test]
+fn test_registry_register() -> Result<(), Error> {
+    let mut registry = HeaderExtensionRegistry::new();
+    registry.register(1, ABS_SEND_TIME_URI)?;
+    registry.register(20, TRANSPORT_CC_URI)?;
+    // Registering the same mapping again is allowed
+    registry.register(1, ABS_SEND_TIME_URI)?;
+
+    assert_eq!(registry.id(ABS_SEND_TIME_URI), Some(1));
+    assert_eq!(registry.id(TRANSPORT_CC_URI), Some(20));
+    assert_eq!(registry.id(SDES_MID_URI), None);
+    assert_eq!(registry.uri(20), Some(TRANSPORT_CC_URI));
+    assert_eq!(registry.uri(2), None);
+
+    assert!(registry.register(0, SDES_MID_URI).is_err());
+    assert!(registry.register(1, SDES_MID_URI).is_err());
+    assert!(registry.register(2, ABS_SEND_TIME_URI).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_registry_from_ext_maps() -> Result<(), Error> {
+    let ext_maps = vec![
+        ext_map(&format!("extmap:3 {}", SDES_MID_URI))?,
+        ext_map(&format!("extmap:5/sendonly {}", AUDIO_LEVEL_URI))?,
+    ];
+    let registry = HeaderExtensionRegistry::from_ext_maps(&ext_maps)?;
+    assert_eq!(registry.id(SDES_MID_URI), Some(3));
+    assert_eq!(registry.id(AUDIO_LEVEL_URI), Some(5));
+
+    let ext_maps = vec![
+        ext_map(&format!("extmap:3 {}", SDES_MID_URI))?,
+        ext_map(&format!("extmap:3 {}", AUDIO_LEVEL_URI))?,
+    ];
+    assert!(HeaderExtensionRegistry::from_ext_maps(&ext_maps).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use super::HeaderExtension;
+
+#[cfg(test)]
+mod sdes_extension_test;
+
+// Max length of an SDES item carried in a two-byte header extension
+const SDES_MAX_LENGTH: usize = 255;
+
+// SdesMidExtension carries the media identification of RFC 8843 as an
+// SDES item, https://tools.ietf.org/html/rfc7941
+//
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   |  len  | SDES Item text value ...                      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SdesMidExtension {
+    pub mid: String,
+}
+
+impl HeaderExtension for SdesMidExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::SDES_MID_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        marshal_sdes(&self.mid, writer)
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Ok(SdesMidExtension {
+            mid: unmarshal_sdes(reader)?,
+        })
+    }
+}
+
+// SdesRtpStreamIdExtension carries the RtpStreamId of RFC 8852, used to
+// tell simulcast encodings apart
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SdesRtpStreamIdExtension {
+    pub rid: String,
+}
+
+impl HeaderExtension for SdesRtpStreamIdExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::SDES_RTP_STREAM_ID_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        validate_rid(&self.rid)?;
+        marshal_sdes(&self.rid, writer)
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let rid = unmarshal_sdes(reader)?;
+        validate_rid(&rid)?;
+        Ok(SdesRtpStreamIdExtension { rid })
+    }
+}
+
+// SdesRepairedRtpStreamIdExtension carries the RtpStreamId of RFC 8852 of
+// the stream a redundancy stream, such as RTX, repairs
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SdesRepairedRtpStreamIdExtension {
+    pub rid: String,
+}
+
+impl HeaderExtension for SdesRepairedRtpStreamIdExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::SDES_REPAIR_RTP_STREAM_ID_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        validate_rid(&self.rid)?;
+        marshal_sdes(&self.rid, writer)
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let rid = unmarshal_sdes(reader)?;
+        validate_rid(&rid)?;
+        Ok(SdesRepairedRtpStreamIdExtension { rid })
+    }
+}
+
+fn marshal_sdes<W: Write>(value: &str, writer: &mut W) -> Result<(), Error> {
+    if value.is_empty() {
+        return Err(Error::new("sdes item must not be empty".to_owned()));
+    }
+    if value.len() > SDES_MAX_LENGTH {
+        return Err(Error::new(format!(
+            "sdes item must be {}bytes or less",
+            SDES_MAX_LENGTH
+        )));
+    }
+    writer.write_all(value.as_bytes())?;
+
+    Ok(())
+}
+
+fn unmarshal_sdes<R: Read>(reader: &mut R) -> Result<String, Error> {
+    let mut raw = vec![];
+    reader.read_to_end(&mut raw)?;
+
+    // Some senders pad the item with zeros
+    while raw.last() == Some(&0) {
+        raw.pop();
+    }
+    if raw.is_empty() {
+        return Err(Error::new("sdes item must not be empty".to_owned()));
+    }
+
+    String::from_utf8(raw).map_err(|e| Error::new(format!("invalid sdes item: {}", e)))
+}
+
+// validate_rid checks the RtpStreamId syntax of RFC 8851
+fn validate_rid(rid: &str) -> Result<(), Error> {
+    if rid
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(Error::new(format!("invalid rtp stream id {}", rid)))
+    }
+}
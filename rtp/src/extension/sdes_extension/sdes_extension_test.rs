@@ -0,0 +1,100 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+#[test]
+fn test_sdes_mid_extension() -> Result<(), Error> {
+    let raw: Vec<u8> = b"audio0".to_vec();
+    let mut reader = BufReader::new(raw.as_slice());
+    let m1 = SdesMidExtension::unmarshal(&mut reader)?;
+    let m2 = SdesMidExtension {
+        mid: "audio0".to_owned(),
+    };
+    assert_eq!(m1, m2);
+
+    let mut dst: Vec<u8> = vec![];
+    m2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    Ok(())
+}
+
+#[test]
+fn test_sdes_mid_extension_padding() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![b'0', 0x00, 0x00];
+    let mut reader = BufReader::new(raw.as_slice());
+    let m = SdesMidExtension::unmarshal(&mut reader)?;
+    assert_eq!(m.mid, "0");
+
+    Ok(())
+}
+
+#[test]
+fn test_sdes_extension_invalid() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(SdesMidExtension::unmarshal(&mut reader).is_err());
+
+    let raw: Vec<u8> = vec![0xFF, 0xFE];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(SdesMidExtension::unmarshal(&mut reader).is_err());
+
+    let mut dst: Vec<u8> = vec![];
+    assert!(SdesMidExtension::default().marshal(&mut dst).is_err());
+    let long = SdesMidExtension {
+        mid: "a".repeat(256),
+    };
+    assert!(long.marshal(&mut dst).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_sdes_rtp_stream_id_extension() -> Result<(), Error> {
+    let raw: Vec<u8> = b"hi-res_1".to_vec();
+    let mut reader = BufReader::new(raw.as_slice());
+    let r1 = SdesRtpStreamIdExtension::unmarshal(&mut reader)?;
+    let r2 = SdesRtpStreamIdExtension {
+        rid: "hi-res_1".to_owned(),
+    };
+    assert_eq!(r1, r2);
+
+    let mut dst: Vec<u8> = vec![];
+    r2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    // RtpStreamId is limited to alphanumeric characters, - and _
+    let raw: Vec<u8> = b"hi res".to_vec();
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(SdesRtpStreamIdExtension::unmarshal(&mut reader).is_err());
+    let invalid = SdesRtpStreamIdExtension {
+        rid: "hi res".to_owned(),
+    };
+    assert!(invalid.marshal(&mut dst).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_sdes_repaired_rtp_stream_id_extension() -> Result<(), Error> {
+    let raw: Vec<u8> = b"q".to_vec();
+    let mut reader = BufReader::new(raw.as_slice());
+    let r1 = SdesRepairedRtpStreamIdExtension::unmarshal(&mut reader)?;
+    let r2 = SdesRepairedRtpStreamIdExtension {
+        rid: "q".to_owned(),
+    };
+    assert_eq!(r1, r2);
+
+    let mut dst: Vec<u8> = vec![];
+    r2.marshal(&mut dst)?;
+    assert_eq!(raw, dst);
+
+    assert_eq!(
+        SdesRepairedRtpStreamIdExtension::uri(),
+        "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id"
+    );
+
+    Ok(())
+}
@@ -2,6 +2,8 @@ use std::io::{Read, Write};
 
 use util::Error;
 
+use super::HeaderExtension;
+
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 #[cfg(test)]
@@ -18,19 +20,23 @@ mod transport_cc_extension_test;
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(PartialEq, Debug)]
 pub struct TransportCCExtension {
-    transport_sequence: u16,
+    pub transport_sequence: u16,
 }
 
-impl TransportCCExtension {
+impl HeaderExtension for TransportCCExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::TRANSPORT_CC_URI
+    }
+
     // Marshal serializes the members to buffer
-    pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         writer.write_u16::<BigEndian>(self.transport_sequence)?;
 
         Ok(())
     }
 
     // Unmarshal parses the passed byte slice and stores the result in the members
-    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let transport_sequence = reader.read_u16::<BigEndian>()?;
 
         Ok(TransportCCExtension { transport_sequence })
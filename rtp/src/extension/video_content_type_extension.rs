@@ -0,0 +1,54 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use super::HeaderExtension;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+#[cfg(test)]
+mod video_content_type_extension_test;
+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum VideoContentType {
+    #[default]
+    Unspecified = 0,
+    Screenshare = 1,
+}
+
+// VideoContentTypeExtension is a extension payload format in
+// http://www.webrtc.org/experiments/rtp-hdrext/video-content-type
+//
+// 0                   1
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=0 | Content type  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct VideoContentTypeExtension {
+    pub content_type: VideoContentType,
+}
+
+impl HeaderExtension for VideoContentTypeExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::VIDEO_CONTENT_TYPE_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u8(self.content_type as u8)?;
+
+        Ok(())
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let content_type = match reader.read_u8()? {
+            0 => VideoContentType::Unspecified,
+            1 => VideoContentType::Screenshare,
+            b => return Err(Error::new(format!("invalid video content type {}", b))),
+        };
+
+        Ok(VideoContentTypeExtension { content_type })
+    }
+}
@@ -0,0 +1,38 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+#[test]
+fn test_video_content_type_extension() -> Result<(), Error> {
+    for &(b, content_type) in &[
+        (0x00, VideoContentType::Unspecified),
+        (0x01, VideoContentType::Screenshare),
+    ] {
+        let raw: Vec<u8> = vec![b];
+        let mut reader = BufReader::new(raw.as_slice());
+        let v1 = VideoContentTypeExtension::unmarshal(&mut reader)?;
+        let v2 = VideoContentTypeExtension { content_type };
+        assert_eq!(v1, v2);
+
+        let mut dst: Vec<u8> = vec![];
+        v2.marshal(&mut dst)?;
+        assert_eq!(raw, dst);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_video_content_type_extension_invalid() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(VideoContentTypeExtension::unmarshal(&mut reader).is_err());
+
+    let raw: Vec<u8> = vec![0x02];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(VideoContentTypeExtension::unmarshal(&mut reader).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::io::{Read, Write};
+
+use util::Error;
+
+use super::HeaderExtension;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+#[cfg(test)]
+mod video_orientation_extension_test;
+
+const CAMERA_SHIFT: u8 = 3;
+const FLIP_SHIFT: u8 = 2;
+const ROTATION_MASK: u8 = 0x3;
+
+// VideoRotation is the clockwise rotation to apply to the video
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum VideoRotation {
+    #[default]
+    Degree0 = 0,
+    Degree90 = 1,
+    Degree180 = 2,
+    Degree270 = 3,
+}
+
+impl From<u8> for VideoRotation {
+    fn from(v: u8) -> Self {
+        match v & ROTATION_MASK {
+            1 => VideoRotation::Degree90,
+            2 => VideoRotation::Degree180,
+            3 => VideoRotation::Degree270,
+            _ => VideoRotation::Degree0,
+        }
+    }
+}
+
+// VideoOrientationExtension is the Coordination of Video Orientation (CVO)
+// extension of 3GPP TS 26.114
+//
+// 0                   1
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=0 |0 0 0 0 C F R R|
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct VideoOrientationExtension {
+    // The video comes from a back-facing camera
+    pub camera: bool,
+    // The video is flipped horizontally
+    pub flip: bool,
+    pub rotation: VideoRotation,
+}
+
+impl HeaderExtension for VideoOrientationExtension {
+    fn uri() -> &'static str {
+        sdp::extmap::VIDEO_ORIENTATION_URI
+    }
+
+    // Marshal serializes the members to buffer
+    fn marshal<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut b = self.rotation as u8;
+        if self.camera {
+            b |= 1 << CAMERA_SHIFT;
+        }
+        if self.flip {
+            b |= 1 << FLIP_SHIFT;
+        }
+        writer.write_u8(b)?;
+
+        Ok(())
+    }
+
+    // Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let b = reader.read_u8()?;
+
+        Ok(VideoOrientationExtension {
+            camera: (b >> CAMERA_SHIFT) & 0x1 != 0,
+            flip: (b >> FLIP_SHIFT) & 0x1 != 0,
+            rotation: VideoRotation::from(b),
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use super::*;
+
+use std::io::BufReader;
+
+use util::Error;
+
+#[test]
+fn test_video_orientation_extension_too_small() -> Result<(), Error> {
+    let raw: Vec<u8> = vec![];
+    let mut reader = BufReader::new(raw.as_slice());
+    assert!(VideoOrientationExtension::unmarshal(&mut reader).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_video_orientation_extension() -> Result<(), Error> {
+    let tests = vec![
+        (
+            0x00,
+            VideoOrientationExtension {
+                camera: false,
+                flip: false,
+                rotation: VideoRotation::Degree0,
+            },
+        ),
+        (
+            0x01,
+            VideoOrientationExtension {
+                camera: false,
+                flip: false,
+                rotation: VideoRotation::Degree90,
+            },
+        ),
+        (
+            0x0E,
+            VideoOrientationExtension {
+                camera: true,
+                flip: true,
+                rotation: VideoRotation::Degree180,
+            },
+        ),
+        (
+            0x0B,
+            VideoOrientationExtension {
+                camera: true,
+                flip: false,
+                rotation: VideoRotation::Degree270,
+            },
+        ),
+    ];
+
+    for (b, expected) in tests {
+        let raw: Vec<u8> = vec![b];
+        let mut reader = BufReader::new(raw.as_slice());
+        let v = VideoOrientationExtension::unmarshal(&mut reader)?;
+        assert_eq!(v, expected);
+
+        let mut dst: Vec<u8> = vec![];
+        expected.marshal(&mut dst)?;
+        assert_eq!(raw, dst);
+    }
+
+    Ok(())
+}
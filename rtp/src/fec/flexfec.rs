@@ -0,0 +1,450 @@
+use crate::header::Header;
+use crate::packet::Packet;
+use crate::sequence::Sequencer;
+
+use std::collections::{HashMap, VecDeque};
+use std::io::BufReader;
+
+use util::Error;
+
+#[cfg(test)]
+mod flexfec_test;
+
+const RTP_HEADER_LENGTH: usize = 12;
+// Recovery fields and SN base preceding the mask
+const FEC_BASE_HEADER_LENGTH: usize = 10;
+const RETRANSMISSION_MASK: u8 = 0x80;
+const FIXED_MASK: u8 = 0x40;
+const MASK_K_BIT: u64 = 1 << 63;
+
+// Number of packets each part of the flexible mask covers
+const MASK_SIZE_0: usize = 15;
+const MASK_SIZE_1: usize = 46;
+// MAX_MASK_SPAN is the largest distance between the first and the last
+// packet protected by a FEC packet, plus one
+pub const MAX_MASK_SPAN: usize = 109;
+
+// How many media packets the decoder keeps to recover lost ones
+const DEFAULT_MEDIA_HISTORY: usize = 1024;
+// How many FEC packets the decoder keeps waiting for recoverable losses
+const DEFAULT_FEC_HISTORY: usize = 128;
+
+// FlexFecEncoder generates FlexFEC repair packets, RFC 8627, over blocks of
+// columns x rows media packets
+//
+// Every row of columns consecutive packets is protected by a row FEC packet
+// when columns > 1, and every column of rows packets, taken columns packets
+// apart, by a column FEC packet when rows > 1. Each FEC packet describes the
+// packets it protects with a flexible mask and lists the protected SSRC as
+// its only CSRC.
+//
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |0|0|P|X|  CC   |M| PT recovery |        length recovery        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          TS recovery                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |           SN base_i           |k|          Mask [0-14]        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                   Mask [15-45] (optional)                   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                                                             |
+// +-+                   Mask [46-108] (optional)                  |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                     ... FEC Payload ...                       |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+pub struct FlexFecEncoder<S: Sequencer> {
+    payload_type: u8,
+    ssrc: u32,
+    protected_ssrc: u32,
+    sequencer: S,
+    columns: usize,
+    rows: usize,
+    // Marshaled media packets of the current block
+    block: Vec<(u16, Vec<u8>)>,
+}
+
+impl<S: Sequencer> FlexFecEncoder<S> {
+    // new constructs an encoder of the FEC stream ssrc protecting the media
+    // stream protected_ssrc
+    pub fn new(
+        payload_type: u8,
+        ssrc: u32,
+        protected_ssrc: u32,
+        sequencer: S,
+        columns: usize,
+        rows: usize,
+    ) -> Result<Self, Error> {
+        if columns == 0 || rows == 0 {
+            return Err(Error::new(
+                "flexfec block must have at least one row and column".to_owned(),
+            ));
+        }
+        if columns > MAX_MASK_SPAN || (rows - 1) * columns + 1 > MAX_MASK_SPAN {
+            return Err(Error::new(format!(
+                "flexfec block of {}x{} exceeds the mask span of {} packets",
+                columns, rows, MAX_MASK_SPAN
+            )));
+        }
+
+        Ok(FlexFecEncoder {
+            payload_type,
+            ssrc,
+            protected_ssrc,
+            sequencer,
+            columns,
+            rows,
+            block: vec![],
+        })
+    }
+
+    // push adds a media packet to the current block and returns the FEC
+    // packets completed by it
+    pub fn push(&mut self, packet: &Packet) -> Result<Vec<Packet>, Error> {
+        if packet.header.ssrc != self.protected_ssrc {
+            return Ok(vec![]);
+        }
+        if let Some((first, _)) = self.block.first() {
+            // A gap in the sequence numbers would break the block layout
+            let expected = first.wrapping_add(self.block.len() as u16);
+            if packet.header.sequence_number != expected {
+                self.block.clear();
+            }
+        }
+
+        let mut raw = vec![];
+        packet.marshal(&mut raw)?;
+        self.block.push((packet.header.sequence_number, raw));
+
+        let mut fec_packets = vec![];
+        let count = self.block.len();
+        if count.is_multiple_of(self.columns) && (self.columns > 1 || self.rows == 1) {
+            let row: Vec<usize> = (count - self.columns..count).collect();
+            fec_packets.push(self.fec_packet(&row)?);
+        }
+        if count == self.columns * self.rows {
+            if self.rows > 1 {
+                for column in 0..self.columns {
+                    let protected: Vec<usize> = (column..count).step_by(self.columns).collect();
+                    fec_packets.push(self.fec_packet(&protected)?);
+                }
+            }
+            self.block.clear();
+        }
+
+        Ok(fec_packets)
+    }
+
+    // fec_packet protects the packets of the block at the indexes protected
+    fn fec_packet(&mut self, protected: &[usize]) -> Result<Packet, Error> {
+        let block = &self.block;
+        let protected: Vec<&(u16, Vec<u8>)> = protected.iter().map(|i| &block[*i]).collect();
+        let sn_base = protected[0].0;
+
+        let mut recovery = [0u8; 8];
+        let mut data = vec![];
+        let mut timestamp = 0;
+        for (_, raw) in &protected {
+            xor_slice(&mut recovery, &bitstring_header(raw));
+            xor_into(&mut data, &raw[RTP_HEADER_LENGTH..]);
+            timestamp = u32::from_be_bytes([raw[4], raw[5], raw[6], raw[7]]);
+        }
+
+        let offsets: Vec<usize> = protected
+            .iter()
+            .map(|(sequence_number, _)| sequence_number.wrapping_sub(sn_base) as usize)
+            .collect();
+
+        let mut payload = Vec::with_capacity(FEC_BASE_HEADER_LENGTH + 14 + data.len());
+        payload.push(recovery[0] & !(RETRANSMISSION_MASK | FIXED_MASK));
+        payload.extend_from_slice(&recovery[1..]);
+        payload.extend_from_slice(&sn_base.to_be_bytes());
+        payload.extend_from_slice(&marshal_mask(&offsets));
+        payload.extend_from_slice(&data);
+
+        Ok(Packet::new(
+            Header {
+                version: 2,
+                payload_type: self.payload_type,
+                sequence_number: self.sequencer.next_sequence_number(),
+                timestamp,
+                ssrc: self.ssrc,
+                csrc: vec![self.protected_ssrc],
+                ..Default::default()
+            },
+            payload,
+        ))
+    }
+}
+
+struct FecPacket {
+    // Sequence numbers of the protected packets
+    protected: Vec<u16>,
+    recovery: [u8; 8],
+    data: Vec<u8>,
+}
+
+// FlexFecDecoder recovers lost media packets of the stream protected_ssrc
+// from the FlexFEC packets protecting it
+pub struct FlexFecDecoder {
+    protected_ssrc: u32,
+    // Marshaled media packets, received or recovered
+    media: HashMap<u16, Vec<u8>>,
+    media_order: VecDeque<u16>,
+    fec: VecDeque<FecPacket>,
+}
+
+impl FlexFecDecoder {
+    pub fn new(protected_ssrc: u32) -> Self {
+        FlexFecDecoder {
+            protected_ssrc,
+            media: HashMap::new(),
+            media_order: VecDeque::new(),
+            fec: VecDeque::new(),
+        }
+    }
+
+    // push_media records a received media packet and returns the packets
+    // it allowed to recover
+    pub fn push_media(&mut self, packet: &Packet) -> Result<Vec<Packet>, Error> {
+        if packet.header.ssrc != self.protected_ssrc {
+            return Ok(vec![]);
+        }
+
+        let mut raw = vec![];
+        packet.marshal(&mut raw)?;
+        self.insert_media(packet.header.sequence_number, raw);
+
+        Ok(self.recover())
+    }
+
+    // push_fec records a FlexFEC packet and returns the packets it allowed
+    // to recover, FEC packets protecting other streams are ignored
+    pub fn push_fec(&mut self, packet: &Packet) -> Result<Vec<Packet>, Error> {
+        if !packet.header.csrc.contains(&self.protected_ssrc) {
+            return Ok(vec![]);
+        }
+
+        let payload = &packet.payload;
+        if payload.len() < FEC_BASE_HEADER_LENGTH + 2 {
+            return Err(Error::new("flexfec packet is too short".to_owned()));
+        }
+        if payload[0] & RETRANSMISSION_MASK != 0 {
+            return Err(Error::new(
+                "flexfec retransmission packets are not supported".to_owned(),
+            ));
+        }
+        if payload[0] & FIXED_MASK != 0 {
+            return Err(Error::new(
+                "flexfec fixed row and column masks are not supported".to_owned(),
+            ));
+        }
+
+        let sn_base = u16::from_be_bytes([payload[8], payload[9]]);
+        let (offsets, mask_length) = unmarshal_mask(&payload[FEC_BASE_HEADER_LENGTH..])?;
+
+        let mut recovery = [0u8; 8];
+        recovery.copy_from_slice(&payload[..8]);
+        self.fec.push_back(FecPacket {
+            protected: offsets
+                .iter()
+                .map(|offset| sn_base.wrapping_add(*offset as u16))
+                .collect(),
+            recovery,
+            data: payload[FEC_BASE_HEADER_LENGTH + mask_length..].to_vec(),
+        });
+        if self.fec.len() > DEFAULT_FEC_HISTORY {
+            self.fec.pop_front();
+        }
+
+        Ok(self.recover())
+    }
+
+    fn insert_media(&mut self, sequence_number: u16, raw: Vec<u8>) {
+        if self.media.insert(sequence_number, raw).is_none() {
+            self.media_order.push_back(sequence_number);
+        }
+        if self.media_order.len() > DEFAULT_MEDIA_HISTORY {
+            if let Some(oldest) = self.media_order.pop_front() {
+                self.media.remove(&oldest);
+            }
+        }
+    }
+
+    // recover rebuilds the packets missing alone from a FEC packet, until
+    // no more can be recovered. FEC packets recovering an invalid packet are
+    // dropped.
+    fn recover(&mut self) -> Vec<Packet> {
+        let mut recovered = vec![];
+        loop {
+            let media = &self.media;
+            // Drop the FEC packets whose protected packets were all received
+            self.fec
+                .retain(|fec| fec.protected.iter().any(|s| !media.contains_key(s)));
+
+            let mut progress = false;
+            let mut invalid = vec![];
+            for i in 0..self.fec.len() {
+                let missing: Vec<u16> = self.fec[i]
+                    .protected
+                    .iter()
+                    .filter(|s| !self.media.contains_key(s))
+                    .copied()
+                    .collect();
+                if missing.len() != 1 {
+                    continue;
+                }
+
+                let result = self
+                    .recover_packet(&self.fec[i], missing[0])
+                    .and_then(|raw| {
+                        let mut reader = BufReader::new(raw.as_slice());
+                        let packet = Packet::unmarshal(&mut reader)?;
+                        Ok((packet, raw))
+                    });
+                match result {
+                    Ok((packet, raw)) => {
+                        recovered.push(packet);
+                        self.insert_media(missing[0], raw);
+                        progress = true;
+                    }
+                    Err(_) => invalid.push(i),
+                }
+            }
+            for i in invalid.into_iter().rev() {
+                self.fec.remove(i);
+            }
+
+            if !progress {
+                return recovered;
+            }
+        }
+    }
+
+    fn recover_packet(&self, fec: &FecPacket, sequence_number: u16) -> Result<Vec<u8>, Error> {
+        let mut recovery = fec.recovery;
+        let mut data = fec.data.clone();
+        for s in &fec.protected {
+            if let Some(raw) = self.media.get(s) {
+                xor_slice(&mut recovery, &bitstring_header(raw));
+                xor_into(&mut data, &raw[RTP_HEADER_LENGTH..]);
+            }
+        }
+
+        let length = u16::from_be_bytes([recovery[2], recovery[3]]) as usize;
+        if length > data.len() {
+            return Err(Error::new(format!(
+                "flexfec recovered length {} exceeds the repair payload of {}",
+                length,
+                data.len()
+            )));
+        }
+
+        let mut raw = Vec::with_capacity(RTP_HEADER_LENGTH + length);
+        // The version is not protected
+        raw.push(0x80 | (recovery[0] & 0x3F));
+        raw.push(recovery[1]);
+        raw.extend_from_slice(&sequence_number.to_be_bytes());
+        raw.extend_from_slice(&recovery[4..8]);
+        raw.extend_from_slice(&self.protected_ssrc.to_be_bytes());
+        raw.extend_from_slice(&data[..length]);
+        Ok(raw)
+    }
+}
+
+// bitstring_header returns the protected part of the RTP header of raw, the
+// sequence number replaced by the length of the packet after the header
+fn bitstring_header(raw: &[u8]) -> [u8; 8] {
+    let length = (raw.len() - RTP_HEADER_LENGTH) as u16;
+    let mut header = [0u8; 8];
+    header[..2].copy_from_slice(&raw[..2]);
+    header[2..4].copy_from_slice(&length.to_be_bytes());
+    header[4..].copy_from_slice(&raw[4..8]);
+    header
+}
+
+// xor_into XORs src into dst, dst grows to the length of src if needed
+fn xor_into(dst: &mut Vec<u8>, src: &[u8]) {
+    if src.len() > dst.len() {
+        dst.resize(src.len(), 0);
+    }
+    xor_slice(dst, src);
+}
+
+fn xor_slice(dst: &mut [u8], src: &[u8]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+// marshal_mask encodes the offsets from SN base into the shortest mask
+fn marshal_mask(offsets: &[usize]) -> Vec<u8> {
+    let mut mask: u128 = 0;
+    let mut max_offset = 0;
+    for offset in offsets {
+        mask |= 1 << (MAX_MASK_SPAN - 1 - offset);
+        max_offset = std::cmp::max(max_offset, *offset);
+    }
+
+    // Mask bits are laid out contiguously, skipping the k bits
+    let bits = |start: usize, count: usize| -> u64 {
+        ((mask >> (MAX_MASK_SPAN - start - count)) & ((1 << count) - 1)) as u64
+    };
+
+    let mut out = vec![];
+    if max_offset < MASK_SIZE_0 {
+        out.extend_from_slice(&((1 << 15 | bits(0, 15)) as u16).to_be_bytes());
+    } else if max_offset < MASK_SIZE_1 {
+        out.extend_from_slice(&(bits(0, 15) as u16).to_be_bytes());
+        out.extend_from_slice(&((1 << 31 | bits(15, 31)) as u32).to_be_bytes());
+    } else {
+        out.extend_from_slice(&(bits(0, 15) as u16).to_be_bytes());
+        out.extend_from_slice(&(bits(15, 31) as u32).to_be_bytes());
+        out.extend_from_slice(&(MASK_K_BIT | bits(46, 63)).to_be_bytes());
+    }
+    out
+}
+
+// unmarshal_mask returns the offsets from SN base of a mask and its length
+fn unmarshal_mask(raw: &[u8]) -> Result<(Vec<usize>, usize), Error> {
+    let too_short = || Error::new("flexfec mask is too short".to_owned());
+
+    if raw.len() < 2 {
+        return Err(too_short());
+    }
+
+    let mut offsets = vec![];
+    let push_bits = |offsets: &mut Vec<usize>, value: u64, start: usize, count: usize| {
+        for i in 0..count {
+            if value & (1 << (count - 1 - i)) != 0 {
+                offsets.push(start + i);
+            }
+        }
+    };
+
+    let word0 = u16::from_be_bytes([raw[0], raw[1]]) as u64;
+    push_bits(&mut offsets, word0 & 0x7FFF, 0, 15);
+    if word0 & 0x8000 != 0 {
+        return Ok((offsets, 2));
+    }
+
+    if raw.len() < 6 {
+        return Err(too_short());
+    }
+    let word1 = u32::from_be_bytes([raw[2], raw[3], raw[4], raw[5]]) as u64;
+    push_bits(&mut offsets, word1 & 0x7FFF_FFFF, 15, 31);
+    if word1 & 0x8000_0000 != 0 {
+        return Ok((offsets, 6));
+    }
+
+    if raw.len() < 14 {
+        return Err(too_short());
+    }
+    let mut word2 = [0u8; 8];
+    word2.copy_from_slice(&raw[6..14]);
+    let word2 = u64::from_be_bytes(word2);
+    push_bits(&mut offsets, word2 & !MASK_K_BIT, 46, 63);
+    Ok((offsets, 14))
+}
@@ -0,0 +1,322 @@
+use super::*;
+use crate::packet::test_packet;
+use crate::sequence::new_fixed_sequencer;
+
+const MEDIA_SSRC: u32 = 0x1234ABCD;
+const FEC_SSRC: u32 = 0x5678EF01;
+
+fn media_packet(sequence_number: u16) -> Packet {
+    // Payload lengths and header fields vary between packets
+    let length = 10 + (sequence_number as usize * 7) % 50;
+    let mut packet = test_packet(
+        MEDIA_SSRC,
+        sequence_number,
+        3000 * (sequence_number as u32 / 3),
+        sequence_number % 3 == 2,
+        (0..length)
+            .map(|i| (i as u16 ^ sequence_number) as u8)
+            .collect(),
+    );
+    if sequence_number & 1 == 0 {
+        packet
+            .header
+            .set_extension(1, &[sequence_number as u8])
+            .unwrap();
+    }
+    packet
+}
+
+fn marshal(packet: &Packet) -> Vec<u8> {
+    let mut raw = vec![];
+    packet.marshal(&mut raw).unwrap();
+    raw
+}
+
+fn encoder(columns: usize, rows: usize) -> FlexFecEncoder<impl Sequencer> {
+    FlexFecEncoder::new(
+        118,
+        FEC_SSRC,
+        MEDIA_SSRC,
+        new_fixed_sequencer(100),
+        columns,
+        rows,
+    )
+    .unwrap()
+}
+
+// Lcg is a deterministic pseudo random loss pattern
+struct Lcg(u64);
+
+impl Lcg {
+    fn lost(&mut self, percent: u64) -> bool {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % 100 < percent
+    }
+}
+
+// simulate_loss sends count media packets from first through the encoder
+// and the decoder, losing percent of the media and FEC packets, and returns
+// the number of lost and recovered media packets
+fn simulate_loss(
+    columns: usize,
+    rows: usize,
+    first: u16,
+    count: usize,
+    percent: u64,
+) -> Result<(usize, usize), Error> {
+    let mut encoder = encoder(columns, rows);
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+    let mut lcg = Lcg(7);
+    let mut lost = HashMap::new();
+    let mut recovered = 0;
+
+    for i in 0..count {
+        let packet = media_packet(first.wrapping_add(i as u16));
+        let fec_packets = encoder.push(&packet)?;
+        if lcg.lost(percent) {
+            lost.insert(packet.header.sequence_number, marshal(&packet));
+        } else {
+            decoder.push_media(&packet)?;
+        }
+
+        for fec in fec_packets {
+            if lcg.lost(percent) {
+                continue;
+            }
+            for r in decoder.push_fec(&fec)? {
+                let original = lost
+                    .get(&r.header.sequence_number)
+                    .expect("recovered a packet which was not lost");
+                assert_eq!(&marshal(&r), original);
+                recovered += 1;
+            }
+        }
+    }
+
+    Ok((lost.len(), recovered))
+}
+
+#[test]
+fn test_flexfec_mask() -> Result<(), Error> {
+    for offsets in [
+        vec![0],
+        vec![0, 14],
+        vec![0, 15],
+        vec![1, 30, 45],
+        vec![0, 46],
+        vec![2, 50, 108],
+    ]
+    .iter()
+    {
+        let raw = marshal_mask(offsets);
+        let expected_length = match offsets.last() {
+            Some(o) if *o < 15 => 2,
+            Some(o) if *o < 46 => 6,
+            _ => 14,
+        };
+        assert_eq!(raw.len(), expected_length, "{:?}", offsets);
+        assert_eq!(unmarshal_mask(&raw)?, (offsets.clone(), expected_length));
+    }
+
+    // The k bit of the last part is missing
+    assert!(unmarshal_mask(&[0x40, 0x00]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_encoder_layout() -> Result<(), Error> {
+    assert!(FlexFecEncoder::new(118, FEC_SSRC, MEDIA_SSRC, new_fixed_sequencer(1), 0, 1).is_err());
+    assert!(
+        FlexFecEncoder::new(118, FEC_SSRC, MEDIA_SSRC, new_fixed_sequencer(1), 10, 12).is_err()
+    );
+
+    // 3 columns and 2 rows: a row FEC packet every 3 packets and 3 column
+    // FEC packets at the end of the block
+    let mut block_encoder = encoder(3, 2);
+    let counts: Vec<usize> = (0..6)
+        .map(|i| block_encoder.push(&media_packet(i)).map(|fec| fec.len()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(counts, vec![0, 0, 1, 0, 0, 4]);
+
+    let mut encoder = encoder(1, 1);
+    let fec = encoder.push(&media_packet(7))?;
+    assert_eq!(fec.len(), 1);
+    let fec = &fec[0];
+    assert_eq!(fec.header.ssrc, FEC_SSRC);
+    assert_eq!(fec.header.payload_type, 118);
+    assert_eq!(fec.header.sequence_number, 100);
+    assert_eq!(fec.header.timestamp, media_packet(7).header.timestamp);
+    assert_eq!(fec.header.csrc, vec![MEDIA_SSRC]);
+    // SN base and a single part mask protecting offset 0
+    assert_eq!(&fec.payload[8..12], &[0x00, 0x07, 0xC0, 0x00]);
+
+    // Packets of other streams are not protected
+    assert!(encoder.push(&Packet::default())?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_recover_single_loss() -> Result<(), Error> {
+    let mut encoder = encoder(4, 1);
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+
+    let packets: Vec<Packet> = (0xFFFEu16..=0xFFFF).chain(0..2).map(media_packet).collect();
+    let mut fec = vec![];
+    for (i, packet) in packets.iter().enumerate() {
+        fec.extend(encoder.push(packet)?);
+        if i != 2 {
+            assert!(decoder.push_media(packet)?.is_empty());
+        }
+    }
+    assert_eq!(fec.len(), 1);
+
+    // FEC packets protecting another stream are ignored
+    let mut other = fec[0].clone();
+    other.header.csrc = vec![MEDIA_SSRC + 1];
+    assert!(decoder.push_fec(&other)?.is_empty());
+    assert!(decoder.fec.is_empty());
+
+    let recovered = decoder.push_fec(&fec[0])?;
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(marshal(&recovered[0]), marshal(&packets[2]));
+
+    // The FEC packet is not used again
+    assert!(decoder.push_media(&packets[2])?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_recover_media_after_fec() -> Result<(), Error> {
+    let mut encoder = encoder(3, 1);
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+
+    let packets: Vec<Packet> = (10..13).map(media_packet).collect();
+    let mut fec = vec![];
+    for packet in &packets {
+        fec.extend(encoder.push(packet)?);
+    }
+
+    // Two packets are missing until the second media packet arrives
+    assert!(decoder.push_media(&packets[0])?.is_empty());
+    assert!(decoder.push_fec(&fec[0])?.is_empty());
+    let recovered = decoder.push_media(&packets[2])?;
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(marshal(&recovered[0]), marshal(&packets[1]));
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_recover_skips_invalid_fec() -> Result<(), Error> {
+    let mut encoder = encoder(2, 1);
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+
+    let packets: Vec<Packet> = (20..24).map(media_packet).collect();
+    let mut fec = vec![];
+    for packet in &packets {
+        fec.extend(encoder.push(packet)?);
+    }
+    assert_eq!(fec.len(), 2);
+
+    // Corrupt the length recovery of the first FEC packet
+    fec[0].payload[2] ^= 0xFF;
+    fec[0].payload[3] ^= 0xFF;
+
+    assert!(decoder.push_media(&packets[0])?.is_empty());
+    assert!(decoder.push_media(&packets[2])?.is_empty());
+    assert!(decoder.push_fec(&fec[0])?.is_empty());
+    assert!(decoder.fec.is_empty(), "invalid FEC packet must be dropped");
+
+    let recovered = decoder.push_fec(&fec[1])?;
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(marshal(&recovered[0]), marshal(&packets[3]));
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_recover_burst_with_columns() -> Result<(), Error> {
+    // A burst of 4 packets in a 4x4 block is recovered by the column FEC
+    // packets only
+    let mut encoder = encoder(4, 4);
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+
+    let mut recovered = vec![];
+    for i in 0..16 {
+        let packet = media_packet(i);
+        let fec = encoder.push(&packet)?;
+        if !(5..9).contains(&i) {
+            recovered.extend(decoder.push_media(&packet)?);
+        }
+        for f in &fec {
+            recovered.extend(decoder.push_fec(f)?);
+        }
+    }
+
+    let mut sequence_numbers: Vec<u16> =
+        recovered.iter().map(|p| p.header.sequence_number).collect();
+    sequence_numbers.sort_unstable();
+    assert_eq!(sequence_numbers, vec![5, 6, 7, 8]);
+    for r in &recovered {
+        assert_eq!(marshal(r), marshal(&media_packet(r.header.sequence_number)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_flexfec_unsupported() {
+    let mut decoder = FlexFecDecoder::new(MEDIA_SSRC);
+    let fec = |payload: Vec<u8>| {
+        Packet::new(
+            Header {
+                csrc: vec![MEDIA_SSRC],
+                ..Default::default()
+            },
+            payload,
+        )
+    };
+    let mask = |first: u8| vec![first, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0];
+
+    assert!(decoder.push_fec(&fec(mask(0x80))).is_err());
+    assert!(decoder.push_fec(&fec(mask(0x40))).is_err());
+    assert!(decoder.push_fec(&fec(mask(0x00))).is_ok());
+    assert!(decoder.push_fec(&fec(vec![0; 4])).is_err());
+}
+
+#[test]
+fn test_flexfec_loss_simulation() -> Result<(), Error> {
+    // (columns, rows, loss %, minimum recovered %)
+    for &(columns, rows, percent, min_recovered) in &[
+        (5, 1, 5, 60),
+        (5, 5, 5, 85),
+        (10, 10, 10, 50),
+        (4, 4, 20, 30),
+    ] {
+        let (lost, recovered) = simulate_loss(columns, rows, 65000, 2000, percent)?;
+        assert!(lost > 0);
+        let rate = recovered * 100 / lost;
+        assert!(
+            rate >= min_recovered,
+            "{}x{} with {}% loss recovered {}/{} packets",
+            columns,
+            rows,
+            percent,
+            recovered,
+            lost
+        );
+    }
+
+    // 2D parity recovers more than row parity alone at the same loss
+    let (lost_row, recovered_row) = simulate_loss(5, 1, 0, 2000, 10)?;
+    let (lost_2d, recovered_2d) = simulate_loss(5, 5, 0, 2000, 10)?;
+    assert!(recovered_2d * lost_row > recovered_row * lost_2d);
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::packet::Packet;
+
+use std::collections::VecDeque;
+
+use util::Error;
+
+#[cfg(test)]
+mod red_test;
+
+const RED_HEADER_LENGTH: usize = 4;
+const RED_LAST_HEADER_LENGTH: usize = 1;
+const RED_FOLLOW_MASK: u8 = 0x80;
+const RED_PAYLOAD_TYPE_MASK: u8 = 0x7F;
+// Largest timestamp offset and block length of a redundant block
+const MAX_TIMESTAMP_OFFSET: u32 = (1 << 14) - 1;
+const MAX_BLOCK_LENGTH: usize = (1 << 10) - 1;
+
+struct RedBlock {
+    payload_type: u8,
+    timestamp: u32,
+    payload: Vec<u8>,
+}
+
+// RedEncoder encapsulates audio packets in RED payloads, RFC 2198, each one
+// carrying the payloads of the previous distance packets
+//
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |F|   block PT  |  timestamp offset         |   block length    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |0|   Block PT  |
+// +-+-+-+-+-+-+-+-+
+pub struct RedEncoder {
+    payload_type: u8,
+    distance: usize,
+    // Previous primary blocks, oldest first
+    history: VecDeque<RedBlock>,
+}
+
+impl RedEncoder {
+    // new constructs an encoder of RED packets of payload_type carrying up
+    // to distance redundant blocks
+    pub fn new(payload_type: u8, distance: usize) -> Self {
+        RedEncoder {
+            payload_type,
+            distance,
+            history: VecDeque::new(),
+        }
+    }
+
+    // encode returns packet with its payload encapsulated with the
+    // redundant blocks of the previous packets
+    //
+    // Redundant blocks too old or too large to be described by a block
+    // header are left out, along with the older ones so the decoder can
+    // derive their sequence numbers.
+    pub fn encode(&mut self, packet: &Packet) -> Packet {
+        let timestamp = packet.header.timestamp;
+        let redundant: Vec<&RedBlock> = self
+            .history
+            .iter()
+            .rev()
+            .take_while(|block| {
+                timestamp.wrapping_sub(block.timestamp) <= MAX_TIMESTAMP_OFFSET
+                    && block.payload.len() <= MAX_BLOCK_LENGTH
+            })
+            .collect();
+
+        let length = redundant
+            .iter()
+            .map(|block| RED_HEADER_LENGTH + block.payload.len())
+            .sum::<usize>()
+            + RED_LAST_HEADER_LENGTH
+            + packet.payload.len();
+        let mut payload = Vec::with_capacity(length);
+        for block in redundant.iter().rev() {
+            let offset = timestamp.wrapping_sub(block.timestamp);
+            let header = (RED_FOLLOW_MASK as u32 | block.payload_type as u32) << 24
+                | offset << 10
+                | block.payload.len() as u32;
+            payload.extend_from_slice(&header.to_be_bytes());
+        }
+        payload.push(packet.header.payload_type & RED_PAYLOAD_TYPE_MASK);
+        for block in redundant.iter().rev() {
+            payload.extend_from_slice(&block.payload);
+        }
+        payload.extend_from_slice(&packet.payload);
+
+        self.history.push_back(RedBlock {
+            payload_type: packet.header.payload_type,
+            timestamp,
+            payload: packet.payload.clone(),
+        });
+        while self.history.len() > self.distance {
+            self.history.pop_front();
+        }
+
+        let mut header = packet.header.clone();
+        header.payload_type = self.payload_type;
+        let mut red = Packet::new(header, payload);
+        if packet.padding_size() > 0 {
+            red.set_padding(packet.padding_size());
+        }
+        red
+    }
+}
+
+// decode splits a RED packet into the packets of its redundant blocks,
+// oldest first, followed by the primary packet
+//
+// The redundant blocks are assumed to come from the packets immediately
+// preceding the primary one, as RedEncoder produces them.
+pub fn decode(packet: &Packet) -> Result<Vec<Packet>, Error> {
+    let payload = &packet.payload;
+
+    // (payload type, timestamp offset, length) of the redundant blocks
+    let mut headers = vec![];
+    let mut offset = 0;
+    loop {
+        if offset >= payload.len() {
+            return Err(Error::new("red payload header is truncated".to_owned()));
+        }
+        if payload[offset] & RED_FOLLOW_MASK == 0 {
+            break;
+        }
+        if offset + RED_HEADER_LENGTH > payload.len() {
+            return Err(Error::new("red payload header is truncated".to_owned()));
+        }
+        let header = u32::from_be_bytes([
+            payload[offset],
+            payload[offset + 1],
+            payload[offset + 2],
+            payload[offset + 3],
+        ]);
+        headers.push((
+            payload[offset] & RED_PAYLOAD_TYPE_MASK,
+            (header >> 10) & MAX_TIMESTAMP_OFFSET,
+            (header as usize) & MAX_BLOCK_LENGTH,
+        ));
+        offset += RED_HEADER_LENGTH;
+    }
+    let primary_payload_type = payload[offset] & RED_PAYLOAD_TYPE_MASK;
+    offset += RED_LAST_HEADER_LENGTH;
+
+    let mut packets = Vec::with_capacity(headers.len() + 1);
+    let count = headers.len();
+    for (i, (payload_type, timestamp_offset, length)) in headers.into_iter().enumerate() {
+        if offset + length > payload.len() {
+            return Err(Error::new(format!(
+                "red block of {} bytes exceeds the payload of {} bytes",
+                length,
+                payload.len() - offset
+            )));
+        }
+
+        let mut header = packet.header.clone();
+        header.payload_type = payload_type;
+        header.timestamp = packet.header.timestamp.wrapping_sub(timestamp_offset);
+        header.sequence_number = packet
+            .header
+            .sequence_number
+            .wrapping_sub((count - i) as u16);
+        header.marker = false;
+        // The padding of the RED packet follows the primary block
+        header.padding = false;
+        packets.push(Packet::new(
+            header,
+            payload[offset..offset + length].to_vec(),
+        ));
+        offset += length;
+    }
+
+    let mut header = packet.header.clone();
+    header.payload_type = primary_payload_type;
+    let mut primary = Packet::new(header, payload[offset..].to_vec());
+    if packet.padding_size() > 0 {
+        primary.set_padding(packet.padding_size());
+    }
+    packets.push(primary);
+
+    Ok(packets)
+}
@@ -0,0 +1,144 @@
+use super::*;
+use crate::header::Header;
+use crate::packet::test_packet;
+
+use std::collections::HashMap;
+
+fn audio_packet(sequence_number: u16, marker: bool) -> Packet {
+    let mut packet = test_packet(
+        0x1234ABCD,
+        sequence_number,
+        (sequence_number as u32).wrapping_mul(960),
+        marker,
+        vec![sequence_number as u8; 20 + sequence_number as usize % 7],
+    );
+    packet.header.payload_type = 111;
+    packet
+}
+
+#[test]
+fn test_red_encode() {
+    let mut encoder = RedEncoder::new(63, 2);
+
+    let first = audio_packet(10, true);
+    let red = encoder.encode(&first);
+    assert_eq!(red.header.payload_type, 63);
+    assert!(red.header.marker);
+    // No redundant block yet
+    assert_eq!(red.payload[0], 111);
+    assert_eq!(&red.payload[1..], first.payload.as_slice());
+
+    encoder.encode(&audio_packet(11, false));
+    let third = audio_packet(12, false);
+    let red = encoder.encode(&third);
+    // Two redundant blocks, oldest first: 1920 and 960 timestamp units back
+    assert_eq!(
+        &red.payload[..9],
+        &[
+            0x80 | 111,
+            (1920 >> 6) as u8,
+            ((1920 << 2) & 0xFF) as u8,
+            first.payload.len() as u8,
+            0x80 | 111,
+            (960 >> 6) as u8,
+            ((960 << 2) & 0xFF) as u8,
+            audio_packet(11, false).payload.len() as u8,
+            111,
+        ]
+    );
+    assert_eq!(
+        red.payload.len(),
+        9 + first.payload.len() + audio_packet(11, false).payload.len() + third.payload.len()
+    );
+}
+
+#[test]
+fn test_red_round_trip() -> Result<(), Error> {
+    let mut encoder = RedEncoder::new(63, 2);
+    let packets: Vec<Packet> = (65534..=65535)
+        .chain(0..3)
+        .map(|s| audio_packet(s, false))
+        .collect();
+
+    let mut last = vec![];
+    for packet in &packets {
+        last = decode(&encoder.encode(packet))?;
+    }
+
+    assert_eq!(last.len(), 3);
+    assert_eq!(last, packets[2..].to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn test_red_skip_blocks() -> Result<(), Error> {
+    let mut encoder = RedEncoder::new(63, 3);
+
+    // A payload too large for the block length field is not repeated, nor
+    // are the blocks before it
+    encoder.encode(&audio_packet(1, false));
+    let mut large = audio_packet(2, false);
+    large.payload = vec![0; MAX_BLOCK_LENGTH + 1];
+    encoder.encode(&large);
+    encoder.encode(&audio_packet(3, false));
+    let decoded = decode(&encoder.encode(&audio_packet(4, false)))?;
+    assert_eq!(
+        decoded,
+        vec![audio_packet(3, false), audio_packet(4, false)]
+    );
+
+    // Blocks too old for the timestamp offset field are not repeated
+    let mut encoder = RedEncoder::new(63, 2);
+    encoder.encode(&audio_packet(1, false));
+    let mut late = audio_packet(2, false);
+    late.header.timestamp += MAX_TIMESTAMP_OFFSET + 1;
+    let decoded = decode(&encoder.encode(&late))?;
+    assert_eq!(decoded, vec![late]);
+
+    Ok(())
+}
+
+#[test]
+fn test_red_decode_errors() {
+    let red = |payload: Vec<u8>| Packet::new(Header::default(), payload);
+
+    assert!(decode(&red(vec![])).is_err());
+    assert!(decode(&red(vec![0x80 | 111, 0, 0])).is_err());
+    // The redundant block is longer than the payload
+    assert!(decode(&red(vec![0x80 | 111, 0, 0x04, 0x05, 111, 1, 2])).is_err());
+    // An empty primary block is valid
+    assert_eq!(decode(&red(vec![111])).map(|p| p.len()).ok(), Some(1));
+}
+
+#[test]
+fn test_red_loss_simulation() -> Result<(), Error> {
+    // A loss every pattern packets is recovered with a redundant block
+    for &(distance, pattern, expected_rate) in &[(1, 3, 100), (1, 2, 100), (2, 4, 100), (0, 3, 0)] {
+        let mut encoder = RedEncoder::new(63, distance);
+        let mut received = HashMap::new();
+        let mut lost = 0;
+        for s in 0..=300u16 {
+            let red = encoder.encode(&audio_packet(s, false));
+            if s % pattern == 1 {
+                lost += 1;
+                continue;
+            }
+            for packet in decode(&red)? {
+                assert_eq!(packet, audio_packet(packet.header.sequence_number, false));
+                received.insert(packet.header.sequence_number, packet);
+            }
+        }
+
+        let recovered = lost - (301 - received.len());
+        assert_eq!(
+            recovered * 100 / lost,
+            expected_rate,
+            "distance {} losing 1 packet in {}",
+            distance,
+            pattern
+        );
+    }
+
+    Ok(())
+}
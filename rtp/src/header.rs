@@ -4,30 +4,30 @@ use util::Error;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-const HEADER_LENGTH: usize = 4;
-const VERSION_SHIFT: u8 = 6;
-const VERSION_MASK: u8 = 0x3;
-const PADDING_SHIFT: u8 = 5;
-const PADDING_MASK: u8 = 0x1;
-const EXTENSION_SHIFT: u8 = 4;
-const EXTENSION_MASK: u8 = 0x1;
-const EXTENSION_PROFILE_ONE_BYTE: u16 = 0xBEDE;
-const EXTENSION_PROFILE_TWO_BYTE: u16 = 0x1000;
-const EXTENSION_ID_RESERVED: u8 = 0xF;
-const CC_MASK: u8 = 0xF;
-const MARKER_SHIFT: u8 = 7;
-const MARKER_MASK: u8 = 0x1;
-const PT_MASK: u8 = 0x7F;
-const SEQ_NUM_OFFSET: usize = 2;
-const SEQ_NUM_LENGTH: usize = 2;
-const TIMESTAMP_OFFSET: usize = 4;
-const TIMESTAMP_LENGTH: usize = 4;
-const SSRC_OFFSET: usize = 8;
-const SSRC_LENGTH: usize = 4;
-const CSRC_OFFSET: usize = 12;
-const CSRC_LENGTH: usize = 4;
-
-#[derive(Debug, Eq, PartialEq, Default)]
+pub(crate) const HEADER_LENGTH: usize = 4;
+pub(crate) const VERSION_SHIFT: u8 = 6;
+pub(crate) const VERSION_MASK: u8 = 0x3;
+pub(crate) const PADDING_SHIFT: u8 = 5;
+pub(crate) const PADDING_MASK: u8 = 0x1;
+pub(crate) const EXTENSION_SHIFT: u8 = 4;
+pub(crate) const EXTENSION_MASK: u8 = 0x1;
+pub(crate) const EXTENSION_PROFILE_ONE_BYTE: u16 = 0xBEDE;
+pub(crate) const EXTENSION_PROFILE_TWO_BYTE: u16 = 0x1000;
+pub(crate) const EXTENSION_ID_RESERVED: u8 = 0xF;
+pub(crate) const CC_MASK: u8 = 0xF;
+pub(crate) const MARKER_SHIFT: u8 = 7;
+pub(crate) const MARKER_MASK: u8 = 0x1;
+pub(crate) const PT_MASK: u8 = 0x7F;
+pub(crate) const SEQ_NUM_OFFSET: usize = 2;
+pub(crate) const SEQ_NUM_LENGTH: usize = 2;
+pub(crate) const TIMESTAMP_OFFSET: usize = 4;
+pub(crate) const TIMESTAMP_LENGTH: usize = 4;
+pub(crate) const SSRC_OFFSET: usize = 8;
+pub(crate) const SSRC_LENGTH: usize = 4;
+pub(crate) const CSRC_OFFSET: usize = 12;
+pub(crate) const CSRC_LENGTH: usize = 4;
+
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
 pub struct Extension {
     pub id: u8,
     pub payload: Vec<u8>,
@@ -35,7 +35,7 @@ pub struct Extension {
 
 // Header represents an RTP packet header
 // NOTE: PayloadOffset is populated by Marshal/Unmarshal and should not be modified
-#[derive(Debug, Eq, PartialEq, Default)]
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
 pub struct Header {
     pub version: u8,
     pub padding: bool,
@@ -57,7 +57,8 @@ impl Header {
     pub fn size(&self) -> usize {
         let mut head_size = 12 + (self.csrc.len() * CSRC_LENGTH);
         if self.extension {
-            head_size += 4 + self.get_extension_payload_len();
+            // The extension payload is padded to 32-bit words
+            head_size += 4 + self.get_extension_payload_len().div_ceil(4) * 4;
         }
         head_size
     }
@@ -86,65 +87,51 @@ impl Header {
     }
 
     // SetExtension sets an RTP header extension
+    //
+    // The one-byte profile is used while every extension fits in it,
+    // the header switches to the two-byte profile otherwise.
     pub fn set_extension(&mut self, id: u8, payload: &[u8]) -> Result<(), Error> {
-        if self.extension {
-            match self.extension_profile {
-                EXTENSION_PROFILE_ONE_BYTE => {
-                    if id < 1 || id > 14 {
-                        return Err(Error::new(
-                            "header extension id must be between 1 and 14 for RFC 5285 extensions"
-                                .to_owned(),
-                        ));
-                    }
-                    if payload.len() > 16 {
-                        return Err(Error::new("header extension payload must be 16bytes or less for RFC 5285 one byte extensions".to_owned()));
-                    }
+        if !self.extension {
+            // No existing header extensions
+            self.extension = true;
+            self.extension_profile = EXTENSION_PROFILE_ONE_BYTE;
+            self.extensions.clear();
+        }
+
+        match self.extension_profile {
+            EXTENSION_PROFILE_ONE_BYTE | EXTENSION_PROFILE_TWO_BYTE => {
+                if id < 1 {
+                    return Err(Error::new(
+                        "header extension id must be between 1 and 255 for RFC 5285 extensions"
+                            .to_owned(),
+                    ));
                 }
-                EXTENSION_PROFILE_TWO_BYTE => {
-                    if id < 1 {
-                        return Err(Error::new(
-                            "header extension id must be between 1 and 255 for RFC 5285 extensions"
-                                .to_owned(),
-                        ));
-                    }
-                    if payload.len() > 255 {
-                        return Err(Error::new("header extension payload must be 255bytes or less for RFC 5285 two byte extensions".to_owned()));
-                    }
+                if payload.len() > 255 {
+                    return Err(Error::new("header extension payload must be 255bytes or less for RFC 5285 two byte extensions".to_owned()));
                 }
-                _ => {
-                    if id != 0 {
-                        return Err(Error::new(
-                            "header extension id must be 0 for none RFC 5285 extensions".to_owned(),
-                        ));
-                    }
+                if self.extension_profile == EXTENSION_PROFILE_ONE_BYTE
+                    && !fits_one_byte_profile(id, payload.len())
+                {
+                    self.extension_profile = EXTENSION_PROFILE_TWO_BYTE;
                 }
-            };
-
-            // Update existing if it exists else add new extension
-            for extension in &mut self.extensions {
-                if extension.id == id {
-                    extension.payload.clear();
-                    extension.payload.extend_from_slice(payload);
-                    return Ok(());
+            }
+            _ => {
+                if id != 0 {
+                    return Err(Error::new(
+                        "header extension id must be 0 for none RFC 5285 extensions".to_owned(),
+                    ));
                 }
             }
-            self.extensions.push(Extension {
-                id,
-                payload: payload.to_vec(),
-            });
-            return Ok(());
-        }
-
-        // No existing header extensions
-        self.extension = true;
+        };
 
-        let len = payload.len();
-        if len <= 16 {
-            self.extension_profile = EXTENSION_PROFILE_ONE_BYTE
-        } else if len > 16 && len < 256 {
-            self.extension_profile = EXTENSION_PROFILE_TWO_BYTE
+        // Update existing if it exists else add new extension
+        for extension in &mut self.extensions {
+            if extension.id == id {
+                extension.payload.clear();
+                extension.payload.extend_from_slice(payload);
+                return Ok(());
+            }
         }
-
         self.extensions.push(Extension {
             id,
             payload: payload.to_vec(),
@@ -392,3 +379,9 @@ impl Header {
         Ok(())
     }
 }
+
+// fits_one_byte_profile checks if an extension can be sent with the RFC 8285
+// one-byte header
+fn fits_one_byte_profile(id: u8, len: usize) -> bool {
+    id <= 14 && (1..=16).contains(&len)
+}
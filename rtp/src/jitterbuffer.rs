@@ -0,0 +1,413 @@
+use crate::packet::Packet;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod jitterbuffer_test;
+
+pub const DEFAULT_MIN_DELAY: Duration = Duration::from_millis(10);
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(500);
+
+// Target delay as a multiple of the interarrival jitter
+const JITTER_DELAY_FACTOR: f64 = 4.0;
+// The target delay grows immediately and shrinks by 1/TARGET_DELAY_DECAY of
+// the difference for every packet
+const TARGET_DELAY_DECAY: u32 = 32;
+// Sequence number jump treated as a restart of the stream, RFC 3550 A.1
+const MAX_DROPOUT: u64 = 3000;
+// Packets needed in sequence after a jump before the stream is restarted
+// from them, RFC 3550 A.1
+const MIN_SEQUENTIAL: usize = 2;
+
+const SEQ_CYCLE: u64 = 1 << 16;
+const TIMESTAMP_CYCLE: u64 = 1 << 32;
+
+// JitterBufferMode selects what pop returns
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JitterBufferMode {
+    // One packet at a time, lost packets are reported so audio decoders can
+    // conceal them frame by frame
+    Audio,
+    // All the packets of a frame at once, frames missing packets at their
+    // playout time are dropped
+    Video,
+}
+
+// Playout is the output of JitterBuffer::pop
+#[derive(Debug, PartialEq)]
+pub enum Playout {
+    Packet(Packet),
+    Lost(u16),
+    Frame(Vec<Packet>),
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct JitterBufferStats {
+    pub packets_received: u64,
+    pub packets_duplicated: u64,
+    // Packets arrived after their playout time
+    pub packets_late: u64,
+    // Packets missing at their playout time
+    pub packets_lost: u64,
+    pub frames_dropped: u64,
+    // Interarrival jitter in timestamp units, RFC 3550 6.4.1
+    pub jitter: u32,
+    pub target_delay: Duration,
+}
+
+struct Entry {
+    packet: Packet,
+    timestamp: u64,
+}
+
+// JitterBuffer reorders RTP packets and releases them at their playout time
+//
+// The playout time of a packet is the arrival time its timestamp predicts
+// from the fastest packet received so far, plus a target delay adapting to
+// the interarrival jitter.
+pub struct JitterBuffer {
+    clock_rate: u32,
+    mode: JitterBufferMode,
+    min_delay: Duration,
+    max_delay: Duration,
+    target_delay: Duration,
+
+    packets: BTreeMap<u64, Entry>,
+    missing: BTreeSet<u64>,
+
+    // Extended sequence numbers and timestamp
+    highest_sequence_number: Option<u64>,
+    next_sequence_number: Option<u64>,
+    last_timestamp: Option<u64>,
+    started: bool,
+
+    // Origin of the arrival clock
+    epoch: Option<Instant>,
+    // Lowest arrival time minus media time seen, in seconds since epoch
+    base_offset: Option<f64>,
+    last_transit: Option<f64>,
+    jitter: f64,
+
+    // Timestamp of a frame which lost packets
+    dropped_timestamp: Option<u64>,
+
+    // Packets following a sequence number jump, held back until
+    // MIN_SEQUENTIAL of them arrive in sequence
+    probation: Vec<(Packet, Instant)>,
+
+    stats: JitterBufferStats,
+}
+
+impl JitterBuffer {
+    pub fn new(clock_rate: u32, mode: JitterBufferMode) -> Self {
+        JitterBuffer {
+            clock_rate,
+            mode,
+            min_delay: DEFAULT_MIN_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            target_delay: DEFAULT_MIN_DELAY,
+            packets: BTreeMap::new(),
+            missing: BTreeSet::new(),
+            highest_sequence_number: None,
+            next_sequence_number: None,
+            last_timestamp: None,
+            started: false,
+            epoch: None,
+            base_offset: None,
+            last_transit: None,
+            jitter: 0.0,
+            dropped_timestamp: None,
+            probation: vec![],
+            stats: JitterBufferStats {
+                target_delay: DEFAULT_MIN_DELAY,
+                ..Default::default()
+            },
+        }
+    }
+
+    // with_delay_bounds sets the range the target delay adapts in
+    pub fn with_delay_bounds(mut self, min_delay: Duration, max_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self.max_delay = std::cmp::max(min_delay, max_delay);
+        self.target_delay = min_delay;
+        self.stats.target_delay = min_delay;
+        self
+    }
+
+    pub fn stats(&self) -> JitterBufferStats {
+        self.stats
+    }
+
+    pub fn target_delay(&self) -> Duration {
+        self.target_delay
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    // missing returns the sequence numbers still awaited, to be requested
+    // with a NACK
+    pub fn missing(&self) -> Vec<u16> {
+        self.missing.iter().map(|s| *s as u16).collect()
+    }
+
+    // push inserts a packet received at arrival
+    pub fn push(&mut self, packet: Packet, arrival: Instant) {
+        self.stats.packets_received += 1;
+
+        if let Some(highest) = self.highest_sequence_number {
+            let sequence_number = extend_sequence_number(highest, packet.header.sequence_number);
+            if sequence_number > highest + MAX_DROPOUT || sequence_number + MAX_DROPOUT < highest {
+                self.probe(packet, arrival);
+                return;
+            }
+        }
+        self.probation.clear();
+
+        self.insert(packet, arrival);
+    }
+
+    // probe holds back a packet far from the expected sequence numbers, the
+    // stream is restarted from the held back packets once MIN_SEQUENTIAL of
+    // them follow each other
+    fn probe(&mut self, packet: Packet, arrival: Instant) {
+        let in_sequence = matches!(self.probation.last(), Some((last, _))
+            if last.header.sequence_number.wrapping_add(1) == packet.header.sequence_number);
+        if !in_sequence {
+            self.probation.clear();
+        }
+        self.probation.push((packet, arrival));
+        if self.probation.len() < MIN_SEQUENTIAL {
+            return;
+        }
+
+        let probation = std::mem::take(&mut self.probation);
+        self.restart();
+        for (packet, arrival) in probation {
+            self.insert(packet, arrival);
+        }
+    }
+
+    fn insert(&mut self, packet: Packet, arrival: Instant) {
+        let sequence_number = match self.highest_sequence_number {
+            Some(highest) => extend_sequence_number(highest, packet.header.sequence_number),
+            None => SEQ_CYCLE + packet.header.sequence_number as u64,
+        };
+        let timestamp = match self.last_timestamp {
+            Some(last) => extend_timestamp(last, packet.header.timestamp),
+            None => TIMESTAMP_CYCLE + packet.header.timestamp as u64,
+        };
+
+        if let Some(next) = self.next_sequence_number {
+            if sequence_number < next {
+                if self.started {
+                    self.stats.packets_late += 1;
+                    return;
+                }
+                // Nothing was played out yet, start from this packet
+                self.missing.extend(sequence_number + 1..next);
+                self.next_sequence_number = Some(sequence_number);
+            }
+        }
+        if self.packets.contains_key(&sequence_number) {
+            self.stats.packets_duplicated += 1;
+            return;
+        }
+
+        let epoch = *self.epoch.get_or_insert(arrival);
+        let arrival_secs = arrival.saturating_duration_since(epoch).as_secs_f64();
+        self.update_jitter(arrival_secs, timestamp);
+
+        match self.highest_sequence_number {
+            Some(highest) if sequence_number > highest => {
+                self.missing.extend(highest + 1..sequence_number);
+                self.highest_sequence_number = Some(sequence_number);
+            }
+            Some(_) => {
+                self.missing.remove(&sequence_number);
+            }
+            None => {
+                self.highest_sequence_number = Some(sequence_number);
+                self.next_sequence_number = Some(sequence_number);
+            }
+        }
+        self.last_timestamp = Some(timestamp);
+
+        self.packets
+            .insert(sequence_number, Entry { packet, timestamp });
+    }
+
+    // pop returns the next playout due at now
+    pub fn pop(&mut self, now: Instant) -> Option<Playout> {
+        match self.mode {
+            JitterBufferMode::Audio => self.pop_packet(now),
+            JitterBufferMode::Video => self.pop_frame(now),
+        }
+    }
+
+    fn pop_packet(&mut self, now: Instant) -> Option<Playout> {
+        let next = self.next_sequence_number?;
+
+        if let Some(entry) = self.packets.get(&next) {
+            if !self.is_due(entry.timestamp, now) {
+                return None;
+            }
+            let entry = self.packets.remove(&next)?;
+            self.advance(next + 1);
+            return Some(Playout::Packet(entry.packet));
+        }
+
+        // The packet is lost once the following one is due
+        let (_, entry) = self.packets.iter().next()?;
+        if !self.is_due(entry.timestamp, now) {
+            return None;
+        }
+        self.stats.packets_lost += 1;
+        self.advance(next + 1);
+        Some(Playout::Lost(next as u16))
+    }
+
+    fn pop_frame(&mut self, now: Instant) -> Option<Playout> {
+        loop {
+            let next = self.next_sequence_number?;
+            let (&first, entry) = self.packets.iter().next()?;
+            let timestamp = entry.timestamp;
+            if !self.is_due(timestamp, now) {
+                return None;
+            }
+
+            if first != next {
+                // The packets before the first buffered one are lost, the
+                // frame of the first one may be missing its beginning
+                self.stats.packets_lost += first - next;
+                self.drop_frame(timestamp);
+                self.advance(first);
+                continue;
+            }
+
+            // A frame ends at the marker bit or before a timestamp change
+            let mut end = next;
+            let mut complete = false;
+            let mut s = next;
+            while let Some(entry) = self.packets.get(&s) {
+                if entry.timestamp != timestamp {
+                    complete = true;
+                    break;
+                }
+                end = s;
+                if entry.packet.header.marker {
+                    complete = true;
+                    break;
+                }
+                s += 1;
+            }
+
+            let frame: Vec<Packet> = (next..=end)
+                .filter_map(|s| self.packets.remove(&s))
+                .map(|entry| entry.packet)
+                .collect();
+            self.advance(end + 1);
+
+            if !complete || self.dropped_timestamp == Some(timestamp) {
+                self.drop_frame(timestamp);
+                continue;
+            }
+            self.dropped_timestamp = None;
+
+            return Some(Playout::Frame(frame));
+        }
+    }
+
+    fn drop_frame(&mut self, timestamp: u64) {
+        if self.dropped_timestamp != Some(timestamp) {
+            self.stats.frames_dropped += 1;
+            self.dropped_timestamp = Some(timestamp);
+        }
+    }
+
+    fn advance(&mut self, next: u64) {
+        self.started = true;
+        self.next_sequence_number = Some(next);
+        self.missing = self.missing.split_off(&next);
+    }
+
+    fn restart(&mut self) {
+        self.packets.clear();
+        self.missing.clear();
+        self.highest_sequence_number = None;
+        self.next_sequence_number = None;
+        self.last_timestamp = None;
+        self.started = false;
+        self.base_offset = None;
+        self.last_transit = None;
+        self.dropped_timestamp = None;
+    }
+
+    fn update_jitter(&mut self, arrival_secs: f64, timestamp: u64) {
+        let clock_rate = self.clock_rate as f64;
+
+        // J(i) = J(i-1) + (|D(i-1,i)| - J(i-1))/16
+        let transit = arrival_secs * clock_rate - timestamp as f64;
+        if let Some(last_transit) = self.last_transit {
+            let d = (transit - last_transit).abs();
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.last_transit = Some(transit);
+
+        let offset = arrival_secs - timestamp as f64 / clock_rate;
+        self.base_offset = Some(match self.base_offset {
+            Some(base_offset) if base_offset < offset => base_offset,
+            _ => offset,
+        });
+
+        let desired = Duration::from_secs_f64(self.jitter * JITTER_DELAY_FACTOR / clock_rate);
+        let desired = std::cmp::min(std::cmp::max(desired, self.min_delay), self.max_delay);
+        if desired > self.target_delay {
+            self.target_delay = desired;
+        } else {
+            self.target_delay -= (self.target_delay - desired) / TARGET_DELAY_DECAY;
+        }
+
+        self.stats.jitter = self.jitter as u32;
+        self.stats.target_delay = self.target_delay;
+    }
+
+    fn is_due(&self, timestamp: u64, now: Instant) -> bool {
+        match (self.epoch, self.base_offset) {
+            (Some(epoch), Some(base_offset)) => {
+                let media_time = base_offset + timestamp as f64 / self.clock_rate as f64;
+                let playout = epoch + Duration::from_secs_f64(media_time.max(0.0));
+                playout + self.target_delay <= now
+            }
+            _ => false,
+        }
+    }
+}
+
+// extend_sequence_number returns the extended sequence number of value
+// closest to highest
+fn extend_sequence_number(highest: u64, value: u16) -> u64 {
+    extend(highest, value as u64, SEQ_CYCLE)
+}
+
+// extend_timestamp returns the extended timestamp of value closest to last
+fn extend_timestamp(last: u64, value: u32) -> u64 {
+    extend(last, value as u64, TIMESTAMP_CYCLE)
+}
+
+fn extend(reference: u64, value: u64, cycle: u64) -> u64 {
+    let extended = (reference & !(cycle - 1)) | value;
+    if extended > reference && extended - reference > cycle / 2 && extended >= cycle {
+        extended - cycle
+    } else if extended < reference && reference - extended > cycle / 2 {
+        extended + cycle
+    } else {
+        extended
+    }
+}
@@ -0,0 +1,377 @@
+use super::*;
+use crate::packet::test_packet;
+
+fn packet(sequence_number: u16, timestamp: u32, marker: bool) -> Packet {
+    test_packet(
+        0,
+        sequence_number,
+        timestamp,
+        marker,
+        vec![sequence_number as u8],
+    )
+}
+
+fn sequence_numbers(playouts: &[Playout]) -> Vec<i32> {
+    playouts
+        .iter()
+        .map(|p| match p {
+            Playout::Packet(p) => p.header.sequence_number as i32,
+            Playout::Lost(s) => -(*s as i32),
+            Playout::Frame(_) => panic!("unexpected frame"),
+        })
+        .collect()
+}
+
+fn pop_all(jb: &mut JitterBuffer, now: Instant) -> Vec<Playout> {
+    let mut playouts = vec![];
+    while let Some(p) = jb.pop(now) {
+        playouts.push(p);
+    }
+    playouts
+}
+
+// push_trace pushes (sequence number, timestamp, marker, arrival in ms)
+fn push_trace(jb: &mut JitterBuffer, start: Instant, trace: &[(u16, u32, bool, u64)]) {
+    for &(sequence_number, timestamp, marker, arrival) in trace {
+        jb.push(
+            packet(sequence_number, timestamp, marker),
+            start + Duration::from_millis(arrival),
+        );
+    }
+}
+
+#[test]
+fn test_extend() {
+    assert_eq!(extend_sequence_number(SEQ_CYCLE + 10, 11), SEQ_CYCLE + 11);
+    assert_eq!(extend_sequence_number(SEQ_CYCLE + 10, 9), SEQ_CYCLE + 9);
+    assert_eq!(extend_sequence_number(SEQ_CYCLE + 65535, 0), 2 * SEQ_CYCLE);
+    assert_eq!(
+        extend_sequence_number(2 * SEQ_CYCLE, 65535),
+        SEQ_CYCLE + 65535
+    );
+    assert_eq!(extend_sequence_number(0, 65535), 65535);
+
+    assert_eq!(
+        extend_timestamp(TIMESTAMP_CYCLE + 0xFFFF_FF00, 0x100),
+        2 * TIMESTAMP_CYCLE + 0x100
+    );
+    assert_eq!(
+        extend_timestamp(2 * TIMESTAMP_CYCLE + 0x100, 0xFFFF_FF00),
+        TIMESTAMP_CYCLE + 0xFFFF_FF00
+    );
+}
+
+#[test]
+fn test_jitter_buffer_playout_delay() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(
+        &mut jb,
+        start,
+        &[(0, 0, false, 0), (1, 960, false, 20), (2, 1920, false, 40)],
+    );
+
+    assert_eq!(jb.target_delay(), DEFAULT_MIN_DELAY);
+    assert_eq!(jb.pop(start + Duration::from_millis(9)), None);
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(11))),
+        vec![0]
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(31))),
+        vec![1]
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(51))),
+        vec![2]
+    );
+    assert!(jb.is_empty());
+}
+
+#[test]
+fn test_jitter_buffer_reorder() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    // The first packet pushed is not the first of the stream
+    push_trace(
+        &mut jb,
+        start,
+        &[
+            (11, 960, false, 0),
+            (10, 0, false, 2),
+            (13, 2880, false, 60),
+            (12, 1920, false, 61),
+        ],
+    );
+
+    assert!(jb.missing().is_empty());
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(100))),
+        vec![10, 11, 12, 13]
+    );
+    assert_eq!(jb.stats().packets_late, 0);
+}
+
+#[test]
+fn test_jitter_buffer_wrap_around() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(
+        &mut jb,
+        start,
+        &[
+            (65534, 0xFFFF_FC40, false, 0),
+            (0, 0x0000_0140, false, 40),
+            (65535, 0xFFFF_FF00, false, 41),
+            (1, 0x0000_0500, false, 60),
+        ],
+    );
+
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(100))),
+        vec![65534, 65535, 0, 1]
+    );
+}
+
+#[test]
+fn test_jitter_buffer_loss() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(
+        &mut jb,
+        start,
+        &[(0, 0, false, 0), (2, 1920, false, 40), (3, 2880, false, 60)],
+    );
+    assert_eq!(jb.missing(), vec![1]);
+
+    // The lost packet is reported once the following one is due
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(31))),
+        vec![0]
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(51))),
+        vec![-1, 2]
+    );
+    assert!(jb.missing().is_empty());
+    assert_eq!(jb.stats().packets_lost, 1);
+
+    // A retransmission arriving after playout is late
+    push_trace(&mut jb, start, &[(1, 960, false, 55)]);
+    assert_eq!(jb.stats().packets_late, 1);
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(71))),
+        vec![3]
+    );
+}
+
+#[test]
+fn test_jitter_buffer_retransmission() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(&mut jb, start, &[(0, 0, false, 0), (2, 1920, false, 40)]);
+    assert_eq!(jb.missing(), vec![1]);
+
+    // The retransmission arrives before the playout time of the packet
+    push_trace(&mut jb, start, &[(1, 960, false, 28)]);
+    assert!(jb.missing().is_empty());
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(100))),
+        vec![0, 1, 2]
+    );
+    assert_eq!(jb.stats().packets_lost, 0);
+}
+
+#[test]
+fn test_jitter_buffer_duplicate() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(&mut jb, start, &[(0, 0, false, 0), (0, 0, false, 1)]);
+
+    assert_eq!(jb.len(), 1);
+    assert_eq!(jb.stats().packets_received, 2);
+    assert_eq!(jb.stats().packets_duplicated, 1);
+}
+
+#[test]
+fn test_jitter_buffer_restart() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(
+        &mut jb,
+        start,
+        &[(0, 0, false, 0), (20000, 500_000, false, 20)],
+    );
+
+    // A single packet far ahead is held back
+    assert_eq!(jb.len(), 1);
+    push_trace(&mut jb, start, &[(20001, 500_960, false, 40)]);
+
+    // The stream restarts from the packets following each other
+    assert_eq!(jb.len(), 2);
+    assert!(jb.missing().is_empty());
+    assert_eq!(jb.stats().packets_received, 3);
+    assert_eq!(jb.pop(start + Duration::from_millis(29)), None);
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(31))),
+        vec![20000]
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(51))),
+        vec![20001]
+    );
+}
+
+#[test]
+fn test_jitter_buffer_restart_backward() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+    push_trace(
+        &mut jb,
+        start,
+        &[(5000, 0, false, 0), (5001, 960, false, 20)],
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(31))),
+        vec![5000, 5001]
+    );
+
+    // A stray packet far behind does not restart the stream
+    push_trace(
+        &mut jb,
+        start,
+        &[(1000, 1920, false, 40), (5002, 1920, false, 41)],
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(51))),
+        vec![5002]
+    );
+
+    // The stream restarts once packets far behind follow each other
+    push_trace(
+        &mut jb,
+        start,
+        &[(1000, 2880, false, 60), (1001, 3840, false, 80)],
+    );
+    assert_eq!(
+        sequence_numbers(&pop_all(&mut jb, start + Duration::from_millis(91))),
+        vec![1000, 1001]
+    );
+
+    let stats = jb.stats();
+    assert_eq!(stats.packets_received, 6);
+    assert_eq!(stats.packets_late, 0);
+}
+
+#[test]
+fn test_jitter_buffer_adaptive_delay() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio);
+
+    // Every other packet is delayed by 15ms, |D| is 720 timestamp units
+    let mut playouts = vec![];
+    for ms in 0..2100u64 {
+        if ms % 40 == 0 || ms % 40 == 35 {
+            let i = (ms / 20) as u16;
+            if ms / 20 < 100 {
+                push_trace(&mut jb, start, &[(i, i as u32 * 960, false, ms)]);
+            }
+        }
+        while let Some(p) = jb.pop(start + Duration::from_millis(ms)) {
+            playouts.push(p);
+        }
+    }
+    // Playout is not interrupted by the delayed packets
+    assert_eq!(sequence_numbers(&playouts), (0..100).collect::<Vec<i32>>());
+
+    let stats = jb.stats();
+    assert!(
+        stats.jitter > 700 && stats.jitter <= 720,
+        "{}",
+        stats.jitter
+    );
+    assert!(stats.target_delay > Duration::from_millis(55));
+    assert!(stats.target_delay <= Duration::from_millis(60));
+
+    // The target delay decreases slowly once the network is stable again
+    for i in 100..300u16 {
+        push_trace(&mut jb, start, &[(i, i as u32 * 960, false, i as u64 * 20)]);
+    }
+    assert!(jb.target_delay() < Duration::from_millis(15));
+    assert!(jb.target_delay() >= DEFAULT_MIN_DELAY);
+
+    // The target delay is bounded
+    let mut jb = JitterBuffer::new(48000, JitterBufferMode::Audio)
+        .with_delay_bounds(Duration::from_millis(20), Duration::from_millis(30));
+    assert_eq!(jb.target_delay(), Duration::from_millis(20));
+    for i in 0..100u16 {
+        let arrival = i as u64 * 20 + if i % 2 == 1 { 15 } else { 0 };
+        push_trace(&mut jb, start, &[(i, i as u32 * 960, false, arrival)]);
+    }
+    assert_eq!(jb.target_delay(), Duration::from_millis(30));
+}
+
+#[test]
+fn test_jitter_buffer_video() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(90000, JitterBufferMode::Video);
+    // Three frames of three packets, the second one loses a packet
+    push_trace(
+        &mut jb,
+        start,
+        &[
+            (0, 0, false, 0),
+            (1, 0, false, 0),
+            (2, 0, true, 1),
+            (3, 3000, false, 33),
+            (5, 3000, true, 34),
+            (6, 6000, false, 66),
+            (7, 6000, false, 67),
+            (8, 6000, true, 67),
+        ],
+    );
+    assert_eq!(jb.missing(), vec![4]);
+
+    let frames: Vec<Vec<u16>> = pop_all(&mut jb, start + Duration::from_millis(200))
+        .into_iter()
+        .map(|p| match p {
+            Playout::Frame(packets) => packets.iter().map(|p| p.header.sequence_number).collect(),
+            _ => panic!("unexpected playout"),
+        })
+        .collect();
+    assert_eq!(frames, vec![vec![0, 1, 2], vec![6, 7, 8]]);
+
+    let stats = jb.stats();
+    assert_eq!(stats.frames_dropped, 1);
+    assert_eq!(stats.packets_lost, 1);
+}
+
+#[test]
+fn test_jitter_buffer_video_frame_complete() {
+    let start = Instant::now();
+    let mut jb = JitterBuffer::new(90000, JitterBufferMode::Video);
+    push_trace(
+        &mut jb,
+        start,
+        &[(0, 0, false, 0), (2, 0, true, 1), (3, 3000, false, 33)],
+    );
+
+    // The frame waits for its missing packet until its playout time
+    assert_eq!(jb.pop(start + Duration::from_millis(5)), None);
+    push_trace(&mut jb, start, &[(1, 0, false, 8)]);
+    match jb.pop(start + Duration::from_millis(11)) {
+        Some(Playout::Frame(packets)) => assert_eq!(packets.len(), 3),
+        p => panic!("unexpected playout {:?}", p),
+    }
+
+    // A frame ends before a timestamp change even without the marker bit
+    push_trace(&mut jb, start, &[(4, 6000, true, 66)]);
+    match jb.pop(start + Duration::from_millis(44)) {
+        Some(Playout::Frame(packets)) => {
+            assert_eq!(packets.len(), 1);
+            assert_eq!(packets[0].header.sequence_number, 3);
+        }
+        p => panic!("unexpected playout {:?}", p),
+    }
+    assert_eq!(jb.stats().frames_dropped, 0);
+}
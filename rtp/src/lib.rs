@@ -3,7 +3,14 @@
 
 pub mod codecs;
 pub mod extension;
+pub mod fec;
 pub mod header;
+pub mod jitterbuffer;
 pub mod packet;
+pub mod packet_ref;
 pub mod packetizer;
+pub mod padding;
+pub mod rewriter;
+pub mod rtx;
+pub mod samplebuilder;
 pub mod sequence;
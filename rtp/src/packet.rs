@@ -1,18 +1,28 @@
 use std::fmt;
 use std::io::{Read, Write};
 
+use crate::extension::registry::HeaderExtensionRegistry;
+use crate::extension::HeaderExtension;
 use crate::header::*;
 use util::Error;
 
 #[cfg(test)]
 mod packet_test;
 
+// Largest padding of a packet, its size is counted by a single octet
+pub const MAX_PADDING_SIZE: u8 = 255;
+
 // Packet represents an RTP Packet
 // NOTE: Raw is populated by Marshal/Unmarshal and should not be modified
-#[derive(Debug, Eq, PartialEq, Default)]
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
 pub struct Packet {
     pub header: Header,
     pub payload: Vec<u8>,
+    // Number of padding octets following the payload, the last one included,
+    // populated by unmarshal and set_padding. It is written by marshal when
+    // the header has the padding bit set, a padding bit without padding_size
+    // leaves the padding to the payload.
+    padding_size: u8,
 }
 
 impl fmt::Display for Packet {
@@ -32,19 +42,51 @@ impl fmt::Display for Packet {
 }
 
 impl Packet {
+    pub fn new(header: Header, payload: Vec<u8>) -> Self {
+        Packet {
+            header,
+            payload,
+            padding_size: 0,
+        }
+    }
+
     // MarshalSize returns the size of the packet once marshaled.
     pub fn size(&self) -> usize {
-        self.header.size() + self.payload.len()
+        self.header.size() + self.payload.len() + self.padding_len()
     }
 
     // Unmarshal parses the passed byte slice and stores the result in the Header this method is called upon
+    //
+    // The padding of the packet is removed from the payload, its size is
+    // returned by padding_size.
     pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let header = Header::unmarshal(reader)?;
 
         let mut payload = vec![];
         reader.read_to_end(&mut payload)?;
 
-        Ok(Packet { header, payload })
+        let mut padding_size = 0;
+        if header.padding {
+            // The last octet counts the padding octets, itself included
+            padding_size = match payload.last() {
+                Some(&padding_size) if padding_size != 0 => padding_size,
+                _ => return Err(Error::new("RTP padding size is zero or missing".to_owned())),
+            };
+            if padding_size as usize > payload.len() {
+                return Err(Error::new(format!(
+                    "RTP padding of {} bytes exceeds the payload of {} bytes",
+                    padding_size,
+                    payload.len()
+                )));
+            }
+            payload.truncate(payload.len() - padding_size as usize);
+        }
+
+        Ok(Packet {
+            header,
+            payload,
+            padding_size,
+        })
     }
 
     // Marshal serializes the header and writes to the buffer.
@@ -52,6 +94,117 @@ impl Packet {
         self.header.marshal(writer)?;
         writer.write_all(&self.payload)?;
 
+        let padding_len = self.padding_len();
+        if padding_len > 0 {
+            let mut padding = vec![0; padding_len];
+            padding[padding_len - 1] = padding_len as u8;
+            writer.write_all(&padding)?;
+        }
+
+        Ok(())
+    }
+
+    // padding_size returns the number of padding octets following the
+    // payload, the last one included
+    pub fn padding_size(&self) -> u8 {
+        self.padding_size
+    }
+
+    // set_padding sets padding_size octets of padding after the payload,
+    // 0 removes the padding
+    pub fn set_padding(&mut self, padding_size: u8) {
+        self.header.padding = padding_size > 0;
+        self.padding_size = padding_size;
+    }
+
+    // pad_to_multiple pads the packet so its marshaled size is a multiple
+    // of block_size, e.g. for the block alignment of SRTP ciphers or to hide
+    // the payload size
+    pub fn pad_to_multiple(&mut self, block_size: usize) -> Result<(), Error> {
+        if block_size == 0 || block_size > MAX_PADDING_SIZE as usize {
+            return Err(Error::new(format!(
+                "padding block size must be between 1 and {}, got {}",
+                MAX_PADDING_SIZE, block_size
+            )));
+        }
+
+        self.set_padding(0);
+        let remainder = self.size() % block_size;
+        if remainder != 0 {
+            self.set_padding((block_size - remainder) as u8);
+        }
         Ok(())
     }
+
+    // Padding octets written after the payload
+    fn padding_len(&self) -> usize {
+        if self.header.padding {
+            self.padding_size as usize
+        } else {
+            0
+        }
+    }
+
+    // get_header_extension returns the typed header extension T if it was
+    // negotiated in registry and is present in the packet
+    pub fn get_header_extension<T: HeaderExtension>(
+        &self,
+        registry: &HeaderExtensionRegistry,
+    ) -> Result<Option<T>, Error> {
+        let id = match registry.id(T::uri()) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        match self.header.get_extension(id) {
+            Some(mut payload) => Ok(Some(T::unmarshal(&mut payload)?)),
+            None => Ok(None),
+        }
+    }
+
+    // set_header_extension sets the typed header extension T with the id
+    // negotiated in registry
+    pub fn set_header_extension<T: HeaderExtension>(
+        &mut self,
+        registry: &HeaderExtensionRegistry,
+        extension: &T,
+    ) -> Result<(), Error> {
+        let id = match registry.id(T::uri()) {
+            Some(id) => id,
+            None => {
+                return Err(Error::new(format!(
+                    "header extension {} is not registered",
+                    T::uri()
+                )))
+            }
+        };
+
+        let mut payload = vec![];
+        extension.marshal(&mut payload)?;
+        self.header.set_extension(id, &payload)
+    }
+}
+
+// test_packet returns a version 2 packet with payload type 96, shared by the
+// tests of the crate
+#[cfg(test)]
+pub(crate) fn test_packet(
+    ssrc: u32,
+    sequence_number: u16,
+    timestamp: u32,
+    marker: bool,
+    payload: Vec<u8>,
+) -> Packet {
+    Packet::new(
+        Header {
+            version: 2,
+            marker,
+            payload_type: 96,
+            sequence_number,
+            timestamp,
+            ssrc,
+            ..Default::default()
+        },
+        payload,
+    )
 }
@@ -4,9 +4,6 @@ use std::io::{BufReader, BufWriter};
 
 use util::Error;
 
-//TODO: BenchmarkMarshal
-//TODO: BenchmarkUnmarshal
-
 #[test]
 fn test_basic() -> Result<(), Error> {
     let empty_bytes = vec![];
@@ -20,8 +17,8 @@ fn test_basic() -> Result<(), Error> {
         0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x00, 0x01, 0x00,
         0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x98, 0x36, 0xbe, 0x88, 0x9e,
     ];
-    let parsed_packet = Packet {
-        header: Header {
+    let parsed_packet = Packet::new(
+        Header {
             version: 2,
             padding: false,
             extension: true,
@@ -39,8 +36,8 @@ fn test_basic() -> Result<(), Error> {
             payload_offset: 20,
             ..Default::default()
         },
-        payload: vec![0x98, 0x36, 0xbe, 0x88, 0x9e],
-    };
+        vec![0x98, 0x36, 0xbe, 0x88, 0x9e],
+    );
 
     let mut reader = BufReader::new(raw_pkt.as_slice());
     let packet = Packet::unmarshal(&mut reader)?;
@@ -101,8 +98,8 @@ fn test_extension() -> Result<(), Error> {
         );
     }
 
-    let packet = Packet {
-        header: Header {
+    let packet = Packet::new(
+        Header {
             extension: true,
             extension_profile: 3,
             extensions: vec![Extension {
@@ -111,8 +108,8 @@ fn test_extension() -> Result<(), Error> {
             }],
             ..Default::default()
         },
-        payload: vec![],
-    };
+        vec![],
+    );
 
     let mut raw: Vec<u8> = vec![];
     {
@@ -138,8 +135,8 @@ fn test_rfc8285_one_byte_extension() -> Result<(), Error> {
     let mut reader = BufReader::new(raw_pkt.as_slice());
     Packet::unmarshal(&mut reader)?;
 
-    let p = Packet {
-        header: Header {
+    let p = Packet::new(
+        Header {
             marker: true,
             extension: true,
             extension_profile: 0xBEDE,
@@ -156,8 +153,8 @@ fn test_rfc8285_one_byte_extension() -> Result<(), Error> {
             csrc: vec![],
             ..Default::default()
         },
-        payload: raw_pkt[20..].to_vec(),
-    };
+        raw_pkt[20..].to_vec(),
+    );
 
     let mut dst: Vec<u8> = vec![];
     {
@@ -203,8 +200,8 @@ fn test_rfc8285one_byte_two_extension_of_two_bytes() -> Result<(), Error> {
     }
 
     // Test Marshal
-    let p = Packet {
-        header: Header {
+    let p = Packet::new(
+        Header {
             marker: true,
             extension: true,
             extension_profile: 0xBEDE,
@@ -227,8 +224,8 @@ fn test_rfc8285one_byte_two_extension_of_two_bytes() -> Result<(), Error> {
             csrc: vec![],
             ..Default::default()
         },
-        payload: raw_pkt[20..].to_vec(),
-    };
+        raw_pkt[20..].to_vec(),
+    );
 
     let mut dst: Vec<u8> = vec![];
     {
@@ -243,3 +240,218 @@ fn test_rfc8285one_byte_two_extension_of_two_bytes() -> Result<(), Error> {
 //TODO: TestRFC8285OneByteMultipleExtensionsWithPadding
 //TODO: ...
 //TODO: TestRoundtrip
+
+#[test]
+fn test_set_extension_profile() -> Result<(), Error> {
+    let mut header = Header::default();
+
+    header.set_extension(1, &[0xAA])?;
+    assert_eq!(header.extension_profile, 0xBEDE);
+
+    // The id does not fit in the one-byte header
+    header.set_extension(15, &[0xBB])?;
+    assert_eq!(header.extension_profile, 0x1000);
+    assert_eq!(header.get_extension(1), Some(&[0xAA][..]));
+
+    let mut header = Header::default();
+    header.set_extension(1, &[0xAA])?;
+    // The payload does not fit in the one-byte header
+    header.set_extension(2, &[0xBB; 17])?;
+    assert_eq!(header.extension_profile, 0x1000);
+
+    let mut header = Header::default();
+    // Empty payloads need the two-byte header
+    header.set_extension(1, &[])?;
+    assert_eq!(header.extension_profile, 0x1000);
+
+    assert!(header.set_extension(0, &[0xAA]).is_err());
+    assert!(header.set_extension(1, &[0xAA; 256]).is_err());
+
+    // The two-byte header round trips
+    let mut header = Header {
+        version: 2,
+        ..Default::default()
+    };
+    header.set_extension(1, &[0xAA])?;
+    header.set_extension(200, &[0xBB; 20])?;
+    let mut raw: Vec<u8> = vec![];
+    header.marshal(&mut raw)?;
+    assert_eq!(raw.len(), header.size());
+
+    let mut reader = BufReader::new(raw.as_slice());
+    let unmarshaled = Header::unmarshal(&mut reader)?;
+    assert_eq!(unmarshaled.extension_profile, 0x1000);
+    assert_eq!(unmarshaled.get_extension(1), Some(&[0xAA][..]));
+    assert_eq!(unmarshaled.get_extension(200), Some(&[0xBB; 20][..]));
+
+    Ok(())
+}
+
+#[test]
+fn test_typed_header_extension() -> Result<(), Error> {
+    use crate::extension::audio_level_extension::AudioLevelExtension;
+    use crate::extension::transport_cc_extension::TransportCCExtension;
+    use sdp::extmap::{AUDIO_LEVEL_URI, TRANSPORT_CC_URI};
+
+    let mut registry = HeaderExtensionRegistry::new();
+    registry.register(3, AUDIO_LEVEL_URI)?;
+    registry.register(5, TRANSPORT_CC_URI)?;
+
+    let mut packet = Packet::new(
+        Header {
+            version: 2,
+            ..Default::default()
+        },
+        vec![0x01],
+    );
+    assert_eq!(
+        packet.get_header_extension::<AudioLevelExtension>(&registry)?,
+        None
+    );
+
+    let audio_level = AudioLevelExtension {
+        level: 42,
+        voice: true,
+    };
+    packet.set_header_extension(&registry, &audio_level)?;
+    packet.set_header_extension(
+        &registry,
+        &TransportCCExtension {
+            transport_sequence: 1234,
+        },
+    )?;
+    assert_eq!(packet.header.get_extension(3), Some(&[0xAA][..]));
+
+    let mut raw: Vec<u8> = vec![];
+    packet.marshal(&mut raw)?;
+    let mut reader = BufReader::new(raw.as_slice());
+    let packet = Packet::unmarshal(&mut reader)?;
+
+    assert_eq!(
+        packet.get_header_extension::<AudioLevelExtension>(&registry)?,
+        Some(AudioLevelExtension {
+            level: 42,
+            voice: true,
+        })
+    );
+    assert_eq!(
+        packet.get_header_extension::<TransportCCExtension>(&registry)?,
+        Some(TransportCCExtension {
+            transport_sequence: 1234
+        })
+    );
+
+    // Extensions must be negotiated to be set
+    let mut packet = Packet::default();
+    assert!(packet
+        .set_header_extension(&HeaderExtensionRegistry::new(), &audio_level)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_padding() -> Result<(), Error> {
+    // 3 octets of padding after a 2 octet payload
+    let raw_pkt = vec![
+        0xa0, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x98, 0x36, 0x00,
+        0x00, 0x03,
+    ];
+    let mut reader = BufReader::new(raw_pkt.as_slice());
+    let packet = Packet::unmarshal(&mut reader)?;
+    assert!(packet.header.padding);
+    assert_eq!(packet.padding_size(), 3);
+    assert_eq!(packet.payload, vec![0x98, 0x36]);
+    assert_eq!(packet.size(), raw_pkt.len());
+
+    let mut raw: Vec<u8> = vec![];
+    packet.marshal(&mut raw)?;
+    assert_eq!(raw, raw_pkt);
+
+    // Padding-only packet
+    let raw_pkt = vec![
+        0xa0, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x00, 0x02,
+    ];
+    let mut reader = BufReader::new(raw_pkt.as_slice());
+    let packet = Packet::unmarshal(&mut reader)?;
+    assert_eq!(packet.padding_size(), 2);
+    assert!(packet.payload.is_empty());
+
+    // Invalid padding sizes
+    for raw_pkt in &[
+        vec![
+            0xa0, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82,
+        ],
+        vec![
+            0xa0, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x98, 0x00,
+        ],
+        vec![
+            0xa0, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x98, 0x03,
+        ],
+    ] {
+        let mut reader = BufReader::new(raw_pkt.as_slice());
+        assert!(
+            Packet::unmarshal(&mut reader).is_err(),
+            "Unmarshal did not error on invalid padding {:?}",
+            raw_pkt
+        );
+    }
+
+    // A padding bit without padding_size leaves the padding to the payload
+    let packet = Packet::new(
+        Header {
+            version: 2,
+            padding: true,
+            ..Default::default()
+        },
+        vec![0x98, 0x00, 0x02],
+    );
+    let mut raw: Vec<u8> = vec![];
+    packet.marshal(&mut raw)?;
+    assert_eq!(
+        raw,
+        vec![
+            0xa0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x98, 0x00,
+            0x02
+        ],
+        "Marshal of padding bit without padding_size mismatch"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_pad_to_multiple() -> Result<(), Error> {
+    let mut packet = Packet::new(
+        Header {
+            version: 2,
+            ..Default::default()
+        },
+        vec![0x01; 5],
+    );
+
+    packet.pad_to_multiple(16)?;
+    assert!(packet.header.padding);
+    assert_eq!(packet.padding_size(), 15);
+    let mut raw: Vec<u8> = vec![];
+    packet.marshal(&mut raw)?;
+    assert_eq!(raw.len(), 32);
+    assert_eq!(&raw[17..31], &[0; 14][..], "padding octets must be zero");
+    assert_eq!(raw[31], 15);
+
+    let mut reader = BufReader::new(raw.as_slice());
+    let parsed = Packet::unmarshal(&mut reader)?;
+    assert_eq!(parsed.padding_size(), 15);
+    assert_eq!(parsed.payload, packet.payload);
+
+    // Already aligned, the padding is removed
+    packet.payload = vec![0x01; 4];
+    packet.pad_to_multiple(16)?;
+    assert!(!packet.header.padding);
+    assert_eq!(packet.size(), 16);
+
+    assert!(packet.pad_to_multiple(0).is_err());
+    assert!(packet.pad_to_multiple(256).is_err());
+
+    Ok(())
+}
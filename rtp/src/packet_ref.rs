@@ -0,0 +1,374 @@
+use crate::header::*;
+use crate::packet::Packet;
+
+use std::fmt;
+
+use util::Error;
+
+#[cfg(test)]
+mod packet_ref_test;
+
+// Offsets of the parts of a RTP packet in its buffer
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Layout {
+    // Start of the extension elements and their end, padding included
+    extension: Option<(usize, usize)>,
+    payload_offset: usize,
+    // Start of the padding
+    payload_end: usize,
+}
+
+impl Layout {
+    fn parse(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < CSRC_OFFSET {
+            return Err(Error::new(format!(
+                "RTP header size insufficient; {} < {}",
+                buf.len(),
+                CSRC_OFFSET
+            )));
+        }
+
+        let cc = (buf[0] & CC_MASK) as usize;
+        let mut offset = CSRC_OFFSET + cc * CSRC_LENGTH;
+
+        let mut extension = None;
+        if buf[0] >> EXTENSION_SHIFT & EXTENSION_MASK != 0 {
+            if buf.len() < offset + 4 {
+                return Err(Error::new(format!(
+                    "RTP header size insufficient for extension; {} < {}",
+                    buf.len(),
+                    offset + 4
+                )));
+            }
+            let length = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize * 4;
+            offset += 4;
+            if buf.len() < offset + length {
+                return Err(Error::new(format!(
+                    "RTP header size insufficient for extension length; {} < {}",
+                    buf.len(),
+                    offset + length
+                )));
+            }
+            extension = Some((offset, offset + length));
+            offset += length;
+        } else if buf.len() < offset {
+            return Err(Error::new(format!(
+                "RTP header size insufficient; {} < {}",
+                buf.len(),
+                offset
+            )));
+        }
+
+        let mut payload_end = buf.len();
+        if buf[0] >> PADDING_SHIFT & PADDING_MASK != 0 {
+            // The last octet counts the padding octets, itself included
+            let padding = buf[buf.len() - 1] as usize;
+            if padding == 0 || offset + padding > buf.len() {
+                return Err(Error::new(format!(
+                    "RTP padding of {} bytes is invalid for a payload of {} bytes",
+                    padding,
+                    buf.len() - offset
+                )));
+            }
+            payload_end -= padding;
+        }
+
+        Ok(Layout {
+            extension,
+            payload_offset: offset,
+            payload_end,
+        })
+    }
+}
+
+// PacketRef is a read-only view over a marshaled RTP packet
+//
+// Only the offsets of the parts of the packet are computed when it is
+// created, header fields and extensions are read from the buffer when
+// accessed, without copying.
+#[derive(Copy, Clone, PartialEq)]
+pub struct PacketRef<'a> {
+    buf: &'a [u8],
+    layout: Layout,
+}
+
+impl<'a> PacketRef<'a> {
+    pub fn new(buf: &'a [u8]) -> Result<Self, Error> {
+        Ok(PacketRef {
+            buf,
+            layout: Layout::parse(buf)?,
+        })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.buf[0] >> VERSION_SHIFT & VERSION_MASK
+    }
+
+    pub fn padding(&self) -> bool {
+        self.buf[0] >> PADDING_SHIFT & PADDING_MASK != 0
+    }
+
+    pub fn extension(&self) -> bool {
+        self.layout.extension.is_some()
+    }
+
+    pub fn marker(&self) -> bool {
+        self.buf[1] >> MARKER_SHIFT & MARKER_MASK != 0
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.buf[1] & PT_MASK
+    }
+
+    pub fn sequence_number(&self) -> u16 {
+        u16::from_be_bytes([self.buf[SEQ_NUM_OFFSET], self.buf[SEQ_NUM_OFFSET + 1]])
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        read_u32(self.buf, TIMESTAMP_OFFSET)
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        read_u32(self.buf, SSRC_OFFSET)
+    }
+
+    pub fn csrc(&self) -> impl Iterator<Item = u32> + 'a {
+        let buf = self.buf;
+        let cc = (buf[0] & CC_MASK) as usize;
+        (0..cc).map(move |i| read_u32(buf, CSRC_OFFSET + i * CSRC_LENGTH))
+    }
+
+    // extension_profile returns the profile of the header extension, if any
+    pub fn extension_profile(&self) -> Option<u16> {
+        self.layout
+            .extension
+            .map(|(start, _)| u16::from_be_bytes([self.buf[start - 4], self.buf[start - 3]]))
+    }
+
+    // extensions returns the header extensions as (id, payload) pairs,
+    // a header extension of another profile than RFC 8285 ones is returned
+    // whole with the id 0
+    pub fn extensions(&self) -> Extensions<'a> {
+        Extensions::new(self.buf, self.layout.extension, self.extension_profile())
+    }
+
+    pub fn get_extension(&self, id: u8) -> Option<&'a [u8]> {
+        self.extensions()
+            .find(|(extension_id, _)| *extension_id == id)
+            .map(|(_, payload)| payload)
+    }
+
+    // payload returns the payload of the packet, padding excluded
+    pub fn payload(&self) -> &'a [u8] {
+        &self.buf[self.layout.payload_offset..self.layout.payload_end]
+    }
+
+    pub fn payload_offset(&self) -> usize {
+        self.layout.payload_offset
+    }
+
+    // padding_size returns the number of padding octets, the last one
+    // included
+    pub fn padding_size(&self) -> usize {
+        self.buf.len() - self.layout.payload_end
+    }
+
+    // as_bytes returns the whole packet
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    // to_packet copies the packet into an owned Packet
+    pub fn to_packet(&self) -> Result<Packet, Error> {
+        let mut reader = self.buf;
+        Packet::unmarshal(&mut reader)
+    }
+}
+
+impl<'a> fmt::Debug for PacketRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketRef")
+            .field("marker", &self.marker())
+            .field("payload_type", &self.payload_type())
+            .field("sequence_number", &self.sequence_number())
+            .field("timestamp", &self.timestamp())
+            .field("ssrc", &self.ssrc())
+            .field("payload_len", &self.payload().len())
+            .finish()
+    }
+}
+
+// PacketMut is a view over a marshaled RTP packet allowing to edit it in
+// place, e.g. to rewrite the SSRC, sequence number and timestamp of a
+// forwarded packet
+pub struct PacketMut<'a> {
+    buf: &'a mut [u8],
+    layout: Layout,
+}
+
+impl<'a> PacketMut<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Result<Self, Error> {
+        let layout = Layout::parse(buf)?;
+        Ok(PacketMut { buf, layout })
+    }
+
+    // as_packet_ref returns a read-only view of the packet
+    pub fn as_packet_ref(&self) -> PacketRef<'_> {
+        PacketRef {
+            buf: self.buf,
+            layout: self.layout,
+        }
+    }
+
+    pub fn set_marker(&mut self, marker: bool) {
+        if marker {
+            self.buf[1] |= 1 << MARKER_SHIFT;
+        } else {
+            self.buf[1] &= !(1 << MARKER_SHIFT);
+        }
+    }
+
+    pub fn set_payload_type(&mut self, payload_type: u8) {
+        self.buf[1] = (self.buf[1] & !PT_MASK) | (payload_type & PT_MASK);
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u16) {
+        self.buf[SEQ_NUM_OFFSET..SEQ_NUM_OFFSET + SEQ_NUM_LENGTH]
+            .copy_from_slice(&sequence_number.to_be_bytes());
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        self.buf[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + TIMESTAMP_LENGTH]
+            .copy_from_slice(&timestamp.to_be_bytes());
+    }
+
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        self.buf[SSRC_OFFSET..SSRC_OFFSET + SSRC_LENGTH].copy_from_slice(&ssrc.to_be_bytes());
+    }
+
+    // extension_mut returns the payload of the header extension id to edit
+    // its value in place
+    pub fn extension_mut(&mut self, id: u8) -> Option<&mut [u8]> {
+        let profile = self.as_packet_ref().extension_profile();
+        let (start, end) = Extensions::new(self.buf, self.layout.extension, profile)
+            .ranges()
+            .find(|(extension_id, _, _)| *extension_id == id)
+            .map(|(_, start, end)| (start, end))?;
+        Some(&mut self.buf[start..end])
+    }
+
+    // set_extension replaces the value of the header extension id, which
+    // must be present with the same length since the packet is not resized
+    pub fn set_extension(&mut self, id: u8, payload: &[u8]) -> Result<(), Error> {
+        let extension = self
+            .extension_mut(id)
+            .ok_or_else(|| Error::new(format!("header extension {} not found", id)))?;
+        if extension.len() != payload.len() {
+            return Err(Error::new(format!(
+                "header extension {} is {} bytes, cannot set {} bytes in place",
+                id,
+                extension.len(),
+                payload.len()
+            )));
+        }
+        extension.copy_from_slice(payload);
+        Ok(())
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[self.layout.payload_offset..self.layout.payload_end]
+    }
+}
+
+// Extensions iterates over the header extensions of a PacketRef
+pub struct Extensions<'a> {
+    buf: &'a [u8],
+    profile: Option<u16>,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Extensions<'a> {
+    fn new(buf: &'a [u8], range: Option<(usize, usize)>, profile: Option<u16>) -> Self {
+        let (offset, end) = range.unwrap_or((0, 0));
+        Extensions {
+            buf,
+            profile,
+            offset,
+            end,
+        }
+    }
+
+    // ranges returns the ids and the ranges of the extension payloads in
+    // the buffer
+    fn ranges(mut self) -> impl Iterator<Item = (u8, usize, usize)> + 'a {
+        std::iter::from_fn(move || self.next_range())
+    }
+
+    fn next_range(&mut self) -> Option<(u8, usize, usize)> {
+        let buf = self.buf;
+        match self.profile? {
+            EXTENSION_PROFILE_ONE_BYTE | EXTENSION_PROFILE_TWO_BYTE => loop {
+                if self.offset >= self.end {
+                    return None;
+                }
+                if buf[self.offset] == 0x00 {
+                    // padding
+                    self.offset += 1;
+                    continue;
+                }
+
+                let (id, len, header) = if self.profile == Some(EXTENSION_PROFILE_ONE_BYTE) {
+                    let id = buf[self.offset] >> 4;
+                    if id == EXTENSION_ID_RESERVED {
+                        self.offset = self.end;
+                        return None;
+                    }
+                    (id, (buf[self.offset] & 0x0F) as usize + 1, 1)
+                } else {
+                    if self.offset + 1 >= self.end {
+                        self.offset = self.end;
+                        return None;
+                    }
+                    (buf[self.offset], buf[self.offset + 1] as usize, 2)
+                };
+
+                let start = self.offset + header;
+                if start + len > self.end {
+                    // Malformed extension, the remaining ones are ignored
+                    self.offset = self.end;
+                    return None;
+                }
+                self.offset = start + len;
+                return Some((id, start, start + len));
+            },
+            _ => {
+                if self.offset >= self.end {
+                    return None;
+                }
+                let range = (0, self.offset, self.end);
+                self.offset = self.end;
+                Some(range)
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Extensions<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buf = self.buf;
+        self.next_range()
+            .map(|(id, start, end)| (id, &buf[start..end]))
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
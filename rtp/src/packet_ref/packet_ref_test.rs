@@ -0,0 +1,178 @@
+use super::*;
+use crate::packet::test_packet;
+
+fn marshal(packet: &Packet) -> Vec<u8> {
+    let mut raw = vec![];
+    packet.marshal(&mut raw).unwrap();
+    raw
+}
+
+fn packet() -> Packet {
+    let mut packet = test_packet(
+        476325762,
+        27023,
+        3653407706,
+        true,
+        vec![0x98, 0x36, 0xbe, 0x88, 0x9e],
+    );
+    packet.header.csrc = vec![0x11223344, 0x55667788];
+    packet.header.set_extension(1, &[0xAA]).unwrap();
+    packet.header.set_extension(3, &[0xBB, 0xCC, 0xDD]).unwrap();
+    packet
+}
+
+#[test]
+fn test_packet_ref_fields() -> Result<(), Error> {
+    let packet = packet();
+    let raw = marshal(&packet);
+    let p = PacketRef::new(&raw)?;
+
+    assert_eq!(p.version(), 2);
+    assert!(!p.padding());
+    assert!(p.extension());
+    assert!(p.marker());
+    assert_eq!(p.payload_type(), 96);
+    assert_eq!(p.sequence_number(), 27023);
+    assert_eq!(p.timestamp(), 3653407706);
+    assert_eq!(p.ssrc(), 476325762);
+    assert_eq!(p.csrc().collect::<Vec<u32>>(), packet.header.csrc);
+    assert_eq!(p.extension_profile(), Some(0xBEDE));
+    assert_eq!(
+        p.extensions().collect::<Vec<(u8, &[u8])>>(),
+        vec![(1, &[0xAA][..]), (3, &[0xBB, 0xCC, 0xDD][..])]
+    );
+    assert_eq!(p.get_extension(3), Some(&[0xBB, 0xCC, 0xDD][..]));
+    assert_eq!(p.get_extension(2), None);
+    assert_eq!(p.payload(), packet.payload.as_slice());
+    assert_eq!(p.payload_offset(), packet.header.size());
+    assert_eq!(p.as_bytes(), raw.as_slice());
+
+    // Borrowed slices outlive the view
+    let payload = PacketRef::new(&raw)?.payload();
+    assert_eq!(payload.len(), 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_packet_ref_matches_owned() -> Result<(), Error> {
+    let mut two_byte = packet();
+    two_byte.header.set_extension(20, &[0x01; 20])?;
+    let mut no_extension = packet();
+    no_extension.header.extension = false;
+    no_extension.header.extensions.clear();
+    let raw_pkt = vec![
+        0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x00, 0x01, 0x00,
+        0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x98, 0x36, 0xbe, 0x88, 0x9e,
+    ];
+
+    for raw in &[
+        marshal(&packet()),
+        marshal(&two_byte),
+        marshal(&no_extension),
+        raw_pkt,
+    ] {
+        let p = PacketRef::new(raw)?;
+        let owned = p.to_packet()?;
+
+        assert_eq!(p.marker(), owned.header.marker);
+        assert_eq!(p.payload_type(), owned.header.payload_type);
+        assert_eq!(p.sequence_number(), owned.header.sequence_number);
+        assert_eq!(p.timestamp(), owned.header.timestamp);
+        assert_eq!(p.ssrc(), owned.header.ssrc);
+        assert_eq!(p.csrc().collect::<Vec<u32>>(), owned.header.csrc);
+        assert_eq!(p.payload(), owned.payload.as_slice());
+        assert_eq!(p.payload_offset(), owned.header.payload_offset);
+        let extensions: Vec<(u8, Vec<u8>)> = owned
+            .header
+            .extensions
+            .iter()
+            .map(|e| (e.id, e.payload.clone()))
+            .collect();
+        assert_eq!(
+            p.extensions()
+                .map(|(id, payload)| (id, payload.to_vec()))
+                .collect::<Vec<(u8, Vec<u8>)>>(),
+            extensions
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_packet_ref_padding() -> Result<(), Error> {
+    let mut packet = Packet::new(
+        Header {
+            version: 2,
+            ..Default::default()
+        },
+        vec![0x01, 0x02],
+    );
+    packet.set_padding(3);
+    let mut raw = marshal(&packet);
+    let p = PacketRef::new(&raw)?;
+    assert!(p.padding());
+    assert_eq!(p.padding_size(), 3);
+    assert_eq!(p.payload(), &[0x01, 0x02]);
+
+    // The padding is longer than the payload
+    let last = raw.len() - 1;
+    raw[last] = 6;
+    assert!(PacketRef::new(&raw).is_err());
+    raw[last] = 0;
+    assert!(PacketRef::new(&raw).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_packet_ref_invalid() {
+    let raw = marshal(&packet());
+
+    // Truncated fixed header, CSRC list and extension
+    for len in &[0, 11, 12, 19, 21, 24] {
+        assert!(PacketRef::new(&raw[..*len]).is_err(), "length {}", len);
+    }
+    assert!(PacketRef::new(&raw[..raw.len() - 5]).is_ok());
+
+    // A malformed extension element ends the iteration
+    let mut raw = raw;
+    raw[26] = 0x3F;
+    let p = PacketRef::new(&raw).unwrap();
+    assert_eq!(p.extensions().count(), 1);
+}
+
+#[test]
+fn test_packet_mut() -> Result<(), Error> {
+    let packet = packet();
+    let mut raw = marshal(&packet);
+    let mut p = PacketMut::new(&mut raw)?;
+
+    p.set_ssrc(0xDEADBEEF);
+    p.set_sequence_number(65535);
+    p.set_timestamp(42);
+    p.set_payload_type(111);
+    p.set_marker(false);
+    p.set_extension(3, &[0x01, 0x02, 0x03])?;
+    p.extension_mut(1).unwrap()[0] = 0x55;
+    p.payload_mut()[0] = 0x00;
+
+    assert!(p.set_extension(3, &[0x01]).is_err());
+    assert!(p.set_extension(2, &[0x01]).is_err());
+    assert!(p.extension_mut(2).is_none());
+    assert_eq!(p.as_packet_ref().ssrc(), 0xDEADBEEF);
+
+    let mut expected = packet;
+    expected.header.ssrc = 0xDEADBEEF;
+    expected.header.sequence_number = 65535;
+    expected.header.timestamp = 42;
+    expected.header.payload_type = 111;
+    expected.header.marker = false;
+    expected.header.set_extension(3, &[0x01, 0x02, 0x03])?;
+    expected.header.set_extension(1, &[0x55])?;
+    expected.payload[0] = 0x00;
+    assert_eq!(raw, marshal(&expected));
+
+    Ok(())
+}
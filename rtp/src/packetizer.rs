@@ -1,10 +1,12 @@
 use crate::extension::abs_send_time_extension::*;
+use crate::extension::HeaderExtension;
 use crate::header::*;
 use crate::packet::*;
 use crate::sequence::*;
 
 use util::Error;
 
+use std::convert::TryFrom;
 use std::io::{BufWriter, Read};
 use std::time::{Duration, SystemTime};
 
@@ -13,7 +15,7 @@ mod packetizer_test;
 
 // Payloader payloads a byte array for use as rtp.Packet payloads
 pub trait Payloader {
-    fn payload<R: Read>(&self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error>;
+    fn payload<R: Read>(&mut self, mtu: isize, reader: &mut R) -> Result<Vec<Vec<u8>>, Error>;
 }
 
 // Packetizer packetizes a payload
@@ -26,11 +28,27 @@ pub trait Packetizer {
         samples: u32,
     ) -> Result<Vec<Packet>, Error>;
     fn enable_abs_send_time(&mut self, value: u8);
+    // SkipSamples advances the timestamp of the next packets, for media
+    // that is not sent such as suppressed silence
+    fn skip_samples(&mut self, skipped_samples: u32);
 }
 
 // Depacketizer depacketizes a RTP payload, removing any RTP specific data from the payload
 pub trait Depacketizer {
     fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error>;
+
+    // Payload returns the media data of the last depacketized RTP payload
+    fn payload(&self) -> &[u8];
+
+    // IsPartitionHead checks if this is the head of a packetized frame
+    fn is_partition_head(&self, _payload: &[u8]) -> bool {
+        true
+    }
+
+    // IsPartitionTail checks if this is the tail of a packetized frame
+    fn is_partition_tail(&self, marker: bool, _payload: &[u8]) -> bool {
+        marker
+    }
 }
 
 pub type FnTimeGen = fn() -> Duration;
@@ -41,6 +59,12 @@ pub struct PacketizerImpl {
     ssrc: u32,
     timestamp: u32,
     clock_rate: u32,
+    // Fraction of a timestamp unit left over by media durations, in
+    // nanoseconds times the clock rate
+    sample_remainder: u64,
+    // Wallclock time since the unix epoch and RTP timestamp of the same
+    // instant
+    reference: Option<(Duration, u32)>,
     abs_send_time: u8, //http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
     time_gen: Option<FnTimeGen>,
 }
@@ -53,54 +77,118 @@ impl PacketizerImpl {
             ssrc,
             timestamp: rand::random::<u32>(), //TODO: globalMathRandomGenerator?
             clock_rate,
+            sample_remainder: 0,
+            reference: None,
             abs_send_time: 0,
             time_gen: None,
         }
     }
-}
 
-impl Packetizer for PacketizerImpl {
-    fn enable_abs_send_time(&mut self, value: u8) {
-        self.abs_send_time = value
+    // packetize_duration packetizes a payload presented for duration, the
+    // fractions of timestamp units are carried over to the next payloads
+    pub fn packetize_duration<R: Read, P: Payloader, S: Sequencer>(
+        &mut self,
+        reader: &mut R,
+        payloader: &mut P,
+        sequencer: &mut S,
+        duration: Duration,
+    ) -> Result<Vec<Packet>, Error> {
+        let (samples, sample_remainder) = self.duration_to_samples(duration)?;
+        let packets = self.packetize(reader, payloader, sequencer, samples)?;
+        self.sample_remainder = sample_remainder;
+        Ok(packets)
     }
 
-    fn packetize<R: Read, P: Payloader, S: Sequencer>(
+    // packetize_at packetizes a payload captured at capture_time since the
+    // unix epoch, the timestamp is derived from the first capture time
+    pub fn packetize_at<R: Read, P: Payloader, S: Sequencer>(
+        &mut self,
+        reader: &mut R,
+        payloader: &mut P,
+        sequencer: &mut S,
+        capture_time: Duration,
+    ) -> Result<Vec<Packet>, Error> {
+        match self.reference {
+            Some(reference) => {
+                self.timestamp = media_time_to_timestamp(reference, capture_time, self.clock_rate)
+            }
+            None => self.reference = Some((capture_time, self.timestamp)),
+        }
+        self.sample_remainder = 0;
+
+        self.packetize_payloads(reader, payloader, sequencer)
+    }
+
+    // skip_duration advances the timestamp of the next packets by duration
+    pub fn skip_duration(&mut self, duration: Duration) -> Result<(), Error> {
+        let (samples, sample_remainder) = self.duration_to_samples(duration)?;
+        self.skip_samples(samples);
+        self.sample_remainder = sample_remainder;
+        Ok(())
+    }
+
+    // ntp_rtp_timestamps returns the NTP timestamp of now, since the unix
+    // epoch, and the RTP timestamp of the same instant, as reported in
+    // Sender Reports
+    pub fn ntp_rtp_timestamps(&self, now: Duration) -> Option<(u64, u32)> {
+        let reference = self.reference?;
+        Some((
+            unix2ntp(now),
+            media_time_to_timestamp(reference, now, self.clock_rate),
+        ))
+    }
+
+    // duration_to_samples returns the timestamp units of duration and the
+    // remainder left for the next durations, the caller commits the
+    // remainder once the samples are used
+    fn duration_to_samples(&self, duration: Duration) -> Result<(u32, u64), Error> {
+        let total = duration.as_nanos() * self.clock_rate as u128 + self.sample_remainder as u128;
+        let samples = u32::try_from(total / 1_000_000_000).map_err(|_| {
+            Error::new(format!(
+                "duration of {:?} does not fit in the RTP timestamp at {} Hz",
+                duration, self.clock_rate
+            ))
+        })?;
+        Ok((samples, (total % 1_000_000_000) as u64))
+    }
+
+    fn now(&self) -> Result<Duration, Error> {
+        Ok(if let Some(fn_time_gen) = &self.time_gen {
+            fn_time_gen()
+        } else {
+            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?
+        })
+    }
+
+    fn packetize_payloads<R: Read, P: Payloader, S: Sequencer>(
         &mut self,
         reader: &mut R,
         payloader: &mut P,
         sequencer: &mut S,
-        samples: u32,
     ) -> Result<Vec<Packet>, Error> {
         let payloads = payloader.payload(self.mtu - 12, reader)?;
         let mut packets = vec![];
         let (mut i, l) = (0, payloads.len());
         for payload in payloads {
-            packets.push(Packet {
-                header: Header {
+            packets.push(Packet::new(
+                Header {
                     version: 2,
                     padding: false,
                     extension: false,
                     marker: i == l - 1,
                     payload_type: self.payload_type,
                     sequence_number: sequencer.next_sequence_number(),
-                    timestamp: self.timestamp, //TODO: Figure out how to do timestamps
+                    timestamp: self.timestamp,
                     ssrc: self.ssrc,
                     ..Default::default()
                 },
                 payload,
-            });
+            ));
             i += 1;
         }
 
-        self.timestamp += samples;
-
         if l != 0 && self.abs_send_time != 0 {
-            let d = if let Some(fn_time_gen) = &self.time_gen {
-                fn_time_gen()
-            } else {
-                SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?
-            };
-            let send_time = AbsSendTimeExtension::new(d);
+            let send_time = AbsSendTimeExtension::new(self.now()?);
             //apply http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
             let mut raw: Vec<u8> = vec![];
             {
@@ -116,3 +204,40 @@ impl Packetizer for PacketizerImpl {
         Ok(packets)
     }
 }
+
+impl Packetizer for PacketizerImpl {
+    fn enable_abs_send_time(&mut self, value: u8) {
+        self.abs_send_time = value
+    }
+
+    fn skip_samples(&mut self, skipped_samples: u32) {
+        self.timestamp = self.timestamp.wrapping_add(skipped_samples);
+    }
+
+    fn packetize<R: Read, P: Payloader, S: Sequencer>(
+        &mut self,
+        reader: &mut R,
+        payloader: &mut P,
+        sequencer: &mut S,
+        samples: u32,
+    ) -> Result<Vec<Packet>, Error> {
+        if self.reference.is_none() {
+            self.reference = Some((self.now()?, self.timestamp));
+        }
+
+        let packets = self.packetize_payloads(reader, payloader, sequencer)?;
+        self.timestamp = self.timestamp.wrapping_add(samples);
+
+        Ok(packets)
+    }
+}
+
+// media_time_to_timestamp converts time to a RTP timestamp from the
+// reference time and timestamp of the same instant
+fn media_time_to_timestamp(reference: (Duration, u32), time: Duration, clock_rate: u32) -> u32 {
+    let (reference_time, reference_timestamp) = reference;
+    let elapsed = time.as_nanos() as i128 - reference_time.as_nanos() as i128;
+    // Round to the nearest timestamp unit
+    let samples = (elapsed * clock_rate as i128 + 500_000_000).div_euclid(1_000_000_000);
+    reference_timestamp.wrapping_add(samples as u32)
+}
@@ -2,7 +2,7 @@ use super::*;
 use crate::codecs::*;
 
 use chrono::prelude::*;
-use std::io::BufReader;
+use std::io::{BufReader, Read};
 use std::time::Duration;
 
 use util::Error;
@@ -56,8 +56,8 @@ fn test_packetizer_abs_send_time() -> Result<(), Error> {
     let mut seq = new_fixed_sequencer(1234);
     let packets = pktizer.packetize(&mut reader, &mut g722, &mut seq, 2000)?;
 
-    let expected = Packet {
-        header: Header {
+    let expected = Packet::new(
+        Header {
             version: 2,
             padding: false,
             extension: true,
@@ -74,8 +74,8 @@ fn test_packetizer_abs_send_time() -> Result<(), Error> {
                 payload: vec![0x40, 0, 0],
             }],
         },
-        payload: vec![0x11, 0x12, 0x13, 0x14],
-    };
+        vec![0x11, 0x12, 0x13, 0x14],
+    );
 
     if packets.len() != 1 {
         assert!(false, "Generated {} packets instead of 1", packets.len())
@@ -85,3 +85,164 @@ fn test_packetizer_abs_send_time() -> Result<(), Error> {
 
     Ok(())
 }
+
+type PacketizeFn<'a> =
+    dyn FnMut(&mut PacketizerImpl, &mut BufReader<&[u8]>) -> Result<Vec<Packet>, Error> + 'a;
+
+fn packetize_timestamps(
+    pktizer: &mut PacketizerImpl,
+    f: &mut PacketizeFn<'_>,
+    count: usize,
+) -> Result<Vec<u32>, Error> {
+    let payload = vec![0x11, 0x12, 0x13, 0x14];
+    let mut timestamps = vec![];
+    for _ in 0..count {
+        let mut reader = BufReader::new(payload.as_slice());
+        let packets = f(pktizer, &mut reader)?;
+        timestamps.push(packets[0].header.timestamp);
+    }
+    Ok(timestamps)
+}
+
+#[test]
+fn test_packetizer_duration() -> Result<(), Error> {
+    let mut pktizer = PacketizerImpl::new(100, 98, 0x1234ABCD, 90000);
+    pktizer.timestamp = 1000;
+    pktizer.time_gen = Some(fixed_time_gen);
+
+    let mut g722 = g722::G722Payloader;
+    let mut seq = new_fixed_sequencer(1234);
+    // 30 fps does not divide 90kHz in nanoseconds
+    let frame = Duration::from_secs(1) / 30;
+    let timestamps = packetize_timestamps(
+        &mut pktizer,
+        &mut |p, r| p.packetize_duration(r, &mut g722, &mut seq, frame),
+        301,
+    )?;
+
+    assert_eq!(&timestamps[..4], &[1000, 3999, 6999, 9999]);
+    // Ten seconds later the timestamp did not drift
+    assert_eq!(timestamps[300], 1000 + 899_999);
+
+    Ok(())
+}
+
+struct FailingPayloader;
+
+impl Payloader for FailingPayloader {
+    fn payload<R: Read>(&mut self, _mtu: isize, _reader: &mut R) -> Result<Vec<Vec<u8>>, Error> {
+        Err(Error::new("payloader failure".to_owned()))
+    }
+}
+
+#[test]
+fn test_packetizer_duration_errors() -> Result<(), Error> {
+    let mut pktizer = PacketizerImpl::new(100, 98, 0x1234ABCD, 90000);
+    pktizer.timestamp = 1000;
+    pktizer.time_gen = Some(fixed_time_gen);
+
+    let mut g722 = g722::G722Payloader;
+    let mut seq = new_fixed_sequencer(1234);
+    let frame = Duration::from_secs(1) / 30;
+    let payload = vec![0x11, 0x12, 0x13, 0x14];
+
+    // A failed packetization leaves the fraction of timestamp units and the
+    // timestamp untouched
+    let mut reader = BufReader::new(payload.as_slice());
+    assert!(pktizer
+        .packetize_duration(&mut reader, &mut FailingPayloader, &mut seq, frame)
+        .is_err());
+
+    // The samples of a duration beyond the timestamp range are not truncated
+    let mut reader = BufReader::new(payload.as_slice());
+    assert!(pktizer
+        .packetize_duration(
+            &mut reader,
+            &mut g722,
+            &mut seq,
+            Duration::from_secs(50_000)
+        )
+        .is_err());
+    assert!(pktizer.skip_duration(Duration::from_secs(50_000)).is_err());
+
+    let timestamps = packetize_timestamps(
+        &mut pktizer,
+        &mut |p, r| p.packetize_duration(r, &mut g722, &mut seq, frame),
+        3,
+    )?;
+    assert_eq!(timestamps, vec![1000, 3999, 6999]);
+
+    Ok(())
+}
+
+#[test]
+fn test_packetizer_capture_time() -> Result<(), Error> {
+    let mut pktizer = PacketizerImpl::new(100, 98, 0x1234ABCD, 48000);
+    pktizer.timestamp = 1000;
+
+    let mut g722 = g722::G722Payloader;
+    let mut seq = new_fixed_sequencer(1234);
+    let start = fixed_time_gen();
+    // The third frame is suppressed
+    let capture_times = vec![
+        start,
+        start + Duration::from_millis(20),
+        start + Duration::from_millis(60),
+        // Capture clock jitter is rounded to the nearest timestamp unit
+        start + Duration::from_micros(80_010),
+    ];
+    let mut captures = capture_times.into_iter();
+    let timestamps = packetize_timestamps(
+        &mut pktizer,
+        &mut |p, r| p.packetize_at(r, &mut g722, &mut seq, captures.next().unwrap()),
+        4,
+    )?;
+
+    assert_eq!(timestamps, vec![1000, 1960, 3880, 4840]);
+
+    // Sender Reports map the wallclock to the timestamp of the capture clock
+    let now = start + Duration::from_secs(1);
+    assert_eq!(
+        pktizer.ntp_rtp_timestamps(now),
+        Some((unix2ntp(now), 1000 + 48000))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_packetizer_skip() -> Result<(), Error> {
+    let mut pktizer = PacketizerImpl::new(100, 98, 0x1234ABCD, 48000);
+    pktizer.timestamp = u32::MAX - 100;
+    pktizer.time_gen = Some(fixed_time_gen);
+    assert_eq!(pktizer.ntp_rtp_timestamps(fixed_time_gen()), None);
+
+    let mut g722 = g722::G722Payloader;
+    let mut seq = new_fixed_sequencer(1234);
+    let mut timestamps = vec![];
+    let payload = vec![0x11, 0x12, 0x13, 0x14];
+    for i in 0..3 {
+        let mut reader = BufReader::new(payload.as_slice());
+        let packets = pktizer.packetize(&mut reader, &mut g722, &mut seq, 960)?;
+        timestamps.push(packets[0].header.timestamp);
+        if i == 0 {
+            pktizer.skip_samples(960);
+        } else {
+            pktizer.skip_duration(Duration::from_millis(40))?;
+        }
+    }
+
+    // The timestamp wraps around
+    assert_eq!(timestamps, vec![u32::MAX - 100, 1819, 1819 + 960 + 1920]);
+
+    // The mapping is taken at the first packetization
+    assert_eq!(
+        pktizer.ntp_rtp_timestamps(fixed_time_gen() + Duration::from_millis(500)),
+        Some((
+            unix2ntp(fixed_time_gen() + Duration::from_millis(500)),
+            (u32::MAX - 100).wrapping_add(24000)
+        ))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::extension::registry::HeaderExtensionRegistry;
+use crate::extension::transport_cc_extension::TransportCCExtension;
+use crate::extension::HeaderExtension;
+use crate::header::Header;
+use crate::packet::{Packet, MAX_PADDING_SIZE};
+use crate::sequence::Sequencer;
+
+use util::Error;
+
+#[cfg(test)]
+mod padding_test;
+
+// PaddingGenerator generates padding-only packets to probe the available
+// bandwidth, RFC 3550 5.1
+//
+// Each packet carries a transport-wide sequence number so the receiver
+// reports its arrival in transport-cc feedback. Padding packets are usually
+// sent on the RTX stream, whose receiver discards them.
+pub struct PaddingGenerator<S: Sequencer> {
+    ssrc: u32,
+    payload_type: u8,
+    sequencer: S,
+    transport_cc_id: u8,
+}
+
+impl<S: Sequencer> PaddingGenerator<S> {
+    // new constructs a generator of packets on ssrc, the transport-cc
+    // header extension must be negotiated in registry
+    pub fn new(
+        ssrc: u32,
+        payload_type: u8,
+        sequencer: S,
+        registry: &HeaderExtensionRegistry,
+    ) -> Result<Self, Error> {
+        let transport_cc_id = registry.id(TransportCCExtension::uri()).ok_or_else(|| {
+            Error::new(format!(
+                "header extension {} is not registered",
+                TransportCCExtension::uri()
+            ))
+        })?;
+
+        Ok(PaddingGenerator {
+            ssrc,
+            payload_type,
+            sequencer,
+            transport_cc_id,
+        })
+    }
+
+    // generate returns padding-only packets carrying at least bytes octets
+    // of padding, with the timestamp of the last media packet sent and the
+    // next numbers of the transport-wide sequencer shared by the streams
+    // of the transport
+    pub fn generate<T: Sequencer>(
+        &mut self,
+        bytes: usize,
+        timestamp: u32,
+        transport_sequencer: &mut T,
+    ) -> Result<Vec<Packet>, Error> {
+        let mut packets = vec![];
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let padding_size = std::cmp::min(remaining, MAX_PADDING_SIZE as usize);
+            remaining -= padding_size;
+
+            let mut packet = Packet::new(
+                Header {
+                    version: 2,
+                    payload_type: self.payload_type,
+                    sequence_number: self.sequencer.next_sequence_number(),
+                    timestamp,
+                    ssrc: self.ssrc,
+                    ..Default::default()
+                },
+                vec![],
+            );
+
+            let mut transport_cc = vec![];
+            TransportCCExtension {
+                transport_sequence: transport_sequencer.next_sequence_number(),
+            }
+            .marshal(&mut transport_cc)?;
+            packet
+                .header
+                .set_extension(self.transport_cc_id, &transport_cc)?;
+            packet.set_padding(padding_size as u8);
+
+            packets.push(packet);
+        }
+
+        Ok(packets)
+    }
+}
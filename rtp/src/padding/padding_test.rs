@@ -0,0 +1,67 @@
+use super::*;
+use crate::sequence::new_fixed_sequencer;
+
+use std::io::BufReader;
+
+fn registry() -> HeaderExtensionRegistry {
+    let mut registry = HeaderExtensionRegistry::new();
+    registry.register(5, sdp::extmap::TRANSPORT_CC_URI).unwrap();
+    registry
+}
+
+#[test]
+fn test_padding_generator() -> Result<(), Error> {
+    let registry = registry();
+    let mut generator = PaddingGenerator::new(0x1234, 97, new_fixed_sequencer(100), &registry)?;
+    let mut transport_sequencer = new_fixed_sequencer(65535);
+
+    let packets = generator.generate(600, 3000, &mut transport_sequencer)?;
+    assert_eq!(packets.len(), 3);
+    let padding_sizes: Vec<u8> = packets.iter().map(|p| p.padding_size()).collect();
+    assert_eq!(padding_sizes, vec![255, 255, 90]);
+
+    for (i, packet) in packets.iter().enumerate() {
+        assert_eq!(packet.header.ssrc, 0x1234);
+        assert_eq!(packet.header.payload_type, 97);
+        assert_eq!(packet.header.sequence_number, 100 + i as u16);
+        assert_eq!(packet.header.timestamp, 3000);
+        assert!(!packet.header.marker);
+
+        // The packet survives a round trip with an empty payload
+        let mut raw = vec![];
+        packet.marshal(&mut raw)?;
+        assert_eq!(raw.len(), packet.size());
+        let mut reader = BufReader::new(raw.as_slice());
+        let parsed = Packet::unmarshal(&mut reader)?;
+        assert!(parsed.payload.is_empty());
+        assert_eq!(parsed.padding_size(), padding_sizes[i]);
+        assert_eq!(
+            parsed.get_header_extension::<TransportCCExtension>(&registry)?,
+            Some(TransportCCExtension {
+                transport_sequence: 65535u16.wrapping_add(i as u16)
+            })
+        );
+    }
+
+    // The sequence numbers continue on the next probe
+    let packets = generator.generate(1, 6000, &mut transport_sequencer)?;
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].header.sequence_number, 103);
+    assert_eq!(packets[0].padding_size(), 1);
+    assert!(generator
+        .generate(0, 6000, &mut transport_sequencer)?
+        .is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_padding_generator_requires_transport_cc() {
+    let result = PaddingGenerator::new(
+        0x1234,
+        97,
+        new_fixed_sequencer(100),
+        &HeaderExtensionRegistry::new(),
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,154 @@
+use crate::packet::Packet;
+
+use std::time::Instant;
+
+#[cfg(test)]
+mod rewriter_test;
+
+// How far the current source moves past the key frame it started at before
+// packets older than that key frame are no longer expected
+const REORDER_WINDOW: u16 = 0x1000;
+
+// StreamRewriter forwards the packets of one of several source streams,
+// such as the simulcast encodings of a track, on a single SSRC with
+// continuous sequence numbers and timestamps
+//
+// Switching to another source is requested with switch_to and takes effect
+// at the next key frame of that source, so the receiver can decode the new
+// stream from the first packet forwarded. Gaps and reordering within a
+// source are kept, the receiver NACKs and reorders them as usual.
+pub struct StreamRewriter {
+    ssrc: u32,
+    clock_rate: u32,
+
+    current: Option<u32>,
+    pending: Option<u32>,
+
+    // Added to the sequence numbers and timestamps of the current source
+    sequence_number_offset: u16,
+    timestamp_offset: u32,
+    // Sequence number of the key frame the current source started at,
+    // older packets would collide with the previous source. Cleared once
+    // the source moved past it by REORDER_WINDOW, before the sequence
+    // numbers wrap around it.
+    start_sequence_number: Option<u16>,
+
+    // Newest packet forwarded, after rewriting
+    last_sequence_number: Option<u16>,
+    last_timestamp: u32,
+    last_time: Option<Instant>,
+}
+
+impl StreamRewriter {
+    // new constructs a rewriter forwarding on ssrc, timestamps of the
+    // sources being in clock_rate units
+    pub fn new(ssrc: u32, clock_rate: u32) -> Self {
+        StreamRewriter {
+            ssrc,
+            clock_rate,
+            current: None,
+            pending: None,
+            sequence_number_offset: 0,
+            timestamp_offset: 0,
+            start_sequence_number: None,
+            last_sequence_number: None,
+            last_timestamp: 0,
+            last_time: None,
+        }
+    }
+
+    // current_source returns the SSRC of the source forwarded
+    pub fn current_source(&self) -> Option<u32> {
+        self.current
+    }
+
+    // pending_source returns the SSRC of the source waiting for a key frame
+    pub fn pending_source(&self) -> Option<u32> {
+        self.pending
+    }
+
+    // switch_to requests forwarding the source ssrc from its next key frame
+    pub fn switch_to(&mut self, ssrc: u32) {
+        self.pending = if self.current == Some(ssrc) {
+            None
+        } else {
+            Some(ssrc)
+        };
+    }
+
+    // rewrite returns packet rewritten onto the output stream, or None if
+    // it is not forwarded. keyframe is reported by the codec depacketizer
+    // for the packet, e.g. VP8Packet::keyframe, and arrival is used to
+    // advance the timestamps across a switch.
+    pub fn rewrite(&mut self, packet: &Packet, keyframe: bool, arrival: Instant) -> Option<Packet> {
+        let ssrc = packet.header.ssrc;
+        if keyframe && self.pending == Some(ssrc) {
+            self.switch(packet, arrival);
+        }
+        if self.current != Some(ssrc) {
+            return None;
+        }
+
+        // Packets of the new source older than the switch would reuse the
+        // sequence numbers of the previous one
+        let sequence_number = packet.header.sequence_number;
+        if let Some(start_sequence_number) = self.start_sequence_number {
+            if is_older(sequence_number, start_sequence_number) {
+                return None;
+            }
+            if sequence_number.wrapping_sub(start_sequence_number) >= REORDER_WINDOW {
+                self.start_sequence_number = None;
+            }
+        }
+
+        let mut rewritten = packet.clone();
+        rewritten.header.ssrc = self.ssrc;
+        rewritten.header.sequence_number =
+            sequence_number.wrapping_add(self.sequence_number_offset);
+        rewritten.header.timestamp = packet.header.timestamp.wrapping_add(self.timestamp_offset);
+
+        match self.last_sequence_number {
+            Some(last) if is_older(rewritten.header.sequence_number, last) => {}
+            _ => {
+                self.last_sequence_number = Some(rewritten.header.sequence_number);
+                self.last_timestamp = rewritten.header.timestamp;
+                self.last_time = Some(arrival);
+            }
+        }
+
+        Some(rewritten)
+    }
+
+    fn switch(&mut self, packet: &Packet, arrival: Instant) {
+        let sequence_number = packet.header.sequence_number;
+        let timestamp = packet.header.timestamp;
+
+        match (self.last_sequence_number, self.last_time) {
+            (Some(last_sequence_number), Some(last_time)) => {
+                // The timestamp advances by the time elapsed since the last
+                // packet, at least by one so the new frame is distinct
+                let elapsed = arrival.saturating_duration_since(last_time);
+                let samples = (elapsed.as_secs_f64() * self.clock_rate as f64) as u32;
+                let next_timestamp = self.last_timestamp.wrapping_add(std::cmp::max(samples, 1));
+
+                self.sequence_number_offset = last_sequence_number
+                    .wrapping_add(1)
+                    .wrapping_sub(sequence_number);
+                self.timestamp_offset = next_timestamp.wrapping_sub(timestamp);
+            }
+            _ => {
+                self.sequence_number_offset = 0;
+                self.timestamp_offset = 0;
+            }
+        }
+
+        self.current = Some(packet.header.ssrc);
+        self.pending = None;
+        self.start_sequence_number = Some(sequence_number);
+    }
+}
+
+// is_older reports if the sequence number a precedes b
+fn is_older(a: u16, b: u16) -> bool {
+    a != b && b.wrapping_sub(a) < 0x8000
+}
@@ -0,0 +1,210 @@
+use super::*;
+use crate::packet::test_packet;
+
+use std::time::Duration;
+
+const OUTPUT_SSRC: u32 = 0xAAAA;
+const LOW: u32 = 1;
+const HIGH: u32 = 2;
+
+fn packet(ssrc: u32, sequence_number: u16, timestamp: u32) -> Packet {
+    test_packet(
+        ssrc,
+        sequence_number,
+        timestamp,
+        false,
+        vec![ssrc as u8, sequence_number as u8],
+    )
+}
+
+// rewrite_all returns the (sequence number, timestamp) of the packets
+// forwarded, None for the dropped ones
+fn rewrite_all(
+    rewriter: &mut StreamRewriter,
+    start: Instant,
+    packets: &[(u32, u16, u32, bool, u64)],
+) -> Vec<Option<(u16, u32)>> {
+    packets
+        .iter()
+        .map(|&(ssrc, sequence_number, timestamp, keyframe, arrival)| {
+            rewriter
+                .rewrite(
+                    &packet(ssrc, sequence_number, timestamp),
+                    keyframe,
+                    start + Duration::from_millis(arrival),
+                )
+                .map(|p| {
+                    assert_eq!(p.header.ssrc, OUTPUT_SSRC);
+                    assert_eq!(p.payload, vec![ssrc as u8, sequence_number as u8]);
+                    (p.header.sequence_number, p.header.timestamp)
+                })
+        })
+        .collect()
+}
+
+#[test]
+fn test_stream_rewriter_start() {
+    let start = Instant::now();
+    let mut rewriter = StreamRewriter::new(OUTPUT_SSRC, 90000);
+
+    // Nothing is forwarded before a source is selected
+    assert_eq!(
+        rewrite_all(&mut rewriter, start, &[(LOW, 10, 1000, true, 0)]),
+        vec![None]
+    );
+
+    // The source starts at its next key frame
+    rewriter.switch_to(LOW);
+    assert_eq!(rewriter.pending_source(), Some(LOW));
+    assert_eq!(
+        rewrite_all(
+            &mut rewriter,
+            start,
+            &[
+                (LOW, 11, 4000, false, 33),
+                (LOW, 12, 7000, true, 66),
+                (LOW, 13, 7000, false, 66),
+                (LOW, 14, 10000, false, 100),
+            ]
+        ),
+        vec![None, Some((12, 7000)), Some((13, 7000)), Some((14, 10000))]
+    );
+    assert_eq!(rewriter.current_source(), Some(LOW));
+    assert_eq!(rewriter.pending_source(), None);
+}
+
+#[test]
+fn test_stream_rewriter_switch() {
+    let start = Instant::now();
+    let mut rewriter = StreamRewriter::new(OUTPUT_SSRC, 90000);
+    rewriter.switch_to(LOW);
+    rewrite_all(&mut rewriter, start, &[(LOW, 100, 1000, true, 0)]);
+
+    rewriter.switch_to(HIGH);
+    assert_eq!(
+        rewrite_all(
+            &mut rewriter,
+            start,
+            &[
+                // The current source is forwarded until the key frame
+                (HIGH, 5000, 50_000, false, 10),
+                (LOW, 101, 4000, false, 33),
+                (HIGH, 5001, 53_000, false, 33),
+                // 40ms after the last packet forwarded
+                (HIGH, 5002, 56_000, true, 73),
+                (LOW, 102, 7000, false, 74),
+                (HIGH, 5003, 56_000, false, 74),
+                (HIGH, 5004, 59_000, false, 106),
+            ]
+        ),
+        vec![
+            None,
+            Some((101, 4000)),
+            None,
+            Some((102, 4000 + 3600)),
+            None,
+            Some((103, 7600)),
+            Some((104, 10_600)),
+        ]
+    );
+    assert_eq!(rewriter.current_source(), Some(HIGH));
+
+    // Switching back continues the same sequence
+    rewriter.switch_to(LOW);
+    assert_eq!(
+        rewrite_all(&mut rewriter, start, &[(LOW, 110, 31_000, true, 106)]),
+        vec![Some((105, 10_601))]
+    );
+}
+
+#[test]
+fn test_stream_rewriter_gap_and_reorder() {
+    let start = Instant::now();
+    let mut rewriter = StreamRewriter::new(OUTPUT_SSRC, 90000);
+    rewriter.switch_to(LOW);
+    rewrite_all(&mut rewriter, start, &[(LOW, 65534, 1000, true, 0)]);
+
+    rewriter.switch_to(HIGH);
+    assert_eq!(
+        rewrite_all(
+            &mut rewriter,
+            start,
+            &[
+                (HIGH, 20, 500, true, 10),
+                // Lost packet 21 is left as a gap for the receiver to NACK
+                (HIGH, 22, 3500, false, 43),
+                // Reordered packets keep their place
+                (HIGH, 21, 500, false, 44),
+                // Older than the switch, it would collide with LOW
+                (HIGH, 19, 500, false, 45),
+                (HIGH, 23, 3500, false, 46),
+            ]
+        ),
+        vec![
+            Some((65535, 1900)),
+            Some((1, 4900)),
+            Some((0, 1900)),
+            None,
+            Some((2, 4900)),
+        ]
+    );
+
+    // The timestamp of a switch follows the newest packet, not a reordered
+    // one
+    rewriter.switch_to(LOW);
+    assert_eq!(
+        rewrite_all(&mut rewriter, start, &[(LOW, 65535, 4000, true, 56)]),
+        vec![Some((3, 4900 + 900))]
+    );
+}
+
+#[test]
+fn test_stream_rewriter_long_run() {
+    let start = Instant::now();
+    let mut rewriter = StreamRewriter::new(OUTPUT_SSRC, 90000);
+    rewriter.switch_to(LOW);
+
+    // The sequence numbers move more than half their range past the key
+    // frame the source started at
+    for sequence_number in 0..40000u16 {
+        let rewritten = rewriter.rewrite(
+            &packet(LOW, sequence_number, 0),
+            sequence_number == 0,
+            start,
+        );
+        assert_eq!(
+            rewritten.map(|p| p.header.sequence_number),
+            Some(sequence_number),
+            "packet {} was dropped",
+            sequence_number
+        );
+    }
+}
+
+#[test]
+fn test_stream_rewriter_switch_request() {
+    let start = Instant::now();
+    let mut rewriter = StreamRewriter::new(OUTPUT_SSRC, 48000);
+    rewriter.switch_to(LOW);
+    rewrite_all(&mut rewriter, start, &[(LOW, 1, 0, true, 0)]);
+
+    // Requesting the current source cancels a pending switch
+    rewriter.switch_to(HIGH);
+    rewriter.switch_to(LOW);
+    assert_eq!(rewriter.pending_source(), None);
+    assert_eq!(
+        rewrite_all(
+            &mut rewriter,
+            start,
+            &[(HIGH, 1, 0, true, 20), (LOW, 2, 960, false, 20)]
+        ),
+        vec![None, Some((2, 960))]
+    );
+
+    // A key frame arriving at the same time still advances the timestamp
+    rewriter.switch_to(HIGH);
+    assert_eq!(
+        rewrite_all(&mut rewriter, start, &[(HIGH, 2, 5000, true, 20)]),
+        vec![Some((3, 961))]
+    );
+}
@@ -0,0 +1,144 @@
+use crate::packet::Packet;
+use crate::sequence::Sequencer;
+
+use rtcp::transport_layer_nack::TransportLayerNack;
+use util::Error;
+
+#[cfg(test)]
+mod rtx_test;
+
+// Size of the original sequence number prefixed to RTX payloads
+const OSN_LENGTH: usize = 2;
+
+// wrap builds the RTX packet retransmitting packet, RFC 4588 section 4
+//
+// The RTX packet keeps the timestamp, marker bit, CSRCs and header
+// extensions of the original packet, and takes the SSRC, payload type and
+// a sequence number of the RTX stream.
+//
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         RTP Header                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |            OSN                |                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+// |                  Original RTP Packet Payload                  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+pub fn wrap<S: Sequencer>(
+    packet: &Packet,
+    rtx_ssrc: u32,
+    rtx_payload_type: u8,
+    sequencer: &mut S,
+) -> Packet {
+    let mut header = packet.header.clone();
+    header.ssrc = rtx_ssrc;
+    header.payload_type = rtx_payload_type;
+    header.sequence_number = sequencer.next_sequence_number();
+    // The padding of the original packet is not retransmitted
+    header.padding = false;
+
+    let mut payload = Vec::with_capacity(OSN_LENGTH + packet.payload.len());
+    payload.extend_from_slice(&packet.header.sequence_number.to_be_bytes());
+    payload.extend_from_slice(&packet.payload);
+
+    Packet::new(header, payload)
+}
+
+// unwrap rebuilds the original packet from a RTX packet, given the SSRC and
+// payload type of the stream it repairs
+pub fn unwrap(rtx: &Packet, media_ssrc: u32, media_payload_type: u8) -> Result<Packet, Error> {
+    if rtx.payload.len() < OSN_LENGTH {
+        return Err(Error::new(
+            "rtx payload is too short to carry an original sequence number".to_owned(),
+        ));
+    }
+
+    let mut header = rtx.header.clone();
+    header.ssrc = media_ssrc;
+    header.payload_type = media_payload_type;
+    header.sequence_number = u16::from_be_bytes([rtx.payload[0], rtx.payload[1]]);
+    header.padding = false;
+
+    Ok(Packet::new(header, rtx.payload[OSN_LENGTH..].to_vec()))
+}
+
+// RtxSendHistory keeps the last packets sent on a media stream to
+// retransmit them on the RTX stream when they are NACKed
+pub struct RtxSendHistory<S: Sequencer> {
+    media_ssrc: u32,
+    rtx_ssrc: u32,
+    rtx_payload_type: u8,
+    sequencer: S,
+    // Packets indexed by sequence number modulo the capacity
+    packets: Vec<Option<Packet>>,
+}
+
+impl<S: Sequencer> RtxSendHistory<S> {
+    // new constructs a history of capacity packets, the RTX sequence
+    // numbers are drawn from sequencer
+    pub fn new(
+        capacity: usize,
+        media_ssrc: u32,
+        rtx_ssrc: u32,
+        rtx_payload_type: u8,
+        sequencer: S,
+    ) -> Self {
+        RtxSendHistory {
+            media_ssrc,
+            rtx_ssrc,
+            rtx_payload_type,
+            sequencer,
+            packets: (0..std::cmp::max(capacity, 1)).map(|_| None).collect(),
+        }
+    }
+
+    // push records a packet sent on the media stream, evicting the oldest
+    // one when the history is full
+    pub fn push(&mut self, packet: Packet) {
+        if packet.header.ssrc != self.media_ssrc {
+            return;
+        }
+        let index = self.index(packet.header.sequence_number);
+        self.packets[index] = Some(packet);
+    }
+
+    // get returns the packet sent with sequence_number if it is still in
+    // the history
+    pub fn get(&self, sequence_number: u16) -> Option<&Packet> {
+        match &self.packets[self.index(sequence_number)] {
+            Some(packet) if packet.header.sequence_number == sequence_number => Some(packet),
+            _ => None,
+        }
+    }
+
+    // on_nack returns the RTX packets answering nack, packets no longer in
+    // the history are skipped
+    pub fn on_nack(&mut self, nack: &TransportLayerNack) -> Vec<Packet> {
+        if nack.media_ssrc != self.media_ssrc {
+            return vec![];
+        }
+
+        let mut rtx_packets = vec![];
+        for pair in &nack.nacks {
+            for sequence_number in pair.packet_list() {
+                let index = self.index(sequence_number);
+                if let Some(packet) = &self.packets[index] {
+                    if packet.header.sequence_number == sequence_number {
+                        rtx_packets.push(wrap(
+                            packet,
+                            self.rtx_ssrc,
+                            self.rtx_payload_type,
+                            &mut self.sequencer,
+                        ));
+                    }
+                }
+            }
+        }
+        rtx_packets
+    }
+
+    fn index(&self, sequence_number: u16) -> usize {
+        sequence_number as usize % self.packets.len()
+    }
+}
@@ -0,0 +1,113 @@
+use super::*;
+use crate::header::Header;
+use crate::packet::test_packet;
+use crate::sequence::new_fixed_sequencer;
+
+use rtcp::transport_layer_nack::NackPair;
+
+const MEDIA_SSRC: u32 = 0x1234ABCD;
+const RTX_SSRC: u32 = 0x5678EF01;
+
+fn media_packet(sequence_number: u16) -> Packet {
+    let mut packet = test_packet(
+        MEDIA_SSRC,
+        sequence_number,
+        3000,
+        true,
+        vec![0x01, 0x02, 0x03],
+    );
+    packet.header.csrc = vec![1];
+    packet.header.set_extension(1, &[0xAA]).unwrap();
+    packet
+}
+
+fn nack(media_ssrc: u32, nacks: Vec<NackPair>) -> TransportLayerNack {
+    TransportLayerNack {
+        sender_ssrc: 1,
+        media_ssrc,
+        nacks,
+    }
+}
+
+#[test]
+fn test_rtx_wrap_unwrap() -> Result<(), Error> {
+    let packet = media_packet(0x1234);
+    let mut sequencer = new_fixed_sequencer(100);
+
+    let rtx = wrap(&packet, RTX_SSRC, 97, &mut sequencer);
+    assert_eq!(rtx.header.ssrc, RTX_SSRC);
+    assert_eq!(rtx.header.payload_type, 97);
+    assert_eq!(rtx.header.sequence_number, 100);
+    assert_eq!(rtx.header.timestamp, packet.header.timestamp);
+    assert_eq!(rtx.header.marker, packet.header.marker);
+    assert_eq!(rtx.header.csrc, packet.header.csrc);
+    assert_eq!(rtx.header.extensions, packet.header.extensions);
+    assert_eq!(rtx.payload, vec![0x12, 0x34, 0x01, 0x02, 0x03]);
+
+    // RTX packets have their own sequence space
+    let rtx = wrap(&packet, RTX_SSRC, 97, &mut sequencer);
+    assert_eq!(rtx.header.sequence_number, 101);
+
+    assert_eq!(unwrap(&rtx, MEDIA_SSRC, 96)?, packet);
+
+    Ok(())
+}
+
+#[test]
+fn test_rtx_unwrap_too_short() {
+    let rtx = Packet::new(Header::default(), vec![0x12]);
+    assert!(unwrap(&rtx, MEDIA_SSRC, 96).is_err());
+}
+
+#[test]
+fn test_rtx_send_history() -> Result<(), Error> {
+    let mut history = RtxSendHistory::new(4, MEDIA_SSRC, RTX_SSRC, 97, new_fixed_sequencer(500));
+    for sequence_number in 65530..=65535 {
+        history.push(media_packet(sequence_number));
+    }
+    history.push(media_packet(0));
+    // Packets of other streams are not recorded
+    let mut other = media_packet(1);
+    other.header.ssrc = 1;
+    history.push(other);
+
+    // The history keeps the last four packets
+    assert!(history.get(65532).is_none());
+    assert!(history.get(65533).is_some());
+    assert!(history.get(0).is_some());
+    assert!(history.get(1).is_none());
+
+    // 65532 was evicted, the bitmask wraps around the sequence numbers
+    let rtx_packets = history.on_nack(&nack(
+        MEDIA_SSRC,
+        vec![NackPair {
+            packet_id: 65532,
+            lost_packets: 0b1001,
+        }],
+    ));
+    let repaired: Vec<Packet> = rtx_packets
+        .iter()
+        .map(|rtx| unwrap(rtx, MEDIA_SSRC, 96))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(repaired, vec![media_packet(65533), media_packet(0)]);
+    assert_eq!(
+        rtx_packets
+            .iter()
+            .map(|p| p.header.sequence_number)
+            .collect::<Vec<u16>>(),
+        vec![500, 501]
+    );
+
+    // NACKs for other streams are ignored
+    assert!(history
+        .on_nack(&nack(
+            1,
+            vec![NackPair {
+                packet_id: 0,
+                lost_packets: 0,
+            }],
+        ))
+        .is_empty());
+
+    Ok(())
+}
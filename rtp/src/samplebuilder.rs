@@ -0,0 +1,387 @@
+use crate::packet::Packet;
+use crate::packetizer::Depacketizer;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[cfg(test)]
+mod samplebuilder_test;
+
+// Sample is a media frame rebuilt from one or more RTP packets
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Sample {
+    pub data: Vec<u8>,
+    pub duration: Duration,
+    // RTP timestamp of the packets the sample was built from
+    pub packet_timestamp: u32,
+    // Number of packets dropped since the previous sample
+    pub prev_dropped_packets: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SequenceComparison {
+    Void,
+    Before,
+    Inside,
+    After,
+}
+
+// SampleSequenceLocation is a range of sequence numbers, head inclusive and
+// tail exclusive
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct SampleSequenceLocation {
+    head: u16,
+    tail: u16,
+}
+
+impl SampleSequenceLocation {
+    fn empty(&self) -> bool {
+        self.head == self.tail
+    }
+
+    fn has_data(&self) -> bool {
+        self.head != self.tail
+    }
+
+    fn count(&self) -> u16 {
+        seqnum_distance(self.head, self.tail)
+    }
+
+    fn compare(&self, pos: u16) -> SequenceComparison {
+        if self.head == self.tail {
+            return SequenceComparison::Void;
+        }
+
+        if self.head < self.tail {
+            if self.head <= pos && pos < self.tail {
+                return SequenceComparison::Inside;
+            }
+        } else if self.head <= pos || pos < self.tail {
+            return SequenceComparison::Inside;
+        }
+
+        if self.head.wrapping_sub(pos) <= pos.wrapping_sub(self.tail) {
+            SequenceComparison::Before
+        } else {
+            SequenceComparison::After
+        }
+    }
+}
+
+// SampleBuilder buffers RTP packets until whole media samples can be built
+//
+// Packets are reordered by sequence number. A sample ends at a packet for
+// which the depacketizer reports a partition tail, or before a packet with a
+// different timestamp. Samples not starting with a partition head are
+// dropped, as are packets that fall behind the max late window.
+pub struct SampleBuilder<T: Depacketizer> {
+    // How many packets to wait until a sample is built regardless of loss
+    max_late: u16,
+    // Max RTP timestamp distance between the oldest and newest packet
+    // before the oldest ones are dropped, 0 disables the check
+    max_late_timestamp: u32,
+    // Indexed by sequence number, packets are boxed to keep the 65536
+    // slots small
+    buffer: Vec<Option<Box<Packet>>>,
+    prepared_samples: VecDeque<Sample>,
+
+    depacketizer: T,
+    // Allows computing the duration of samples
+    sample_rate: u32,
+
+    // Range of the packets inserted into the buffer
+    filled: SampleSequenceLocation,
+    // Range of the packets of the sample being built
+    active: SampleSequenceLocation,
+
+    // Number of packets dropped since the last sample
+    dropped_packets: u16,
+}
+
+impl<T: Depacketizer> SampleBuilder<T> {
+    // new constructs a SampleBuilder waiting up to max_late packets for a
+    // sample to complete
+    pub fn new(max_late: u16, depacketizer: T, sample_rate: u32) -> Self {
+        SampleBuilder {
+            max_late,
+            max_late_timestamp: 0,
+            buffer: (0..=u16::MAX).map(|_| None).collect(),
+            prepared_samples: VecDeque::new(),
+            depacketizer,
+            sample_rate,
+            filled: SampleSequenceLocation::default(),
+            active: SampleSequenceLocation::default(),
+            dropped_packets: 0,
+        }
+    }
+
+    // with_max_time_delay additionally drops packets that are older than
+    // max_late_duration compared to the newest packet
+    pub fn with_max_time_delay(mut self, max_late_duration: Duration) -> Self {
+        self.max_late_timestamp =
+            (self.sample_rate as u128 * max_late_duration.as_millis() / 1000) as u32;
+        self
+    }
+
+    // push adds a RTP packet to the buffer
+    pub fn push(&mut self, p: Packet) {
+        let sequence_number = p.header.sequence_number;
+        self.buffer[sequence_number as usize] = Some(Box::new(p));
+
+        match self.filled.compare(sequence_number) {
+            SequenceComparison::Void => {
+                self.filled.head = sequence_number;
+                self.filled.tail = sequence_number.wrapping_add(1);
+            }
+            SequenceComparison::Before => {
+                self.filled.head = sequence_number;
+            }
+            SequenceComparison::After => {
+                self.filled.tail = sequence_number.wrapping_add(1);
+            }
+            SequenceComparison::Inside => {}
+        }
+
+        self.purge_buffers();
+    }
+
+    // pop builds pushed RTP packets into samples and returns the next
+    // complete sample, if any
+    pub fn pop(&mut self) -> Option<Sample> {
+        self.build_sample(false);
+        self.prepared_samples.pop_front()
+    }
+
+    fn packet(&self, i: u16) -> Option<&Packet> {
+        self.buffer[i as usize].as_deref()
+    }
+
+    // too_old checks if the packets of location span more than the max
+    // late timestamp distance
+    fn too_old(&self, location: SampleSequenceLocation) -> bool {
+        if self.max_late_timestamp == 0 {
+            return false;
+        }
+
+        let mut found_head = None;
+        let mut i = location.head;
+        while i != location.tail {
+            if let Some(packet) = self.packet(i) {
+                found_head = Some(packet.header.timestamp);
+                break;
+            }
+            i = i.wrapping_add(1);
+        }
+
+        let mut found_tail = None;
+        let mut i = location.tail.wrapping_sub(1);
+        while i != location.head {
+            if let Some(packet) = self.packet(i) {
+                found_tail = Some(packet.header.timestamp);
+                break;
+            }
+            i = i.wrapping_sub(1);
+        }
+
+        match (found_head, found_tail) {
+            (Some(head), Some(tail)) => timestamp_distance(head, tail) > self.max_late_timestamp,
+            _ => false,
+        }
+    }
+
+    // fetch_timestamp returns the timestamp of the packet at the head of
+    // location
+    fn fetch_timestamp(&self, location: SampleSequenceLocation) -> Option<u32> {
+        if location.empty() {
+            return None;
+        }
+        self.packet(location.head).map(|p| p.header.timestamp)
+    }
+
+    fn release_packet(&mut self, i: u16) {
+        self.buffer[i as usize] = None;
+    }
+
+    // purge_consumed_buffers clears the buffers already consumed by popping
+    fn purge_consumed_buffers(&mut self) {
+        let active = self.active;
+        self.purge_consumed_location(active, false);
+    }
+
+    // purge_consumed_location clears the buffers consumed while building a
+    // sample
+    fn purge_consumed_location(&mut self, consume: SampleSequenceLocation, force_consume: bool) {
+        while self.filled.has_data() {
+            match consume.compare(self.filled.head) {
+                SequenceComparison::Inside if force_consume => {}
+                SequenceComparison::Before => {}
+                _ => break,
+            }
+
+            self.release_packet(self.filled.head);
+            self.filled.head = self.filled.head.wrapping_add(1);
+        }
+    }
+
+    // purge_buffers flushes the buffers already consumed and the ones too
+    // late to be consumed
+    fn purge_buffers(&mut self) {
+        self.purge_consumed_buffers();
+
+        while (self.too_old(self.filled) || self.filled.count() > self.max_late)
+            && self.filled.has_data()
+        {
+            if self.active.empty() {
+                // Refill the active range from the filled packets
+                self.active = self.filled;
+            }
+
+            if self.active.has_data() && self.active.head == self.filled.head {
+                // Force the active packets to be consumed even though
+                // outstanding data may still arrive
+                if self.build_sample(true) {
+                    continue;
+                }
+
+                // The sample could not be built, drop it
+                self.active.head = self.active.head.wrapping_add(1);
+                self.dropped_packets = self.dropped_packets.wrapping_add(1);
+            }
+
+            self.release_packet(self.filled.head);
+            self.filled.head = self.filled.head.wrapping_add(1);
+        }
+    }
+
+    // build_sample builds a sample from the packets at the head of the
+    // active range, returns true if a sample was prepared
+    fn build_sample(&mut self, purging_buffers: bool) -> bool {
+        if self.active.empty() {
+            self.active = self.filled;
+        }
+        if self.active.empty() {
+            return false;
+        }
+
+        if self.filled.compare(self.active.tail) == SequenceComparison::Inside {
+            self.active.tail = self.filled.tail;
+        }
+
+        let head_timestamp = self.fetch_timestamp(self.active);
+        let mut consume = SampleSequenceLocation::default();
+        let mut i = self.active.head;
+        while let Some(packet) = self.packet(i) {
+            if self.active.compare(i) == SequenceComparison::After {
+                break;
+            }
+
+            if self
+                .depacketizer
+                .is_partition_tail(packet.header.marker, &packet.payload)
+            {
+                consume.head = self.active.head;
+                consume.tail = i.wrapping_add(1);
+                break;
+            }
+
+            if let Some(head_timestamp) = head_timestamp {
+                if packet.header.timestamp != head_timestamp {
+                    consume.head = self.active.head;
+                    consume.tail = i;
+                    break;
+                }
+            }
+
+            i = i.wrapping_add(1);
+        }
+
+        if consume.empty() {
+            return false;
+        }
+
+        if !purging_buffers && self.packet(consume.tail).is_none() {
+            // Wait for the packet following the sample to know its duration,
+            // unless the sample has to be released right now
+            return false;
+        }
+
+        let sample_timestamp = head_timestamp.unwrap_or(0);
+        let mut after_timestamp = sample_timestamp;
+        // The timestamp of the next packet gives the sample duration
+        let mut i = consume.tail;
+        while i != self.active.tail {
+            if let Some(packet) = self.packet(i) {
+                after_timestamp = packet.header.timestamp;
+                break;
+            }
+            i = i.wrapping_add(1);
+        }
+
+        // The head packets are now fully consumed
+        self.active.head = consume.tail;
+
+        // Check if the sample would be dropped before depacketizing it
+        let is_head = match self.packet(consume.head) {
+            Some(packet) => self.depacketizer.is_partition_head(&packet.payload),
+            None => false,
+        };
+        if !is_head {
+            self.drop_consumed(consume);
+            return false;
+        }
+
+        // Merge all the packets into a sample
+        let mut data = vec![];
+        let mut i = consume.head;
+        while i != consume.tail {
+            if let Some(packet) = self.buffer[i as usize].as_ref() {
+                let mut reader = packet.payload.as_slice();
+                if self.depacketizer.depacketize(&mut reader).is_err() {
+                    self.drop_consumed(consume);
+                    return false;
+                }
+                data.extend_from_slice(self.depacketizer.payload());
+            }
+            i = i.wrapping_add(1);
+        }
+
+        let samples = after_timestamp.wrapping_sub(sample_timestamp);
+        let duration = if self.sample_rate == 0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_nanos(samples as u64 * 1_000_000_000 / self.sample_rate as u64)
+        };
+
+        self.prepared_samples.push_back(Sample {
+            data,
+            duration,
+            packet_timestamp: sample_timestamp,
+            prev_dropped_packets: self.dropped_packets,
+        });
+        self.dropped_packets = 0;
+
+        self.purge_consumed_location(consume, true);
+        self.purge_consumed_buffers();
+
+        true
+    }
+
+    // drop_consumed drops the packets of a sample that cannot be built
+    fn drop_consumed(&mut self, consume: SampleSequenceLocation) {
+        self.dropped_packets = self.dropped_packets.wrapping_add(consume.count());
+        self.purge_consumed_location(consume, true);
+        self.purge_consumed_buffers();
+    }
+}
+
+// seqnum_distance computes the distance between two sequence numbers
+fn seqnum_distance(x: u16, y: u16) -> u16 {
+    let diff = x.wrapping_sub(y) as i16;
+    diff.unsigned_abs()
+}
+
+// timestamp_distance computes the distance between two timestamps
+fn timestamp_distance(x: u32, y: u32) -> u32 {
+    let diff = x.wrapping_sub(y) as i32;
+    diff.unsigned_abs()
+}
@@ -0,0 +1,309 @@
+use super::*;
+use crate::packet::test_packet;
+
+use std::io::Read;
+
+use util::Error;
+
+#[derive(Default)]
+struct FakeDepacketizer {
+    head_checker: bool,
+    head_bytes: Vec<u8>,
+    // Payloads starting with one of these bytes fail to depacketize
+    invalid_bytes: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+impl Depacketizer for FakeDepacketizer {
+    fn depacketize<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        self.payload.clear();
+        reader.read_to_end(&mut self.payload)?;
+        match self.payload.first() {
+            Some(b) if self.invalid_bytes.contains(b) => {
+                Err(Error::new("invalid payload".to_owned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    fn is_partition_head(&self, payload: &[u8]) -> bool {
+        if !self.head_checker {
+            // Without a head checker every packet is a head
+            return true;
+        }
+        self.head_bytes.iter().any(|b| payload[0] == *b)
+    }
+}
+
+fn packet(sequence_number: u16, timestamp: u32, marker: bool, payload: &[u8]) -> Packet {
+    test_packet(0, sequence_number, timestamp, marker, payload.to_vec())
+}
+
+fn sample(data: &[u8], duration: u64, packet_timestamp: u32, prev_dropped_packets: u16) -> Sample {
+    Sample {
+        data: data.to_vec(),
+        duration: Duration::from_secs(duration),
+        packet_timestamp,
+        prev_dropped_packets,
+    }
+}
+
+fn pop_all<T: Depacketizer>(s: &mut SampleBuilder<T>) -> Vec<Sample> {
+    let mut samples = vec![];
+    while let Some(sample) = s.pop() {
+        samples.push(sample);
+    }
+    samples
+}
+
+#[test]
+fn test_sample_builder() {
+    struct TestCase {
+        message: &'static str,
+        packets: Vec<Packet>,
+        samples: Vec<Sample>,
+        max_late: u16,
+    }
+
+    let test_cases = vec![
+        TestCase {
+            message: "SampleBuilder shouldn't emit anything if only one RTP packet has been pushed",
+            packets: vec![packet(5000, 5, false, &[0x01])],
+            samples: vec![],
+            max_late: 50,
+        },
+        TestCase {
+            message:
+                "SampleBuilder should emit one packet, we had three packets with unique timestamps",
+            packets: vec![
+                packet(5000, 5, false, &[0x01]),
+                packet(5001, 6, false, &[0x02]),
+                packet(5002, 7, false, &[0x03]),
+            ],
+            samples: vec![sample(&[0x01], 1, 5, 0), sample(&[0x02], 1, 6, 0)],
+            max_late: 50,
+        },
+        TestCase {
+            message: "SampleBuilder should merge packets sharing a timestamp",
+            packets: vec![
+                packet(5000, 5, false, &[0x01]),
+                packet(5001, 5, false, &[0x02]),
+                packet(5002, 5, false, &[0x03]),
+                packet(5003, 7, false, &[0x04]),
+            ],
+            samples: vec![sample(&[0x01, 0x02, 0x03], 2, 5, 0)],
+            max_late: 50,
+        },
+        TestCase {
+            message: "SampleBuilder should end a sample at the marker bit",
+            packets: vec![
+                packet(5000, 5, false, &[0x01]),
+                packet(5001, 5, true, &[0x02]),
+                packet(5002, 6, false, &[0x03]),
+            ],
+            samples: vec![sample(&[0x01, 0x02], 1, 5, 0)],
+            max_late: 50,
+        },
+        TestCase {
+            message: "SampleBuilder should reorder packets",
+            packets: vec![
+                packet(5000, 5, false, &[0x01]),
+                packet(5002, 7, false, &[0x03]),
+                packet(5001, 6, false, &[0x02]),
+                packet(5003, 8, false, &[0x04]),
+            ],
+            samples: vec![
+                sample(&[0x01], 1, 5, 0),
+                sample(&[0x02], 1, 6, 0),
+                sample(&[0x03], 1, 7, 0),
+            ],
+            max_late: 50,
+        },
+        TestCase {
+            message: "SampleBuilder should handle sequence number wrap around",
+            packets: vec![
+                packet(65534, 1, false, &[0x01]),
+                packet(65535, 2, false, &[0x02]),
+                packet(0, 3, false, &[0x03]),
+                packet(1, 4, false, &[0x04]),
+            ],
+            samples: vec![
+                sample(&[0x01], 1, 1, 0),
+                sample(&[0x02], 1, 2, 0),
+                sample(&[0x03], 1, 3, 0),
+            ],
+            max_late: 50,
+        },
+        TestCase {
+            message: "SampleBuilder should drop packets beyond max late and report them",
+            packets: vec![
+                packet(5000, 1, false, &[0x01]),
+                packet(5001, 2, false, &[0x02]),
+                packet(5003, 4, false, &[0x04]),
+                packet(5004, 5, false, &[0x05]),
+                packet(5005, 6, false, &[0x06]),
+                packet(5006, 7, false, &[0x07]),
+                packet(5007, 8, false, &[0x08]),
+            ],
+            samples: vec![
+                sample(&[0x01], 1, 1, 0),
+                sample(&[0x04], 1, 4, 2),
+                sample(&[0x05], 1, 5, 0),
+                sample(&[0x06], 1, 6, 0),
+                sample(&[0x07], 1, 7, 0),
+            ],
+            max_late: 5,
+        },
+        TestCase {
+            message: "SampleBuilder should wait for a lost packet within max late",
+            packets: vec![
+                packet(5000, 1, false, &[0x01]),
+                packet(5001, 2, false, &[0x02]),
+                packet(5003, 4, false, &[0x04]),
+                packet(5004, 5, false, &[0x05]),
+            ],
+            samples: vec![sample(&[0x01], 1, 1, 0)],
+            max_late: 50,
+        },
+    ];
+
+    for t in test_cases {
+        let mut s = SampleBuilder::new(t.max_late, FakeDepacketizer::default(), 1);
+        for p in t.packets {
+            s.push(p);
+        }
+        assert_eq!(pop_all(&mut s), t.samples, "{}", t.message);
+    }
+}
+
+#[test]
+fn test_sample_builder_partition_head() {
+    let depacketizer = FakeDepacketizer {
+        head_checker: true,
+        head_bytes: vec![0x01],
+        ..Default::default()
+    };
+    let mut s = SampleBuilder::new(50, depacketizer, 1);
+
+    s.push(packet(5000, 1, false, &[0x02]));
+    s.push(packet(5001, 2, false, &[0x01]));
+    s.push(packet(5002, 2, false, &[0x03]));
+    s.push(packet(5003, 3, false, &[0x01]));
+
+    // The first sample does not start with a partition head
+    assert_eq!(s.pop(), None);
+    assert_eq!(s.pop(), Some(sample(&[0x01, 0x03], 1, 2, 1)));
+    assert_eq!(s.pop(), None);
+}
+
+#[test]
+fn test_sample_builder_depacketize_error() {
+    let depacketizer = FakeDepacketizer {
+        invalid_bytes: vec![0xFF],
+        ..Default::default()
+    };
+    let mut s = SampleBuilder::new(50, depacketizer, 1);
+
+    s.push(packet(5000, 1, false, &[0x01]));
+    s.push(packet(5001, 1, false, &[0xFF]));
+    s.push(packet(5002, 2, false, &[0x03]));
+    s.push(packet(5003, 3, false, &[0x04]));
+
+    // The first sample cannot be depacketized, its packets are dropped
+    assert_eq!(s.pop(), None);
+    assert!(s.buffer[5000].is_none());
+    assert!(s.buffer[5001].is_none());
+    assert_eq!(s.pop(), Some(sample(&[0x03], 1, 2, 2)));
+    assert_eq!(s.pop(), None);
+}
+
+#[test]
+fn test_sample_builder_max_time_delay() {
+    let packets = vec![
+        packet(5000, 0, false, &[0x01]),
+        packet(5002, 20, false, &[0x03]),
+        packet(5003, 40, false, &[0x04]),
+    ];
+
+    // Without a time limit the builder waits for the lost packet
+    let mut s = SampleBuilder::new(50, FakeDepacketizer::default(), 1000);
+    for p in &packets {
+        s.push(packet(
+            p.header.sequence_number,
+            p.header.timestamp,
+            false,
+            &p.payload,
+        ));
+    }
+    assert_eq!(s.pop(), None);
+
+    let mut s = SampleBuilder::new(50, FakeDepacketizer::default(), 1000)
+        .with_max_time_delay(Duration::from_millis(10));
+    for p in packets {
+        s.push(p);
+    }
+    assert_eq!(
+        pop_all(&mut s),
+        vec![Sample {
+            data: vec![0x03],
+            duration: Duration::from_millis(20),
+            packet_timestamp: 20,
+            prev_dropped_packets: 2,
+        }]
+    );
+}
+
+#[test]
+fn test_sample_builder_clean_reference() {
+    for &seq_start in &[0u16, 0xFFF8, 0xFFFE] {
+        let mut s = SampleBuilder::new(10, FakeDepacketizer::default(), 1);
+        s.push(packet(seq_start, 0, false, &[0x01]));
+        s.push(packet(seq_start.wrapping_add(1), 0, false, &[0x02]));
+        s.push(packet(seq_start.wrapping_add(2), 0, false, &[0x03]));
+        s.push(packet(seq_start.wrapping_add(14), 120, false, &[0x04]));
+
+        // The sample could not be completed within max late and was dropped
+        for i in 0..3u16 {
+            assert!(
+                s.buffer[seq_start.wrapping_add(i) as usize].is_none(),
+                "Old packet ({}) is not unreferenced (seq_start: {})",
+                i,
+                seq_start
+            );
+        }
+        assert!(s.buffer[seq_start.wrapping_add(14) as usize].is_some());
+    }
+}
+
+#[test]
+fn test_sample_builder_h264() -> Result<(), Error> {
+    use crate::codecs::h264::{H264Packet, H264Payloader};
+    use crate::packetizer::Payloader;
+
+    let mut payloader = H264Payloader::default();
+    let frame = vec![
+        0x00, 0x00, 0x00, 0x01, 0x65, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+    ];
+    let mut reader = frame.as_slice();
+    let payloads = payloader.payload(5, &mut reader)?;
+    assert_eq!(payloads.len(), 2);
+
+    let mut s = SampleBuilder::new(50, H264Packet::default(), 90000);
+    let count = payloads.len();
+    // The first fragment arrives last
+    for (i, p) in payloads.iter().enumerate().rev() {
+        s.push(packet(100 + i as u16, 3000, i + 1 == count, p));
+    }
+    s.push(packet(100 + count as u16, 6000, true, &[0x41, 0x9a]));
+
+    let sample = s.pop().expect("sample must be complete");
+    assert_eq!(sample.data, frame);
+    assert_eq!(sample.packet_timestamp, 3000);
+    assert_eq!(sample.duration, Duration::from_nanos(33_333_333));
+
+    Ok(())
+}
@@ -22,7 +22,7 @@ pub fn new_random_sequencer() -> impl Sequencer {
 pub fn new_fixed_sequencer(s: u16) -> impl Sequencer {
     SequencerImpl {
         mutex: Arc::new(Mutex::new(SequencerInternal {
-            sequence_number: s - 1,
+            sequence_number: s.wrapping_sub(1),
             roll_over_count: 0,
         })),
     }
@@ -42,7 +42,7 @@ struct SequencerImpl {
 impl Sequencer for SequencerImpl {
     fn next_sequence_number(&mut self) -> u16 {
         let mut s = self.mutex.lock().unwrap();
-        s.sequence_number += 1;
+        s.sequence_number = s.sequence_number.wrapping_add(1);
         if s.sequence_number == 0 {
             s.roll_over_count += 1;
         }
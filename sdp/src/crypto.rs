@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io;
+
+use util::Error;
+
+use super::common_description::*;
+
+#[cfg(test)]
+mod crypto_test;
+
+// Crypto suites of RFC 4568 and RFC 7714
+pub const CRYPTO_SUITE_AES_CM_128_HMAC_SHA1_80: &str = "AES_CM_128_HMAC_SHA1_80";
+pub const CRYPTO_SUITE_AES_CM_128_HMAC_SHA1_32: &str = "AES_CM_128_HMAC_SHA1_32";
+pub const CRYPTO_SUITE_AEAD_AES_128_GCM: &str = "AEAD_AES_128_GCM";
+pub const CRYPTO_SUITE_AEAD_AES_256_GCM: &str = "AEAD_AES_256_GCM";
+
+pub const CRYPTO_KEY_METHOD_INLINE: &str = "inline";
+
+const MAX_CRYPTO_TAG_DIGITS: usize = 9;
+const MAX_MKI_LENGTH: u8 = 128;
+
+//CryptoLifetime is the number of packets a master key may protect
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CryptoLifetime {
+    //PowerOfTwo is the "2^n" notation
+    PowerOfTwo(u32),
+    //Packets is the plain decimal notation
+    Packets(u64),
+}
+
+impl fmt::Display for CryptoLifetime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoLifetime::PowerOfTwo(n) => write!(f, "2^{}", n),
+            CryptoLifetime::Packets(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl CryptoLifetime {
+    //Packets returns the lifetime as a number of packets, saturating at u64::MAX
+    pub fn packets(&self) -> u64 {
+        match *self {
+            CryptoLifetime::PowerOfTwo(n) => 1u64.checked_shl(n).unwrap_or(u64::MAX),
+            CryptoLifetime::Packets(n) => n,
+        }
+    }
+}
+
+//CryptoMki is the master key identifier carried in every packet protected with the key
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CryptoMki {
+    pub value: u64,
+    //Length of the MKI field in bytes
+    pub length: u8,
+}
+
+//CryptoKeyParam is a single "inline:" key-param of an a=crypto attribute
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CryptoKeyParam {
+    //Concatenated master key and master salt
+    pub key_salt: Vec<u8>,
+    pub lifetime: Option<CryptoLifetime>,
+    pub mki: Option<CryptoMki>,
+}
+
+impl fmt::Display for CryptoKeyParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut output = format!(
+            "{}:{}",
+            CRYPTO_KEY_METHOD_INLINE,
+            base64::encode(&self.key_salt)
+        );
+
+        if let Some(lifetime) = &self.lifetime {
+            output += format!("|{}", lifetime).as_str();
+        }
+
+        if let Some(mki) = &self.mki {
+            output += format!("|{}:{}", mki.value, mki.length).as_str();
+        }
+
+        write!(f, "{}", output)
+    }
+}
+
+impl CryptoKeyParam {
+    fn unmarshal(raw: &str) -> Result<Self, Error> {
+        let parts: Vec<&str> = raw.splitn(2, ':').collect();
+        if parts.len() != 2 {
+            return Err(Error::new(format!("SyntaxError: {}", raw)));
+        }
+        if parts[0] != CRYPTO_KEY_METHOD_INLINE {
+            return Err(Error::new(format!("unsupported key method {}", parts[0])));
+        }
+
+        let fields: Vec<&str> = parts[1].split('|').collect();
+        if fields.len() > 3 {
+            return Err(Error::new(format!("SyntaxError: {}", raw)));
+        }
+
+        let key_salt = base64::decode(fields[0])
+            .map_err(|err| Error::new(format!("invalid inline key {}: {}", fields[0], err)))?;
+        if key_salt.is_empty() {
+            return Err(Error::new(format!("SyntaxError: {}", raw)));
+        }
+
+        let mut lifetime = None;
+        let mut mki = None;
+        for field in &fields[1..] {
+            if field.contains(':') {
+                // MKI must come after the lifetime
+                if mki.is_some() {
+                    return Err(Error::new(format!("SyntaxError: {}", raw)));
+                }
+                let mki_parts: Vec<&str> = field.splitn(2, ':').collect();
+                let value = mki_parts[0].parse::<u64>()?;
+                let length = mki_parts[1].parse::<u8>()?;
+                if length == 0 || length > MAX_MKI_LENGTH {
+                    return Err(Error::new(format!(
+                        "SyntaxError: {} -- MKI length must be in the range 1-128",
+                        field
+                    )));
+                }
+                mki = Some(CryptoMki { value, length });
+            } else {
+                if lifetime.is_some() || mki.is_some() {
+                    return Err(Error::new(format!("SyntaxError: {}", raw)));
+                }
+                lifetime = Some(if let Some(exponent) = field.strip_prefix("2^") {
+                    CryptoLifetime::PowerOfTwo(exponent.parse::<u32>()?)
+                } else {
+                    CryptoLifetime::Packets(field.parse::<u64>()?)
+                });
+            }
+        }
+
+        Ok(CryptoKeyParam {
+            key_salt,
+            lifetime,
+            mki,
+        })
+    }
+}
+
+//CryptoAttribute represents an SDES a=crypto attribute as defined in RFC 4568
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CryptoAttribute {
+    pub tag: u32,
+    pub suite: String,
+    pub key_params: Vec<CryptoKeyParam>,
+    pub session_params: Vec<String>,
+}
+
+impl fmt::Display for CryptoAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key_params: Vec<String> = self.key_params.iter().map(|k| k.to_string()).collect();
+        let mut output = format!("{} {} {}", self.tag, self.suite, key_params.join(";"));
+
+        for session_param in &self.session_params {
+            output += format!(" {}", session_param).as_str();
+        }
+
+        write!(f, "{}", output)
+    }
+}
+
+impl CryptoAttribute {
+    //Convert converts this object to an Attribute
+    pub fn convert(&self) -> Attribute {
+        Attribute {
+            key: "crypto".to_string(),
+            value: Some(self.to_string()),
+        }
+    }
+
+    //Unmarshal creates a CryptoAttribute from a string
+    pub fn unmarshal<R: io::BufRead>(reader: &mut R) -> Result<Self, Error> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let parts: Vec<&str> = line.trim().splitn(2, ':').collect();
+        if parts.len() != 2 || parts[0] != "crypto" {
+            return Err(Error::new(format!("SyntaxError: {}", line)));
+        }
+
+        CryptoAttribute::parse(parts[1])
+    }
+
+    //Parse creates a CryptoAttribute from the value of an a=crypto attribute
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        if fields.len() < 3 {
+            return Err(Error::new(format!("SyntaxError: {}", value)));
+        }
+
+        if fields[0].is_empty()
+            || fields[0].len() > MAX_CRYPTO_TAG_DIGITS
+            || !fields[0].chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(Error::new(format!(
+                "SyntaxError: {} -- crypto tag must be 1 to 9 digits",
+                fields[0]
+            )));
+        }
+        let tag = fields[0].parse::<u32>()?;
+
+        let suite = fields[1].to_owned();
+
+        let mut key_params = vec![];
+        for key_param in fields[2].split(';') {
+            key_params.push(CryptoKeyParam::unmarshal(key_param)?);
+        }
+
+        let session_params = fields[3..].iter().map(|s| (*s).to_owned()).collect();
+
+        Ok(CryptoAttribute {
+            tag,
+            suite,
+            key_params,
+            session_params,
+        })
+    }
+
+    //Marshal creates a string from a CryptoAttribute
+    pub fn marshal(&self) -> String {
+        "crypto:".to_string() + self.to_string().as_str()
+    }
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+use util::Error;
+
+use std::io::BufReader;
+
+const EXAMPLE_ATTR_CRYPTO1: &str =
+    "crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20|1:32";
+const EXAMPLE_ATTR_CRYPTO2: &str =
+    "crypto:2 AES_CM_128_HMAC_SHA1_32 inline:NzB4d1BINUAvLEw6UzF3WSJ+PSdFcGdUJShpX1Zj|1048576";
+const EXAMPLE_ATTR_CRYPTO3: &str = "crypto:3 AEAD_AES_128_GCM inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg== KDR=1 UNENCRYPTED_SRTCP";
+const EXAMPLE_ATTR_CRYPTO4: &str = "crypto:4 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20|1:4;inline:QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVphYmNk|2^20|2:4";
+
+#[test]
+fn test_crypto_attribute() -> Result<(), Error> {
+    let passingtests = [
+        EXAMPLE_ATTR_CRYPTO1,
+        EXAMPLE_ATTR_CRYPTO2,
+        EXAMPLE_ATTR_CRYPTO3,
+        EXAMPLE_ATTR_CRYPTO4,
+    ];
+    let failingtests = vec![
+        // Missing key params
+        "crypto:1 AES_CM_128_HMAC_SHA1_80",
+        // Tag too long
+        "crypto:1234567890 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+        // Non-numeric tag
+        "crypto:a AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+        // Unknown key method
+        "crypto:1 AES_CM_128_HMAC_SHA1_80 uri:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+        // Invalid base64
+        "crypto:1 AES_CM_128_HMAC_SHA1_80 inline:!!!",
+        // MKI length out of range
+        "crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|1:0",
+        // Lifetime after MKI
+        "crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|1:4|2^20",
+        // Not a crypto attribute
+        "extmap:1 http://example.com/082005/ext.htm#ttime",
+    ];
+
+    for (i, u) in passingtests.iter().enumerate() {
+        let mut reader = BufReader::new(u.as_bytes());
+        let actual = CryptoAttribute::unmarshal(&mut reader)?;
+        assert_eq!(*u, actual.marshal(), "{}: {} vs {}", i, u, actual.marshal());
+    }
+
+    for u in failingtests {
+        let mut reader = BufReader::new(u.as_bytes());
+        let actual = CryptoAttribute::unmarshal(&mut reader);
+        assert!(actual.is_err(), "{} should fail", u);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_crypto_attribute_fields() -> Result<(), Error> {
+    let mut reader = BufReader::new(EXAMPLE_ATTR_CRYPTO4.as_bytes());
+    let c = CryptoAttribute::unmarshal(&mut reader)?;
+
+    assert_eq!(c.tag, 4);
+    assert_eq!(c.suite, CRYPTO_SUITE_AES_CM_128_HMAC_SHA1_80);
+    assert_eq!(c.key_params.len(), 2);
+    assert_eq!(c.key_params[0].key_salt.len(), 30);
+    assert_eq!(
+        c.key_params[0].lifetime,
+        Some(CryptoLifetime::PowerOfTwo(20))
+    );
+    assert_eq!(c.key_params[0].lifetime.map(|l| l.packets()), Some(1 << 20));
+    assert_eq!(
+        c.key_params[1].mki,
+        Some(CryptoMki {
+            value: 2,
+            length: 4
+        })
+    );
+    assert!(c.session_params.is_empty());
+
+    let c = CryptoAttribute::parse(
+        "3 AEAD_AES_128_GCM inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg== KDR=1 UNENCRYPTED_SRTCP",
+    )?;
+    assert_eq!(
+        &c.key_params[0].key_salt[..],
+        b"1234567890ABCDEFGHIJKLMNOPQR"
+    );
+    assert_eq!(c.key_params[0].lifetime, None);
+    assert_eq!(c.key_params[0].mki, None);
+    assert_eq!(c.session_params, vec!["KDR=1", "UNENCRYPTED_SRTCP"]);
+
+    let attr = c.convert();
+    assert_eq!(attr.key, "crypto");
+    assert_eq!(
+        attr.value.as_deref(),
+        Some(
+            "3 AEAD_AES_128_GCM inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg== KDR=1 UNENCRYPTED_SRTCP"
+        )
+    );
+
+    Ok(())
+}
@@ -21,6 +21,15 @@ pub const TRANSPORT_CC_URI: &str =
     "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
 pub const SDES_MID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:mid";
 pub const SDES_RTP_STREAM_ID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id";
+pub const SDES_REPAIR_RTP_STREAM_ID_URI: &str =
+    "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id";
+pub const AUDIO_LEVEL_URI: &str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+pub const VIDEO_ORIENTATION_URI: &str = "urn:3gpp:video-orientation";
+pub const PLAYOUT_DELAY_URI: &str = "http://www.webrtc.org/experiments/rtp-hdrext/playout-delay";
+pub const ABS_CAPTURE_TIME_URI: &str =
+    "http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time";
+pub const VIDEO_CONTENT_TYPE_URI: &str =
+    "http://www.webrtc.org/experiments/rtp-hdrext/video-content-type";
 
 //ExtMap represents the activation of a single RTP header extension
 #[derive(Debug, Clone, Default)]
@@ -52,6 +61,22 @@ impl fmt::Display for ExtMap {
 }
 
 impl ExtMap {
+    pub fn value(&self) -> isize {
+        self.value
+    }
+
+    pub fn direction(&self) -> &Direction {
+        &self.direction
+    }
+
+    pub fn uri(&self) -> Option<&Url> {
+        self.uri.as_ref()
+    }
+
+    pub fn ext_attr(&self) -> Option<&str> {
+        self.ext_attr.as_deref()
+    }
+
     //Clone converts this object to an Attribute
     pub fn convert(&self) -> Attribute {
         Attribute {
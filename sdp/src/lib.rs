@@ -2,6 +2,7 @@
 #![allow(dead_code)]
 
 pub mod common_description;
+pub mod crypto;
 pub mod direction;
 pub mod extmap;
 pub mod media_description;
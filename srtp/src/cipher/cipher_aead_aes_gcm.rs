@@ -6,7 +6,7 @@ use byteorder::{BigEndian, ByteOrder};
 use rtp::header;
 use util::Error;
 
-use super::Cipher;
+use super::{Cipher, ERR_FAILED_TO_VERIFY_AUTH_TAG};
 use crate::{context, key_derivation};
 
 pub(crate) const CIPHER_AEAD_AES_GCM_AUTH_TAG_LEN: usize = 16;
@@ -54,15 +54,26 @@ impl Cipher for CipherAeadAesGcm {
         header: &header::Header,
         roc: u32,
     ) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < header.payload_offset + self.auth_tag_len() {
+            return Err(Error::new(format!(
+                "too short SRTP packet: only {} bytes, expected > {} bytes",
+                ciphertext.len(),
+                header.payload_offset + self.auth_tag_len()
+            )));
+        }
+
         let nonce = self.rtp_initialization_vector(header, roc);
 
-        let decrypted_msg: Vec<u8> = self.srtp_cipher.decrypt(
-            Nonce::from_slice(&nonce),
-            Payload {
-                msg: &ciphertext[header.payload_offset..],
-                aad: &ciphertext[..header.payload_offset],
-            },
-        )?;
+        let decrypted_msg: Vec<u8> = self
+            .srtp_cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext[header.payload_offset..],
+                    aad: &ciphertext[..header.payload_offset],
+                },
+            )
+            .map_err(|_| ERR_FAILED_TO_VERIFY_AUTH_TAG.clone())?;
 
         let mut decrypted_msg = [vec![0; header.payload_offset], decrypted_msg].concat();
 
@@ -104,17 +115,28 @@ impl Cipher for CipherAeadAesGcm {
         srtcp_index: usize,
         ssrc: u32,
     ) -> Result<Vec<u8>, Error> {
+        if encrypted.len() < 8 + self.auth_tag_len() + context::SRTCP_INDEX_SIZE {
+            return Err(Error::new(format!(
+                "too short SRTCP packet: only {} bytes, expected > {} bytes",
+                encrypted.len(),
+                8 + self.auth_tag_len() + context::SRTCP_INDEX_SIZE,
+            )));
+        }
+
         let nonce = self.rtcp_initialization_vector(srtcp_index, ssrc);
 
         let aad = self.rtcp_additional_authenticated_data(&encrypted, srtcp_index);
 
-        let decrypted_data = self.srtcp_cipher.decrypt(
-            Nonce::from_slice(&nonce),
-            Payload {
-                msg: &encrypted[8..(encrypted.len() - context::SRTCP_INDEX_SIZE)],
-                aad: &aad,
-            },
-        )?;
+        let decrypted_data = self
+            .srtcp_cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &encrypted[8..(encrypted.len() - context::SRTCP_INDEX_SIZE)],
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| ERR_FAILED_TO_VERIFY_AUTH_TAG.clone())?;
 
         let decrypted_data = [encrypted[..8].to_vec(), decrypted_data].concat();
         Ok(decrypted_data)
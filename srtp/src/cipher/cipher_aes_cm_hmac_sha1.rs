@@ -1,4 +1,4 @@
-use super::Cipher;
+use super::{Cipher, ERR_FAILED_TO_VERIFY_AUTH_TAG};
 use crate::context::*;
 use crate::key_derivation::*;
 use crate::protection_profile::*;
@@ -230,7 +230,7 @@ impl Cipher for CipherAesCmHmacSha1 {
         // See if the auth tag actually matches.
         // We use a constant time comparison to prevent timing attacks.
         if actual_tag.ct_eq(&expected_tag).unwrap_u8() != 1 {
-            return Err(Error::new("failed to verify auth tag".to_string()));
+            return Err(ERR_FAILED_TO_VERIFY_AUTH_TAG.clone());
         }
 
         // Write cipher_text to the destination buffer.
@@ -329,7 +329,7 @@ impl Cipher for CipherAesCmHmacSha1 {
         // See if the auth tag actually matches.
         // We use a constant time comparison to prevent timing attacks.
         if actual_tag.ct_eq(&expected_tag).unwrap_u8() != 1 {
-            return Err(Error::new("failed to verify auth tag".to_string()));
+            return Err(ERR_FAILED_TO_VERIFY_AUTH_TAG.clone());
         }
 
         let counter = generate_counter(
@@ -5,8 +5,15 @@ mod test;
 pub(crate) use cipher_aead_aes_gcm::CipherAeadAesGcm;
 pub(crate) use cipher_aes_cm_hmac_sha1::CipherAesCmHmacSha1;
 
+use lazy_static::lazy_static;
 use util::Error;
 
+lazy_static! {
+    // Returned when the authentication tag of a packet does not match its contents
+    pub(crate) static ref ERR_FAILED_TO_VERIFY_AUTH_TAG: Error =
+        Error::new("failed to verify auth tag".to_owned());
+}
+
 /// Cipher represents a implementation of one
 /// of the SRTP Specific ciphers.
 pub(crate) trait Cipher {
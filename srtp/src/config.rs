@@ -3,8 +3,16 @@ use util::Error;
 use super::option::*;
 use super::protection_profile::*;
 
+use sdp::crypto::*;
+
+mod config_test;
+
 const LABEL_EXTRACTOR_DTLS_SRTP: &str = "EXTRACTOR-dtls_srtp";
 
+// Key lifetimes are not enforced, so only keys that last at least the SRTCP index
+// space of 2^31 packets are accepted
+const MIN_CRYPTO_KEY_LIFETIME: u64 = 1 << 31;
+
 /// KeyingMaterialExporter allows package SRTP to extract keying material
 pub trait KeyingMaterialExporter {
     fn export_keying_material(
@@ -23,6 +31,97 @@ pub struct SessionKeys {
     pub remote_master_salt: Vec<u8>,
 }
 
+impl SessionKeys {
+    /// from_crypto_attributes builds SessionKeys from the SDES a=crypto attributes
+    /// of RFC 4568 negotiated for each direction: `local` carries the key we send
+    /// with, `remote` the key the remote peer sends with. Both must use the same suite,
+    /// which is returned as the ProtectionProfile of the session.
+    /// Session parameters (KDR, UNENCRYPTED_SRTP, FEC_ORDER, WSH, ...), MKIs and key
+    /// lifetimes shorter than 2^31 packets are not supported and rejected.
+    pub fn from_crypto_attributes(
+        local: &CryptoAttribute,
+        remote: &CryptoAttribute,
+    ) -> Result<(SessionKeys, ProtectionProfile), Error> {
+        if local.suite != remote.suite {
+            return Err(Error::new(format!(
+                "crypto suite mismatch: local {}, remote {}",
+                local.suite, remote.suite
+            )));
+        }
+
+        let profile = match local.suite.as_str() {
+            CRYPTO_SUITE_AES_CM_128_HMAC_SHA1_80 => ProtectionProfile::AES128CMHMACSHA1_80,
+            CRYPTO_SUITE_AEAD_AES_128_GCM => ProtectionProfile::AEADAES128GCM,
+            suite => return Err(Error::new(format!("unsupported crypto suite {}", suite))),
+        };
+
+        let (local_master_key, local_master_salt) = split_crypto_key_salt(local, profile)?;
+        let (remote_master_key, remote_master_salt) = split_crypto_key_salt(remote, profile)?;
+
+        Ok((
+            SessionKeys {
+                local_master_key,
+                local_master_salt,
+                remote_master_key,
+                remote_master_salt,
+            },
+            profile,
+        ))
+    }
+}
+
+fn split_crypto_key_salt(
+    crypto: &CryptoAttribute,
+    profile: ProtectionProfile,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let key_param = match crypto.key_params.as_slice() {
+        [key_param] => key_param,
+        _ => {
+            return Err(Error::new(format!(
+                "crypto tag {}: exactly one key is supported, got {}",
+                crypto.tag,
+                crypto.key_params.len()
+            )))
+        }
+    };
+    if let Some(session_param) = crypto.session_params.first() {
+        return Err(Error::new(format!(
+            "crypto tag {}: session parameter {} is not supported",
+            crypto.tag, session_param
+        )));
+    }
+    if let Some(lifetime) = &key_param.lifetime {
+        if lifetime.packets() < MIN_CRYPTO_KEY_LIFETIME {
+            return Err(Error::new(format!(
+                "crypto tag {}: key lifetime {} is not supported",
+                crypto.tag, lifetime
+            )));
+        }
+    }
+    if key_param.mki.is_some() {
+        return Err(Error::new(format!(
+            "crypto tag {}: MKI is not supported",
+            crypto.tag
+        )));
+    }
+
+    let key_len = profile.key_len()?;
+    let salt_len = profile.salt_len()?;
+    if key_param.key_salt.len() != key_len + salt_len {
+        return Err(Error::new(format!(
+            "crypto tag {}: key and salt must be len {}, got {}",
+            crypto.tag,
+            key_len + salt_len,
+            key_param.key_salt.len()
+        )));
+    }
+
+    Ok((
+        key_param.key_salt[..key_len].to_vec(),
+        key_param.key_salt[key_len..].to_vec(),
+    ))
+}
+
 /// Config is used to configure a session.
 /// You can provide either a KeyingMaterialExporter to export keys
 /// or directly pass the keys themselves.
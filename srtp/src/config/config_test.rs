@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod config_test {
+    use crate::{config::SessionKeys, context::Context, protection_profile::ProtectionProfile};
+
+    use sdp::crypto::CryptoAttribute;
+    use std::io::BufWriter;
+    use util::Error;
+
+    #[test]
+    fn test_session_keys_from_crypto_attributes() -> Result<(), Error> {
+        let offer = CryptoAttribute::parse(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^31",
+        )?;
+        let answer = CryptoAttribute::parse(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVphYmNk",
+        )?;
+
+        // The offerer sends with the key of its offer and receives with the answer's key
+        let (offerer_keys, profile) = SessionKeys::from_crypto_attributes(&offer, &answer)?;
+        let (answerer_keys, _) = SessionKeys::from_crypto_attributes(&answer, &offer)?;
+
+        assert!(matches!(profile, ProtectionProfile::AES128CMHMACSHA1_80));
+        assert_eq!(offerer_keys.local_master_key.len(), 16);
+        assert_eq!(offerer_keys.local_master_salt.len(), 14);
+        assert_eq!(&offerer_keys.remote_master_key[..], b"ABCDEFGHIJKLMNOP");
+        assert_eq!(&offerer_keys.remote_master_salt[..], b"QRSTUVWXYZabcd");
+
+        let mut encrypt_context = Context::new(
+            &offerer_keys.local_master_key,
+            &offerer_keys.local_master_salt,
+            profile,
+            None,
+            None,
+        )?;
+        let mut decrypt_context = Context::new(
+            &answerer_keys.remote_master_key,
+            &answerer_keys.remote_master_salt,
+            profile,
+            None,
+            None,
+        )?;
+
+        let pkt = rtp::packet::Packet::new(
+            rtp::header::Header {
+                sequence_number: 1,
+                ..Default::default()
+            },
+            vec![0x00, 0x01, 0x02, 0x03],
+        );
+        let mut pkt_raw: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(pkt_raw.as_mut());
+            pkt.marshal(&mut writer)?;
+        }
+
+        let encrypted = encrypt_context.encrypt_rtp(&pkt_raw)?;
+        let decrypted = decrypt_context.decrypt_rtp(&encrypted)?;
+        assert_eq!(decrypted, pkt_raw);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_keys_from_invalid_crypto_attributes() -> Result<(), Error> {
+        let valid = CryptoAttribute::parse(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+        )?;
+
+        let invalid = vec![
+            // Suite mismatch
+            "1 AEAD_AES_128_GCM inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg==",
+            // Unsupported suite
+            "1 AES_CM_128_HMAC_SHA1_32 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+            // MKI
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^31|1:4",
+            // Session parameters
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR UNENCRYPTED_SRTP",
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR KDR=1",
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR WSH=64",
+            // Key lifetime shorter than 2^31 packets
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20",
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|1048576",
+            // Wrong key length
+            "1 AES_CM_128_HMAC_SHA1_80 inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg==",
+            // More than one key
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR;inline:QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVphYmNk",
+        ];
+
+        for value in invalid {
+            let crypto = CryptoAttribute::parse(value)?;
+            assert!(
+                SessionKeys::from_crypto_attributes(&valid, &crypto).is_err(),
+                "{} should be rejected",
+                value
+            );
+        }
+
+        let gcm = CryptoAttribute::parse(
+            "1 AEAD_AES_128_GCM inline:MTIzNDU2Nzg5MEFCQ0RFRkdISUpLTE1OT1BRUg==",
+        )?;
+        let (keys, profile) = SessionKeys::from_crypto_attributes(&gcm, &gcm)?;
+        assert!(matches!(profile, ProtectionProfile::AEADAES128GCM));
+        assert_eq!(&keys.local_master_salt[..], b"GHIJKLMNOPQR");
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use transport::replay_detector::*;
 use util::Error;
@@ -21,9 +22,21 @@ pub const LABEL_SRTCP_AUTHENTICATION_TAG: u8 = 0x04;
 pub const LABEL_SRTCP_SALT: u8 = 0x05;
 
 const MAX_ROC_DISORDER: u16 = 100;
+
+/// Largest ROC recovery window, every candidate ROC costs an authentication
+/// attempt for each packet that fails to decrypt
+pub const MAX_ROC_RECOVERY_WINDOW: u32 = 4;
+/// How long the keys replaced by Context::update_keys are still tried for packets
+/// that fail authentication, so packets sent before the key change are not lost
+pub const KEY_UPDATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
 pub(crate) const MAX_SEQUENCE_NUMBER: u16 = 65535;
 pub(crate) const SRTCP_INDEX_SIZE: usize = 4;
 
+// srtp_index returns the 48-bit SRTP packet index of RFC 3711 section 3.3.1
+fn srtp_index(rollover_counter: u32, sequence_number: u16) -> u64 {
+    ((rollover_counter as u64) << 16) | sequence_number as u64
+}
+
 // Encrypt/Decrypt state for a single SRTP SSRC
 #[derive(Default)]
 pub struct SrtpSsrcState {
@@ -32,6 +45,7 @@ pub struct SrtpSsrcState {
     rollover_has_processed: bool,
     last_sequence_number: u16,
     replay_detector: Option<Box<dyn ReplayDetector>>,
+    stats: SrtpSsrcStats,
 }
 
 // Encrypt/Decrypt state for a single SRTCP SSRC
@@ -40,6 +54,44 @@ pub struct SrtcpSsrcState {
     srtcp_index: usize,
     ssrc: u32,
     replay_detector: Option<Box<dyn ReplayDetector>>,
+    stats: SrtcpSsrcStats,
+}
+
+/// SrtpSsrcStats is a snapshot of the counters kept for a single SRTP SSRC
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct SrtpSsrcStats {
+    pub ssrc: u32,
+    /// Number and total size of packets encrypted
+    pub packets_protected: u64,
+    pub bytes_protected: u64,
+    /// Number and total size of packets decrypted
+    pub packets_unprotected: u64,
+    pub bytes_unprotected: u64,
+    /// Number of packets whose authentication tag did not match, e.g. because of a wrong key
+    pub auth_failures: u64,
+    /// Number of packets rejected by the replay detector
+    pub replays: u64,
+    /// Number of packets decrypted only after ROC recovery, see Context::set_roc_recovery_window
+    pub roc_recoveries: u64,
+    pub rollover_counter: u32,
+    pub last_sequence_number: u16,
+}
+
+/// SrtcpSsrcStats is a snapshot of the counters kept for a single SRTCP SSRC
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct SrtcpSsrcStats {
+    pub ssrc: u32,
+    /// Number and total size of packets encrypted
+    pub packets_protected: u64,
+    pub bytes_protected: u64,
+    /// Number and total size of packets decrypted
+    pub packets_unprotected: u64,
+    pub bytes_unprotected: u64,
+    /// Number of packets whose authentication tag did not match, e.g. because of a wrong key
+    pub auth_failures: u64,
+    /// Number of packets rejected by the replay detector
+    pub replays: u64,
+    pub srtcp_index: usize,
 }
 
 impl SrtpSsrcState {
@@ -106,12 +158,16 @@ impl SrtpSsrcState {
 // it must either used ONLY for encryption or ONLY for decryption
 pub struct Context {
     cipher: Box<dyn cipher::Cipher + Send>,
+    // cipher replaced by update_keys and the instant it stops being tried
+    previous_cipher: Option<(Box<dyn cipher::Cipher + Send>, Instant)>,
 
     srtp_ssrc_states: HashMap<u32, SrtpSsrcState>,
     srtcp_ssrc_states: HashMap<u32, SrtcpSsrcState>,
 
     new_srtp_replay_detector: option::ContextOption,
     new_srtcp_replay_detector: option::ContextOption,
+
+    srtp_roc_recovery_window: u32,
 }
 
 unsafe impl Send for Context {}
@@ -125,6 +181,37 @@ impl Context {
         srtp_ctx_opt: Option<option::ContextOption>,
         srtcp_ctx_opt: Option<option::ContextOption>,
     ) -> Result<Context, Error> {
+        let cipher = Context::new_cipher(master_key, master_salt, profile)?;
+
+        let srtp_ctx_opt = if let Some(ctx_opt) = srtp_ctx_opt {
+            ctx_opt
+        } else {
+            option::srtp_no_replay_protection()
+        };
+
+        let srtcp_ctx_opt = if let Some(ctx_opt) = srtcp_ctx_opt {
+            ctx_opt
+        } else {
+            option::srtcp_no_replay_protection()
+        };
+
+        Ok(Context {
+            cipher,
+            previous_cipher: None,
+            srtp_ssrc_states: HashMap::new(),
+            srtcp_ssrc_states: HashMap::new(),
+            new_srtp_replay_detector: srtp_ctx_opt,
+            new_srtcp_replay_detector: srtcp_ctx_opt,
+            srtp_roc_recovery_window: 0,
+        })
+    }
+
+    // check_keys checks the master key and salt lengths against the profile
+    pub(crate) fn check_keys(
+        master_key: &[u8],
+        master_salt: &[u8],
+        profile: ProtectionProfile,
+    ) -> Result<(), Error> {
         let key_len = profile.key_len()?;
         let salt_len = profile.salt_len()?;
 
@@ -142,6 +229,17 @@ impl Context {
             )));
         }
 
+        Ok(())
+    }
+
+    // new_cipher checks the master key and salt lengths and creates the cipher of the profile
+    fn new_cipher(
+        master_key: &[u8],
+        master_salt: &[u8],
+        profile: ProtectionProfile,
+    ) -> Result<Box<dyn cipher::Cipher + Send>, Error> {
+        Context::check_keys(master_key, master_salt, profile)?;
+
         let cipher: Box<dyn cipher::Cipher + Send> = match profile {
             ProtectionProfile::AES128CMHMACSHA1_80 => {
                 Box::new(cipher::CipherAesCmHmacSha1::new(master_key, master_salt)?)
@@ -152,25 +250,36 @@ impl Context {
             }
         };
 
-        let srtp_ctx_opt = if let Some(ctx_opt) = srtp_ctx_opt {
-            ctx_opt
-        } else {
-            option::srtp_no_replay_protection()
-        };
+        Ok(cipher)
+    }
 
-        let srtcp_ctx_opt = if let Some(ctx_opt) = srtcp_ctx_opt {
-            ctx_opt
-        } else {
-            option::srtcp_no_replay_protection()
-        };
+    /// update_keys replaces the master key and salt of the context, e.g. after a DTLS
+    /// re-handshake. Per-SSRC rollover counters, SRTCP indexes, replay windows and
+    /// statistics are kept, so packet indexes carry on across the key change.
+    /// For decryption, packets failing authentication with the new keys are retried
+    /// with the replaced ones for KEY_UPDATE_GRACE_PERIOD.
+    /// On error the context keeps its current keys.
+    pub fn update_keys(
+        &mut self,
+        master_key: &[u8],
+        master_salt: &[u8],
+        profile: ProtectionProfile,
+    ) -> Result<(), Error> {
+        let cipher = Context::new_cipher(master_key, master_salt, profile)?;
+        let previous = std::mem::replace(&mut self.cipher, cipher);
+        self.previous_cipher = Some((previous, Instant::now() + KEY_UPDATE_GRACE_PERIOD));
+        Ok(())
+    }
 
-        Ok(Context {
-            cipher,
-            srtp_ssrc_states: HashMap::new(),
-            srtcp_ssrc_states: HashMap::new(),
-            new_srtp_replay_detector: srtp_ctx_opt,
-            new_srtcp_replay_detector: srtcp_ctx_opt,
-        })
+    // previous_cipher returns the cipher replaced by update_keys until its grace period
+    // is over, then drops it
+    fn previous_cipher(&mut self) -> Option<&mut Box<dyn cipher::Cipher + Send>> {
+        if let Some((_, expiry)) = &self.previous_cipher {
+            if Instant::now() >= *expiry {
+                self.previous_cipher = None;
+            }
+        }
+        self.previous_cipher.as_mut().map(|(cipher, _)| cipher)
     }
 
     // ToDo: We shouldnt be using an optional return as we can send a cloned "s".
@@ -178,6 +287,10 @@ impl Context {
         let s = SrtpSsrcState {
             ssrc,
             replay_detector: Some((self.new_srtp_replay_detector)()),
+            stats: SrtpSsrcStats {
+                ssrc,
+                ..Default::default()
+            },
             ..Default::default()
         };
 
@@ -189,14 +302,18 @@ impl Context {
         let s = SrtcpSsrcState {
             ssrc,
             replay_detector: Some((self.new_srtcp_replay_detector)()),
+            stats: SrtcpSsrcStats {
+                ssrc,
+                ..Default::default()
+            },
             ..Default::default()
         };
         self.srtcp_ssrc_states.entry(ssrc).or_insert(s);
         self.srtcp_ssrc_states.get_mut(&ssrc)
     }
 
-    // roc returns SRTP rollover counter value of specified SSRC.
-    fn get_roc(&self, ssrc: u32) -> Option<u32> {
+    /// get_roc returns SRTP rollover counter value of specified SSRC.
+    pub fn get_roc(&self, ssrc: u32) -> Option<u32> {
         if let Some(s) = self.srtp_ssrc_states.get(&ssrc) {
             Some(s.rollover_counter)
         } else {
@@ -204,13 +321,24 @@ impl Context {
         }
     }
 
-    // set_roc sets SRTP rollover counter value of specified SSRC.
-    fn set_roc(&mut self, ssrc: u32, roc: u32) {
+    /// set_roc sets SRTP rollover counter value of specified SSRC,
+    /// e.g. when it is signalled out of band.
+    pub fn set_roc(&mut self, ssrc: u32, roc: u32) {
         if let Some(s) = self.get_srtp_ssrc_state(ssrc) {
             s.rollover_counter = roc;
         }
     }
 
+    /// set_roc_recovery_window enables ROC recovery for decryption. When a SRTP packet
+    /// fails authentication, or is rejected by the replay detector, up to `window`
+    /// rollover counters on either side of the estimated one are tried before giving up.
+    /// This lets decryption resynchronise after a gap of more than 32768 packets.
+    /// Every candidate costs one authentication attempt; 0 (the default) disables it.
+    /// The window is capped to MAX_ROC_RECOVERY_WINDOW.
+    pub fn set_roc_recovery_window(&mut self, window: u32) {
+        self.srtp_roc_recovery_window = window.min(MAX_ROC_RECOVERY_WINDOW);
+    }
+
     // index returns SRTCP index value of specified SSRC.
     fn get_index(&self, ssrc: u32) -> Option<usize> {
         if let Some(s) = self.srtcp_ssrc_states.get(&ssrc) {
@@ -226,4 +354,21 @@ impl Context {
             s.srtcp_index = index;
         }
     }
+
+    /// get_srtp_stats returns SRTP statistics of specified SSRC.
+    pub fn get_srtp_stats(&self, ssrc: u32) -> Option<SrtpSsrcStats> {
+        self.srtp_ssrc_states.get(&ssrc).map(|s| SrtpSsrcStats {
+            rollover_counter: s.rollover_counter,
+            last_sequence_number: s.last_sequence_number,
+            ..s.stats
+        })
+    }
+
+    /// get_srtcp_stats returns SRTCP statistics of specified SSRC.
+    pub fn get_srtcp_stats(&self, ssrc: u32) -> Option<SrtcpSsrcStats> {
+        self.srtcp_ssrc_states.get(&ssrc).map(|s| SrtcpSsrcStats {
+            srtcp_index: s.srtcp_index,
+            ..s.stats
+        })
+    }
 }
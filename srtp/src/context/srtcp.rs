@@ -5,6 +5,12 @@ use std::io::BufReader;
 
 pub(crate) const MAX_SRTCP_INDEX: usize = 0x7FFFFFFF;
 
+// srtcp_min_len returns the size of an SRTCP packet without any report, the
+// header and sender SSRC followed by the index and auth tag
+fn srtcp_min_len(auth_tag_len: usize) -> usize {
+    rtcp::header::HEADER_LENGTH + rtcp::header::SSRC_LENGTH + SRTCP_INDEX_SIZE + auth_tag_len
+}
+
 impl Context {
     /// DecryptRTCP decrypts a RTCP packet with an encrypted payload
     pub fn decrypt_rtcp(&mut self, encrypted: &[u8]) -> Result<Vec<u8>, Error> {
@@ -13,6 +19,15 @@ impl Context {
             rtcp::header::Header::unmarshal(&mut reader)?;
         }
 
+        let min_len = srtcp_min_len(self.cipher.auth_tag_len());
+        if encrypted.len() < min_len {
+            return Err(Error::new(format!(
+                "too short SRTCP packet: only {} bytes, expected > {} bytes",
+                encrypted.len(),
+                min_len
+            )));
+        }
+
         let index = self.cipher.get_rtcp_index(encrypted);
         let ssrc = {
             let mut reader = BufReader::new(&encrypted[4..]);
@@ -23,6 +38,7 @@ impl Context {
             if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
                 if let Some(replay_detector) = &mut state.replay_detector {
                     if !replay_detector.check(index as u64) {
+                        state.stats.replays += 1;
                         return Err(Error::new(format!(
                             "srtcp ssrc={} index={}: duplicated",
                             ssrc, index
@@ -37,13 +53,68 @@ impl Context {
             }
         }
 
-        let dst = self.cipher.decrypt_rtcp(encrypted, index, ssrc)?;
+        let result = self
+            .cipher
+            .decrypt_rtcp(encrypted, index, ssrc)
+            .or_else(|err| self.decrypt_rtcp_with_previous_cipher(encrypted, index, ssrc, err));
+
+        let dst = match result {
+            Ok(dst) => dst,
+            Err(err) => {
+                if err == *cipher::ERR_FAILED_TO_VERIFY_AUTH_TAG {
+                    if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
+                        state.stats.auth_failures += 1;
+                    }
+                }
+                return Err(err);
+            }
+        };
 
         {
             if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
                 if let Some(replay_detector) = &mut state.replay_detector {
                     replay_detector.accept();
                 }
+                state.stats.packets_unprotected += 1;
+                state.stats.bytes_unprotected += dst.len() as u64;
+            }
+        }
+
+        Ok(dst)
+    }
+
+    // decrypt_rtcp_with_previous_cipher retries decryption with the cipher replaced by
+    // update_keys. The SRTCP index is located after the payload, so it is read again if
+    // the previous profile has another authentication tag length.
+    fn decrypt_rtcp_with_previous_cipher(
+        &mut self,
+        encrypted: &[u8],
+        index: usize,
+        ssrc: u32,
+        err: Error,
+    ) -> Result<Vec<u8>, Error> {
+        let (dst, previous_index) = match self.previous_cipher() {
+            Some(cipher) if encrypted.len() >= srtcp_min_len(cipher.auth_tag_len()) => {
+                let previous_index = cipher.get_rtcp_index(encrypted);
+                match cipher.decrypt_rtcp(encrypted, previous_index, ssrc) {
+                    Ok(dst) => (dst, previous_index),
+                    Err(_) => return Err(err),
+                }
+            }
+            _ => return Err(err),
+        };
+
+        if previous_index != index {
+            if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
+                if let Some(replay_detector) = &mut state.replay_detector {
+                    if !replay_detector.check(previous_index as u64) {
+                        state.stats.replays += 1;
+                        return Err(Error::new(format!(
+                            "srtcp ssrc={} index={}: duplicated",
+                            ssrc, previous_index
+                        )));
+                    }
+                }
             }
         }
 
@@ -71,6 +142,8 @@ impl Context {
                     state.srtcp_index = 0;
                 }
                 index = state.srtcp_index;
+                state.stats.packets_protected += 1;
+                state.stats.bytes_protected += decrypted.len() as u64;
             } else {
                 return Err(Error::new(format!(
                     "ssrc {} not exist in srtcp_ssrc_state",
@@ -5,6 +5,7 @@ mod test {
     use byteorder::{BigEndian, ReadBytesExt};
     use lazy_static::lazy_static;
     use std::io::Cursor;
+    use std::time::Instant;
     use util::Error;
 
     pub struct RTCPTestCase {
@@ -192,6 +193,47 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_rtcp_update_keys_grace_period() -> Result<(), Error> {
+        let new_master_key = vec![0x11; 16];
+        let new_master_salt = vec![0x22; 14];
+
+        let mut decrypt_context = Context::new(
+            &RTCP_TEST_MASTER_KEY,
+            &RTCP_TEST_MASTER_SALT,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+            None,
+            Some(option::srtcp_replay_protection(10)),
+        )?;
+        decrypt_context.update_keys(
+            &new_master_key,
+            &new_master_salt,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+
+        // A packet sent with the old keys
+        let decrypted = decrypt_context.decrypt_rtcp(&RTCP_TEST_CASES[0].encrypted)?;
+        assert_eq!(decrypted, RTCP_TEST_CASES[0].decrypted);
+        assert!(
+            decrypt_context
+                .decrypt_rtcp(&RTCP_TEST_CASES[0].encrypted)
+                .is_err(),
+            "Was able to decrypt duplicated RTCP packet"
+        );
+
+        if let Some((_, expiry)) = &mut decrypt_context.previous_cipher {
+            *expiry = Instant::now();
+        }
+        assert!(
+            decrypt_context
+                .decrypt_rtcp(&RTCP_TEST_CASES[1].encrypted)
+                .is_err(),
+            "old keys were used after the grace period"
+        );
+
+        Ok(())
+    }
+
     fn get_rtcp_index(encrypted: &[u8], auth_tag_len: usize) -> u32 {
         let tail_offset = encrypted.len() - (auth_tag_len + context::SRTCP_INDEX_SIZE);
         let mut reader =
@@ -248,4 +290,66 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_rtcp_stats() -> Result<(), Error> {
+        let auth_tag_len = ProtectionProfile::AES128CMHMACSHA1_80.auth_tag_len()?;
+
+        let mut decrypt_context = Context::new(
+            &RTCP_TEST_MASTER_KEY,
+            &RTCP_TEST_MASTER_SALT,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+            None,
+            Some(option::srtcp_replay_protection(10)),
+        )?;
+
+        let ssrc = RTCP_TEST_CASES[0].ssrc;
+        assert!(decrypt_context.get_srtcp_stats(ssrc).is_none());
+
+        let rtcp_packet = RTCP_TEST_CASES[0].encrypted.clone();
+        decrypt_context.decrypt_rtcp(&rtcp_packet)?;
+
+        // Replay
+        assert!(decrypt_context.decrypt_rtcp(&rtcp_packet).is_err());
+
+        // Zero out auth tag of a packet with a fresh index
+        let mut encrypt_context = Context::new(
+            &RTCP_TEST_MASTER_KEY,
+            &RTCP_TEST_MASTER_SALT,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+            None,
+            None,
+        )?;
+        encrypt_context.set_index(ssrc, 5);
+        let mut invalid = encrypt_context.encrypt_rtcp(&RTCP_TEST_CASES[0].decrypted)?;
+        let invalid_len = invalid.len();
+        invalid[invalid_len - auth_tag_len..].copy_from_slice(&vec![0; auth_tag_len]);
+        assert!(decrypt_context.decrypt_rtcp(&invalid).is_err());
+
+        // A packet too short to carry an auth tag is not an auth failure
+        assert!(decrypt_context.decrypt_rtcp(&invalid[..12]).is_err());
+
+        let stats = encrypt_context
+            .get_srtcp_stats(ssrc)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.packets_protected, 1);
+        assert_eq!(
+            stats.bytes_protected,
+            RTCP_TEST_CASES[0].decrypted.len() as u64
+        );
+        assert_eq!(stats.srtcp_index, 6);
+
+        let stats = decrypt_context
+            .get_srtcp_stats(ssrc)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.packets_unprotected, 1);
+        assert_eq!(
+            stats.bytes_unprotected,
+            RTCP_TEST_CASES[0].decrypted.len() as u64
+        );
+        assert_eq!(stats.replays, 1);
+        assert_eq!(stats.auth_failures, 1);
+
+        Ok(())
+    }
 }
@@ -9,16 +9,14 @@ impl Context {
         header: &rtp::header::Header,
     ) -> Result<Vec<u8>, Error> {
         let roc;
+        let replayed;
         {
             if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    if !replay_detector.check(header.sequence_number as u64) {
-                        return Err(Error::new(format!(
-                            "srtp ssrc={} index={}: duplicated",
-                            header.ssrc, header.sequence_number
-                        )));
-                    }
-                }
+                replayed = if let Some(replay_detector) = &mut state.replay_detector {
+                    !replay_detector.check(header.sequence_number as u64)
+                } else {
+                    false
+                };
 
                 roc = state.next_rollover_count(header.sequence_number);
             } else {
@@ -29,19 +27,137 @@ impl Context {
             }
         }
 
-        let dst = self.cipher.decrypt_rtp(encrypted, header, roc)?;
-        {
-            if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    replay_detector.accept();
+        let result = if replayed {
+            Err(Error::new(format!(
+                "srtp ssrc={} index={}: duplicated",
+                header.ssrc, header.sequence_number
+            )))
+        } else {
+            self.decrypt_rtp_with_ciphers(encrypted, header, roc)
+        };
+
+        let dst = match result {
+            Ok(dst) => {
+                if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+                    if let Some(replay_detector) = &mut state.replay_detector {
+                        replay_detector.accept();
+                    }
+                    state.update_rollover_count(header.sequence_number);
+                }
+                dst
+            }
+            Err(err) => {
+                if let Some(dst) = self.recover_rtp(encrypted, header, roc, replayed) {
+                    dst
+                } else {
+                    if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+                        if replayed {
+                            state.stats.replays += 1;
+                        } else if err == *cipher::ERR_FAILED_TO_VERIFY_AUTH_TAG {
+                            state.stats.auth_failures += 1;
+                        }
+                    }
+                    return Err(err);
                 }
-                state.update_rollover_count(header.sequence_number);
             }
+        };
+
+        if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+            state.stats.packets_unprotected += 1;
+            state.stats.bytes_unprotected += dst.len() as u64;
         }
 
         Ok(dst)
     }
 
+    // decrypt_rtp_with_ciphers decrypts with the current cipher, then with the one
+    // replaced by update_keys during its grace period. The error of the current cipher
+    // is returned when both fail.
+    fn decrypt_rtp_with_ciphers(
+        &mut self,
+        encrypted: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<Vec<u8>, Error> {
+        self.cipher
+            .decrypt_rtp(encrypted, header, roc)
+            .or_else(|err| match self.previous_cipher() {
+                Some(cipher) => cipher.decrypt_rtp(encrypted, header, roc).map_err(|_| err),
+                None => Err(err),
+            })
+    }
+
+    // recover_rtp retries decryption with the rollover counters around the estimated one,
+    // see set_roc_recovery_window, with the previous keys too during a key update. Only a packet authenticating with an index ahead of the
+    // last processed one is accepted: the replay detector only sees sequence numbers, so
+    // it cannot tell an older index apart from a replay. If it rejected the packet, it is
+    // restarted from the recovered index.
+    fn recover_rtp(
+        &mut self,
+        encrypted: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+        replayed: bool,
+    ) -> Option<Vec<u8>> {
+        if self.srtp_roc_recovery_window == 0 {
+            return None;
+        }
+
+        let last_index = match self.srtp_ssrc_states.get(&header.ssrc) {
+            Some(state) if state.rollover_has_processed => Some(srtp_index(
+                state.rollover_counter,
+                state.last_sequence_number,
+            )),
+            _ => None,
+        };
+
+        // Candidates are tried nearest first, without collecting them
+        let window = self.srtp_roc_recovery_window;
+        let candidates = (0..=window).flat_map(|distance| {
+            let (ahead, behind) = if distance == 0 {
+                (if replayed { Some(roc) } else { None }, None)
+            } else {
+                (roc.checked_add(distance), roc.checked_sub(distance))
+            };
+            ahead.into_iter().chain(behind)
+        });
+
+        for candidate in candidates {
+            if let Some(last_index) = last_index {
+                if srtp_index(candidate, header.sequence_number) <= last_index {
+                    continue;
+                }
+            }
+
+            if let Ok(dst) = self.decrypt_rtp_with_ciphers(encrypted, header, candidate) {
+                let replay_detector = if replayed {
+                    Some((self.new_srtp_replay_detector)())
+                } else {
+                    None
+                };
+
+                if let Some(state) = self.srtp_ssrc_states.get_mut(&header.ssrc) {
+                    if let Some(mut replay_detector) = replay_detector {
+                        replay_detector.check(header.sequence_number as u64);
+                        state.replay_detector = Some(replay_detector);
+                    }
+                    if let Some(replay_detector) = &mut state.replay_detector {
+                        replay_detector.accept();
+                    }
+
+                    state.rollover_counter = candidate;
+                    state.last_sequence_number = header.sequence_number;
+                    state.rollover_has_processed = true;
+                    state.stats.roc_recoveries += 1;
+                }
+
+                return Some(dst);
+            }
+        }
+
+        None
+    }
+
     // DecryptRTP decrypts a RTP packet with an encrypted payload
     pub fn decrypt_rtp(&mut self, encrypted: &[u8]) -> Result<Vec<u8>, Error> {
         let mut reader = BufReader::new(encrypted);
@@ -73,6 +189,8 @@ impl Context {
         {
             if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
                 state.update_rollover_count(header.sequence_number);
+                state.stats.packets_protected += 1;
+                state.stats.bytes_protected += plaintext.len() as u64;
             }
         }
 
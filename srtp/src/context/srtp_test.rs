@@ -3,8 +3,9 @@
 //TODO: BenchmarkDecryptRTP
 #[cfg(test)]
 mod srtp_test {
-    use crate::{context::Context, protection_profile::ProtectionProfile};
+    use crate::{context::Context, option, protection_profile::ProtectionProfile};
     use std::io::BufWriter;
+    use std::time::Instant;
     use util::Error;
 
     use lazy_static::lazy_static;
@@ -100,13 +101,13 @@ mod srtp_test {
         )?;
 
         for test_case in &*RTP_TEST_CASES {
-            let pkt = rtp::packet::Packet {
-                header: rtp::header::Header {
+            let pkt = rtp::packet::Packet::new(
+                rtp::header::Header {
                     sequence_number: test_case.sequence_number,
                     ..Default::default()
                 },
-                payload: RTP_TEST_CASE_DECRYPTED.clone(),
-            };
+                RTP_TEST_CASE_DECRYPTED.clone(),
+            );
             let mut pkt_raw: Vec<u8> = vec![];
             {
                 let mut writer = BufWriter::<&mut Vec<u8>>::new(pkt_raw.as_mut());
@@ -133,13 +134,13 @@ mod srtp_test {
         let auth_tag_len = ProtectionProfile::AES128CMHMACSHA1_80.auth_tag_len()?;
 
         for test_case in RTP_TEST_CASES.iter() {
-            let decrypted_pkt = rtp::packet::Packet {
-                header: rtp::header::Header {
+            let decrypted_pkt = rtp::packet::Packet::new(
+                rtp::header::Header {
                     sequence_number: test_case.sequence_number,
                     ..Default::default()
                 },
-                payload: RTP_TEST_CASE_DECRYPTED.clone(),
-            };
+                RTP_TEST_CASE_DECRYPTED.clone(),
+            );
 
             let mut decrypted_raw: Vec<u8> = vec![];
             {
@@ -147,13 +148,13 @@ mod srtp_test {
                 decrypted_pkt.marshal(&mut writer)?;
             }
 
-            let encrypted_pkt = rtp::packet::Packet {
-                header: rtp::header::Header {
+            let encrypted_pkt = rtp::packet::Packet::new(
+                rtp::header::Header {
                     sequence_number: test_case.sequence_number,
                     ..Default::default()
                 },
-                payload: test_case.encrypted.clone(),
-            };
+                test_case.encrypted.clone(),
+            );
             let mut encrypted_raw: Vec<u8> = vec![];
             {
                 let mut writer = BufWriter::<&mut Vec<u8>>::new(encrypted_raw.as_mut());
@@ -183,4 +184,440 @@ mod srtp_test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_rtp_stats() -> Result<(), Error> {
+        let mut encrypt_context = build_test_context()?;
+        let mut decrypt_context = Context::new(
+            &[
+                0x0d, 0xcd, 0x21, 0x3e, 0x4c, 0xbc, 0xf2, 0x8f, 0x01, 0x7f, 0x69, 0x94, 0x40, 0x1e,
+                0x28, 0x89,
+            ],
+            &[
+                0x62, 0x77, 0x60, 0x38, 0xc0, 0x6d, 0xc9, 0x41, 0x9f, 0x6d, 0xd9, 0x43, 0x3e, 0x7c,
+            ],
+            ProtectionProfile::AES128CMHMACSHA1_80,
+            Some(option::srtp_replay_protection(64)),
+            None,
+        )?;
+
+        assert!(decrypt_context.get_srtp_stats(0).is_none());
+
+        let mut encrypted = vec![];
+        for sequence_number in 5000..5003u16 {
+            let pkt = rtp::packet::Packet::new(
+                rtp::header::Header {
+                    sequence_number,
+                    ..Default::default()
+                },
+                RTP_TEST_CASE_DECRYPTED.clone(),
+            );
+            let mut pkt_raw: Vec<u8> = vec![];
+            {
+                let mut writer = BufWriter::<&mut Vec<u8>>::new(pkt_raw.as_mut());
+                pkt.marshal(&mut writer)?;
+            }
+            encrypted.push(encrypt_context.encrypt_rtp(&pkt_raw)?);
+        }
+
+        for pkt in &encrypted {
+            decrypt_context.decrypt_rtp(pkt)?;
+        }
+
+        // Replay the first packet
+        assert!(decrypt_context.decrypt_rtp(&encrypted[0]).is_err());
+
+        // Corrupt the auth tag of a fresh packet
+        let mut corrupted = encrypt_context.encrypt_rtp(&{
+            let mut pkt_raw: Vec<u8> = vec![];
+            {
+                let mut writer = BufWriter::<&mut Vec<u8>>::new(pkt_raw.as_mut());
+                rtp::packet::Packet::new(
+                    rtp::header::Header {
+                        sequence_number: 5003,
+                        ..Default::default()
+                    },
+                    RTP_TEST_CASE_DECRYPTED.clone(),
+                )
+                .marshal(&mut writer)?;
+            }
+            pkt_raw
+        })?;
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xFF;
+        assert!(decrypt_context.decrypt_rtp(&corrupted).is_err());
+
+        let plaintext_len = (12 + RTP_TEST_CASE_DECRYPTED.len()) as u64;
+
+        let stats = encrypt_context
+            .get_srtp_stats(0)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.packets_protected, 4);
+        assert_eq!(stats.bytes_protected, 4 * plaintext_len);
+        assert_eq!(stats.packets_unprotected, 0);
+        assert_eq!(stats.last_sequence_number, 5003);
+
+        let stats = decrypt_context
+            .get_srtp_stats(0)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.packets_unprotected, 3);
+        assert_eq!(stats.bytes_unprotected, 3 * plaintext_len);
+        assert_eq!(stats.packets_protected, 0);
+        assert_eq!(stats.replays, 1);
+        assert_eq!(stats.auth_failures, 1);
+        assert_eq!(stats.rollover_counter, 0);
+        assert_eq!(stats.last_sequence_number, 5002);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_stats_auth_failures() -> Result<(), Error> {
+        let key = [0x01; 16];
+        let salt = [0x02; 12];
+        let mut encrypt_context =
+            Context::new(&key, &salt, ProtectionProfile::AEADAES128GCM, None, None)?;
+        let mut decrypt_context =
+            Context::new(&key, &salt, ProtectionProfile::AEADAES128GCM, None, None)?;
+
+        let mut encrypted = encrypt_rtp_range(&mut encrypt_context, 5000..5001)?.remove(0);
+
+        // A packet too short to carry an auth tag is not an auth failure
+        assert!(decrypt_context.decrypt_rtp(&encrypted[..16]).is_err());
+        let stats = decrypt_context
+            .get_srtp_stats(0)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.auth_failures, 0);
+
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 0xFF;
+        assert!(decrypt_context.decrypt_rtp(&encrypted).is_err());
+        let stats = decrypt_context
+            .get_srtp_stats(0)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.auth_failures, 1);
+
+        Ok(())
+    }
+
+    fn encrypt_rtp_range(
+        context: &mut Context,
+        sequence_numbers: std::ops::Range<u32>,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut out = vec![];
+        for sequence_number in sequence_numbers {
+            let pkt = rtp::packet::Packet::new(
+                rtp::header::Header {
+                    sequence_number: sequence_number as u16,
+                    ..Default::default()
+                },
+                RTP_TEST_CASE_DECRYPTED.clone(),
+            );
+            let mut pkt_raw: Vec<u8> = vec![];
+            {
+                let mut writer = BufWriter::<&mut Vec<u8>>::new(pkt_raw.as_mut());
+                pkt.marshal(&mut writer)?;
+            }
+            out.push(context.encrypt_rtp(&pkt_raw)?);
+        }
+
+        Ok(out)
+    }
+
+    fn build_replay_protected_context(roc_recovery_window: u32) -> Result<Context, Error> {
+        let mut context = Context::new(
+            &[
+                0x0d, 0xcd, 0x21, 0x3e, 0x4c, 0xbc, 0xf2, 0x8f, 0x01, 0x7f, 0x69, 0x94, 0x40, 0x1e,
+                0x28, 0x89,
+            ],
+            &[
+                0x62, 0x77, 0x60, 0x38, 0xc0, 0x6d, 0xc9, 0x41, 0x9f, 0x6d, 0xd9, 0x43, 0x3e, 0x7c,
+            ],
+            ProtectionProfile::AES128CMHMACSHA1_80,
+            Some(option::srtp_replay_protection(64)),
+            None,
+        )?;
+        context.set_roc_recovery_window(roc_recovery_window);
+
+        Ok(context)
+    }
+
+    type Packets = Vec<Vec<u8>>;
+
+    // Packets before and after an outage: the sender keeps counting while nothing
+    // reaches the receiver, so the second batch is encrypted with the given ROC.
+    fn build_outage(
+        before: std::ops::Range<u32>,
+        roc_after: u32,
+        after: std::ops::Range<u32>,
+    ) -> Result<(Packets, Packets), Error> {
+        let mut sender = build_test_context()?;
+        let before = encrypt_rtp_range(&mut sender, before)?;
+
+        let mut sender = build_test_context()?;
+        sender.set_roc(0, roc_after);
+        let after = encrypt_rtp_range(&mut sender, after)?;
+
+        Ok((before, after))
+    }
+
+    #[test]
+    fn test_rtp_roc_recovery_after_long_outage() -> Result<(), Error> {
+        // 40000 packets lost across a sequence number wrap
+        let (before, after) = build_outage(65000..65011, 1, 39475..39485)?;
+
+        let mut receiver = build_replay_protected_context(0)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        for pkt in &after {
+            assert!(
+                receiver.decrypt_rtp(pkt).is_err(),
+                "Decrypted after long outage without ROC recovery"
+            );
+        }
+
+        let mut receiver = build_replay_protected_context(2)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        for pkt in &after {
+            let decrypted = receiver.decrypt_rtp(pkt)?;
+            assert_eq!(&decrypted[12..], &RTP_TEST_CASE_DECRYPTED[..]);
+        }
+
+        // Replay protection still holds after resynchronisation
+        assert!(receiver.decrypt_rtp(&after[0]).is_err());
+        assert!(receiver.decrypt_rtp(&before[10]).is_err());
+
+        let stats = receiver
+            .get_srtp_stats(0)
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.roc_recoveries, 1);
+        assert_eq!(stats.rollover_counter, 1);
+        assert_eq!(stats.last_sequence_number, 39484);
+        assert_eq!(stats.packets_unprotected, 21);
+        // The old packet is out of the replay window's reach, so it
+        // is only rejected because no candidate ROC is ahead of it
+        assert_eq!(stats.replays, 1);
+        assert_eq!(stats.auth_failures, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_roc_recovery_without_wrap() -> Result<(), Error> {
+        // 40000 packets lost, the sequence number does not wrap but
+        // the replay detector takes the jump for an old packet
+        let (before, after) = build_outage(100..110, 0, 40110..40120)?;
+
+        let mut receiver = build_replay_protected_context(0)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        assert!(receiver.decrypt_rtp(&after[0]).is_err());
+
+        let mut receiver = build_replay_protected_context(1)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        for pkt in &after {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        assert_eq!(receiver.get_roc(0), Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_roc_recovery_window_bound() -> Result<(), Error> {
+        // More than two full wraps lost
+        let (before, after) = build_outage(100..110, 2, 5000..5010)?;
+
+        let mut receiver = build_replay_protected_context(1)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        for pkt in &after {
+            assert!(
+                receiver.decrypt_rtp(pkt).is_err(),
+                "Decrypted with ROC outside of recovery window"
+            );
+        }
+
+        let mut receiver = build_replay_protected_context(2)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        for pkt in &after {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        assert_eq!(receiver.get_roc(0), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_roc_recovery_window_capped() -> Result<(), Error> {
+        let receiver = build_replay_protected_context(u32::MAX)?;
+        assert_eq!(
+            receiver.srtp_roc_recovery_window,
+            crate::context::MAX_ROC_RECOVERY_WINDOW
+        );
+
+        // Out of the capped window
+        let (before, after) = build_outage(100..110, 6, 5000..5010)?;
+        let mut receiver = build_replay_protected_context(u32::MAX)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        assert!(receiver.decrypt_rtp(&after[0]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_set_roc() -> Result<(), Error> {
+        // Receiver joins a stream whose ROC is signalled out of band
+        let mut sender = build_test_context()?;
+        sender.set_roc(0, 5);
+        let packets = encrypt_rtp_range(&mut sender, 1000..1005)?;
+
+        let mut receiver = build_replay_protected_context(0)?;
+        assert!(receiver.decrypt_rtp(&packets[0]).is_err());
+
+        let mut receiver = build_replay_protected_context(0)?;
+        receiver.set_roc(0, 5);
+        for pkt in &packets {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        assert_eq!(receiver.get_roc(0), Some(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_update_keys() -> Result<(), Error> {
+        let new_master_key = vec![0x11; 16];
+        let new_master_salt = vec![0x22; 14];
+
+        let mut sender = build_test_context()?;
+        let mut receiver = build_replay_protected_context(0)?;
+
+        let packets = encrypt_rtp_range(&mut sender, 65530..65536)?;
+        for pkt in &packets {
+            receiver.decrypt_rtp(pkt)?;
+        }
+
+        assert!(
+            receiver
+                .update_keys(&new_master_key, &[], ProtectionProfile::AES128CMHMACSHA1_80)
+                .is_err(),
+            "update_keys accepted a 0 length salt"
+        );
+        // A failed update keeps the old keys
+        let packets = encrypt_rtp_range(&mut sender, 0..1)?;
+        receiver.decrypt_rtp(&packets[0])?;
+
+        sender.update_keys(
+            &new_master_key,
+            &new_master_salt,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+        receiver.update_keys(
+            &new_master_key,
+            &new_master_salt,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+
+        // ROC and replay state carry on across the key change
+        let packets = encrypt_rtp_range(&mut sender, 1..5)?;
+        for pkt in &packets {
+            let decrypted = receiver.decrypt_rtp(pkt)?;
+            assert_eq!(&decrypted[12..], &RTP_TEST_CASE_DECRYPTED[..]);
+        }
+        assert!(receiver.decrypt_rtp(&packets[0]).is_err());
+        assert_eq!(sender.get_roc(0), Some(1));
+        assert_eq!(receiver.get_roc(0), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_update_keys_grace_period() -> Result<(), Error> {
+        let new_master_key = vec![0x11; 16];
+        let new_master_salt = vec![0x22; 14];
+
+        let mut sender = build_test_context()?;
+        let mut receiver = build_replay_protected_context(0)?;
+
+        // Packets sent with the old keys are still in flight when the receiver updates
+        let in_flight = encrypt_rtp_range(&mut sender, 0..3)?;
+        receiver.update_keys(
+            &new_master_key,
+            &new_master_salt,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+        sender.update_keys(
+            &new_master_key,
+            &new_master_salt,
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+
+        let packets = encrypt_rtp_range(&mut sender, 3..5)?;
+        receiver.decrypt_rtp(&packets[0])?;
+        for pkt in &in_flight[..2] {
+            let decrypted = receiver.decrypt_rtp(pkt)?;
+            assert_eq!(&decrypted[12..], &RTP_TEST_CASE_DECRYPTED[..]);
+        }
+        // The replay detector still applies to packets decrypted with the old keys
+        assert!(receiver.decrypt_rtp(&in_flight[0]).is_err());
+
+        // Once the grace period is over the old keys are dropped
+        if let Some((_, expiry)) = &mut receiver.previous_cipher {
+            *expiry = Instant::now();
+        }
+        assert!(
+            receiver.decrypt_rtp(&in_flight[2]).is_err(),
+            "old keys were used after the grace period"
+        );
+        assert!(receiver.previous_cipher.is_none());
+        receiver.decrypt_rtp(&packets[1])?;
+
+        let stats = receiver.get_srtp_stats(0).expect("stats must exist");
+        assert_eq!(stats.packets_unprotected, 4);
+        assert_eq!(stats.replays, 1);
+        assert_eq!(stats.auth_failures, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rtp_update_keys_grace_period_roc_recovery() -> Result<(), Error> {
+        // Packets sent with the old keys after a long outage are still in flight
+        // when the receiver updates
+        let (before, after) = build_outage(65000..65011, 1, 39475..39485)?;
+
+        let mut receiver = build_replay_protected_context(2)?;
+        for pkt in &before {
+            receiver.decrypt_rtp(pkt)?;
+        }
+        receiver.update_keys(
+            &[0x11; 16],
+            &[0x22; 14],
+            ProtectionProfile::AES128CMHMACSHA1_80,
+        )?;
+
+        for pkt in &after {
+            let decrypted = receiver.decrypt_rtp(pkt)?;
+            assert_eq!(&decrypted[12..], &RTP_TEST_CASE_DECRYPTED[..]);
+        }
+
+        let stats = receiver.get_srtp_stats(0).expect("stats must exist");
+        assert_eq!(stats.roc_recoveries, 1);
+        assert_eq!(stats.rollover_counter, 1);
+        assert_eq!(stats.packets_unprotected, 21);
+
+        Ok(())
+    }
 }
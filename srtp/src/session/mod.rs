@@ -1,7 +1,13 @@
 mod session_rtcp_test;
 mod session_rtp_test;
 
-use crate::{config::Config, context::Context, option, stream::Stream};
+use crate::{
+    config::{Config, SessionKeys},
+    context::{Context, SrtcpSsrcStats, SrtpSsrcStats},
+    option,
+    protection_profile::ProtectionProfile,
+    stream::{Stream, WriteStream},
+};
 
 use transport::{buffer::ERR_BUFFER_FULL, Buffer};
 use util::Error;
@@ -26,6 +32,7 @@ const DEFAULT_SESSION_SRTCP_REPLAY_PROTECTION_WINDOW: usize = 64;
 /// instead of making everyone re-implement
 pub struct Session {
     local_context: Arc<Mutex<Context>>,
+    remote_context: Arc<Mutex<Context>>,
     streams_map: Arc<Mutex<HashMap<u32, Buffer>>>,
     new_stream_rx: mpsc::Receiver<Stream>,
     close_stream_tx: mpsc::Sender<u32>,
@@ -44,7 +51,7 @@ impl Session {
             config.local_rtcp_options,
         )?;
 
-        let mut remote_context = Context::new(
+        let remote_context = Context::new(
             &config.keys.remote_master_key,
             &config.keys.remote_master_salt,
             config.profile,
@@ -64,6 +71,8 @@ impl Session {
             },
         )?;
 
+        let remote_context = Arc::new(Mutex::new(remote_context));
+        let cloned_remote_context = Arc::clone(&remote_context);
         let streams_map = Arc::new(Mutex::new(HashMap::new()));
         let (mut new_stream_tx, new_stream_rx) = mpsc::channel(1);
         let (close_stream_tx, mut close_stream_rx) = mpsc::channel(1);
@@ -83,7 +92,7 @@ impl Session {
                     &cloned_streams_map,
                     &cloned_close_stream_tx,
                     &mut new_stream_tx,
-                    &mut remote_context,
+                    &cloned_remote_context,
                     is_rtp,
                 );
                 let close_stream = close_stream_rx.recv();
@@ -104,6 +113,7 @@ impl Session {
 
         Ok(Session {
             local_context: Arc::new(Mutex::new(local_context)),
+            remote_context,
             streams_map,
             new_stream_rx,
             close_stream_tx,
@@ -124,7 +134,7 @@ impl Session {
         streams_map: &Arc<Mutex<HashMap<u32, Buffer>>>,
         close_stream_tx: &mpsc::Sender<u32>,
         new_stream_tx: &mut mpsc::Sender<Stream>,
-        remote_context: &mut Context,
+        remote_context: &Arc<Mutex<Context>>,
         is_rtp: bool,
     ) -> Result<(), Error> {
         let n = udp_rx.recv(buf).await?;
@@ -132,10 +142,13 @@ impl Session {
             return Err(Error::new("EOF".to_string()));
         }
 
-        let decrypted = if is_rtp {
-            remote_context.decrypt_rtp(&buf[0..n])?
-        } else {
-            remote_context.decrypt_rtcp(&buf[0..n])?
+        let decrypted = {
+            let mut remote_context = remote_context.lock().await;
+            if is_rtp {
+                remote_context.decrypt_rtp(&buf[0..n])?
+            } else {
+                remote_context.decrypt_rtcp(&buf[0..n])?
+            }
         };
 
         let ssrcs = if is_rtp {
@@ -182,6 +195,16 @@ impl Session {
         }
     }
 
+    // open_write_stream returns a stream to protect and send packets of a single local SSRC
+    pub fn open_write_stream(&self, ssrc: u32) -> WriteStream {
+        WriteStream::new(
+            ssrc,
+            Arc::clone(&self.local_context),
+            Arc::clone(&self.udp_tx),
+            self.is_rtp,
+        )
+    }
+
     // accept returns a stream to handle RTCP for a single SSRC
     pub async fn accept(&mut self) -> Result<Stream, Error> {
         let result = self.new_stream_rx.recv().await;
@@ -192,6 +215,62 @@ impl Session {
         }
     }
 
+    /// get_local_srtp_stats returns statistics of specified SSRC as sent by this session
+    pub async fn get_local_srtp_stats(&self, ssrc: u32) -> Option<SrtpSsrcStats> {
+        self.local_context.lock().await.get_srtp_stats(ssrc)
+    }
+
+    /// get_remote_srtp_stats returns statistics of specified SSRC as received by this session
+    pub async fn get_remote_srtp_stats(&self, ssrc: u32) -> Option<SrtpSsrcStats> {
+        self.remote_context.lock().await.get_srtp_stats(ssrc)
+    }
+
+    /// get_local_srtcp_stats returns statistics of specified SSRC as sent by this session
+    pub async fn get_local_srtcp_stats(&self, ssrc: u32) -> Option<SrtcpSsrcStats> {
+        self.local_context.lock().await.get_srtcp_stats(ssrc)
+    }
+
+    /// get_remote_srtcp_stats returns statistics of specified SSRC as received by this session
+    pub async fn get_remote_srtcp_stats(&self, ssrc: u32) -> Option<SrtcpSsrcStats> {
+        self.remote_context.lock().await.get_srtcp_stats(ssrc)
+    }
+
+    /// update_keys replaces the keys of both directions without tearing down the session,
+    /// e.g. after a DTLS re-handshake or an SDES re-offer. Per-SSRC rollover counters and
+    /// replay windows are kept and existing streams keep reading. Packets still protected
+    /// with the old remote keys are accepted for KEY_UPDATE_GRACE_PERIOD. On error neither
+    /// direction is changed.
+    pub async fn update_keys(
+        &self,
+        keys: SessionKeys,
+        profile: ProtectionProfile,
+    ) -> Result<(), Error> {
+        Context::check_keys(&keys.local_master_key, &keys.local_master_salt, profile)?;
+        Context::check_keys(&keys.remote_master_key, &keys.remote_master_salt, profile)?;
+
+        let mut local_context = self.local_context.lock().await;
+        let mut remote_context = self.remote_context.lock().await;
+        local_context.update_keys(&keys.local_master_key, &keys.local_master_salt, profile)?;
+        remote_context.update_keys(&keys.remote_master_key, &keys.remote_master_salt, profile)?;
+
+        Ok(())
+    }
+
+    /// set_remote_roc sets the SRTP rollover counter of specified SSRC received
+    /// from the remote peer, e.g. when it is signalled out of band
+    pub async fn set_remote_roc(&self, ssrc: u32, roc: u32) {
+        self.remote_context.lock().await.set_roc(ssrc, roc)
+    }
+
+    /// set_roc_recovery_window enables ROC recovery when decrypting incoming packets,
+    /// see Context::set_roc_recovery_window
+    pub async fn set_roc_recovery_window(&self, window: u32) {
+        self.remote_context
+            .lock()
+            .await
+            .set_roc_recovery_window(window)
+    }
+
     pub async fn close(&mut self) -> Result<(), Error> {
         self.close_session_tx.send(()).await?;
 
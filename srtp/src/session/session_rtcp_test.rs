@@ -270,4 +270,40 @@ mod session_rtcp_test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_session_srtcp_write_stream() -> Result<(), Error> {
+        let (sa, mut sb) = build_session_srtcp_pair().await?;
+
+        let mut write_stream = sa.open_write_stream(TEST_SSRC + 1);
+        assert!(!write_stream.is_rtp_stream());
+
+        let mut read_stream = sb.listen(TEST_SSRC).await?;
+
+        let rtcp_packet = rtcp::packet::Packet::PictureLossIndication(
+            rtcp::picture_loss_indication::PictureLossIndication {
+                sender_ssrc: TEST_SSRC + 1,
+                media_ssrc: TEST_SSRC,
+            },
+        );
+        write_stream.write_rtcp(&rtcp_packet).await?;
+
+        let sender_ssrc = get_sender_ssrc(&mut read_stream).await?;
+        assert_eq!(sender_ssrc, TEST_SSRC + 1);
+
+        // Packets sent by another SSRC are rejected
+        let rtcp_packet = rtcp::packet::Packet::PictureLossIndication(
+            rtcp::picture_loss_indication::PictureLossIndication {
+                sender_ssrc: TEST_SSRC + 2,
+                media_ssrc: TEST_SSRC,
+            },
+        );
+        assert!(write_stream.write_rtcp(&rtcp_packet).await.is_err());
+        assert!(write_stream
+            .write_rtp(&rtp::packet::Packet::default())
+            .await
+            .is_err());
+
+        Ok(())
+    }
 }
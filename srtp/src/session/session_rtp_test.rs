@@ -93,13 +93,13 @@ mod session_rtp_test {
 
         let (mut sa, mut sb) = build_session_srtp_pair().await?;
 
-        let packet = rtp::packet::Packet {
-            header: rtp::header::Header {
+        let packet = rtp::packet::Packet::new(
+            rtp::header::Header {
                 ssrc: TEST_SSRC,
                 ..Default::default()
             },
-            payload: test_payload.clone(),
-        };
+            test_payload.clone(),
+        );
         sa.write_rtp(&packet).await?;
 
         let mut read_stream = sb.accept().await?;
@@ -133,13 +133,13 @@ mod session_rtp_test {
 
         let (mut sa, mut sb) = build_session_srtp_pair().await?;
 
-        let packet = rtp::packet::Packet {
-            header: rtp::header::Header {
+        let packet = rtp::packet::Packet::new(
+            rtp::header::Header {
                 ssrc: TEST_SSRC,
                 ..Default::default()
             },
-            payload: test_payload.clone(),
-        };
+            test_payload.clone(),
+        );
 
         let mut read_stream = sb.listen(TEST_SSRC).await?;
 
@@ -176,13 +176,13 @@ mod session_rtp_test {
         }
 
         for ssrc in &ssrcs {
-            let packet = rtp::packet::Packet {
-                header: rtp::header::Header {
+            let packet = rtp::packet::Packet::new(
+                rtp::header::Header {
                     ssrc: *ssrc,
                     ..Default::default()
                 },
-                payload: test_payload.clone(),
-            };
+                test_payload.clone(),
+            );
             sa.write_rtp(&packet).await?;
 
             if let Some(read_stream) = read_streams.get_mut(ssrc) {
@@ -255,14 +255,14 @@ mod session_rtp_test {
             while i != 0x10 {
                 expected_sequence_number.push(i);
 
-                let packet = rtp::packet::Packet {
-                    header: rtp::header::Header {
+                let packet = rtp::packet::Packet::new(
+                    rtp::header::Header {
                         ssrc: TEST_SSRC,
                         sequence_number: i,
                         ..Default::default()
                     },
-                    payload: test_payload.clone(),
-                };
+                    test_payload.clone(),
+                );
 
                 let encrypted = encrypt_srtp(&mut local_context, &packet)?;
 
@@ -323,4 +323,117 @@ mod session_rtp_test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_session_srtp_update_keys() -> Result<(), Error> {
+        let test_payload = vec![0x00, 0x01, 0x03, 0x04];
+
+        let (mut sa, mut sb) = build_session_srtp_pair().await?;
+
+        let mut read_stream = sb.listen(TEST_SSRC).await?;
+
+        let mut packet = rtp::packet::Packet::new(
+            rtp::header::Header {
+                ssrc: TEST_SSRC,
+                sequence_number: 0xFFFF,
+                ..Default::default()
+            },
+            test_payload.clone(),
+        );
+        sa.write_rtp(&packet).await?;
+        let seq = payload_srtp(&mut read_stream, RTP_HEADER_SIZE, &test_payload).await?;
+        assert_eq!(seq, 0xFFFF);
+
+        let keys = || config::SessionKeys {
+            local_master_key: vec![0x11; 16],
+            local_master_salt: vec![0x22; 14],
+            remote_master_key: vec![0x11; 16],
+            remote_master_salt: vec![0x22; 14],
+        };
+        assert!(
+            sa.update_keys(
+                config::SessionKeys {
+                    local_master_key: vec![],
+                    ..keys()
+                },
+                ProtectionProfile::AES128CMHMACSHA1_80
+            )
+            .await
+            .is_err(),
+            "update_keys accepted a 0 length key"
+        );
+        sa.update_keys(keys(), ProtectionProfile::AES128CMHMACSHA1_80)
+            .await?;
+        sb.update_keys(keys(), ProtectionProfile::AES128CMHMACSHA1_80)
+            .await?;
+
+        // Same stream, new keys, sequence number wraps across the key change
+        packet.header.sequence_number = 0;
+        sa.write_rtp(&packet).await?;
+        let seq = payload_srtp(&mut read_stream, RTP_HEADER_SIZE, &test_payload).await?;
+        assert_eq!(seq, 0);
+
+        let stats = sb
+            .get_remote_srtp_stats(TEST_SSRC)
+            .await
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.rollover_counter, 1);
+        assert_eq!(stats.packets_unprotected, 2);
+
+        sa.close().await?;
+        sb.close().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_srtp_write_stream() -> Result<(), Error> {
+        let test_payload = vec![0x00, 0x01, 0x03, 0x04];
+
+        let (mut sa, mut sb) = build_session_srtp_pair().await?;
+
+        let mut write_stream = sa.open_write_stream(TEST_SSRC);
+        assert_eq!(write_stream.get_ssrc(), TEST_SSRC);
+        assert!(write_stream.is_rtp_stream());
+
+        // The SSRC of the header is taken from the stream
+        let packet = rtp::packet::Packet::new(
+            rtp::header::Header {
+                sequence_number: 7,
+                ..Default::default()
+            },
+            test_payload.clone(),
+        );
+        write_stream.write_rtp(&packet).await?;
+
+        let mut read_stream = sb.accept().await?;
+        assert_eq!(read_stream.get_ssrc(), TEST_SSRC);
+        let seq = payload_srtp(&mut read_stream, RTP_HEADER_SIZE, &test_payload).await?;
+        assert_eq!(seq, 7);
+
+        // Raw packets of another SSRC are rejected
+        let mut raw = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+            packet.marshal(&mut writer)?;
+        }
+        assert!(write_stream.write(&raw).await.is_err());
+        assert!(write_stream
+            .write_rtcp(&rtcp::packet::Packet::PictureLossIndication(
+                Default::default()
+            ))
+            .await
+            .is_err());
+
+        let stats = sa
+            .get_local_srtp_stats(TEST_SSRC)
+            .await
+            .expect("stats must exist for used SSRC");
+        assert_eq!(stats.packets_protected, 1);
+
+        sa.close().await?;
+        sb.close().await?;
+
+        Ok(())
+    }
 }
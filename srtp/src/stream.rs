@@ -1,9 +1,18 @@
+use crate::context::Context;
+
 use transport::Buffer;
 use util::Error;
 
-use tokio::sync::mpsc;
+use byteorder::{BigEndian, ByteOrder};
+use tokio::{
+    net::UdpSocket,
+    sync::{mpsc, Mutex},
+};
 
-use std::io::Cursor;
+use std::{
+    io::{BufWriter, Cursor},
+    sync::Arc,
+};
 
 /// Limit the buffer size to 1MB
 pub const SRTP_BUFFER_SIZE: usize = 1000 * 1000;
@@ -11,6 +20,11 @@ pub const SRTP_BUFFER_SIZE: usize = 1000 * 1000;
 /// Limit the buffer size to 100KB
 pub const SRTCP_BUFFER_SIZE: usize = 100 * 1000;
 
+// Offset of the SSRC in a RTP packet, and of the sender SSRC in a RTCP packet
+const RTP_SSRC_OFFSET: usize = 8;
+const RTCP_SSRC_OFFSET: usize = 4;
+const SSRC_LENGTH: usize = 4;
+
 /// Stream handles decryption for a single RTP/RTCP SSRC
 pub struct Stream {
     ssrc: u32,
@@ -98,3 +112,108 @@ impl Stream {
         Ok(())
     }
 }
+
+/// WriteStream handles encryption for a single local RTP/RTCP SSRC
+pub struct WriteStream {
+    ssrc: u32,
+    local_context: Arc<Mutex<Context>>,
+    udp_tx: Arc<UdpSocket>,
+    is_rtp: bool,
+}
+
+impl WriteStream {
+    pub(crate) fn new(
+        ssrc: u32,
+        local_context: Arc<Mutex<Context>>,
+        udp_tx: Arc<UdpSocket>,
+        is_rtp: bool,
+    ) -> Self {
+        WriteStream {
+            ssrc,
+            local_context,
+            udp_tx,
+            is_rtp,
+        }
+    }
+
+    /// GetSSRC returns the SSRC we are sending with
+    pub fn get_ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    /// Check if RTP is a stream.
+    pub fn is_rtp_stream(&self) -> bool {
+        self.is_rtp
+    }
+
+    /// Write encrypts and sends a marshaled RTP or RTCP packet. The SSRC of the packet
+    /// (the sender SSRC for RTCP) must be the one of the stream.
+    pub async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let ssrc_offset = if self.is_rtp {
+            RTP_SSRC_OFFSET
+        } else {
+            RTCP_SSRC_OFFSET
+        };
+        if buf.len() < ssrc_offset + SSRC_LENGTH {
+            return Err(Error::new(format!(
+                "packet too short to carry a SSRC: {} bytes",
+                buf.len()
+            )));
+        }
+
+        let ssrc = BigEndian::read_u32(&buf[ssrc_offset..ssrc_offset + SSRC_LENGTH]);
+        if ssrc != self.ssrc {
+            return Err(Error::new(format!(
+                "packet SSRC {} does not match stream SSRC {}",
+                ssrc, self.ssrc
+            )));
+        }
+
+        let encrypted = {
+            let mut local_context = self.local_context.lock().await;
+            if self.is_rtp {
+                local_context.encrypt_rtp(buf)?
+            } else {
+                local_context.encrypt_rtcp(buf)?
+            }
+        };
+
+        Ok(self.udp_tx.send(&encrypted).await?)
+    }
+
+    /// WriteRTP marshals, encrypts and sends an RTP packet.
+    /// The SSRC of the packet header is replaced by the one of the stream.
+    pub async fn write_rtp(&mut self, packet: &rtp::packet::Packet) -> Result<usize, Error> {
+        if !self.is_rtp {
+            return Err(Error::new("this stream is not RTPStream".to_string()));
+        }
+
+        let mut raw: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+            packet.marshal(&mut writer)?;
+        }
+        BigEndian::write_u32(
+            &mut raw[RTP_SSRC_OFFSET..RTP_SSRC_OFFSET + SSRC_LENGTH],
+            self.ssrc,
+        );
+
+        self.write(&raw).await
+    }
+
+    /// WriteRTCP marshals, encrypts and sends an RTCP packet.
+    /// The sender SSRC of the packet must be the one of the stream.
+    pub async fn write_rtcp(&mut self, packet: &rtcp::packet::Packet) -> Result<usize, Error> {
+        if self.is_rtp {
+            return Err(Error::new("this stream is not RTCPStream".to_string()));
+        }
+
+        let mut raw: Vec<u8> = vec![];
+        {
+            let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+            packet.marshal(&mut writer)?;
+        }
+
+        self.write(&raw).await
+    }
+}